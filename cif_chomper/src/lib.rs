@@ -1,4 +1,18 @@
 #[cfg(test)]
 mod macro_tests {
-    use super::*;
+    cif_chomper_macros::make_model!(CifCore);
+
+    #[test]
+    fn test_cif_core_model() {
+        let mut model = CifCore::default();
+        model.cell.length_a = Some(5.0);
+        model.space_group.name_h_m_alt = Some("P 1".to_string());
+        model.atom.atom_site.push(AtomSite {
+            label: Some("O1".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(model.cell.length_a, Some(5.0));
+        assert_eq!(model.atom.atom_site.len(), 1);
+        assert_eq!(CifCore::NAMESPACE, "CifCore");
+    }
 }
@@ -0,0 +1,1412 @@
+/// Reader and writer for BinaryCIF, the MessagePack encoding of CIF used by
+/// the PDB archive. Details on the format:
+/// https://github.com/molstar/BinaryCIF/blob/master/encoding.md
+///
+/// Decoding produces a [`BinaryCifFile`] that owns its strings, from which a
+/// [`RawModel`] borrowing those strings can be built, mirroring how the text
+/// parser borrows from its input.
+use std::collections::HashMap;
+use std::fmt;
+
+use rmpv::Value;
+
+use crate::raw_model::{RawDataBlock, RawDataItem, RawDataItemContent, RawModel};
+
+const BCIF_VERSION: &str = "0.3.0";
+const BCIF_ENCODER: &str = "cif_chomper";
+const RAW_MODEL_HEADING: &str = r"#\#CIF_2.0";
+
+const NOT_SPECIFIED: &str = ".";
+const UNKNOWN: &str = "?";
+
+#[derive(Debug, PartialEq)]
+pub enum BinaryCifError {
+    MessagePack(String),
+    MissingField(&'static str),
+    InvalidField(&'static str),
+    UnknownEncoding(String),
+    UnknownDataType(i64),
+    Length { expected: usize, found: usize },
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for BinaryCifError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryCifError::MessagePack(e) => write!(f, "invalid MessagePack: {e}"),
+            BinaryCifError::MissingField(name) => write!(f, "missing field '{name}'"),
+            BinaryCifError::InvalidField(name) => write!(f, "invalid value for field '{name}'"),
+            BinaryCifError::UnknownEncoding(kind) => write!(f, "unknown encoding '{kind}'"),
+            BinaryCifError::UnknownDataType(code) => write!(f, "unknown data type {code}"),
+            BinaryCifError::Length { expected, found } => {
+                write!(f, "expected {expected} values, found {found}")
+            }
+            BinaryCifError::Unsupported(what) => write!(f, "{what} cannot be stored in BinaryCIF"),
+        }
+    }
+}
+
+impl std::error::Error for BinaryCifError {}
+
+/// Element types of `ByteArray` encoded data, using the codes from the spec
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataType {
+    Int8,
+    Int16,
+    Int32,
+    Uint8,
+    Uint16,
+    Uint32,
+    Float32,
+    Float64,
+}
+
+impl DataType {
+    fn from_code(code: i64) -> Result<Self, BinaryCifError> {
+        Ok(match code {
+            1 => DataType::Int8,
+            2 => DataType::Int16,
+            3 => DataType::Int32,
+            4 => DataType::Uint8,
+            5 => DataType::Uint16,
+            6 => DataType::Uint32,
+            32 => DataType::Float32,
+            33 => DataType::Float64,
+            _ => return Err(BinaryCifError::UnknownDataType(code)),
+        })
+    }
+
+    fn code(self) -> i64 {
+        match self {
+            DataType::Int8 => 1,
+            DataType::Int16 => 2,
+            DataType::Int32 => 3,
+            DataType::Uint8 => 4,
+            DataType::Uint16 => 5,
+            DataType::Uint32 => 6,
+            DataType::Float32 => 32,
+            DataType::Float64 => 33,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            DataType::Int8 | DataType::Uint8 => 1,
+            DataType::Int16 | DataType::Uint16 => 2,
+            DataType::Int32 | DataType::Uint32 | DataType::Float32 => 4,
+            DataType::Float64 => 8,
+        }
+    }
+}
+
+/// One step of a column's encoding chain. Steps are listed in the order they
+/// were applied when writing, so decoding walks them in reverse.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Encoding {
+    ByteArray {
+        data_type: DataType,
+    },
+    FixedPoint {
+        factor: f64,
+        src_type: DataType,
+    },
+    IntervalQuantization {
+        min: f64,
+        max: f64,
+        num_steps: i64,
+        src_type: DataType,
+    },
+    RunLength {
+        src_type: DataType,
+        src_size: usize,
+    },
+    Delta {
+        origin: i64,
+        src_type: DataType,
+    },
+    IntegerPacking {
+        byte_count: usize,
+        is_unsigned: bool,
+        src_size: usize,
+    },
+    StringArray {
+        data_encoding: Vec<Encoding>,
+        string_data: String,
+        offset_encoding: Vec<Encoding>,
+        offsets: Vec<u8>,
+    },
+}
+
+/// Intermediate representation of a column while walking an encoding chain
+#[derive(Debug, Clone, PartialEq)]
+enum ColumnData {
+    Bytes(Vec<u8>),
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+    Float32(Vec<f32>),
+    Str(Vec<Option<String>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryCifColumn {
+    pub name: String,
+    /// Full data name, e.g. `_atom_site.label` for column `label` of category
+    /// `_atom_site`
+    pub data_name: String,
+    /// Values in their CIF text form, with masked entries given as `.` or `?`
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryCifCategory {
+    pub name: String,
+    pub row_count: usize,
+    pub columns: Vec<BinaryCifColumn>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryCifBlock {
+    pub header: String,
+    pub categories: Vec<BinaryCifCategory>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryCifFile {
+    pub version: String,
+    pub encoder: String,
+    pub data_blocks: Vec<BinaryCifBlock>,
+}
+
+fn field<'v>(map: &'v Value, name: &'static str) -> Result<&'v Value, BinaryCifError> {
+    map.as_map()
+        .ok_or(BinaryCifError::InvalidField(name))?
+        .iter()
+        .find(|(k, _)| k.as_str() == Some(name))
+        .map(|(_, v)| v)
+        .ok_or(BinaryCifError::MissingField(name))
+}
+fn opt_field<'v>(map: &'v Value, name: &'static str) -> Option<&'v Value> {
+    field(map, name).ok().filter(|v| !v.is_nil())
+}
+fn str_field<'v>(map: &'v Value, name: &'static str) -> Result<&'v str, BinaryCifError> {
+    field(map, name)?
+        .as_str()
+        .ok_or(BinaryCifError::InvalidField(name))
+}
+fn int_field(map: &Value, name: &'static str) -> Result<i64, BinaryCifError> {
+    field(map, name)?
+        .as_i64()
+        .ok_or(BinaryCifError::InvalidField(name))
+}
+fn usize_field(map: &Value, name: &'static str) -> Result<usize, BinaryCifError> {
+    usize::try_from(int_field(map, name)?).map_err(|_| BinaryCifError::InvalidField(name))
+}
+fn float_field(map: &Value, name: &'static str) -> Result<f64, BinaryCifError> {
+    let value = field(map, name)?;
+    value
+        .as_f64()
+        .or_else(|| value.as_i64().map(|v| v as f64))
+        .ok_or(BinaryCifError::InvalidField(name))
+}
+fn bool_field(map: &Value, name: &'static str) -> Result<bool, BinaryCifError> {
+    field(map, name)?
+        .as_bool()
+        .ok_or(BinaryCifError::InvalidField(name))
+}
+fn array_field<'v>(map: &'v Value, name: &'static str) -> Result<&'v [Value], BinaryCifError> {
+    field(map, name)?
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or(BinaryCifError::InvalidField(name))
+}
+fn bytes_field<'v>(map: &'v Value, name: &'static str) -> Result<&'v [u8], BinaryCifError> {
+    field(map, name)?
+        .as_slice()
+        .ok_or(BinaryCifError::InvalidField(name))
+}
+
+impl Encoding {
+    fn from_value(value: &Value) -> Result<Self, BinaryCifError> {
+        let kind = str_field(value, "kind")?;
+        let src_type = || DataType::from_code(int_field(value, "srcType")?);
+        Ok(match kind {
+            "ByteArray" => Encoding::ByteArray {
+                data_type: DataType::from_code(int_field(value, "type")?)?,
+            },
+            "FixedPoint" => Encoding::FixedPoint {
+                factor: float_field(value, "factor")?,
+                src_type: src_type()?,
+            },
+            "IntervalQuantization" => Encoding::IntervalQuantization {
+                min: float_field(value, "min")?,
+                max: float_field(value, "max")?,
+                num_steps: int_field(value, "numSteps")?,
+                src_type: src_type()?,
+            },
+            "RunLength" => Encoding::RunLength {
+                src_type: src_type()?,
+                src_size: usize_field(value, "srcSize")?,
+            },
+            "Delta" => Encoding::Delta {
+                origin: int_field(value, "origin")?,
+                src_type: src_type()?,
+            },
+            "IntegerPacking" => Encoding::IntegerPacking {
+                byte_count: usize_field(value, "byteCount")?,
+                is_unsigned: bool_field(value, "isUnsigned")?,
+                src_size: usize_field(value, "srcSize")?,
+            },
+            "StringArray" => Encoding::StringArray {
+                data_encoding: encodings_from_value(field(value, "dataEncoding")?)?,
+                string_data: str_field(value, "stringData")?.to_string(),
+                offset_encoding: encodings_from_value(field(value, "offsetEncoding")?)?,
+                offsets: bytes_field(value, "offsets")?.to_vec(),
+            },
+            other => return Err(BinaryCifError::UnknownEncoding(other.to_string())),
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let entries: Vec<(&str, Value)> = match self {
+            Encoding::ByteArray { data_type } => vec![
+                ("kind", "ByteArray".into()),
+                ("type", data_type.code().into()),
+            ],
+            Encoding::FixedPoint { factor, src_type } => vec![
+                ("kind", "FixedPoint".into()),
+                ("factor", (*factor).into()),
+                ("srcType", src_type.code().into()),
+            ],
+            Encoding::IntervalQuantization {
+                min,
+                max,
+                num_steps,
+                src_type,
+            } => vec![
+                ("kind", "IntervalQuantization".into()),
+                ("min", (*min).into()),
+                ("max", (*max).into()),
+                ("numSteps", (*num_steps).into()),
+                ("srcType", src_type.code().into()),
+            ],
+            Encoding::RunLength { src_type, src_size } => vec![
+                ("kind", "RunLength".into()),
+                ("srcType", src_type.code().into()),
+                ("srcSize", (*src_size as i64).into()),
+            ],
+            Encoding::Delta { origin, src_type } => vec![
+                ("kind", "Delta".into()),
+                ("origin", (*origin).into()),
+                ("srcType", src_type.code().into()),
+            ],
+            Encoding::IntegerPacking {
+                byte_count,
+                is_unsigned,
+                src_size,
+            } => vec![
+                ("kind", "IntegerPacking".into()),
+                ("byteCount", (*byte_count as i64).into()),
+                ("isUnsigned", (*is_unsigned).into()),
+                ("srcSize", (*src_size as i64).into()),
+            ],
+            Encoding::StringArray {
+                data_encoding,
+                string_data,
+                offset_encoding,
+                offsets,
+            } => vec![
+                ("kind", "StringArray".into()),
+                ("dataEncoding", encodings_to_value(data_encoding)),
+                ("stringData", string_data.as_str().into()),
+                ("offsetEncoding", encodings_to_value(offset_encoding)),
+                ("offsets", Value::Binary(offsets.clone())),
+            ],
+        };
+        map_value(entries)
+    }
+
+    /// Undo this encoding step. Run-length decoding is the only step that
+    /// expands the data, and may not produce more than `limit` values as
+    /// sizes in the header cannot be trusted.
+    fn decode(&self, data: ColumnData, limit: usize) -> Result<ColumnData, BinaryCifError> {
+        match (self, data) {
+            (Encoding::ByteArray { data_type }, ColumnData::Bytes(bytes)) => {
+                decode_byte_array(*data_type, &bytes)
+            }
+            (Encoding::FixedPoint { factor, src_type }, ColumnData::Int(values)) => Ok(float_data(
+                values.iter().map(|v| *v as f64 / factor),
+                *src_type,
+            )),
+            (
+                Encoding::IntervalQuantization {
+                    min,
+                    max,
+                    num_steps,
+                    src_type,
+                },
+                ColumnData::Int(values),
+            ) => {
+                let intervals = num_steps
+                    .checked_sub(1)
+                    .ok_or(BinaryCifError::InvalidField("numSteps"))?;
+                let delta = (max - min) / intervals.max(1) as f64;
+                Ok(float_data(
+                    values.iter().map(|v| min + delta * *v as f64),
+                    *src_type,
+                ))
+            }
+            (Encoding::RunLength { src_size, .. }, ColumnData::Int(values)) => {
+                check_size(*src_size, limit)?;
+                let mut output = Vec::new();
+                for pair in values.chunks(2) {
+                    if let [value, count] = pair {
+                        let count = usize::try_from(*count)
+                            .map_err(|_| BinaryCifError::InvalidField("data"))?;
+                        check_size(output.len().saturating_add(count), *src_size)?;
+                        output.extend(std::iter::repeat_n(*value, count));
+                    }
+                }
+                Ok(ColumnData::Int(output))
+            }
+            (Encoding::Delta { origin, .. }, ColumnData::Int(values)) => {
+                let mut acc = *origin;
+                let values = values
+                    .iter()
+                    .map(|v| {
+                        acc = acc
+                            .checked_add(*v)
+                            .ok_or(BinaryCifError::InvalidField("data"))?;
+                        Ok(acc)
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(ColumnData::Int(values))
+            }
+            (
+                Encoding::IntegerPacking {
+                    byte_count,
+                    is_unsigned,
+                    src_size,
+                },
+                ColumnData::Int(values),
+            ) => Ok(ColumnData::Int(unpack_integers(
+                &values,
+                *byte_count,
+                *is_unsigned,
+                *src_size,
+            )?)),
+            (
+                Encoding::StringArray {
+                    data_encoding,
+                    string_data,
+                    offset_encoding,
+                    offsets,
+                },
+                ColumnData::Bytes(bytes),
+            ) => {
+                let units: Vec<u16> = string_data.encode_utf16().collect();
+                let offsets = decode_ints(offset_encoding, offsets.clone(), units.len() + 1)?;
+                let indices = decode_ints(data_encoding, bytes, limit)?;
+                let strings = offsets
+                    .windows(2)
+                    .map(|w| {
+                        let (start, end) = (w[0] as usize, w[1] as usize);
+                        units
+                            .get(start..end)
+                            .map(String::from_utf16_lossy)
+                            .ok_or(BinaryCifError::InvalidField("offsets"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let values = indices
+                    .iter()
+                    .map(|i| match usize::try_from(*i) {
+                        Ok(i) => strings
+                            .get(i)
+                            .cloned()
+                            .map(Some)
+                            .ok_or(BinaryCifError::InvalidField("dataEncoding")),
+                        Err(_) => Ok(None),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(ColumnData::Str(values))
+            }
+            _ => Err(BinaryCifError::InvalidField("encoding")),
+        }
+    }
+}
+
+fn encodings_from_value(value: &Value) -> Result<Vec<Encoding>, BinaryCifError> {
+    value
+        .as_array()
+        .ok_or(BinaryCifError::InvalidField("encoding"))?
+        .iter()
+        .map(Encoding::from_value)
+        .collect()
+}
+fn encodings_to_value(encodings: &[Encoding]) -> Value {
+    Value::Array(encodings.iter().map(Encoding::to_value).collect())
+}
+fn map_value(entries: Vec<(&str, Value)>) -> Value {
+    Value::Map(entries.into_iter().map(|(k, v)| (k.into(), v)).collect())
+}
+
+fn decode_byte_array(data_type: DataType, bytes: &[u8]) -> Result<ColumnData, BinaryCifError> {
+    let size = data_type.size();
+    if !bytes.len().is_multiple_of(size) {
+        return Err(BinaryCifError::InvalidField("data"));
+    }
+    let chunks = bytes.chunks_exact(size);
+    Ok(match data_type {
+        DataType::Int8 => ColumnData::Int(chunks.map(|c| c[0] as i8 as i64).collect()),
+        DataType::Uint8 => ColumnData::Int(chunks.map(|c| c[0] as i64).collect()),
+        DataType::Int16 => ColumnData::Int(
+            chunks
+                .map(|c| i16::from_le_bytes([c[0], c[1]]) as i64)
+                .collect(),
+        ),
+        DataType::Uint16 => ColumnData::Int(
+            chunks
+                .map(|c| u16::from_le_bytes([c[0], c[1]]) as i64)
+                .collect(),
+        ),
+        DataType::Int32 => ColumnData::Int(
+            chunks
+                .map(|c| i32::from_le_bytes([c[0], c[1], c[2], c[3]]) as i64)
+                .collect(),
+        ),
+        DataType::Uint32 => ColumnData::Int(
+            chunks
+                .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]) as i64)
+                .collect(),
+        ),
+        DataType::Float32 => ColumnData::Float32(
+            chunks
+                .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        ),
+        DataType::Float64 => ColumnData::Float(
+            chunks
+                .map(|c| f64::from_le_bytes(c.try_into().expect("chunk of 8 bytes")))
+                .collect(),
+        ),
+    })
+}
+
+/// Decoded reals, kept as `f32` when that is their source type so they are
+/// written without the digits of the widened value
+fn float_data(values: impl Iterator<Item = f64>, src_type: DataType) -> ColumnData {
+    match src_type {
+        DataType::Float32 => ColumnData::Float32(values.map(|v| v as f32).collect()),
+        _ => ColumnData::Float(values.collect()),
+    }
+}
+
+/// Reject a decoded size larger than `limit`
+fn check_size(size: usize, limit: usize) -> Result<(), BinaryCifError> {
+    match size > limit {
+        true => Err(BinaryCifError::Length {
+            expected: limit,
+            found: size,
+        }),
+        false => Ok(()),
+    }
+}
+
+fn decode_data(
+    encodings: &[Encoding],
+    bytes: Vec<u8>,
+    limit: usize,
+) -> Result<ColumnData, BinaryCifError> {
+    encodings
+        .iter()
+        .rev()
+        .try_fold(ColumnData::Bytes(bytes), |data, encoding| {
+            encoding.decode(data, limit)
+        })
+}
+fn decode_ints(
+    encodings: &[Encoding],
+    bytes: Vec<u8>,
+    limit: usize,
+) -> Result<Vec<i64>, BinaryCifError> {
+    match decode_data(encodings, bytes, limit)? {
+        ColumnData::Int(values) => Ok(values),
+        _ => Err(BinaryCifError::InvalidField("encoding")),
+    }
+}
+
+fn packing_limits(byte_count: usize, is_unsigned: bool) -> (i64, i64) {
+    match (byte_count, is_unsigned) {
+        (1, true) => (0, u8::MAX as i64),
+        (1, false) => (i8::MIN as i64, i8::MAX as i64),
+        (_, true) => (0, u16::MAX as i64),
+        (_, false) => (i16::MIN as i64, i16::MAX as i64),
+    }
+}
+
+fn unpack_integers(
+    values: &[i64],
+    byte_count: usize,
+    is_unsigned: bool,
+    src_size: usize,
+) -> Result<Vec<i64>, BinaryCifError> {
+    let (lower, upper) = packing_limits(byte_count, is_unsigned);
+    let mut output = Vec::new();
+    let mut acc: i64 = 0;
+    for v in values {
+        acc = acc
+            .checked_add(*v)
+            .ok_or(BinaryCifError::InvalidField("data"))?;
+        if *v != upper && (is_unsigned || *v != lower) {
+            check_size(output.len() + 1, src_size)?;
+            output.push(acc);
+            acc = 0;
+        }
+    }
+    Ok(output)
+}
+
+/// Number of values `pack_integers` produces, computed without packing so
+/// large values can be ruled out cheaply
+fn packed_len(values: &[i64], byte_count: usize, is_unsigned: bool) -> usize {
+    let (lower, upper) = packing_limits(byte_count, is_unsigned);
+    values
+        .iter()
+        .map(|v| match *v >= 0 {
+            true => v / upper + 1,
+            false => v / lower + 1,
+        })
+        .fold(0usize, |n, len| n.saturating_add(len as usize))
+}
+
+fn pack_integers(values: &[i64], byte_count: usize, is_unsigned: bool) -> Vec<i64> {
+    let (lower, upper) = packing_limits(byte_count, is_unsigned);
+    let mut output = Vec::with_capacity(values.len());
+    for v in values {
+        let mut v = *v;
+        if v >= 0 {
+            while v >= upper {
+                output.push(upper);
+                v -= upper;
+            }
+        } else {
+            while v <= lower {
+                output.push(lower);
+                v -= lower;
+            }
+        }
+        output.push(v);
+    }
+    output
+}
+
+fn fits_i32(values: &[i64]) -> bool {
+    values.iter().all(|v| i32::try_from(*v).is_ok())
+}
+
+/// Serialise values already known to fit `data_type`
+fn encode_byte_array(values: &[i64], data_type: DataType) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(values.len() * data_type.size());
+    for v in values {
+        match data_type {
+            DataType::Int8 | DataType::Uint8 => bytes.push(*v as u8),
+            DataType::Int16 | DataType::Uint16 => bytes.extend((*v as u16).to_le_bytes()),
+            _ => bytes.extend((*v as i32).to_le_bytes()),
+        }
+    }
+    bytes
+}
+
+/// Pack and serialise integers, choosing the narrowest byte layout. Packing
+/// is only tried while it is smaller than 32 bit values would be, and `None`
+/// is returned if the values fit neither.
+fn encode_packed(values: &[i64], mut encodings: Vec<Encoding>) -> Option<(Vec<u8>, Vec<Encoding>)> {
+    let is_unsigned = values.iter().all(|v| *v >= 0);
+    let plain_len = values.len().saturating_mul(DataType::Int32.size());
+    let mut best: Option<(Vec<u8>, Encoding, DataType)> = None;
+    for byte_count in [1, 2] {
+        if packed_len(values, byte_count, is_unsigned).saturating_mul(byte_count) >= plain_len {
+            continue;
+        }
+        let packed = pack_integers(values, byte_count, is_unsigned);
+        let data_type = match (byte_count, is_unsigned) {
+            (1, true) => DataType::Uint8,
+            (1, false) => DataType::Int8,
+            (_, true) => DataType::Uint16,
+            (_, false) => DataType::Int16,
+        };
+        let bytes = encode_byte_array(&packed, data_type);
+        if best.as_ref().is_none_or(|(b, _, _)| bytes.len() < b.len()) {
+            let packing = Encoding::IntegerPacking {
+                byte_count,
+                is_unsigned,
+                src_size: values.len(),
+            };
+            best = Some((bytes, packing, data_type));
+        }
+    }
+    match best {
+        Some((bytes, packing, data_type)) => {
+            encodings.push(packing);
+            encodings.push(Encoding::ByteArray { data_type });
+            Some((bytes, encodings))
+        }
+        None if fits_i32(values) => {
+            encodings.push(Encoding::ByteArray {
+                data_type: DataType::Int32,
+            });
+            Some((encode_byte_array(values, DataType::Int32), encodings))
+        }
+        None => None,
+    }
+}
+
+fn run_length(values: &[i64]) -> Vec<i64> {
+    let mut output: Vec<i64> = Vec::new();
+    for v in values {
+        match output.len() {
+            n if n >= 2 && output[n - 2] == *v => output[n - 1] += 1,
+            _ => output.extend([*v, 1]),
+        }
+    }
+    output
+}
+
+fn delta(values: &[i64]) -> Option<(i64, Vec<i64>)> {
+    let origin = values.first().copied().unwrap_or(0);
+    let mut prev = origin;
+    let deltas = values
+        .iter()
+        .map(|v| {
+            let d = v.checked_sub(prev)?;
+            prev = *v;
+            Some(d)
+        })
+        .collect::<Option<_>>()?;
+    Some((origin, deltas))
+}
+
+/// Encode integers with whichever of the plain, run-length and delta +
+/// run-length chains gives the smallest output, skipping chains whose values
+/// do not fit in 32 bits
+fn encode_ints(values: &[i64], prefix: Vec<Encoding>) -> Option<(Vec<u8>, Vec<Encoding>)> {
+    let src_size = values.len();
+    let mut candidates = vec![encode_packed(values, prefix.clone())];
+
+    let mut rle_chain = prefix.clone();
+    rle_chain.push(Encoding::RunLength {
+        src_type: DataType::Int32,
+        src_size,
+    });
+    candidates.push(encode_packed(&run_length(values), rle_chain));
+
+    if let Some((origin, deltas)) = delta(values) {
+        let mut delta_chain = prefix;
+        delta_chain.push(Encoding::Delta {
+            origin,
+            src_type: DataType::Int32,
+        });
+        delta_chain.push(Encoding::RunLength {
+            src_type: DataType::Int32,
+            src_size,
+        });
+        candidates.push(encode_packed(&run_length(&deltas), delta_chain));
+    }
+
+    candidates
+        .into_iter()
+        .flatten()
+        .min_by_key(|(bytes, _)| bytes.len())
+}
+
+fn is_masked(value: &str) -> bool {
+    value == NOT_SPECIFIED || value == UNKNOWN
+}
+
+/// Integers that survive a round trip through their text form unchanged
+fn as_int_column(values: &[&str]) -> Option<Vec<i64>> {
+    values
+        .iter()
+        .map(|s| match is_masked(s) {
+            true => Some(0),
+            false => s
+                .parse::<i32>()
+                .ok()
+                .filter(|v| v.to_string() == *s)
+                .map(i64::from),
+        })
+        .collect()
+}
+
+/// Reals that survive a round trip through fixed point and back to text,
+/// returned with the number of decimal digits needed
+fn as_fixed_point_column(values: &[&str]) -> Option<(Vec<f64>, u32)> {
+    const MAX_DIGITS: u32 = 6;
+    let mut digits = 0;
+    let mut parsed = Vec::with_capacity(values.len());
+    for s in values {
+        if is_masked(s) {
+            parsed.push(0.0);
+            continue;
+        }
+        let v: f64 = s.parse().ok()?;
+        if !v.is_finite() || v.to_string() != *s {
+            return None;
+        }
+        digits = digits.max(s.split_once('.').map_or(0, |(_, frac)| frac.len() as u32));
+        parsed.push(v);
+    }
+    let factor = 10f64.powi(digits as i32);
+    let fits = parsed.iter().all(|v| (v * factor).abs() < i32::MAX as f64);
+    (digits <= MAX_DIGITS && fits).then_some((parsed, digits))
+}
+
+fn encode_strings(values: &[&str]) -> Option<(Vec<u8>, Vec<Encoding>)> {
+    let mut lookup: HashMap<&str, i64> = HashMap::new();
+    let mut string_data = String::new();
+    let mut offsets = vec![0];
+    let mut utf16_len = 0;
+    let indices: Vec<i64> = values
+        .iter()
+        .map(|s| match is_masked(s) {
+            true => -1,
+            false => *lookup.entry(s).or_insert_with(|| {
+                string_data.push_str(s);
+                utf16_len += s.encode_utf16().count() as i64;
+                offsets.push(utf16_len);
+                offsets.len() as i64 - 2
+            }),
+        })
+        .collect();
+    let (offset_bytes, offset_encoding) = encode_ints(&offsets, Vec::new())?;
+    let (data, data_encoding) = encode_ints(&indices, Vec::new())?;
+    let encoding = Encoding::StringArray {
+        data_encoding,
+        string_data,
+        offset_encoding,
+        offsets: offset_bytes,
+    };
+    Some((data, vec![encoding]))
+}
+
+/// Choose the encoding for a column from the text form of its values, falling
+/// back to strings for numbers that cannot be stored in 32 bits
+fn encode_column(values: &[&str]) -> Result<(Vec<u8>, Vec<Encoding>), BinaryCifError> {
+    if let Some(encoded) = as_int_column(values).and_then(|ints| encode_ints(&ints, Vec::new())) {
+        return Ok(encoded);
+    }
+    if let Some((reals, digits)) = as_fixed_point_column(values) {
+        let factor = 10f64.powi(digits as i32);
+        let ints: Vec<i64> = reals.iter().map(|v| (v * factor).round() as i64).collect();
+        let fixed_point = Encoding::FixedPoint {
+            factor,
+            src_type: DataType::Float64,
+        };
+        if let Some(encoded) = encode_ints(&ints, vec![fixed_point]) {
+            return Ok(encoded);
+        }
+    }
+    encode_strings(values).ok_or(BinaryCifError::Unsupported(
+        "strings of 2^31 or more UTF-16 code units",
+    ))
+}
+
+fn encode_mask(values: &[&str]) -> Option<(Vec<u8>, Vec<Encoding>)> {
+    let mask: Vec<i64> = values
+        .iter()
+        .map(|v| match *v {
+            NOT_SPECIFIED => 1,
+            UNKNOWN => 2,
+            _ => 0,
+        })
+        .collect();
+    mask.iter()
+        .any(|m| *m != 0)
+        .then(|| encode_ints(&mask, Vec::new()).expect("mask values fit in 32 bits"))
+}
+
+fn encoded_data_value((data, encoding): (Vec<u8>, Vec<Encoding>)) -> Value {
+    map_value(vec![
+        ("encoding", encodings_to_value(&encoding)),
+        ("data", Value::Binary(data)),
+    ])
+}
+
+fn format_real(value: f64) -> String {
+    value.to_string()
+}
+
+impl BinaryCifColumn {
+    fn from_value(value: &Value, category: &str, row_count: usize) -> Result<Self, BinaryCifError> {
+        let name = str_field(value, "name")?.to_string();
+        let data = field(value, "data")?;
+        let decoded = decode_data(
+            &encodings_from_value(field(data, "encoding")?)?,
+            bytes_field(data, "data")?.to_vec(),
+            row_count,
+        )?;
+        let mut values: Vec<String> = match decoded {
+            ColumnData::Int(v) => v.iter().map(i64::to_string).collect(),
+            ColumnData::Float(v) => v.iter().copied().map(format_real).collect(),
+            ColumnData::Float32(v) => v.iter().map(f32::to_string).collect(),
+            ColumnData::Str(v) => v
+                .into_iter()
+                .map(|s| s.unwrap_or_else(|| UNKNOWN.to_string()))
+                .collect(),
+            ColumnData::Bytes(_) => return Err(BinaryCifError::InvalidField("encoding")),
+        };
+        if values.len() != row_count {
+            return Err(BinaryCifError::Length {
+                expected: row_count,
+                found: values.len(),
+            });
+        }
+        if let Some(mask) = opt_field(value, "mask") {
+            let mask = decode_ints(
+                &encodings_from_value(field(mask, "encoding")?)?,
+                bytes_field(mask, "data")?.to_vec(),
+                row_count,
+            )?;
+            for (value, m) in values.iter_mut().zip(mask) {
+                match m {
+                    1 => *value = NOT_SPECIFIED.to_string(),
+                    2 => *value = UNKNOWN.to_string(),
+                    _ => (),
+                }
+            }
+        }
+        // Columns of categories without a DDLm style name carry the full data
+        // name themselves, see `split_data_name`
+        let data_name = match name.starts_with('_') {
+            true => name.clone(),
+            false => format!("{category}.{name}"),
+        };
+        Ok(BinaryCifColumn {
+            name,
+            data_name,
+            values,
+        })
+    }
+
+    fn to_value(&self) -> Result<Value, BinaryCifError> {
+        let values: Vec<&str> = self.values.iter().map(String::as_str).collect();
+        let mask = encode_mask(&values).map_or(Value::Nil, encoded_data_value);
+        Ok(map_value(vec![
+            ("name", self.name.as_str().into()),
+            ("data", encoded_data_value(encode_column(&values)?)),
+            ("mask", mask),
+        ]))
+    }
+}
+
+impl BinaryCifCategory {
+    fn from_value(value: &Value) -> Result<Self, BinaryCifError> {
+        let name = str_field(value, "name")?.to_string();
+        let row_count = usize_field(value, "rowCount")?;
+        let columns = array_field(value, "columns")?
+            .iter()
+            .map(|c| BinaryCifColumn::from_value(c, &name, row_count))
+            .collect::<Result<_, _>>()?;
+        Ok(BinaryCifCategory {
+            name,
+            row_count,
+            columns,
+        })
+    }
+
+    fn to_value(&self) -> Result<Value, BinaryCifError> {
+        let columns = self
+            .columns
+            .iter()
+            .map(BinaryCifColumn::to_value)
+            .collect::<Result<_, _>>()?;
+        Ok(map_value(vec![
+            ("name", self.name.as_str().into()),
+            ("columns", Value::Array(columns)),
+            ("rowCount", (self.row_count as i64).into()),
+        ]))
+    }
+}
+
+/// Split `_category.object` into its category and column names. Names without
+/// a `.` have no category in the text form, so they are stored with the full
+/// data name as the column name.
+fn split_data_name(name: &str) -> (&str, &str) {
+    name.split_once('.').unwrap_or((name, name))
+}
+
+fn text_value<'a>(content: &'a RawDataItemContent) -> Result<&'a str, BinaryCifError> {
+    match content {
+        RawDataItemContent::Str(s) | RawDataItemContent::Text(s) => Ok(s),
+        RawDataItemContent::Empty => Ok(UNKNOWN),
+        RawDataItemContent::List(_) => Err(BinaryCifError::Unsupported("list values")),
+        RawDataItemContent::Table(_) => Err(BinaryCifError::Unsupported("table values")),
+    }
+}
+
+impl BinaryCifBlock {
+    fn from_value(value: &Value) -> Result<Self, BinaryCifError> {
+        Ok(BinaryCifBlock {
+            header: str_field(value, "header")?.to_string(),
+            categories: array_field(value, "categories")?
+                .iter()
+                .map(BinaryCifCategory::from_value)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn to_value(&self) -> Result<Value, BinaryCifError> {
+        let categories = self
+            .categories
+            .iter()
+            .map(BinaryCifCategory::to_value)
+            .collect::<Result<_, _>>()?;
+        Ok(map_value(vec![
+            ("header", self.header.as_str().into()),
+            ("categories", Value::Array(categories)),
+        ]))
+    }
+
+    fn from_raw_block(block: &RawDataBlock) -> Result<Self, BinaryCifError> {
+        let mut categories: Vec<BinaryCifCategory> = Vec::new();
+        // Set categories collect all their single items, wherever they appear
+        let mut set_categories: HashMap<String, usize> = HashMap::new();
+        for item in &block.content {
+            match item {
+                RawDataItem::Data { name, value } => {
+                    let (category, column) = split_data_name(name);
+                    let column = BinaryCifColumn {
+                        name: column.to_string(),
+                        data_name: name.to_string(),
+                        values: vec![text_value(value)?.to_string()],
+                    };
+                    let key = category.to_lowercase();
+                    match set_categories.get(&key) {
+                        Some(i) if category != *name => categories[*i].columns.push(column),
+                        _ => {
+                            set_categories.insert(key, categories.len());
+                            categories.push(BinaryCifCategory {
+                                name: category.to_string(),
+                                row_count: 1,
+                                columns: vec![column],
+                            });
+                        }
+                    }
+                }
+                RawDataItem::Loop { names, values } => {
+                    let width = names.len();
+                    if width == 0 || !values.len().is_multiple_of(width) {
+                        return Err(BinaryCifError::Length {
+                            expected: values.len().next_multiple_of(width.max(1)),
+                            found: values.len(),
+                        });
+                    }
+                    let (category, _) = split_data_name(names[0]);
+                    let shared = names.iter().all(|n| split_data_name(n).0 == category);
+                    let columns = names
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| {
+                            let column = match shared && name.contains('.') {
+                                true => split_data_name(name).1,
+                                false => name,
+                            };
+                            Ok(BinaryCifColumn {
+                                name: column.to_string(),
+                                data_name: name.to_string(),
+                                values: values
+                                    .iter()
+                                    .skip(i)
+                                    .step_by(width)
+                                    .map(|v| text_value(v).map(str::to_string))
+                                    .collect::<Result<_, _>>()?,
+                            })
+                        })
+                        .collect::<Result<_, BinaryCifError>>()?;
+                    categories.push(BinaryCifCategory {
+                        name: category.to_string(),
+                        row_count: values.len() / width,
+                        columns,
+                    });
+                }
+                RawDataItem::SaveFrame { .. } => {
+                    return Err(BinaryCifError::Unsupported("save frames"));
+                }
+            }
+        }
+        Ok(BinaryCifBlock {
+            header: block.heading.to_string(),
+            categories,
+        })
+    }
+
+    fn to_raw_block(&self) -> Result<RawDataBlock<'_>, BinaryCifError> {
+        let mut content = Vec::new();
+        for category in &self.categories {
+            if let Some(column) = category
+                .columns
+                .iter()
+                .find(|c| c.values.len() != category.row_count)
+            {
+                return Err(BinaryCifError::Length {
+                    expected: category.row_count,
+                    found: column.values.len(),
+                });
+            }
+            if category.row_count == 1 {
+                content.extend(category.columns.iter().map(|c| RawDataItem::Data {
+                    name: &c.data_name,
+                    value: RawDataItemContent::from_text(&c.values[0]),
+                }));
+            } else {
+                let names = category
+                    .columns
+                    .iter()
+                    .map(|c| c.data_name.as_str())
+                    .collect();
+                let values = (0..category.row_count)
+                    .flat_map(|row| {
+                        category
+                            .columns
+                            .iter()
+                            .map(move |c| RawDataItemContent::from_text(&c.values[row]))
+                    })
+                    .collect();
+                content.push(RawDataItem::Loop { names, values });
+            }
+        }
+        Ok(RawDataBlock {
+            heading: &self.header,
+            content,
+        })
+    }
+}
+
+impl BinaryCifFile {
+    /// Decode a BinaryCIF file from its MessagePack bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BinaryCifError> {
+        let mut reader = bytes;
+        let root = rmpv::decode::read_value(&mut reader)
+            .map_err(|e| BinaryCifError::MessagePack(e.to_string()))?;
+        Ok(BinaryCifFile {
+            version: str_field(&root, "version")?.to_string(),
+            encoder: str_field(&root, "encoder")?.to_string(),
+            data_blocks: array_field(&root, "dataBlocks")?
+                .iter()
+                .map(BinaryCifBlock::from_value)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Encode to MessagePack bytes, choosing an encoding per column. Fails
+    /// only for a string too long for the 32 bit offsets of the format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BinaryCifError> {
+        let data_blocks = self
+            .data_blocks
+            .iter()
+            .map(BinaryCifBlock::to_value)
+            .collect::<Result<_, _>>()?;
+        let root = map_value(vec![
+            ("version", self.version.as_str().into()),
+            ("encoder", self.encoder.as_str().into()),
+            ("dataBlocks", Value::Array(data_blocks)),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &root).expect("writing to a Vec cannot fail");
+        Ok(bytes)
+    }
+
+    /// Collect the items of a parsed CIF into categories. Save frames, lists
+    /// and tables have no BinaryCIF representation and are rejected.
+    pub fn from_raw_model(model: &RawModel) -> Result<Self, BinaryCifError> {
+        Ok(BinaryCifFile {
+            version: BCIF_VERSION.to_string(),
+            encoder: BCIF_ENCODER.to_string(),
+            data_blocks: model
+                .content
+                .iter()
+                .map(BinaryCifBlock::from_raw_block)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// View the file as the same model the text parser produces. Categories
+    /// with a single row become individual items, all others become loops.
+    /// Fails if a column does not have one value per row.
+    pub fn to_raw_model(&self) -> Result<RawModel<'_>, BinaryCifError> {
+        Ok(RawModel {
+            heading: RAW_MODEL_HEADING,
+            content: self
+                .data_blocks
+                .iter()
+                .map(BinaryCifBlock::to_raw_block)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+pub fn bcif_file(bytes: &[u8]) -> Result<BinaryCifFile, BinaryCifError> {
+    BinaryCifFile::from_bytes(bytes)
+}
+
+pub fn write_bcif(model: &RawModel) -> Result<Vec<u8>, BinaryCifError> {
+    BinaryCifFile::from_raw_model(model)?.to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use rstest::rstest;
+
+    const MIL_101: &str = include_str!("../../cif_chomper/example_data/mil-101.cif");
+    /// Encoded by hand following the spec, using Delta, RunLength,
+    /// IntegerPacking, FixedPoint, IntervalQuantization, StringArray, Float32
+    /// byte arrays and a mask
+    const TEST_ATOMS_BCIF: &[u8] = include_bytes!("data/test_atoms.bcif");
+    /// The contents of `TEST_ATOMS_BCIF` as text
+    const TEST_ATOMS: &str = r"#\#CIF_2.0
+data_1ABC
+_entry.id 1ABC
+loop_
+_atom_site.id
+_atom_site.type_symbol
+_atom_site.Cartn_x
+_atom_site.occupancy
+_atom_site.B_iso_or_equiv
+_atom_site.label_alt_id
+1 C 1.5 1 0 .
+2 N -0.25 0.5 5 A
+3 C 0 0.1 10 ?
+4 O 12.345 0.3 3 .
+";
+
+    fn column_value(name: &str, encoding: Vec<Encoding>, data: Vec<u8>) -> Value {
+        map_value(vec![
+            ("name", name.into()),
+            (
+                "data",
+                map_value(vec![
+                    ("encoding", encodings_to_value(&encoding)),
+                    ("data", Value::Binary(data)),
+                ]),
+            ),
+            ("mask", Value::Nil),
+        ])
+    }
+
+    fn file_value(columns: Vec<Value>, row_count: i64) -> Vec<u8> {
+        let category = map_value(vec![
+            ("name", "_atom_site".into()),
+            ("columns", Value::Array(columns)),
+            ("rowCount", row_count.into()),
+        ]);
+        let block = map_value(vec![
+            ("header", "TEST".into()),
+            ("categories", Value::Array(vec![category])),
+        ]);
+        let root = map_value(vec![
+            ("version", "0.3.0".into()),
+            ("encoder", "test".into()),
+            ("dataBlocks", Value::Array(vec![block])),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &root).unwrap();
+        bytes
+    }
+
+    #[rstest]
+    #[case(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10])]
+    #[case(vec![0, 0, 0, 0, 1, 1, 1, 1])]
+    #[case(vec![-300, 40000, -5, 127, 128, -128, -129, 255, 256])]
+    #[case(vec![])]
+    #[case(vec![i32::MAX as i64, i32::MIN as i64, i32::MAX as i64])]
+    #[case(vec![i32::MAX as i64 - 1, i32::MAX as i64, i32::MAX as i64])]
+    #[case([vec![i32::MIN as i64], vec![i32::MAX as i64; 10]].concat())]
+    #[case(vec![i32::MAX as i64 + 1, i64::MAX, i64::MIN])]
+    fn test_int_round_trip(#[case] values: Vec<i64>) {
+        match encode_ints(&values, Vec::new()) {
+            Some((bytes, encodings)) => assert_eq!(
+                decode_ints(&encodings, bytes, values.len()).unwrap(),
+                values
+            ),
+            None => assert!(!fits_i32(&values)),
+        }
+    }
+
+    #[rstest]
+    #[case("-2147483648 2147483647 -2147483648")]
+    #[case("2147483647 -2147483648 2147483647")]
+    #[case("2147483648 -9223372036854775808 9223372036854775807")]
+    #[case("0.000001 2147.483647 -2147.483647")]
+    fn test_large_int_column_round_trip(#[case] values: &str) {
+        let mut input = String::from("#\\#CIF_2.0\ndata_t\nloop_\n_a.b\n");
+        input.push_str(values);
+        let model = cif2_file(&input).unwrap();
+        let bytes = write_bcif(&model).unwrap();
+        let file = bcif_file(&bytes).unwrap();
+        assert_eq!(file.to_raw_model().unwrap().content, model.content);
+    }
+
+    #[test]
+    fn test_decode_known_file() {
+        let file = bcif_file(TEST_ATOMS_BCIF).unwrap();
+        assert_eq!(file.encoder, "hand written");
+        let model = file.to_raw_model().unwrap();
+        assert_eq!(model.content, cif2_file(TEST_ATOMS).unwrap().content);
+    }
+
+    #[test]
+    fn test_row_count_mismatch() {
+        let ids = column_value(
+            "id",
+            vec![Encoding::ByteArray {
+                data_type: DataType::Uint8,
+            }],
+            vec![1, 2],
+        );
+        let bytes = file_value(vec![ids], 3);
+        assert_eq!(
+            bcif_file(&bytes),
+            Err(BinaryCifError::Length {
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    fn run_length_column(src_size: i64, runs: Vec<u8>) -> Value {
+        let encoding = map_value(vec![
+            ("kind", "RunLength".into()),
+            ("srcType", 3.into()),
+            ("srcSize", src_size.into()),
+        ]);
+        let byte_array = Encoding::ByteArray {
+            data_type: DataType::Uint8,
+        };
+        map_value(vec![
+            ("name", "id".into()),
+            (
+                "data",
+                map_value(vec![
+                    (
+                        "encoding",
+                        Value::Array(vec![encoding, byte_array.to_value()]),
+                    ),
+                    ("data", Value::Binary(runs)),
+                ]),
+            ),
+            ("mask", Value::Nil),
+        ])
+    }
+
+    #[rstest]
+    #[case(run_length_column(-1, vec![7, 2]), 2, BinaryCifError::InvalidField("srcSize"))]
+    #[case(run_length_column(i64::MAX, vec![7, 2]), 2, BinaryCifError::Length { expected: 2, found: i64::MAX as usize })]
+    #[case(run_length_column(2, vec![7, 200]), 2, BinaryCifError::Length { expected: 2, found: 200 })]
+    #[case(run_length_column(2, vec![7, 2]), -1, BinaryCifError::InvalidField("rowCount"))]
+    fn test_invalid_sizes(
+        #[case] column: Value,
+        #[case] row_count: i64,
+        #[case] expected: BinaryCifError,
+    ) {
+        let bytes = file_value(vec![column], row_count);
+        assert_eq!(bcif_file(&bytes), Err(expected));
+    }
+
+    #[rstest]
+    #[case(Encoding::Delta { origin: i64::MAX, src_type: DataType::Int32 }, "data")]
+    #[case(Encoding::IntervalQuantization { min: 0.0, max: 1.0, num_steps: i64::MIN, src_type: DataType::Float32 }, "numSteps")]
+    fn test_invalid_arithmetic(#[case] encoding: Encoding, #[case] field: &'static str) {
+        let byte_array = Encoding::ByteArray {
+            data_type: DataType::Uint8,
+        };
+        let column = column_value("id", vec![encoding, byte_array], vec![1, 2]);
+        let bytes = file_value(vec![column], 2);
+        assert_eq!(bcif_file(&bytes), Err(BinaryCifError::InvalidField(field)));
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(3)]
+    fn test_raw_model_row_count(#[case] row_count: usize) {
+        let column = BinaryCifColumn {
+            name: "id".to_string(),
+            data_name: "_atom_site.id".to_string(),
+            values: vec!["1".to_string(), "2".to_string()],
+        };
+        let file = BinaryCifFile {
+            version: BCIF_VERSION.to_string(),
+            encoder: BCIF_ENCODER.to_string(),
+            data_blocks: vec![BinaryCifBlock {
+                header: "TEST".to_string(),
+                categories: vec![BinaryCifCategory {
+                    name: "_atom_site".to_string(),
+                    row_count,
+                    columns: vec![column],
+                }],
+            }],
+        };
+        assert_eq!(
+            file.to_raw_model(),
+            Err(BinaryCifError::Length {
+                expected: row_count,
+                found: 2
+            })
+        );
+    }
+
+    #[rstest]
+    #[case(MIL_101)]
+    #[case(TEST_ATOMS)]
+    fn test_round_trip_text_model(#[case] input: &str) {
+        let model = cif2_file(input).unwrap();
+        let bytes = write_bcif(&model).unwrap();
+        let file = bcif_file(&bytes).unwrap();
+        assert_eq!(file.to_raw_model().unwrap().content, model.content);
+    }
+
+    #[test]
+    fn test_round_trip_known_file() {
+        let file = bcif_file(TEST_ATOMS_BCIF).unwrap();
+        let model = file.to_raw_model().unwrap();
+        let decoded = bcif_file(&write_bcif(&model).unwrap()).unwrap();
+        assert_eq!(decoded.data_blocks, file.data_blocks);
+    }
+
+    #[test]
+    fn test_writer_encodings() {
+        let mut input = String::from(
+            "#\\#CIF_2.0\ndata_t\nloop_\n_atom_site.id\n_atom_site.x\n_atom_site.label\n_atom_site.note\n",
+        );
+        for i in 1..=12 {
+            input.push_str(&format!("{i} 0.{i:03} C{} .\n", i % 2));
+        }
+        input.push_str("13 0.250 O1 ?\n");
+        let model = cif2_file(&input).unwrap();
+        let file = BinaryCifFile::from_raw_model(&model).unwrap();
+        let root = rmpv::decode::read_value(&mut file.to_bytes().unwrap().as_slice()).unwrap();
+        let category =
+            &array_field(&array_field(&root, "dataBlocks").unwrap()[0], "categories").unwrap()[0];
+        let columns = array_field(category, "columns").unwrap();
+        let first_kind = |column: &Value| {
+            let encoding = array_field(field(column, "data").unwrap(), "encoding").unwrap();
+            str_field(&encoding[0], "kind").unwrap().to_string()
+        };
+        assert_eq!(first_kind(&columns[0]), "Delta");
+        // "0.250" would lose its trailing zero as a real, so stays text
+        assert_eq!(first_kind(&columns[1]), "StringArray");
+        assert_eq!(first_kind(&columns[2]), "StringArray");
+        assert!(opt_field(&columns[3], "mask").is_some());
+        assert_eq!(bcif_file(&file.to_bytes().unwrap()).unwrap(), file);
+    }
+
+    #[test]
+    fn test_save_frames_unsupported() {
+        let input = "#\\#CIF_2.0\ndata_t\nsave_frame\n_a.b c\nsave_\n";
+        let model = cif2_file(input).unwrap();
+        assert_eq!(
+            write_bcif(&model),
+            Err(BinaryCifError::Unsupported("save frames"))
+        );
+    }
+}
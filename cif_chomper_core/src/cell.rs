@@ -0,0 +1,366 @@
+/// Unit cell geometry from the `_cell` items: metric tensors, volume and
+/// conversion between fractional and Cartesian coordinates
+use std::fmt;
+
+use crate::model::{ModelError, single};
+use crate::numeric::Measurement;
+use crate::raw_model::RawDataBlock;
+
+/// Data names of the cell parameters with their aliases, a, b, c, alpha,
+/// beta and gamma in turn
+pub const PARAMETER_NAMES: [[&str; 2]; 6] = [
+    ["_cell.length_a", "_cell_length_a"],
+    ["_cell.length_b", "_cell_length_b"],
+    ["_cell.length_c", "_cell_length_c"],
+    ["_cell.angle_alpha", "_cell_angle_alpha"],
+    ["_cell.angle_beta", "_cell_angle_beta"],
+    ["_cell.angle_gamma", "_cell_angle_gamma"],
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellError {
+    /// A cell parameter that is missing or not a number
+    Model(ModelError),
+    /// Lengths that are not positive, or angles that do not close a cell
+    Degenerate,
+}
+
+impl fmt::Display for CellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellError::Model(e) => write!(f, "{e}"),
+            CellError::Degenerate => write!(f, "the cell parameters do not describe a cell"),
+        }
+    }
+}
+
+impl std::error::Error for CellError {}
+
+impl From<ModelError> for CellError {
+    fn from(e: ModelError) -> Self {
+        CellError::Model(e)
+    }
+}
+
+pub type Matrix = [[f64; 3]; 3];
+
+pub fn invert(m: &Matrix) -> Matrix {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    std::array::from_fn(|i| {
+        std::array::from_fn(|j| {
+            let (a, b) = ((j + 1) % 3, (j + 2) % 3);
+            let (c, d) = ((i + 1) % 3, (i + 2) % 3);
+            (m[a][c] * m[b][d] - m[a][d] * m[b][c]) / det
+        })
+    })
+}
+
+pub fn multiply(m: &Matrix, v: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row.iter().zip(v).map(|(r, x)| r * x).sum())
+}
+
+/// A unit cell with lengths in ångströms and angles in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitCell {
+    /// a, b and c
+    pub lengths: [Measurement; 3],
+    /// alpha, beta and gamma
+    pub angles: [Measurement; 3],
+}
+
+impl UnitCell {
+    pub fn new(lengths: [Measurement; 3], angles: [Measurement; 3]) -> Result<Self, CellError> {
+        let cell = UnitCell { lengths, angles };
+        if lengths.iter().any(|l| l.value <= 0.0)
+            || angles.iter().any(|a| a.value <= 0.0 || a.value >= 180.0)
+            // Allowing for rounding in angles that close a cell exactly
+            || cell.volume_factor() < 1e-9
+        {
+            return Err(CellError::Degenerate);
+        }
+        Ok(cell)
+    }
+
+    /// A cell from parameters without su
+    pub fn from_parameters(
+        a: f64,
+        b: f64,
+        c: f64,
+        alpha: f64,
+        beta: f64,
+        gamma: f64,
+    ) -> Result<Self, CellError> {
+        UnitCell::new(
+            [a, b, c].map(Measurement::exact),
+            [alpha, beta, gamma].map(Measurement::exact),
+        )
+    }
+
+    /// The cell spanned by the vectors a, b and c, in any orientation
+    pub fn from_vectors(vectors: [[f64; 3]; 3]) -> Result<Self, CellError> {
+        let dot = |u: [f64; 3], v: [f64; 3]| -> f64 { (0..3).map(|i| u[i] * v[i]).sum() };
+        let lengths = vectors.map(|v| dot(v, v).sqrt());
+        let angle = |i: usize, j: usize| {
+            (dot(vectors[i], vectors[j]) / (lengths[i] * lengths[j]))
+                .clamp(-1.0, 1.0)
+                .acos()
+                .to_degrees()
+        };
+        let [a, b, c] = lengths;
+        UnitCell::from_parameters(a, b, c, angle(1, 2), angle(0, 2), angle(0, 1))
+    }
+
+    /// Reads `_cell.length_a` to `_cell.angle_gamma`, or their DDL1 aliases.
+    /// Angles default to 90° as in the core dictionary.
+    pub fn from_block(block: &RawDataBlock) -> Result<Self, CellError> {
+        let mut parameters = [Measurement::exact(90.0); 6];
+        for (i, names) in PARAMETER_NAMES.iter().enumerate() {
+            match single(block, names[0], names)? {
+                Some(value) => parameters[i] = value,
+                None if i >= 3 => {}
+                None => {
+                    return Err(ModelError::Missing {
+                        name: names[0].to_string(),
+                    }
+                    .into());
+                }
+            }
+        }
+        let [a, b, c, alpha, beta, gamma] = parameters;
+        UnitCell::new([a, b, c], [alpha, beta, gamma])
+    }
+
+    fn cosines(&self) -> [f64; 3] {
+        self.angles.map(|a| a.value.to_radians().cos())
+    }
+
+    /// `1 - cos²α - cos²β - cos²γ + 2 cosα cosβ cosγ`, the square of the
+    /// volume of a cell with unit lengths
+    fn volume_factor(&self) -> f64 {
+        let [ca, cb, cg] = self.cosines();
+        1.0 - ca * ca - cb * cb - cg * cg + 2.0 * ca * cb * cg
+    }
+
+    /// `G`, the dot products of the cell vectors
+    pub fn metric_tensor(&self) -> Matrix {
+        let [a, b, c] = self.lengths.map(|l| l.value);
+        let [ca, cb, cg] = self.cosines();
+        [
+            [a * a, a * b * cg, a * c * cb],
+            [a * b * cg, b * b, b * c * ca],
+            [a * c * cb, b * c * ca, c * c],
+        ]
+    }
+
+    /// `G*`, the inverse of the metric tensor, whose diagonal holds the
+    /// squared reciprocal lengths
+    pub fn reciprocal_metric_tensor(&self) -> Matrix {
+        invert(&self.metric_tensor())
+    }
+
+    /// The volume in cubic ångströms, with an su propagated from those of
+    /// the parameters, taken as uncorrelated
+    pub fn volume(&self) -> Measurement {
+        let [a, b, c] = self.lengths.map(|l| l.value);
+        let [ca, cb, cg] = self.cosines();
+        let root = self.volume_factor().sqrt();
+        let volume = a * b * c * root;
+        if self
+            .lengths
+            .iter()
+            .chain(&self.angles)
+            .all(|p| p.su.is_none())
+        {
+            return Measurement::exact(volume);
+        }
+        let by_length = self.lengths.map(|l| volume / l.value * l.su_or_zero());
+        // dV/dα = abc sinα (cosα - cosβ cosγ) / root, and so on
+        let by_angle = [(0, cb, cg), (1, ca, cg), (2, ca, cb)].map(|(i, c1, c2)| {
+            let angle = self.angles[i];
+            let radians = angle.value.to_radians();
+            a * b * c * radians.sin() * (radians.cos() - c1 * c2) / root
+                * angle.su_or_zero().to_radians()
+        });
+        let su = by_length
+            .iter()
+            .chain(&by_angle)
+            .map(|d| d * d)
+            .sum::<f64>()
+            .sqrt();
+        Measurement::new(volume, Some(su))
+    }
+
+    /// The matrix taking fractional to Cartesian coordinates, with a along
+    /// x and b in the xy plane
+    pub fn fractional_to_cartesian(&self) -> Matrix {
+        let [a, b, c] = self.lengths.map(|l| l.value);
+        let [ca, cb, cg] = self.cosines();
+        let sg = self.angles[2].value.to_radians().sin();
+        [
+            [a, b * cg, c * cb],
+            [0.0, b * sg, c * (ca - cb * cg) / sg],
+            [0.0, 0.0, c * self.volume_factor().sqrt() / sg],
+        ]
+    }
+
+    /// The inverse of `fractional_to_cartesian`
+    pub fn cartesian_to_fractional(&self) -> Matrix {
+        invert(&self.fractional_to_cartesian())
+    }
+
+    pub fn to_cartesian(&self, fractional: [f64; 3]) -> [f64; 3] {
+        multiply(&self.fractional_to_cartesian(), fractional)
+    }
+
+    pub fn to_fractional(&self, cartesian: [f64; 3]) -> [f64; 3] {
+        multiply(&self.cartesian_to_fractional(), cartesian)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use rstest::rstest;
+
+    const ZIF_8: &str = include_str!("../../cif_chomper/example_data/zif-8.cif");
+
+    fn assert_close(actual: &Matrix, expected: &Matrix) {
+        for (row, expected_row) in actual.iter().zip(expected) {
+            for (x, y) in row.iter().zip(expected_row) {
+                assert!((x - y).abs() < 1e-9, "{actual:?} != {expected:?}");
+            }
+        }
+    }
+
+    fn monoclinic() -> UnitCell {
+        UnitCell::from_parameters(5.0, 6.0, 7.0, 90.0, 110.0, 90.0).unwrap()
+    }
+
+    #[test]
+    fn test_from_block() {
+        let model = cif2_file(ZIF_8).unwrap();
+        let cell = UnitCell::from_block(&model.content[0]).unwrap();
+        assert_eq!(cell.lengths[0].value, 16.8303);
+        assert_eq!(cell.angles[2].value, 90.0);
+        assert_eq!(cell.volume().to_string(), "4767.33(10)");
+    }
+
+    #[rstest]
+    #[case("_cell.length_a 5\n_cell.length_b 5", "_cell.length_c has no value")]
+    #[case(
+        "_cell.length_a 5\n_cell.length_b 5\n_cell.length_c x",
+        "_cell.length_c: x is not a real number"
+    )]
+    #[case(
+        "_cell.length_a 5\n_cell.length_b 5\n_cell.length_c 5\n_cell.angle_alpha 120\n_cell.angle_beta 120\n_cell.angle_gamma 120",
+        "the cell parameters do not describe a cell"
+    )]
+    #[case(
+        "_cell.length_a 5\n_cell.length_b 5\n_cell.length_c -5",
+        "the cell parameters do not describe a cell"
+    )]
+    fn test_from_block_error(#[case] items: &str, #[case] message: &str) {
+        let text = format!("#\\#CIF_2.0\ndata_x\n{items}\n");
+        let model = cif2_file(&text).unwrap();
+        let error = UnitCell::from_block(&model.content[0]).unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+
+    #[test]
+    fn test_metric_tensors() {
+        let cell = monoclinic();
+        let cb = 110f64.to_radians().cos();
+        assert_close(
+            &cell.metric_tensor(),
+            &[
+                [25.0, 0.0, 35.0 * cb],
+                [0.0, 36.0, 0.0],
+                [35.0 * cb, 0.0, 49.0],
+            ],
+        );
+        let sb = 110f64.to_radians().sin();
+        let reciprocal = cell.reciprocal_metric_tensor();
+        // a* = 1 / (a sinβ)
+        assert!((reciprocal[0][0] - 1.0 / (5.0 * sb).powi(2)).abs() < 1e-12);
+        assert!((reciprocal[1][1] - 1.0 / 36.0).abs() < 1e-12);
+    }
+
+    #[rstest]
+    #[case(UnitCell::from_parameters(2.0, 3.0, 4.0, 90.0, 90.0, 90.0).unwrap(), 24.0)]
+    #[case(monoclinic(), 210.0 * 110f64.to_radians().sin())]
+    #[case(
+        UnitCell::from_parameters(3.0, 3.0, 5.0, 90.0, 90.0, 120.0).unwrap(),
+        45.0 * 3f64.sqrt() / 2.0
+    )]
+    fn test_volume(#[case] cell: UnitCell, #[case] volume: f64) {
+        let computed = cell.volume();
+        assert!((computed.value - volume).abs() < 1e-9);
+        assert_eq!(computed.su, None);
+        let det = {
+            let g = cell.metric_tensor();
+            g[0][0] * (g[1][1] * g[2][2] - g[1][2] * g[2][1])
+                - g[0][1] * (g[1][0] * g[2][2] - g[1][2] * g[2][0])
+                + g[0][2] * (g[1][0] * g[2][1] - g[1][1] * g[2][0])
+        };
+        assert!((det.sqrt() - volume).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_volume_su() {
+        let cell = UnitCell::new(
+            [
+                Measurement::new(10.0, Some(0.01)),
+                Measurement::exact(10.0),
+                Measurement::exact(10.0),
+            ],
+            [
+                Measurement::exact(90.0),
+                Measurement::new(100.0, Some(0.1)),
+                Measurement::exact(90.0),
+            ],
+        )
+        .unwrap();
+        let volume = cell.volume();
+        let sin = 100f64.to_radians().sin();
+        let cos = 100f64.to_radians().cos();
+        assert!((volume.value - 1000.0 * sin).abs() < 1e-9);
+        let expected =
+            ((100.0 * sin * 0.01).powi(2) + (1000.0 * cos * 0.1f64.to_radians()).powi(2)).sqrt();
+        assert!((volume.su.unwrap() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_coordinate_transforms() {
+        let cell = UnitCell::from_parameters(5.0, 6.0, 7.0, 80.0, 110.0, 95.0).unwrap();
+        let m = cell.fractional_to_cartesian();
+        // The columns are the cell vectors, so MᵀM is the metric tensor
+        let mtm: Matrix = std::array::from_fn(|i| {
+            std::array::from_fn(|j| (0..3).map(|k| m[k][i] * m[k][j]).sum())
+        });
+        assert_close(&mtm, &cell.metric_tensor());
+        let point = [0.1, -0.4, 0.75];
+        let back = cell.to_fractional(cell.to_cartesian(point));
+        assert!((0..3).all(|i| (back[i] - point[i]).abs() < 1e-12));
+        assert_eq!(cell.to_cartesian([1.0, 0.0, 0.0]), [5.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_from_vectors() {
+        let cell = UnitCell::from_parameters(5.0, 6.0, 7.0, 80.0, 110.0, 95.0).unwrap();
+        let m = cell.fractional_to_cartesian();
+        // Rotated a quarter turn about z, which leaves the parameters alone
+        let vectors = [0, 1, 2].map(|j| [-m[1][j], m[0][j], m[2][j]]);
+        let read = UnitCell::from_vectors(vectors).unwrap();
+        for i in 0..3 {
+            assert!((read.lengths[i].value - cell.lengths[i].value).abs() < 1e-9);
+            assert!((read.angles[i].value - cell.angles[i].value).abs() < 1e-9);
+        }
+        assert_eq!(
+            UnitCell::from_vectors([[1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 0.0, 1.0]]),
+            Err(CellError::Degenerate)
+        );
+    }
+}
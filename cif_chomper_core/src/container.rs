@@ -0,0 +1,181 @@
+/// Values of List, Array and Matrix items as arrays of the shape declared by
+/// `_type.dimension`, for reading items without a generated model
+use crate::dictionary::{ItemDefinition, TypeContainer};
+use crate::model::{CifValue, ModelError};
+use crate::raw_model::{DataValue, RawDataItemContent};
+
+/// Sizes of the nested lists of a value, outermost first: `[]` for a single
+/// value, `[3]` for `[1 2 3]`. `None` if lists at one level differ in shape.
+pub fn shape(content: &RawDataItemContent) -> Option<Vec<usize>> {
+    match content {
+        RawDataItemContent::List(values) => {
+            let mut inner = values.iter().map(shape);
+            let first = inner.next().unwrap_or(Some(Vec::new()))?;
+            if !inner.all(|s| s.as_ref() == Some(&first)) {
+                return None;
+            }
+            Some(std::iter::once(values.len()).chain(first).collect())
+        }
+        _ => Some(Vec::new()),
+    }
+}
+
+/// A rectangular array of values, stored with the last index varying fastest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Array<T> {
+    shape: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T> Array<T> {
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// The value at `index`, e.g. `[1, 2]` for the element in the second row
+    /// and third column of a matrix
+    pub fn get(&self, index: &[usize]) -> Option<&T> {
+        if index.len() != self.shape.len() {
+            return None;
+        }
+        let mut offset = 0;
+        for (i, n) in index.iter().zip(&self.shape) {
+            if i >= n {
+                return None;
+            }
+            offset = offset * n + i;
+        }
+        self.values.get(offset)
+    }
+}
+
+impl<T: CifValue> Array<T> {
+    /// Reads a value of a List, Array or Matrix item, checking its shape
+    /// against the item's `_type.dimension` if it has one
+    pub fn from_item(
+        definition: &ItemDefinition,
+        content: &RawDataItemContent,
+    ) -> Result<Self, ModelError> {
+        let invalid = || ModelError::InvalidValue {
+            name: definition.name.clone(),
+            value: DataValue::from(content).to_string(),
+            expected: format!("a {} of {}", definition.container, T::expected()),
+        };
+        if !matches!(
+            definition.container,
+            TypeContainer::List | TypeContainer::Array | TypeContainer::Matrix
+        ) || !matches!(content, RawDataItemContent::List(_))
+        {
+            return Err(invalid());
+        }
+        let shape = shape(content);
+        if let Some(dimensions) = definition.dimensions()
+            && shape.as_ref() != Some(&dimensions)
+        {
+            return Err(ModelError::Dimension {
+                name: definition.name.clone(),
+                expected: dimensions,
+                found: shape,
+            });
+        }
+        let shape = shape.ok_or_else(|| ModelError::Dimension {
+            name: definition.name.clone(),
+            expected: Vec::new(),
+            found: None,
+        })?;
+        let mut values = Vec::new();
+        flatten(content, &mut values);
+        let values = values
+            .into_iter()
+            .map(T::from_content)
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        Ok(Array { shape, values })
+    }
+}
+
+fn flatten<'c, 'a>(content: &'c RawDataItemContent<'a>, out: &mut Vec<&'c RawDataItemContent<'a>>) {
+    match content {
+        RawDataItemContent::List(values) => {
+            for value in values {
+                flatten(value, out);
+            }
+        }
+        _ => out.push(content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::Dictionary;
+    use crate::parser::cif2_file;
+    use crate::raw_model::RawDataItem;
+    use crate::test_fixtures::dict;
+    use rstest::rstest;
+
+    fn value(text: &str) -> RawDataItemContent<'_> {
+        let model = cif2_file(text).unwrap();
+        match model.content.into_iter().next().unwrap().content.remove(0) {
+            RawDataItem::Data { value, .. } => value,
+            item => panic!("not a data item: {item:?}"),
+        }
+    }
+
+    #[rstest]
+    #[case("1", Some(vec![]))]
+    #[case("[1 2 3]", Some(vec![3]))]
+    #[case("[[1 2] [3 4] [5 6]]", Some(vec![3, 2]))]
+    #[case("[]", Some(vec![0]))]
+    #[case("[[1 2] [3]]", None)]
+    fn test_shape(#[case] text: &str, #[case] expected: Option<Vec<usize>>) {
+        let input = format!("#\\#CIF_2.0\ndata_x\n_a {text}\n");
+        assert_eq!(shape(&value(&input)), expected);
+    }
+
+    #[rstest]
+    fn test_array_from_item(dict: Dictionary) {
+        let fract_xyz = dict.item("_atom_site.fract_xyz").unwrap();
+        let input = "#\\#CIF_2.0\ndata_x\n_a [0.1 0.25(3) 0.5]\n";
+        let array = Array::<f64>::from_item(fract_xyz, &value(input)).unwrap();
+        assert_eq!(array.shape(), [3]);
+        assert_eq!(array.values(), [0.1, 0.25, 0.5]);
+        assert_eq!(array.get(&[1]), Some(&0.25));
+        assert_eq!(array.get(&[3]), None);
+    }
+
+    #[rstest]
+    #[case(
+        "[0.1 0.2]",
+        "_atom_site.fract_xyz has shape [2] but is declared as [3]"
+    )]
+    #[case(
+        "[[0.1] [0.2 0.3] [0.4]]",
+        "_atom_site.fract_xyz is not a rectangular array"
+    )]
+    #[case(
+        "[0.1 x 0.3]",
+        "_atom_site.fract_xyz: [0.1 x 0.3] is not a Matrix of a real number"
+    )]
+    #[case("0.1", "_atom_site.fract_xyz: 0.1 is not a Matrix of a real number")]
+    fn test_array_from_item_error(dict: Dictionary, #[case] text: &str, #[case] message: &str) {
+        let fract_xyz = dict.item("_atom_site.fract_xyz").unwrap();
+        let input = format!("#\\#CIF_2.0\ndata_x\n_a {text}\n");
+        let error = Array::<f64>::from_item(fract_xyz, &value(&input)).unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+
+    #[rstest]
+    fn test_matrix_get(dict: Dictionary) {
+        let input = "#\\#CIF_2.0\ndata_x\n_a [[1 2 3] [4 5 6]]\n";
+        let mut matrix = dict.item("_atom_site.fract_xyz").unwrap().clone();
+        matrix.dimension = Some("[2,3]".to_string());
+        let array = Array::<i64>::from_item(&matrix, &value(input)).unwrap();
+        assert_eq!(array.get(&[1, 2]), Some(&6));
+        assert_eq!(array.get(&[0, 1]), Some(&2));
+    }
+}
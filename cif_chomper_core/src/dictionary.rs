@@ -1,3 +1,864 @@
 /// Compiles the cif core dictionary into a struct
 /// Details on the dictionary format:
-/// https://www.iucr.org/resources/cif/ddl/ddlm/docs/intro
\ No newline at end of file
+/// https://www.iucr.org/resources/cif/ddl/ddlm/docs/intro
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::import::{ImportError, ImportResolver, resolve_imports};
+use crate::parser::{ParseError, cif2_file};
+use crate::raw_model::{RawDataBlock, RawDataItem, RawDataItemContent, RawModel};
+
+#[derive(Debug, PartialEq)]
+pub enum DictionaryError {
+    Parse(ParseError),
+    NoDataBlock,
+    NoHeadCategory,
+    MissingAttribute {
+        frame: String,
+        attribute: &'static str,
+    },
+    InvalidValue {
+        frame: String,
+        attribute: &'static str,
+        value: String,
+    },
+    Import(ImportError),
+}
+
+impl fmt::Display for DictionaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DictionaryError::Parse(e) => write!(f, "failed to parse dictionary: {e}"),
+            DictionaryError::NoDataBlock => write!(f, "dictionary has no data block"),
+            DictionaryError::NoHeadCategory => write!(f, "dictionary has no Head category"),
+            DictionaryError::MissingAttribute { frame, attribute } => {
+                write!(f, "save_{frame} is missing {attribute}")
+            }
+            DictionaryError::InvalidValue {
+                frame,
+                attribute,
+                value,
+            } => write!(f, "save_{frame} has invalid {attribute} '{value}'"),
+            DictionaryError::Import(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DictionaryError {}
+
+impl From<ImportError> for DictionaryError {
+    fn from(e: ImportError) -> Self {
+        DictionaryError::Import(e)
+    }
+}
+
+/// Owned copy of a [`RawDataItemContent`], so definitions can outlive the text
+/// they were parsed from and be combined across files
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    Empty,
+    Str(String),
+    List(Vec<AttributeValue>),
+    Table(Vec<(AttributeValue, AttributeValue)>),
+}
+
+impl AttributeValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            AttributeValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl From<&RawDataItemContent<'_>> for AttributeValue {
+    fn from(value: &RawDataItemContent<'_>) -> Self {
+        match value {
+            RawDataItemContent::Empty => AttributeValue::Empty,
+            RawDataItemContent::Str(s) | RawDataItemContent::Text(s) => {
+                AttributeValue::Str(s.to_string())
+            }
+            RawDataItemContent::List(l) => AttributeValue::List(l.iter().map(Into::into).collect()),
+            RawDataItemContent::Table(t) => {
+                AttributeValue::Table(t.iter().map(|(k, v)| (k.into(), v.into())).collect())
+            }
+        }
+    }
+}
+
+/// The attributes of one save frame (or of the data block itself), before
+/// interpretation. Looped attributes hold one value per row.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Frame {
+    pub name: String,
+    pub attributes: BTreeMap<String, Vec<AttributeValue>>,
+}
+
+impl Frame {
+    pub fn from_raw(name: &str, content: &[RawDataItem]) -> Self {
+        let mut attributes = BTreeMap::new();
+        for item in content {
+            match item {
+                RawDataItem::Data { name, value } => {
+                    attributes.insert(name.to_lowercase(), vec![value.into()]);
+                }
+                RawDataItem::Loop { names, values } => {
+                    for (i, name) in names.iter().enumerate() {
+                        let column = values
+                            .iter()
+                            .skip(i)
+                            .step_by(names.len())
+                            .map(Into::into)
+                            .collect();
+                        attributes.insert(name.to_lowercase(), column);
+                    }
+                }
+                RawDataItem::SaveFrame { .. } => (),
+            }
+        }
+        Frame {
+            name: name.to_string(),
+            attributes,
+        }
+    }
+
+    pub fn values(&self, attribute: &str) -> &[AttributeValue] {
+        self.attributes
+            .get(attribute)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// First value of an attribute, if it is a string
+    pub fn get(&self, attribute: &str) -> Option<&str> {
+        self.values(attribute)
+            .first()
+            .and_then(AttributeValue::as_str)
+    }
+
+    /// All string values of a (possibly looped) attribute
+    pub fn strings(&self, attribute: &str) -> Vec<String> {
+        self.values(attribute)
+            .iter()
+            .filter_map(AttributeValue::as_str)
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn text(&self, attribute: &str) -> Option<String> {
+        self.get(attribute).map(|s| s.trim().to_string())
+    }
+
+    fn parse<T: FromStr>(&self, attribute: &'static str) -> Result<Option<T>, DictionaryError> {
+        self.get(attribute)
+            .map(|v| {
+                v.parse().map_err(|_| DictionaryError::InvalidValue {
+                    frame: self.name.clone(),
+                    attribute,
+                    value: v.to_string(),
+                })
+            })
+            .transpose()
+    }
+
+    fn require(&self, attribute: &'static str) -> Result<&str, DictionaryError> {
+        self.get(attribute)
+            .ok_or_else(|| DictionaryError::MissingAttribute {
+                frame: self.name.clone(),
+                attribute,
+            })
+    }
+}
+
+/// Split a data block into its own attributes and one [`Frame`] per save frame
+pub fn frames_from_block(block: &RawDataBlock) -> (Frame, Vec<Frame>) {
+    let frames = block
+        .content
+        .iter()
+        .filter_map(|item| match item {
+            RawDataItem::SaveFrame { name, content } => Some(Frame::from_raw(name, content)),
+            _ => None,
+        })
+        .collect();
+    (Frame::from_raw(block.heading, &block.content), frames)
+}
+
+/// Enumerations of DDLm attribute states, matched case-insensitively
+macro_rules! ddlm_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            pub const ALL: &[$name] = &[$($name::$variant),+];
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => stringify!($variant)),+
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ();
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $name::ALL
+                    .iter()
+                    .find(|v| v.as_str().eq_ignore_ascii_case(s))
+                    .copied()
+                    .ok_or(())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+ddlm_enum!(
+    /// `_definition.scope`
+    DefinitionScope {
+        Dictionary,
+        Category,
+        Item,
+    }
+);
+ddlm_enum!(
+    /// `_definition.class` of a category
+    CategoryClass {
+        Head,
+        Set,
+        Loop,
+        Functions,
+    }
+);
+ddlm_enum!(
+    /// `_type.purpose`
+    TypePurpose {
+        Import,
+        Method,
+        Audit,
+        Identify,
+        Extend,
+        Describe,
+        Encode,
+        State,
+        Key,
+        Link,
+        Composite,
+        Number,
+        Measurand,
+        Internal,
+    }
+);
+ddlm_enum!(
+    /// `_type.container`
+    TypeContainer {
+        Single,
+        Multiple,
+        List,
+        Array,
+        Matrix,
+        Table,
+        Implied,
+    }
+);
+ddlm_enum!(
+    /// `_type.contents` states that describe a single value
+    ContentType {
+        Text,
+        Code,
+        Name,
+        Tag,
+        Filename,
+        Uri,
+        Date,
+        DateTime,
+        Version,
+        Dimension,
+        Range,
+        Count,
+        Index,
+        Integer,
+        Real,
+        Imag,
+        Complex,
+        Binary,
+        Hexadecimal,
+        Octal,
+        Symop,
+        Implied,
+        ByReference,
+        Word,
+    }
+);
+ddlm_enum!(
+    /// `_method.purpose`
+    MethodPurpose {
+        Evaluation,
+        Definition,
+        Validation,
+    }
+);
+
+/// `_type.contents`, which may also be a composite such as `List(Real,Real)`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeContents {
+    Simple(ContentType),
+    Other(String),
+}
+
+impl TypeContents {
+    pub fn content_type(&self) -> Option<ContentType> {
+        match self {
+            TypeContents::Simple(c) => Some(*c),
+            TypeContents::Other(_) => None,
+        }
+    }
+}
+
+impl FromStr for TypeContents {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse()
+            .map(TypeContents::Simple)
+            .unwrap_or_else(|_| TypeContents::Other(s.to_string())))
+    }
+}
+
+impl fmt::Display for TypeContents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeContents::Simple(c) => c.fmt(f),
+            TypeContents::Other(s) => f.write_str(s),
+        }
+    }
+}
+
+/// `_enumeration.range`, with either bound optional as in `1.:`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl Range {
+    pub fn contains(&self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+impl FromStr for Range {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) = s.split_once(':').ok_or(())?;
+        let bound = |b: &str| match b.trim() {
+            "" => Ok(None),
+            b => b.parse().map(Some).map_err(|_| ()),
+        };
+        Ok(Range {
+            min: bound(min)?,
+            max: bound(max)?,
+        })
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(min) = self.min {
+            write!(f, "{min}")?;
+        }
+        f.write_str(":")?;
+        if let Some(max) = self.max {
+            write!(f, "{max}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumerationState {
+    pub state: String,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Enumeration {
+    pub range: Option<Range>,
+    pub states: Vec<EnumerationState>,
+    pub default: Option<String>,
+    /// Values outside `states` are permitted, from `_enumeration.mandatory No`
+    pub open: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Method {
+    pub purpose: MethodPurpose,
+    pub expression: String,
+}
+
+fn methods_from_frame(frame: &Frame) -> Result<Vec<Method>, DictionaryError> {
+    frame
+        .strings("_method.purpose")
+        .into_iter()
+        .zip(frame.strings("_method.expression"))
+        .map(|(purpose, expression)| {
+            Ok(Method {
+                purpose: purpose.parse().map_err(|_| DictionaryError::InvalidValue {
+                    frame: frame.name.clone(),
+                    attribute: "_method.purpose",
+                    value: purpose.clone(),
+                })?,
+                expression: expression.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryDefinition {
+    /// `_definition.id`, e.g. `ATOM_SITE`
+    pub name: String,
+    pub class: CategoryClass,
+    /// Parent category from `_name.category_id`, absent for the head category
+    pub parent: Option<String>,
+    /// Data names of `_category_key.name`
+    pub keys: Vec<String>,
+    pub description: Option<String>,
+    pub methods: Vec<Method>,
+}
+
+impl CategoryDefinition {
+    fn from_frame(frame: &Frame) -> Result<Self, DictionaryError> {
+        let class =
+            frame
+                .parse("_definition.class")?
+                .ok_or_else(|| DictionaryError::MissingAttribute {
+                    frame: frame.name.clone(),
+                    attribute: "_definition.class",
+                })?;
+        Ok(CategoryDefinition {
+            name: frame.require("_definition.id")?.to_string(),
+            parent: match class {
+                CategoryClass::Head => None,
+                _ => frame.get("_name.category_id").map(str::to_string),
+            },
+            class,
+            keys: frame.strings("_category_key.name"),
+            description: frame.text("_description.text"),
+            methods: methods_from_frame(frame)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemDefinition {
+    /// `_definition.id`, e.g. `_cell.length_a`
+    pub name: String,
+    pub category_id: String,
+    pub object_id: String,
+    pub purpose: TypePurpose,
+    pub container: TypeContainer,
+    pub contents: TypeContents,
+    /// `_type.dimension`, e.g. `[3,3]`
+    pub dimension: Option<String>,
+    pub enumeration: Enumeration,
+    pub units: Option<String>,
+    pub description: Option<String>,
+    pub examples: Vec<String>,
+    /// Other names for this item from `_alias.definition_id`
+    pub aliases: Vec<String>,
+    /// `_name.linked_item_id`, the item this one refers to
+    pub linked_item: Option<String>,
+    pub methods: Vec<Method>,
+}
+
+impl ItemDefinition {
+    fn from_frame(frame: &Frame) -> Result<Self, DictionaryError> {
+        let enumeration = Enumeration {
+            range: frame.parse("_enumeration.range")?,
+            states: frame
+                .strings("_enumeration_set.state")
+                .into_iter()
+                .enumerate()
+                .map(|(i, state)| EnumerationState {
+                    state,
+                    detail: frame
+                        .values("_enumeration_set.detail")
+                        .get(i)
+                        .and_then(AttributeValue::as_str)
+                        .map(|s| s.trim().to_string()),
+                })
+                .collect(),
+            default: frame.get("_enumeration.default").map(str::to_string),
+            open: frame
+                .get("_enumeration.mandatory")
+                .is_some_and(|m| m.eq_ignore_ascii_case("no")),
+        };
+        Ok(ItemDefinition {
+            name: frame.require("_definition.id")?.to_string(),
+            category_id: frame.require("_name.category_id")?.to_string(),
+            object_id: frame.require("_name.object_id")?.to_string(),
+            purpose: frame
+                .parse("_type.purpose")?
+                .unwrap_or(TypePurpose::Describe),
+            container: frame
+                .parse("_type.container")?
+                .unwrap_or(TypeContainer::Single),
+            contents: frame
+                .parse("_type.contents")?
+                .unwrap_or(TypeContents::Simple(ContentType::Text)),
+            dimension: frame.get("_type.dimension").map(str::to_string),
+            enumeration,
+            units: frame.get("_units.code").map(str::to_string),
+            description: frame.text("_description.text"),
+            examples: frame.strings("_description_example.case"),
+            aliases: frame.strings("_alias.definition_id"),
+            linked_item: frame.get("_name.linked_item_id").map(str::to_string),
+            methods: methods_from_frame(frame)?,
+        })
+    }
+
+    /// Sizes from `_type.dimension`, e.g. `[3,3]`; `None` if absent or
+    /// unbounded, as in `[]`
+    pub fn dimensions(&self) -> Option<Vec<usize>> {
+        let dimension = self.dimension.as_deref()?.trim();
+        let inner = dimension.strip_prefix('[')?.strip_suffix(']')?;
+        inner.split(',').map(|d| d.trim().parse().ok()).collect()
+    }
+}
+
+/// A DDLm dictionary, with categories and items keyed by their lower-cased
+/// canonical names
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dictionary {
+    pub title: String,
+    pub version: Option<String>,
+    pub namespace: Option<String>,
+    pub description: Option<String>,
+    /// Name of the category with `_definition.class Head`
+    pub head: String,
+    pub categories: BTreeMap<String, CategoryDefinition>,
+    pub items: BTreeMap<String, ItemDefinition>,
+    /// Lower-cased `_alias.definition_id` to the lower-cased canonical name
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Dictionary {
+    /// Parse the text of a DDLm dictionary, using its first data block
+    pub fn parse(input: &str) -> Result<Self, DictionaryError> {
+        let model = cif2_file(input).map_err(DictionaryError::Parse)?;
+        let block = model.content.first().ok_or(DictionaryError::NoDataBlock)?;
+        Dictionary::from_block(block)
+    }
+
+    /// Parse the text of a DDLm dictionary, resolving its `_import.get`
+    /// attributes through the given resolver
+    pub fn parse_with_imports<R: ImportResolver + ?Sized>(
+        input: &str,
+        resolver: &R,
+    ) -> Result<Self, DictionaryError> {
+        let model = cif2_file(input).map_err(DictionaryError::Parse)?;
+        let block = model.content.first().ok_or(DictionaryError::NoDataBlock)?;
+        let (attributes, frames) = frames_from_block(block);
+        Dictionary::from_frames(&attributes, &resolve_imports(frames, resolver)?)
+    }
+
+    /// Load a dictionary file, and the files it imports, through a resolver
+    pub fn load<R: ImportResolver + ?Sized>(
+        file: &str,
+        resolver: &R,
+    ) -> Result<Self, DictionaryError> {
+        let input = resolver.resolve(file).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => ImportError::MissingFile(file.to_string()),
+            _ => ImportError::Io {
+                file: file.to_string(),
+                reason: e.to_string(),
+            },
+        })?;
+        Dictionary::parse_with_imports(&input, resolver)
+    }
+
+    pub fn from_block(block: &RawDataBlock) -> Result<Self, DictionaryError> {
+        let (attributes, frames) = frames_from_block(block);
+        Dictionary::from_frames(&attributes, &frames)
+    }
+
+    /// Build a dictionary from the data block attributes and its save frames.
+    /// Frames without a `_definition.id`, such as templates, are ignored.
+    pub fn from_frames(attributes: &Frame, frames: &[Frame]) -> Result<Self, DictionaryError> {
+        let mut categories = BTreeMap::new();
+        let mut items = BTreeMap::new();
+        for frame in frames {
+            if frame.get("_definition.id").is_none() {
+                continue;
+            }
+            let scope = frame
+                .parse("_definition.scope")?
+                .unwrap_or(DefinitionScope::Item);
+            match scope {
+                DefinitionScope::Category => {
+                    let category = CategoryDefinition::from_frame(frame)?;
+                    categories.insert(category.name.to_lowercase(), category);
+                }
+                DefinitionScope::Item => {
+                    let item = ItemDefinition::from_frame(frame)?;
+                    items.insert(item.name.to_lowercase(), item);
+                }
+                DefinitionScope::Dictionary => (),
+            }
+        }
+        let head = categories
+            .values()
+            .find(|c: &&CategoryDefinition| c.class == CategoryClass::Head)
+            .ok_or(DictionaryError::NoHeadCategory)?
+            .name
+            .clone();
+        let aliases = items
+            .iter()
+            .flat_map(|(key, item)| {
+                item.aliases
+                    .iter()
+                    .map(move |alias| (alias.to_lowercase(), key.clone()))
+            })
+            .collect();
+        Ok(Dictionary {
+            title: attributes
+                .get("_dictionary.title")
+                .unwrap_or(&attributes.name)
+                .to_string(),
+            version: attributes.get("_dictionary.version").map(str::to_string),
+            namespace: attributes.get("_dictionary.namespace").map(str::to_string),
+            description: attributes.text("_description.text"),
+            head,
+            categories,
+            items,
+            aliases,
+        })
+    }
+
+    pub fn category(&self, name: &str) -> Option<&CategoryDefinition> {
+        self.categories.get(&name.to_lowercase())
+    }
+
+    /// Look up an item by its canonical name or any of its aliases
+    pub fn item(&self, name: &str) -> Option<&ItemDefinition> {
+        let name = name.to_lowercase();
+        self.items.get(&name).or_else(|| {
+            self.aliases
+                .get(&name)
+                .and_then(|canonical| self.items.get(canonical))
+        })
+    }
+
+    /// The `_definition.id` of the item with this name or alias, e.g.
+    /// `_cell.length_a` for `_cell_length_a`
+    pub fn canonical_name(&self, name: &str) -> Option<&str> {
+        self.item(name).map(|item| item.name.as_str())
+    }
+
+    /// Rename every data name in the model that is an alias, or differs only
+    /// in case, to its canonical name. Unknown names are left untouched.
+    pub fn canonicalise<'a>(&'a self, model: &mut RawModel<'a>) {
+        for block in &mut model.content {
+            self.canonicalise_items(&mut block.content);
+        }
+    }
+
+    fn canonicalise_items<'a>(&'a self, items: &mut [RawDataItem<'a>]) {
+        for item in items {
+            match item {
+                RawDataItem::SaveFrame { content, .. } => self.canonicalise_items(content),
+                RawDataItem::Data { name, .. } => {
+                    if let Some(canonical) = self.canonical_name(name) {
+                        *name = canonical;
+                    }
+                }
+                RawDataItem::Loop { names, .. } => {
+                    for name in names {
+                        if let Some(canonical) = self.canonical_name(name) {
+                            *name = canonical;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn head_category(&self) -> &CategoryDefinition {
+        self.category(&self.head)
+            .expect("head category is validated on construction")
+    }
+
+    /// Categories whose `_name.category_id` is the given category
+    pub fn child_categories(&self, category: &str) -> Vec<&CategoryDefinition> {
+        self.categories
+            .values()
+            .filter(|c| {
+                c.parent
+                    .as_deref()
+                    .is_some_and(|p| p.eq_ignore_ascii_case(category))
+            })
+            .collect()
+    }
+
+    pub fn category_items(&self, category: &str) -> Vec<&ItemDefinition> {
+        self.items
+            .values()
+            .filter(|i| i.category_id.eq_ignore_ascii_case(category))
+            .collect()
+    }
+
+    /// The category an item belongs to
+    pub fn item_category(&self, item: &ItemDefinition) -> Option<&CategoryDefinition> {
+        self.category(&item.category_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{TEST_DIC, dict};
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_dictionary_attributes(dict: Dictionary) {
+        assert_eq!(dict.title, "TEST_DIC");
+        assert_eq!(dict.version.as_deref(), Some("1.0.0"));
+        assert_eq!(dict.namespace.as_deref(), Some("TestDic"));
+        assert_eq!(
+            dict.description.as_deref(),
+            Some("A small dictionary for tests.")
+        );
+        assert_eq!(dict.head_category().name, "TEST_HEAD");
+        assert_eq!(dict.head_category().parent, None);
+    }
+
+    #[rstest]
+    fn test_category_definitions(dict: Dictionary) {
+        let atom_site = dict.category("atom_site").unwrap();
+        assert_eq!(atom_site.class, CategoryClass::Loop);
+        assert_eq!(atom_site.keys, vec!["_atom_site.label"]);
+        assert_eq!(atom_site.parent.as_deref(), Some("TEST_HEAD"));
+        let children: Vec<_> = dict
+            .child_categories("test_head")
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(children, vec!["ATOM_SITE", "CELL", "FUNCTION"]);
+        assert_eq!(dict.category_items("ATOM_SITE").len(), 9);
+    }
+
+    #[rstest]
+    fn test_item_definitions(dict: Dictionary) {
+        let length_a = dict.item("_CELL.LENGTH_A").unwrap();
+        assert_eq!(length_a.purpose, TypePurpose::Measurand);
+        assert_eq!(length_a.contents, TypeContents::Simple(ContentType::Real));
+        assert_eq!(length_a.units.as_deref(), Some("angstroms"));
+        assert_eq!(length_a.aliases, vec!["_cell_length_a"]);
+        assert!(length_a.enumeration.range.unwrap().contains(1.5));
+        assert!(!length_a.enumeration.range.unwrap().contains(0.5));
+        assert_eq!(dict.item_category(length_a).unwrap().name, "CELL");
+
+        let adp_type = dict.item("_atom_site.adp_type").unwrap();
+        assert_eq!(adp_type.aliases.len(), 2);
+        assert_eq!(adp_type.enumeration.states[0].state, "Uani");
+        assert_eq!(
+            adp_type.enumeration.states[1].detail.as_deref(),
+            Some("isotropic U")
+        );
+        assert_eq!(adp_type.enumeration.default.as_deref(), Some("Uiso"));
+        assert!(!adp_type.enumeration.open);
+        assert!(dict.item("_atom_site.calc_flag").unwrap().enumeration.open);
+
+        let type_symbol = dict.item("_atom_site.type_symbol").unwrap();
+        assert_eq!(
+            type_symbol.linked_item.as_deref(),
+            Some("_atom_type.symbol")
+        );
+        assert_eq!(type_symbol.container, TypeContainer::Single);
+
+        let fract_xyz = dict.item("_atom_site.fract_xyz").unwrap();
+        assert_eq!(fract_xyz.container, TypeContainer::Matrix);
+        assert_eq!(fract_xyz.dimension.as_deref(), Some("[3]"));
+        assert_eq!(fract_xyz.methods[0].purpose, MethodPurpose::Evaluation);
+        assert!(fract_xyz.methods[0].expression.starts_with("With a as"));
+    }
+
+    #[rstest]
+    #[case("_cell.length_a", Some("_cell.length_a"))]
+    #[case("_CELL_LENGTH_A", Some("_cell.length_a"))]
+    #[case("_atom_site_thermal_displace_type", Some("_atom_site.adp_type"))]
+    #[case("_cell_length_b", None)]
+    fn test_canonical_name(dict: Dictionary, #[case] name: &str, #[case] expected: Option<&str>) {
+        assert_eq!(dict.canonical_name(name), expected);
+    }
+
+    #[rstest]
+    fn test_canonicalise(dict: Dictionary) {
+        let input = "#\\#CIF_2.0\ndata_test\n_cell_length_a 5.0\n_cell_length_b 6.0\n\
+            loop_\n_atom_site.label\n_atom_site_adp_type\nC1 Uiso\n";
+        let mut model = cif2_file(input).unwrap();
+        dict.canonicalise(&mut model);
+        let block = &model.content[0];
+        assert!(matches!(
+            block.content[0],
+            RawDataItem::Data {
+                name: "_cell.length_a",
+                ..
+            }
+        ));
+        assert!(matches!(
+            block.content[1],
+            RawDataItem::Data {
+                name: "_cell_length_b",
+                ..
+            }
+        ));
+        let RawDataItem::Loop { names, .. } = &block.content[2] else {
+            panic!("expected a loop");
+        };
+        assert_eq!(names, &["_atom_site.label", "_atom_site.adp_type"]);
+    }
+
+    #[rstest]
+    #[case("1.:", Some(1.0), None)]
+    #[case("0.0:180.0", Some(0.0), Some(180.0))]
+    #[case(":10", None, Some(10.0))]
+    #[case("-1.0:1.0", Some(-1.0), Some(1.0))]
+    fn test_range(#[case] input: &str, #[case] min: Option<f64>, #[case] max: Option<f64>) {
+        assert_eq!(input.parse(), Ok(Range { min, max }));
+    }
+
+    #[test]
+    fn test_invalid_attribute() {
+        let input = TEST_DIC.replace("_type.purpose                 Key", "_type.purpose Keys");
+        assert_eq!(
+            Dictionary::parse(&input),
+            Err(DictionaryError::InvalidValue {
+                frame: "atom_site.label".to_string(),
+                attribute: "_type.purpose",
+                value: "Keys".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_contents_composite() {
+        assert_eq!(
+            "List(Real,Real)".parse(),
+            Ok(TypeContents::Other("List(Real,Real)".to_string()))
+        );
+        assert_eq!("real".parse(), Ok(TypeContents::Simple(ContentType::Real)));
+    }
+}
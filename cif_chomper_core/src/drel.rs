@@ -0,0 +1,55 @@
+/// dREL, the language of `_method.expression` in DDLm dictionaries
+/// Details on the language:
+/// https://doi.org/10.1021/ci300076w
+pub mod ast;
+pub mod interpreter;
+pub mod parser;
+
+use std::fmt;
+
+pub use interpreter::{Check, Evaluator, Value};
+pub use parser::parse_method;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrelError {
+    Parse {
+        line: usize,
+        column: usize,
+        near: String,
+    },
+    UnknownItem(String),
+    UnknownCategory(String),
+    UnknownVariable(String),
+    UnknownFunction(String),
+    /// The item is absent from the block and has no Evaluation method
+    Missing(String),
+    /// The item depends on its own value
+    Cycle(String),
+    /// A method finished without assigning its result
+    NoResult(String),
+    Type(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for DrelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrelError::Parse { line, column, near } => {
+                write!(f, "dREL syntax error at {line}:{column} near '{near}'")
+            }
+            DrelError::UnknownItem(name) => write!(f, "{name} is not defined"),
+            DrelError::UnknownCategory(name) => write!(f, "category {name} is not defined"),
+            DrelError::UnknownVariable(name) => write!(f, "variable {name} is not assigned"),
+            DrelError::UnknownFunction(name) => write!(f, "function {name} is not defined"),
+            DrelError::Missing(name) => {
+                write!(f, "{name} is missing and has no Evaluation method")
+            }
+            DrelError::Cycle(name) => write!(f, "{name} depends on itself"),
+            DrelError::NoResult(name) => write!(f, "method for {name} assigned no value"),
+            DrelError::Type(reason) => write!(f, "type error: {reason}"),
+            DrelError::Unsupported(what) => write!(f, "unsupported dREL: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for DrelError {}
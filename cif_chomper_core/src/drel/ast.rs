@@ -0,0 +1,107 @@
+//! Syntax tree of a dREL method. Identifiers, data names and function names
+//! are lower-cased by the parser, as dREL is case insensitive.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Integer(i64),
+    Real(f64),
+    Str(String),
+    Bool(bool),
+    /// A local variable, category alias or category name, e.g. `c`
+    Ident(String),
+    /// A full data name, e.g. `_cell.volume`
+    DataName(String),
+    List(Vec<Expr>),
+    Table(Vec<(Expr, Expr)>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `c.length_a`
+    Attribute(Box<Expr>, String),
+    /// `m[i]` or `m[i,j]`
+    Subscript(Box<Expr>, Vec<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    /// `^`, the vector cross product
+    Cross,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    NotIn,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOp {
+    Assign,
+    AddAssign,
+    SubAssign,
+    MulAssign,
+    /// `++=`, append to a list
+    Append,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Assign {
+        target: Expr,
+        op: AssignOp,
+        value: Expr,
+    },
+    Expr(Expr),
+    /// `With c as cell`, binding an alias for the rest of the method
+    With {
+        alias: String,
+        category: String,
+    },
+    If {
+        branches: Vec<(Expr, Vec<Stmt>)>,
+        otherwise: Vec<Stmt>,
+    },
+    For {
+        var: String,
+        iter: Expr,
+        body: Vec<Stmt>,
+    },
+    /// `Loop t as atom_type : i { }`, iterating over the packets of a category
+    Loop {
+        alias: String,
+        category: String,
+        index: Option<String>,
+        body: Vec<Stmt>,
+    },
+    Do {
+        var: String,
+        start: Expr,
+        end: Expr,
+        step: Option<Expr>,
+        body: Vec<Stmt>,
+    },
+    Repeat(Vec<Stmt>),
+    Break,
+    Next,
+    /// A function definition; the result is assigned to the function name
+    Function {
+        name: String,
+        params: Vec<String>,
+        body: Vec<Stmt>,
+    },
+}
@@ -0,0 +1,1312 @@
+/// Evaluates dREL Evaluation methods against the items of a data block,
+/// deriving missing items on demand from the items they depend on
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use super::DrelError;
+use super::ast::{AssignOp, BinaryOp, Expr, Stmt, UnaryOp};
+use super::parser::parse_method;
+use crate::dictionary::{CategoryClass, ContentType, Dictionary, ItemDefinition, MethodPurpose};
+use crate::numeric::Measurement;
+use crate::raw_model::{RawDataBlock, RawDataItem, RawDataItemContent};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Real(f64),
+    Str(String),
+    List(Vec<Value>),
+    Table(BTreeMap<String, Value>),
+    /// A whole category, e.g. the target of `Loop` or a keyed lookup
+    Category(String),
+    /// One packet of a category; Set categories have the single packet 0
+    Packet {
+        category: String,
+        row: usize,
+    },
+}
+
+impl Value {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Real(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    fn truthy(&self) -> Result<bool, DrelError> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            Value::Integer(i) => Ok(*i != 0),
+            Value::Null => Ok(false),
+            other => Err(DrelError::Type(format!("{other:?} is not a condition"))),
+        }
+    }
+
+    fn key(&self) -> String {
+        match self {
+            Value::Str(s) => s.clone(),
+            Value::Integer(i) => i.to_string(),
+            Value::Real(r) => r.to_string(),
+            other => format!("{other:?}"),
+        }
+    }
+}
+
+/// Comparison of a value supplied in the block with the one its method derives
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub row: usize,
+    pub supplied: Value,
+    pub derived: Value,
+    /// Whether the values agree within three su, or within the last digit
+    /// given when the supplied value has no su
+    pub consistent: bool,
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Next,
+}
+
+struct Function {
+    params: Vec<String>,
+    body: Vec<Stmt>,
+}
+
+struct Context<'a> {
+    /// Item whose method is running, absent inside functions
+    target: Option<&'a ItemDefinition>,
+    /// Function whose body is running, whose name receives the result
+    function: Option<String>,
+    /// Lower-cased category and packet the method is evaluated for
+    category: Option<String>,
+    row: usize,
+    vars: HashMap<String, Value>,
+    result: Option<Value>,
+}
+
+pub struct Evaluator<'a> {
+    dict: &'a Dictionary,
+    /// Lower-cased canonical name to the item values, one per packet
+    columns: HashMap<String, Vec<&'a RawDataItemContent<'a>>>,
+    /// Lower-cased category name to its number of packets in the block
+    packets: HashMap<String, usize>,
+    methods: RefCell<HashMap<String, Rc<Vec<Stmt>>>>,
+    functions: RefCell<Option<HashMap<String, Rc<Function>>>>,
+    derived: RefCell<HashMap<(String, usize), Value>>,
+    in_progress: RefCell<Vec<(String, usize)>>,
+}
+
+fn is_null(raw: &RawDataItemContent) -> bool {
+    matches!(
+        raw,
+        RawDataItemContent::Empty | RawDataItemContent::Str("?" | ".")
+    )
+}
+
+/// Convert a value from the block according to the item's `_type.contents`
+fn convert(definition: &ItemDefinition, raw: &RawDataItemContent) -> Value {
+    match raw {
+        _ if is_null(raw) => Value::Null,
+        RawDataItemContent::Str(s) | RawDataItemContent::Text(s) => {
+            let content_type = definition.contents.content_type();
+            let integral = matches!(
+                content_type,
+                Some(ContentType::Integer | ContentType::Count | ContentType::Index)
+            );
+            let numeric = integral || content_type == Some(ContentType::Real);
+            match s.parse::<Measurement>() {
+                Ok(m) if integral && m.value.fract() == 0.0 => Value::Integer(m.value as i64),
+                Ok(m) if numeric => Value::Real(m.value),
+                _ => Value::Str(s.to_string()),
+            }
+        }
+        RawDataItemContent::List(items) => {
+            Value::List(items.iter().map(|i| convert(definition, i)).collect())
+        }
+        RawDataItemContent::Table(entries) => Value::Table(
+            entries
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.as_str().unwrap_or_default().to_string(),
+                        convert(definition, v),
+                    )
+                })
+                .collect(),
+        ),
+        RawDataItemContent::Empty => Value::Null,
+    }
+}
+
+/// Whether a supplied value agrees with a derived one, see [`Check`]
+fn agrees(raw: &RawDataItemContent, derived: &Value) -> bool {
+    match (raw, derived) {
+        (RawDataItemContent::Str(s) | RawDataItemContent::Text(s), Value::Str(d)) => {
+            s.eq_ignore_ascii_case(d)
+        }
+        (RawDataItemContent::Str(s) | RawDataItemContent::Text(s), d) => {
+            let (Ok(supplied), Some(d)) = (s.parse::<Measurement>(), d.as_f64()) else {
+                return false;
+            };
+            let tolerance = match supplied.su {
+                Some(su) => 3.0 * su,
+                // Appending an su of 1 gives the unit of the last digit
+                None => format!("{s}(1)")
+                    .parse::<Measurement>()
+                    .map_or(0.0, |m| m.su_or_zero()),
+            };
+            (supplied.value - d).abs() <= tolerance + 1e-9 * d.abs()
+        }
+        (RawDataItemContent::List(raws), Value::List(values)) => {
+            raws.len() == values.len() && raws.iter().zip(values).all(|(r, v)| agrees(r, v))
+        }
+        _ => false,
+    }
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(dict: &'a Dictionary, block: &'a RawDataBlock<'a>) -> Self {
+        let mut columns: HashMap<String, Vec<&'a RawDataItemContent<'a>>> = HashMap::new();
+        let key = |name: &str| dict.canonical_name(name).unwrap_or(name).to_lowercase();
+        for item in &block.content {
+            match item {
+                RawDataItem::Data { name, value } => {
+                    columns.insert(key(name), vec![value]);
+                }
+                RawDataItem::Loop { names, values } => {
+                    for (i, name) in names.iter().enumerate() {
+                        let column = values.iter().skip(i).step_by(names.len()).collect();
+                        columns.insert(key(name), column);
+                    }
+                }
+                RawDataItem::SaveFrame { .. } => (),
+            }
+        }
+        let mut packets = HashMap::new();
+        for (name, column) in &columns {
+            if let Some(item) = dict.item(name) {
+                let count = packets.entry(item.category_id.to_lowercase()).or_insert(0);
+                *count = column.len().max(*count);
+            }
+        }
+        Evaluator {
+            dict,
+            columns,
+            packets,
+            methods: RefCell::new(HashMap::new()),
+            functions: RefCell::new(None),
+            derived: RefCell::new(HashMap::new()),
+            in_progress: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Number of packets of a category in the block; Set categories always
+    /// have one, so their items can be derived even when none are given
+    pub fn packet_count(&self, category: &str) -> usize {
+        match self.dict.category(category) {
+            Some(c) if c.class == CategoryClass::Set => 1,
+            _ => self
+                .packets
+                .get(&category.to_lowercase())
+                .copied()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Value of an item in a Set category, from the block or its method
+    pub fn evaluate(&self, name: &str) -> Result<Value, DrelError> {
+        self.value(name, 0)
+    }
+
+    /// Value of an item for one packet, from the block if given there,
+    /// otherwise from its Evaluation method
+    pub fn value(&self, name: &str, row: usize) -> Result<Value, DrelError> {
+        let definition = self.definition(name)?;
+        match self.supplied(definition, row) {
+            Some(raw) => Ok(convert(definition, raw)),
+            None => self.run_method(definition, row),
+        }
+    }
+
+    /// Value of an item for one packet from its Evaluation method, ignoring
+    /// any value given in the block
+    pub fn derive(&self, name: &str, row: usize) -> Result<Value, DrelError> {
+        self.run_method(self.definition(name)?, row)
+    }
+
+    /// Compare the value given in the block with the derived one. `None` if
+    /// the item is not given or has no Evaluation method.
+    pub fn check(&self, name: &str, row: usize) -> Result<Option<Check>, DrelError> {
+        let definition = self.definition(name)?;
+        let Some(raw) = self.supplied(definition, row) else {
+            return Ok(None);
+        };
+        if evaluation_method(definition).is_none() {
+            return Ok(None);
+        }
+        let derived = self.run_method(definition, row)?;
+        Ok(Some(Check {
+            name: definition.name.clone(),
+            row,
+            supplied: convert(definition, raw),
+            consistent: agrees(raw, &derived),
+            derived,
+        }))
+    }
+
+    fn definition(&self, name: &str) -> Result<&'a ItemDefinition, DrelError> {
+        self.dict
+            .item(name)
+            .ok_or_else(|| DrelError::UnknownItem(name.to_string()))
+    }
+
+    fn supplied(
+        &self,
+        definition: &ItemDefinition,
+        row: usize,
+    ) -> Option<&'a RawDataItemContent<'a>> {
+        self.columns
+            .get(&definition.name.to_lowercase())
+            .and_then(|column| column.get(row).copied())
+            .filter(|raw| !is_null(raw))
+    }
+
+    fn method(&self, definition: &ItemDefinition) -> Result<Rc<Vec<Stmt>>, DrelError> {
+        let key = definition.name.to_lowercase();
+        if let Some(method) = self.methods.borrow().get(&key) {
+            return Ok(method.clone());
+        }
+        let expression = evaluation_method(definition)
+            .ok_or_else(|| DrelError::Missing(definition.name.clone()))?;
+        let method = Rc::new(parse_method(expression)?);
+        self.methods.borrow_mut().insert(key, method.clone());
+        Ok(method)
+    }
+
+    fn run_method(&self, definition: &'a ItemDefinition, row: usize) -> Result<Value, DrelError> {
+        let key = (definition.name.to_lowercase(), row);
+        if let Some(value) = self.derived.borrow().get(&key) {
+            return Ok(value.clone());
+        }
+        if self.in_progress.borrow().contains(&key) {
+            return Err(DrelError::Cycle(definition.name.clone()));
+        }
+        let method = self.method(definition)?;
+        let mut ctx = Context {
+            target: Some(definition),
+            function: None,
+            category: Some(definition.category_id.to_lowercase()),
+            row,
+            vars: HashMap::new(),
+            result: None,
+        };
+        self.in_progress.borrow_mut().push(key.clone());
+        let outcome = self.exec_block(&method, &mut ctx);
+        self.in_progress.borrow_mut().pop();
+        outcome?;
+        let value = ctx
+            .result
+            .ok_or_else(|| DrelError::NoResult(definition.name.clone()))?;
+        self.derived.borrow_mut().insert(key, value.clone());
+        Ok(value)
+    }
+
+    fn exec_block(&self, stmts: &[Stmt], ctx: &mut Context<'a>) -> Result<Flow, DrelError> {
+        for stmt in stmts {
+            match self.exec(stmt, ctx)? {
+                Flow::Normal => (),
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Run a loop body, returning whether the loop should stop
+    fn exec_body(&self, body: &[Stmt], ctx: &mut Context<'a>) -> Result<bool, DrelError> {
+        Ok(matches!(self.exec_block(body, ctx)?, Flow::Break))
+    }
+
+    fn exec(&self, stmt: &Stmt, ctx: &mut Context<'a>) -> Result<Flow, DrelError> {
+        match stmt {
+            Stmt::Assign { target, op, value } => {
+                let value = self.eval(value, ctx)?;
+                self.assign(target, *op, value, ctx)?;
+            }
+            Stmt::Expr(expr) => {
+                self.eval(expr, ctx)?;
+            }
+            Stmt::With { alias, category } => {
+                let binding = self.category_binding(category, ctx)?;
+                ctx.vars.insert(alias.clone(), binding);
+            }
+            Stmt::If {
+                branches,
+                otherwise,
+            } => {
+                for (condition, body) in branches {
+                    if self.eval(condition, ctx)?.truthy()? {
+                        return self.exec_block(body, ctx);
+                    }
+                }
+                return self.exec_block(otherwise, ctx);
+            }
+            Stmt::For { var, iter, body } => {
+                let items = match self.eval(iter, ctx)? {
+                    Value::List(items) => items,
+                    Value::Table(table) => table.into_keys().map(Value::Str).collect(),
+                    Value::Str(s) => s.chars().map(|c| Value::Str(c.to_string())).collect(),
+                    other => return Err(DrelError::Type(format!("cannot iterate over {other:?}"))),
+                };
+                for item in items {
+                    ctx.vars.insert(var.clone(), item);
+                    if self.exec_body(body, ctx)? {
+                        break;
+                    }
+                }
+            }
+            Stmt::Loop {
+                alias,
+                category,
+                index,
+                body,
+            } => {
+                let definition = self
+                    .dict
+                    .category(category)
+                    .ok_or_else(|| DrelError::UnknownCategory(category.clone()))?;
+                for row in 0..self.packet_count(category) {
+                    let packet = Value::Packet {
+                        category: definition.name.to_lowercase(),
+                        row,
+                    };
+                    ctx.vars.insert(alias.clone(), packet);
+                    if let Some(index) = index {
+                        ctx.vars.insert(index.clone(), Value::Integer(row as i64));
+                    }
+                    if self.exec_body(body, ctx)? {
+                        break;
+                    }
+                }
+            }
+            Stmt::Do {
+                var,
+                start,
+                end,
+                step,
+                body,
+            } => {
+                let integer = |e: &Expr, ctx: &mut Context<'a>| match self.eval(e, ctx)? {
+                    Value::Integer(i) => Ok(i),
+                    other => Err(DrelError::Type(format!(
+                        "Do bound {other:?} is not an integer"
+                    ))),
+                };
+                let (start, end) = (integer(start, ctx)?, integer(end, ctx)?);
+                let step = match step {
+                    Some(step) => integer(step, ctx)?,
+                    None => 1,
+                };
+                if step == 0 {
+                    return Err(DrelError::Type("Do step is zero".to_string()));
+                }
+                let mut i = start;
+                while (step > 0 && i <= end) || (step < 0 && i >= end) {
+                    ctx.vars.insert(var.clone(), Value::Integer(i));
+                    if self.exec_body(body, ctx)? {
+                        break;
+                    }
+                    // A bound at the end of the integer range ends the loop
+                    let Some(next) = i.checked_add(step) else {
+                        break;
+                    };
+                    i = next;
+                }
+            }
+            Stmt::Repeat(body) => while !self.exec_body(body, ctx)? {},
+            Stmt::Break => return Ok(Flow::Break),
+            Stmt::Next => return Ok(Flow::Next),
+            Stmt::Function { name, params, body } => {
+                self.load_functions();
+                if let Some(functions) = self.functions.borrow_mut().as_mut() {
+                    functions.insert(
+                        name.clone(),
+                        Rc::new(Function {
+                            params: params.clone(),
+                            body: body.clone(),
+                        }),
+                    );
+                }
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Whether an assignment target is the item or function being evaluated
+    fn is_result(&self, target: &Expr, ctx: &Context) -> bool {
+        match (target, ctx.target) {
+            (Expr::Ident(name), _) => ctx.function.as_ref() == Some(name),
+            (Expr::DataName(name), Some(item)) => self
+                .dict
+                .canonical_name(name)
+                .is_some_and(|c| c.eq_ignore_ascii_case(&item.name)),
+            (Expr::Attribute(base, attr), Some(item)) => {
+                let Expr::Ident(alias) = base.as_ref() else {
+                    return false;
+                };
+                let category = match ctx.vars.get(alias) {
+                    Some(Value::Packet { category, .. } | Value::Category(category)) => {
+                        category.as_str()
+                    }
+                    Some(_) => return false,
+                    None => alias.as_str(),
+                };
+                category.eq_ignore_ascii_case(&item.category_id)
+                    && attr.eq_ignore_ascii_case(&item.object_id)
+            }
+            _ => false,
+        }
+    }
+
+    fn assign(
+        &self,
+        target: &Expr,
+        op: AssignOp,
+        value: Value,
+        ctx: &mut Context<'a>,
+    ) -> Result<(), DrelError> {
+        let combine = |current: Option<Value>| -> Result<Value, DrelError> {
+            let current =
+                || current.ok_or_else(|| DrelError::UnknownVariable(format!("{target:?}")));
+            match op {
+                AssignOp::Assign => Ok(value),
+                AssignOp::AddAssign => binary(BinaryOp::Add, current()?, value),
+                AssignOp::SubAssign => binary(BinaryOp::Sub, current()?, value),
+                AssignOp::MulAssign => binary(BinaryOp::Mul, current()?, value),
+                AssignOp::Append => match current()? {
+                    Value::List(mut items) => {
+                        items.push(value);
+                        Ok(Value::List(items))
+                    }
+                    other => Err(DrelError::Type(format!("cannot append to {other:?}"))),
+                },
+            }
+        };
+        if self.is_result(target, ctx) {
+            ctx.result = Some(combine(ctx.result.take())?);
+            return Ok(());
+        }
+        match target {
+            Expr::Ident(name) => {
+                let value = combine(ctx.vars.remove(name))?;
+                ctx.vars.insert(name.clone(), value);
+            }
+            Expr::Subscript(base, index) => {
+                let Expr::Ident(name) = base.as_ref() else {
+                    return Err(DrelError::Unsupported(format!("assignment to {target:?}")));
+                };
+                let index: Vec<_> = index
+                    .iter()
+                    .map(|i| self.eval(i, ctx))
+                    .collect::<Result<_, _>>()?;
+                let mut container = ctx
+                    .vars
+                    .remove(name)
+                    .ok_or_else(|| DrelError::UnknownVariable(name.clone()))?;
+                let slot = element_mut(&mut container, &index);
+                let outcome = slot.and_then(|slot| {
+                    *slot = combine(Some(slot.clone()))?;
+                    Ok(())
+                });
+                ctx.vars.insert(name.clone(), container);
+                outcome?;
+            }
+            _ => return Err(DrelError::Unsupported(format!("assignment to {target:?}"))),
+        }
+        Ok(())
+    }
+
+    fn category_binding(&self, category: &str, ctx: &Context) -> Result<Value, DrelError> {
+        let definition = self
+            .dict
+            .category(category)
+            .ok_or_else(|| DrelError::UnknownCategory(category.to_string()))?;
+        let name = definition.name.to_lowercase();
+        Ok(match definition.class {
+            CategoryClass::Loop if ctx.category.as_ref() == Some(&name) => Value::Packet {
+                category: name,
+                row: ctx.row,
+            },
+            CategoryClass::Loop => Value::Category(name),
+            _ => Value::Packet {
+                category: name,
+                row: 0,
+            },
+        })
+    }
+
+    /// Value of `_category.object` for the packet, or every packet
+    fn item_of(
+        &self,
+        category: &str,
+        object: &str,
+        row: Option<usize>,
+    ) -> Result<Value, DrelError> {
+        let name = format!("_{category}.{object}");
+        match row {
+            Some(row) => self.value(&name, row),
+            None => (0..self.packet_count(category))
+                .map(|row| self.value(&name, row))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+        }
+    }
+
+    fn eval(&self, expr: &Expr, ctx: &mut Context<'a>) -> Result<Value, DrelError> {
+        Ok(match expr {
+            Expr::Integer(i) => Value::Integer(*i),
+            Expr::Real(r) => Value::Real(*r),
+            Expr::Str(s) => Value::Str(s.clone()),
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::Ident(name) => match ctx.vars.get(name) {
+                Some(value) => value.clone(),
+                None if self.dict.category(name).is_some() => self.category_binding(name, ctx)?,
+                None if name == "pi" => Value::Real(PI),
+                None => return Err(DrelError::UnknownVariable(name.clone())),
+            },
+            Expr::DataName(name) => {
+                let definition = self.definition(name)?;
+                let category = definition.category_id.to_lowercase();
+                let row = match self.dict.category(&category).map(|c| c.class) {
+                    Some(CategoryClass::Loop) if ctx.category.as_ref() == Some(&category) => {
+                        Some(ctx.row)
+                    }
+                    Some(CategoryClass::Loop) => None,
+                    _ => Some(0),
+                };
+                self.item_of(&category, &definition.object_id, row)?
+            }
+            Expr::List(items) => Value::List(
+                items
+                    .iter()
+                    .map(|i| self.eval(i, ctx))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expr::Table(entries) => {
+                let mut table = BTreeMap::new();
+                for (key, value) in entries {
+                    let key = self.eval(key, ctx)?.key();
+                    table.insert(key, self.eval(value, ctx)?);
+                }
+                Value::Table(table)
+            }
+            Expr::Unary(UnaryOp::Neg, operand) => {
+                binary(BinaryOp::Mul, Value::Integer(-1), self.eval(operand, ctx)?)?
+            }
+            Expr::Unary(UnaryOp::Not, operand) => Value::Bool(!self.eval(operand, ctx)?.truthy()?),
+            Expr::Binary(BinaryOp::And, lhs, rhs) => {
+                Value::Bool(self.eval(lhs, ctx)?.truthy()? && self.eval(rhs, ctx)?.truthy()?)
+            }
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => {
+                Value::Bool(self.eval(lhs, ctx)?.truthy()? || self.eval(rhs, ctx)?.truthy()?)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs, ctx)?;
+                binary(*op, lhs, self.eval(rhs, ctx)?)?
+            }
+            Expr::Attribute(base, attr) => match self.eval(base, ctx)? {
+                Value::Packet { category, row } => self.item_of(&category, attr, Some(row))?,
+                Value::Category(category) => self.item_of(&category, attr, None)?,
+                Value::Table(table) => table.get(attr).cloned().unwrap_or(Value::Null),
+                other => {
+                    return Err(DrelError::Type(format!(
+                        "{other:?} has no attribute {attr}"
+                    )));
+                }
+            },
+            Expr::Subscript(base, index) => {
+                let base = self.eval(base, ctx)?;
+                let index: Vec<_> = index
+                    .iter()
+                    .map(|i| self.eval(i, ctx))
+                    .collect::<Result<_, _>>()?;
+                match base {
+                    Value::Category(category) => self.keyed_packet(&category, &index)?,
+                    mut container => element_mut(&mut container, &index)?.clone(),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args: Vec<_> = args
+                    .iter()
+                    .map(|a| self.eval(a, ctx))
+                    .collect::<Result<_, _>>()?;
+                match builtin(name, &args) {
+                    Some(result) => result?,
+                    None => self.call(name, args, ctx)?,
+                }
+            }
+        })
+    }
+
+    /// The packet of a Loop category whose key has the given value, as in
+    /// `atom_type[a.type_symbol]`
+    fn keyed_packet(&self, category: &str, index: &[Value]) -> Result<Value, DrelError> {
+        let definition = self
+            .dict
+            .category(category)
+            .ok_or_else(|| DrelError::UnknownCategory(category.to_string()))?;
+        let [key] = definition.keys.as_slice() else {
+            return Err(DrelError::Unsupported(format!(
+                "lookup in {category} without a single key"
+            )));
+        };
+        let [wanted] = index else {
+            return Err(DrelError::Type(format!("{category} takes one key value")));
+        };
+        for row in 0..self.packet_count(category) {
+            if values_equal(&self.value(key, row)?, wanted) {
+                return Ok(Value::Packet {
+                    category: category.to_string(),
+                    row,
+                });
+            }
+        }
+        Err(DrelError::Type(format!(
+            "no {category} packet with key {wanted:?}"
+        )))
+    }
+
+    /// Register the functions defined by methods in Functions categories
+    fn load_functions(&self) {
+        if self.functions.borrow().is_some() {
+            return;
+        }
+        let mut functions = HashMap::new();
+        for item in self.dict.items.values() {
+            let is_function = self
+                .dict
+                .item_category(item)
+                .is_some_and(|c| c.class == CategoryClass::Functions);
+            if !is_function {
+                continue;
+            }
+            // Functions this interpreter cannot parse are reported when called
+            let Some(Ok(stmts)) = evaluation_method(item).map(parse_method) else {
+                continue;
+            };
+            for stmt in stmts {
+                if let Stmt::Function { name, params, body } = stmt {
+                    functions.insert(name, Rc::new(Function { params, body }));
+                }
+            }
+        }
+        *self.functions.borrow_mut() = Some(functions);
+    }
+
+    fn call(&self, name: &str, args: Vec<Value>, ctx: &Context) -> Result<Value, DrelError> {
+        self.load_functions();
+        let function = self
+            .functions
+            .borrow()
+            .as_ref()
+            .and_then(|functions| functions.get(name).cloned())
+            .ok_or_else(|| DrelError::UnknownFunction(name.to_string()))?;
+        if function.params.len() != args.len() {
+            return Err(DrelError::Type(format!(
+                "{name} takes {} arguments, not {}",
+                function.params.len(),
+                args.len()
+            )));
+        }
+        let mut call_ctx = Context {
+            target: None,
+            function: Some(name.to_string()),
+            category: ctx.category.clone(),
+            row: ctx.row,
+            vars: function.params.iter().cloned().zip(args).collect(),
+            result: None,
+        };
+        self.exec_block(&function.body, &mut call_ctx)?;
+        call_ctx
+            .result
+            .ok_or_else(|| DrelError::NoResult(name.to_string()))
+    }
+}
+
+fn evaluation_method(definition: &ItemDefinition) -> Option<&str> {
+    definition
+        .methods
+        .iter()
+        .find(|m| m.purpose == MethodPurpose::Evaluation)
+        .map(|m| m.expression.as_str())
+}
+
+fn element_mut<'v>(container: &'v mut Value, index: &[Value]) -> Result<&'v mut Value, DrelError> {
+    let Some((first, rest)) = index.split_first() else {
+        return Ok(container);
+    };
+    let element = match (container, first) {
+        (Value::List(items), Value::Integer(i)) => {
+            let len = items.len();
+            usize::try_from(*i)
+                .ok()
+                .and_then(|i| items.get_mut(i))
+                .ok_or_else(|| {
+                    DrelError::Type(format!("index {i} out of range for length {len}"))
+                })?
+        }
+        (Value::Table(table), key) => table.entry(key.key()).or_insert(Value::Null),
+        (container, index) => {
+            return Err(DrelError::Type(format!(
+                "cannot index {container:?} with {index:?}"
+            )));
+        }
+    };
+    element_mut(element, rest)
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn real(v: &Value, what: &str) -> Result<f64, DrelError> {
+    v.as_f64()
+        .ok_or_else(|| DrelError::Type(format!("{what} needs a number, not {v:?}")))
+}
+
+fn is_matrix(items: &[Value]) -> bool {
+    !items.is_empty() && items.iter().all(|row| matches!(row, Value::List(_)))
+}
+
+fn rows(matrix: &[Value]) -> Vec<&[Value]> {
+    matrix
+        .iter()
+        .map(|row| match row {
+            Value::List(row) => row.as_slice(),
+            _ => &[],
+        })
+        .collect()
+}
+
+fn dot(a: &[Value], b: &[Value]) -> Result<Value, DrelError> {
+    if a.len() != b.len() {
+        return Err(DrelError::Type(format!(
+            "vector lengths {} and {} differ",
+            a.len(),
+            b.len()
+        )));
+    }
+    a.iter().zip(b).try_fold(Value::Integer(0), |sum, (x, y)| {
+        binary(
+            BinaryOp::Add,
+            sum,
+            binary(BinaryOp::Mul, x.clone(), y.clone())?,
+        )
+    })
+}
+
+fn transpose(matrix: &[Value]) -> Vec<Vec<Value>> {
+    let rows = rows(matrix);
+    let width = rows.first().map_or(0, |r| r.len());
+    (0..width)
+        .map(|j| {
+            rows.iter()
+                .map(|r| r.get(j).cloned().unwrap_or(Value::Null))
+                .collect()
+        })
+        .collect()
+}
+
+/// Products of vectors and matrices: matrix products, or the dot product of
+/// two vectors
+fn multiply_lists(a: Vec<Value>, b: Vec<Value>) -> Result<Value, DrelError> {
+    match (is_matrix(&a), is_matrix(&b)) {
+        (true, true) => {
+            let columns = transpose(&b);
+            rows(&a)
+                .into_iter()
+                .map(|row| {
+                    columns
+                        .iter()
+                        .map(|column| dot(row, column))
+                        .collect::<Result<_, _>>()
+                        .map(Value::List)
+                })
+                .collect::<Result<_, _>>()
+                .map(Value::List)
+        }
+        (true, false) => rows(&a)
+            .into_iter()
+            .map(|row| dot(row, &b))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        (false, true) => transpose(&b)
+            .iter()
+            .map(|column| dot(&a, column))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        (false, false) => dot(&a, &b),
+    }
+}
+
+fn cross(a: &[Value], b: &[Value]) -> Result<Value, DrelError> {
+    let (Ok(a), Ok(b)) = (
+        a.iter()
+            .map(|v| real(v, "^"))
+            .collect::<Result<Vec<_>, _>>(),
+        b.iter()
+            .map(|v| real(v, "^"))
+            .collect::<Result<Vec<_>, _>>(),
+    ) else {
+        return Err(DrelError::Type("^ needs numeric vectors".to_string()));
+    };
+    let ([a0, a1, a2], [b0, b1, b2]) = (a.as_slice(), b.as_slice()) else {
+        return Err(DrelError::Type("^ needs vectors of length 3".to_string()));
+    };
+    Ok(Value::List(
+        [a1 * b2 - a2 * b1, a2 * b0 - a0 * b2, a0 * b1 - a1 * b0]
+            .map(Value::Real)
+            .to_vec(),
+    ))
+}
+
+fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> Result<Value, DrelError> {
+    use BinaryOp::*;
+    match op {
+        Eq => return Ok(Value::Bool(values_equal(&lhs, &rhs))),
+        Ne => return Ok(Value::Bool(!values_equal(&lhs, &rhs))),
+        In | NotIn => {
+            let found = match &rhs {
+                Value::List(items) => items.iter().any(|i| values_equal(i, &lhs)),
+                Value::Str(s) => s.contains(&lhs.key()),
+                Value::Table(table) => table.contains_key(&lhs.key()),
+                other => return Err(DrelError::Type(format!("cannot search {other:?}"))),
+            };
+            return Ok(Value::Bool(found == (op == In)));
+        }
+        Lt | Le | Gt | Ge => {
+            let ordering = match (&lhs, &rhs) {
+                (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+                _ => real(&lhs, "comparison")?.partial_cmp(&real(&rhs, "comparison")?),
+            };
+            let Some(ordering) = ordering else {
+                return Ok(Value::Bool(false));
+            };
+            return Ok(Value::Bool(match op {
+                Lt => ordering.is_lt(),
+                Le => ordering.is_le(),
+                Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }));
+        }
+        And => return Ok(Value::Bool(lhs.truthy()? && rhs.truthy()?)),
+        Or => return Ok(Value::Bool(lhs.truthy()? || rhs.truthy()?)),
+        _ => (),
+    }
+    match (lhs, rhs) {
+        (Value::Integer(a), Value::Integer(b)) if op != Div => {
+            let result = match op {
+                Add => a.checked_add(b),
+                Sub => a.checked_sub(b),
+                Mul => a.checked_mul(b),
+                Pow if b >= 0 => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+                Pow => return Ok(Value::Real((a as f64).powf(b as f64))),
+                _ => return Err(DrelError::Type(format!("{op:?} of integers"))),
+            };
+            result
+                .map(Value::Integer)
+                .ok_or_else(|| DrelError::Type(format!("integer overflow in {op:?}")))
+        }
+        (Value::Str(a), Value::Str(b)) if op == Add => Ok(Value::Str(a + &b)),
+        (Value::List(a), Value::List(b)) => match op {
+            Add | Sub => {
+                if a.len() != b.len() {
+                    return Err(DrelError::Type(format!(
+                        "lengths {} and {} differ in {op:?}",
+                        a.len(),
+                        b.len()
+                    )));
+                }
+                a.into_iter()
+                    .zip(b)
+                    .map(|(x, y)| binary(op, x, y))
+                    .collect::<Result<_, _>>()
+                    .map(Value::List)
+            }
+            Mul => multiply_lists(a, b),
+            Cross => cross(&a, &b),
+            _ => Err(DrelError::Type(format!("{op:?} of lists"))),
+        },
+        (Value::List(a), b) => a
+            .into_iter()
+            .map(|x| binary(op, x, b.clone()))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        (a, Value::List(b)) if op != Div => b
+            .into_iter()
+            .map(|y| binary(op, a.clone(), y))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        (a, b) => {
+            let (a, b) = (real(&a, "arithmetic")?, real(&b, "arithmetic")?);
+            Ok(Value::Real(match op {
+                Add => a + b,
+                Sub => a - b,
+                Mul => a * b,
+                Div => a / b,
+                Pow => a.powf(b),
+                _ => return Err(DrelError::Type(format!("{op:?} of numbers"))),
+            }))
+        }
+    }
+}
+
+fn numbers(args: &[Value], name: &str) -> Result<Vec<f64>, DrelError> {
+    let args = match args {
+        [Value::List(items)] => items.as_slice(),
+        args => args,
+    };
+    args.iter().map(|a| real(a, name)).collect()
+}
+
+fn square_matrix(args: &[Value], name: &str) -> Result<Vec<Vec<f64>>, DrelError> {
+    let [Value::List(matrix)] = args else {
+        return Err(DrelError::Type(format!("{name} takes one matrix")));
+    };
+    if !is_matrix(matrix) {
+        return Err(DrelError::Type(format!("{name} needs a square matrix")));
+    }
+    let matrix: Vec<Vec<f64>> = rows(matrix)
+        .into_iter()
+        .map(|row| row.iter().map(|v| real(v, name)).collect())
+        .collect::<Result<_, _>>()?;
+    if matrix.iter().any(|row| row.len() != matrix.len()) {
+        return Err(DrelError::Type(format!("{name} needs a square matrix")));
+    }
+    Ok(matrix)
+}
+
+/// Gauss-Jordan elimination, giving the determinant and, if it is not
+/// singular, the inverse
+fn gauss_jordan(mut m: Vec<Vec<f64>>) -> (f64, Option<Vec<Vec<f64>>>) {
+    let n = m.len();
+    let mut inverse: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    let mut det = 1.0;
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))
+            .unwrap_or(col);
+        if m[pivot][col] == 0.0 {
+            return (0.0, None);
+        }
+        if pivot != col {
+            m.swap(pivot, col);
+            inverse.swap(pivot, col);
+            det = -det;
+        }
+        let p = m[col][col];
+        det *= p;
+        for j in 0..n {
+            m[col][j] /= p;
+            inverse[col][j] /= p;
+        }
+        for row in 0..n {
+            if row != col {
+                let factor = m[row][col];
+                for j in 0..n {
+                    m[row][j] -= factor * m[col][j];
+                    inverse[row][j] -= factor * inverse[col][j];
+                }
+            }
+        }
+    }
+    (det, Some(inverse))
+}
+
+fn real_matrix(m: Vec<Vec<f64>>) -> Value {
+    Value::List(
+        m.into_iter()
+            .map(|row| Value::List(row.into_iter().map(Value::Real).collect()))
+            .collect(),
+    )
+}
+
+/// Built-in dREL functions, `None` if there is no such built-in
+fn builtin(name: &str, args: &[Value]) -> Option<Result<Value, DrelError>> {
+    let unary = |f: fn(f64) -> f64| -> Result<Value, DrelError> {
+        match args {
+            [x] => Ok(Value::Real(f(real(x, name)?))),
+            _ => Err(DrelError::Type(format!("{name} takes one argument"))),
+        }
+    };
+    let binary_fn = |f: fn(f64, f64) -> f64| -> Result<Value, DrelError> {
+        match args {
+            [x, y] => Ok(Value::Real(f(real(x, name)?, real(y, name)?))),
+            _ => Err(DrelError::Type(format!("{name} takes two arguments"))),
+        }
+    };
+    Some(match name {
+        "sqrt" => unary(f64::sqrt),
+        "exp" => unary(f64::exp),
+        "log" => unary(f64::ln),
+        "log10" => unary(f64::log10),
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "asin" => unary(f64::asin),
+        "acos" => unary(f64::acos),
+        "atan" => unary(f64::atan),
+        "sind" => unary(|x| x.to_radians().sin()),
+        "cosd" => unary(|x| x.to_radians().cos()),
+        "tand" => unary(|x| x.to_radians().tan()),
+        "asind" => unary(|x| x.asin().to_degrees()),
+        "acosd" => unary(|x| x.acos().to_degrees()),
+        "atand" => unary(|x| x.atan().to_degrees()),
+        "atan2" => binary_fn(f64::atan2),
+        "atan2d" => binary_fn(|y, x| y.atan2(x).to_degrees()),
+        "float" | "real" => unary(|x| x),
+        "abs" => match args {
+            [Value::Integer(i)] => Ok(Value::Integer(i.abs())),
+            _ => unary(f64::abs),
+        },
+        "int" => match args {
+            [x] => real(x, name).map(|x| Value::Integer(x.trunc() as i64)),
+            _ => Err(DrelError::Type("int takes one argument".to_string())),
+        },
+        "round" => match args {
+            [x] => real(x, name).map(|x| Value::Integer(x.round() as i64)),
+            _ => Err(DrelError::Type("round takes one argument".to_string())),
+        },
+        "mod" => match args {
+            [Value::Integer(a), Value::Integer(b)] if *b != 0 => {
+                Ok(Value::Integer(a.rem_euclid(*b)))
+            }
+            _ => binary_fn(f64::rem_euclid),
+        },
+        "len" => match args {
+            [Value::List(items)] => Ok(Value::Integer(items.len() as i64)),
+            [Value::Str(s)] => Ok(Value::Integer(s.chars().count() as i64)),
+            [Value::Table(t)] => Ok(Value::Integer(t.len() as i64)),
+            _ => Err(DrelError::Type(
+                "len takes a list, string or table".to_string(),
+            )),
+        },
+        "min" | "max" => numbers(args, name).and_then(|values| {
+            let fold = if name == "min" { f64::min } else { f64::max };
+            values
+                .into_iter()
+                .reduce(fold)
+                .map(Value::Real)
+                .ok_or_else(|| DrelError::Type(format!("{name} of nothing")))
+        }),
+        "sum" => match args {
+            [Value::List(items)] => items
+                .iter()
+                .cloned()
+                .try_fold(Value::Integer(0), |sum, x| binary(BinaryOp::Add, sum, x)),
+            _ => Err(DrelError::Type("sum takes a list".to_string())),
+        },
+        "norm" => {
+            numbers(args, name).map(|v| Value::Real(v.iter().map(|x| x * x).sum::<f64>().sqrt()))
+        }
+        "transpose" => match args {
+            [Value::List(matrix)] if is_matrix(matrix) => Ok(Value::List(
+                transpose(matrix).into_iter().map(Value::List).collect(),
+            )),
+            _ => Err(DrelError::Type("transpose takes a matrix".to_string())),
+        },
+        "det" | "determinant" => square_matrix(args, name).map(|m| Value::Real(gauss_jordan(m).0)),
+        "inverse" | "inv" => square_matrix(args, name).and_then(|m| {
+            gauss_jordan(m)
+                .1
+                .map(real_matrix)
+                .ok_or_else(|| DrelError::Type("inverse of a singular matrix".to_string()))
+        }),
+        "upper" | "lower" => match args {
+            [Value::Str(s)] if name == "upper" => Ok(Value::Str(s.to_uppercase())),
+            [Value::Str(s)] => Ok(Value::Str(s.to_lowercase())),
+            _ => Err(DrelError::Type(format!("{name} takes a string"))),
+        },
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use crate::test_fixtures::dict;
+    use rstest::rstest;
+
+    const BLOCK: &str = "#\\#CIF_2.0
+data_test
+_cell.length_a 5.0
+_cell.length_b 6.0
+_cell.length_c 7.0
+_cell.angle_alpha 90
+_cell.angle_beta 100.0
+_cell.angle_gamma 90
+loop_
+_atom_site.label
+_atom_site.fract_x
+_atom_site.fract_y
+_atom_site.fract_z
+C1 0.1 0.2 0.2
+O1 0.3 0.4 0.0
+";
+
+    fn with_block<T>(input: &str, dict: &Dictionary, f: impl FnOnce(&Evaluator) -> T) -> T {
+        let model = cif2_file(input).unwrap();
+        f(&Evaluator::new(dict, &model.content[0]))
+    }
+
+    fn assert_close(value: Value, expected: f64) {
+        let value = value.as_f64().unwrap();
+        assert!((value - expected).abs() < 1e-9, "{value} != {expected}");
+    }
+
+    #[rstest]
+    fn test_derive_volume(dict: Dictionary) {
+        let expected = 5.0 * 6.0 * 7.0 * 100f64.to_radians().sin();
+        with_block(BLOCK, &dict, |evaluator| {
+            assert_close(evaluator.evaluate("_cell.volume").unwrap(), expected);
+            assert_close(evaluator.evaluate("_cell_length_a").unwrap(), 5.0);
+        });
+    }
+
+    #[rstest]
+    fn test_loop_methods(dict: Dictionary) {
+        with_block(BLOCK, &dict, |evaluator| {
+            assert_eq!(evaluator.packet_count("atom_site"), 2);
+            assert_eq!(
+                evaluator.value("_atom_site.fract_xyz", 1).unwrap(),
+                Value::List(vec![Value::Real(0.3), Value::Real(0.4), Value::Real(0.0)])
+            );
+            // Uses the Hypot function defined in the FUNCTION category
+            assert_close(
+                evaluator.value("_atom_site.distance_origin", 1).unwrap(),
+                2.5,
+            );
+        });
+    }
+
+    const TETRAGONAL: &str = "#\\#CIF_2.0
+data_test
+_cell.length_a 5.0
+_cell.length_b 5.0
+_cell.length_c 6.408
+_cell.angle_alpha 90
+_cell.angle_beta 90
+_cell.angle_gamma 90
+";
+
+    #[rstest]
+    #[case(TETRAGONAL, "160.2", true)]
+    #[case(BLOCK, "206.8(2)", true)]
+    #[case(BLOCK, "206.0(2)", false)]
+    #[case(BLOCK, "210", false)]
+    fn test_check(
+        dict: Dictionary,
+        #[case] block: &str,
+        #[case] volume: &str,
+        #[case] consistent: bool,
+    ) {
+        let input = format!("{block}_cell.volume {volume}\n");
+        with_block(&input, &dict, |evaluator| {
+            let check = evaluator.check("_cell.volume", 0).unwrap().unwrap();
+            assert_eq!(check.consistent, consistent, "{check:?}");
+            assert_eq!(evaluator.check("_cell.length_a", 0), Ok(None));
+        });
+    }
+
+    #[rstest]
+    fn test_missing_and_cycle(mut dict: Dictionary) {
+        with_block(
+            "#\\#CIF_2.0\ndata_empty\n_cell.length_a 5.0\n",
+            &dict,
+            |evaluator| {
+                assert_eq!(
+                    evaluator.evaluate("_cell.volume"),
+                    Err(DrelError::Missing("_cell.angle_alpha".to_string()))
+                );
+            },
+        );
+        dict.items.get_mut("_cell.volume").unwrap().methods[0].expression =
+            "_cell.volume = 2 * _cell.volume".to_string();
+        with_block(BLOCK, &dict, |evaluator| {
+            assert_eq!(
+                evaluator.evaluate("_cell.volume"),
+                Err(DrelError::Cycle("_cell.volume".to_string()))
+            );
+        });
+    }
+
+    fn int_list(values: &[i64]) -> Value {
+        Value::List(values.iter().copied().map(Value::Integer).collect())
+    }
+
+    #[rstest]
+    #[case("_cell.volume = 7 / 2", Value::Real(3.5))]
+    #[case("_cell.volume = 2 ** 10 - 1", Value::Integer(1023))]
+    #[case("_cell.volume = [[1, 2], [3, 4]] * [1, 1]", int_list(&[3, 7]))]
+    #[case("_cell.volume = [1, 2, 3] * [4, 5, 6]", Value::Integer(32))]
+    #[case("_cell.volume = 2 * [1, 2] + [1, 1]", int_list(&[3, 5]))]
+    #[case("_cell.volume = Det([[2., 0.], [0., 3.]])", Value::Real(6.0))]
+    #[case(
+        "_cell.volume = Inverse([[2., 0.], [0., 4.]])[1, 1]",
+        Value::Real(0.25)
+    )]
+    #[case("_cell.volume = [1, 0, 0] ^ [0, 1, 0]", Value::List(vec![Value::Real(0.0), Value::Real(0.0), Value::Real(1.0)]))]
+    #[case("x = 0\nDo i = 1, 4 { x += i }\n_cell.volume = x", Value::Integer(10))]
+    #[case(
+        "x = 0\nDo i = 9223372036854775806, 9223372036854775807 { x += 1 }\n_cell.volume = x",
+        Value::Integer(2)
+    )]
+    #[case("x = []\nFor v in [3, 4, 5] {\n If (v == 4) Next\n x ++= v\n}\n_cell.volume = x", int_list(&[3, 5]))]
+    #[case(
+        "n = 0\nLoop a as atom_site : i { n += i + 1 }\n_cell.volume = n",
+        Value::Integer(3)
+    )]
+    #[case("_cell.volume = atom_site['O1'].fract_y", Value::Real(0.4))]
+    #[case(
+        "c = 0\nRepeat {\n c += 1\n If (c >= 3) Break\n}\n_cell.volume = c",
+        Value::Integer(3)
+    )]
+    #[case("_cell.volume = 'a' in ['a', 'b'] and not 2 > 3", Value::Bool(true))]
+    #[case("m = [[1, 2], [3, 4]]\nm[0, 1] = 5\n_cell.volume = Transpose(m)[1]", int_list(&[5, 4]))]
+    #[case(
+        "If (cell.length_a < 1) x = 1\nElse If (cell.length_a < 10) x = 2\nElse x = 3\ncell.volume = x",
+        Value::Integer(2)
+    )]
+    fn test_method(mut dict: Dictionary, #[case] method: &str, #[case] expected: Value) {
+        dict.items.get_mut("_cell.volume").unwrap().methods[0].expression = method.to_string();
+        with_block(BLOCK, &dict, |evaluator| {
+            assert_eq!(evaluator.evaluate("_cell.volume"), Ok(expected));
+        });
+    }
+
+    #[rstest]
+    #[case("_cell.volume = Nonesuch(1)", DrelError::UnknownFunction("nonesuch".to_string()))]
+    #[case("_cell.volume = y", DrelError::UnknownVariable("y".to_string()))]
+    #[case("x = 1", DrelError::NoResult("_cell.volume".to_string()))]
+    #[case("_cell.volume = 'a' * 2", DrelError::Type("arithmetic needs a number, not Str(\"a\")".to_string()))]
+    fn test_method_errors(mut dict: Dictionary, #[case] method: &str, #[case] expected: DrelError) {
+        dict.items.get_mut("_cell.volume").unwrap().methods[0].expression = method.to_string();
+        with_block(BLOCK, &dict, |evaluator| {
+            assert_eq!(evaluator.evaluate("_cell.volume"), Err(expected));
+        });
+    }
+}
@@ -0,0 +1,564 @@
+/// Parser for the dREL subset used by DDLm dictionary methods. Newlines are
+/// treated as whitespace, so a statement ends where its expression can no
+/// longer continue.
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::{tag, tag_no_case, take_until, take_while, take_while1},
+    character::complete::{char, digit0, digit1, multispace1, not_line_ending, one_of, satisfy},
+    combinator::{cut, map, not, opt, peek, recognize, value, verify},
+    error::Error,
+    multi::{many0, separated_list0},
+    sequence::{delimited, pair, preceded, terminated},
+};
+
+use super::DrelError;
+use super::ast::{AssignOp, BinaryOp, Expr, Stmt, UnaryOp};
+
+const KEYWORDS: &[&str] = &[
+    "if", "else", "elseif", "for", "in", "loop", "as", "do", "repeat", "break", "next", "with",
+    "function", "and", "or", "not", "true", "false",
+];
+
+fn ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn comment(input: &str) -> IResult<&str, &str> {
+    recognize(pair(char('#'), not_line_ending)).parse(input)
+}
+fn sp(input: &str) -> IResult<&str, ()> {
+    value((), many0(alt((multispace1, comment)))).parse(input)
+}
+fn sym<'a>(s: &'static str) -> impl Parser<&'a str, Output = &'a str, Error = Error<&'a str>> {
+    preceded(sp, tag(s))
+}
+/// A symbol that is not the start of a longer operator
+fn op<'a>(
+    s: &'static str,
+    not_followed_by: &'static str,
+) -> impl Parser<&'a str, Output = &'a str, Error = Error<&'a str>> {
+    terminated(sym(s), not(peek(one_of(not_followed_by))))
+}
+fn kw<'a>(k: &'static str) -> impl Parser<&'a str, Output = &'a str, Error = Error<&'a str>> {
+    preceded(
+        sp,
+        terminated(tag_no_case(k), not(peek(satisfy(ident_char)))),
+    )
+}
+
+fn ident(input: &str) -> IResult<&str, String> {
+    let (inp, name) = preceded(
+        sp,
+        verify(
+            recognize(pair(satisfy(char::is_alphabetic), take_while(ident_char))),
+            |s: &str| !KEYWORDS.contains(&s.to_lowercase().as_str()),
+        ),
+    )
+    .parse(input)?;
+    Ok((inp, name.to_lowercase()))
+}
+fn data_name(input: &str) -> IResult<&str, Expr> {
+    let (inp, name) = preceded(
+        sp,
+        recognize(pair(char('_'), take_while1(|c| ident_char(c) || c == '.'))),
+    )
+    .parse(input)?;
+    Ok((inp, Expr::DataName(name.to_lowercase())))
+}
+
+fn exponent(input: &str) -> IResult<&str, &str> {
+    recognize((one_of("eE"), opt(one_of("+-")), digit1)).parse(input)
+}
+fn number(input: &str) -> IResult<&str, Expr> {
+    let (inp, text) = preceded(
+        sp,
+        recognize(alt((
+            recognize((digit1, opt(pair(char('.'), digit0)), opt(exponent))),
+            recognize((char('.'), digit1, opt(exponent))),
+        ))),
+    )
+    .parse(input)?;
+    let number = if text.contains(['.', 'e', 'E']) {
+        text.parse().map(Expr::Real).ok()
+    } else {
+        text.parse().map(Expr::Integer).ok()
+    };
+    match number {
+        Some(number) => Ok((inp, number)),
+        None => Err(nom::Err::Error(Error::new(
+            input,
+            nom::error::ErrorKind::Digit,
+        ))),
+    }
+}
+
+fn string(input: &str) -> IResult<&str, Expr> {
+    let (inp, s) = preceded(
+        sp,
+        alt((
+            delimited(tag("\"\"\""), take_until("\"\"\""), tag("\"\"\"")),
+            delimited(tag("'''"), take_until("'''"), tag("'''")),
+            delimited(char('"'), take_while(|c| c != '"' && c != '\n'), char('"')),
+            delimited(
+                char('\''),
+                take_while(|c| c != '\'' && c != '\n'),
+                char('\''),
+            ),
+        )),
+    )
+    .parse(input)?;
+    Ok((inp, Expr::Str(s.to_string())))
+}
+
+fn list(input: &str) -> IResult<&str, Expr> {
+    map(
+        delimited(
+            sym("["),
+            terminated(separated_list0(sym(","), expr), opt(sym(","))),
+            sym("]"),
+        ),
+        Expr::List,
+    )
+    .parse(input)
+}
+fn table(input: &str) -> IResult<&str, Expr> {
+    map(
+        delimited(
+            sym("{"),
+            separated_list0(sym(","), pair(expr, preceded(sym(":"), expr))),
+            sym("}"),
+        ),
+        Expr::Table,
+    )
+    .parse(input)
+}
+fn call_or_ident(input: &str) -> IResult<&str, Expr> {
+    let (inp, name) = ident(input)?;
+    let (inp, args) = opt(delimited(
+        sym("("),
+        separated_list0(sym(","), expr),
+        sym(")"),
+    ))
+    .parse(inp)?;
+    Ok((
+        inp,
+        match args {
+            Some(args) => Expr::Call(name, args),
+            None => Expr::Ident(name),
+        },
+    ))
+}
+fn primary(input: &str) -> IResult<&str, Expr> {
+    alt((
+        number,
+        string,
+        list,
+        table,
+        delimited(sym("("), expr, sym(")")),
+        value(Expr::Bool(true), kw("true")),
+        value(Expr::Bool(false), kw("false")),
+        data_name,
+        call_or_ident,
+    ))
+    .parse(input)
+}
+
+enum Postfix {
+    Attribute(String),
+    Subscript(Vec<Expr>),
+}
+fn postfix(input: &str) -> IResult<&str, Expr> {
+    let (inp, base) = primary(input)?;
+    let (inp, suffixes) = many0(alt((
+        map(preceded(sym("."), ident), Postfix::Attribute),
+        map(
+            delimited(sym("["), separated_list0(sym(","), expr), sym("]")),
+            Postfix::Subscript,
+        ),
+    )))
+    .parse(inp)?;
+    let expr = suffixes
+        .into_iter()
+        .fold(base, |expr, suffix| match suffix {
+            Postfix::Attribute(name) => Expr::Attribute(Box::new(expr), name),
+            Postfix::Subscript(index) => Expr::Subscript(Box::new(expr), index),
+        });
+    Ok((inp, expr))
+}
+
+fn power(input: &str) -> IResult<&str, Expr> {
+    let (inp, base) = postfix(input)?;
+    let (inp, exponent) = opt(preceded(sym("**"), unary)).parse(inp)?;
+    Ok((
+        inp,
+        match exponent {
+            Some(exponent) => Expr::Binary(BinaryOp::Pow, Box::new(base), Box::new(exponent)),
+            None => base,
+        },
+    ))
+}
+fn unary(input: &str) -> IResult<&str, Expr> {
+    alt((
+        map(preceded(op("-", "="), unary), |e| {
+            Expr::Unary(UnaryOp::Neg, Box::new(e))
+        }),
+        preceded(op("+", "="), unary),
+        power,
+    ))
+    .parse(input)
+}
+
+/// Left associative chain of binary operators
+fn binary_chain<'a>(
+    input: &'a str,
+    operand: fn(&'a str) -> IResult<&'a str, Expr>,
+    operator: fn(&'a str) -> IResult<&'a str, BinaryOp>,
+) -> IResult<&'a str, Expr> {
+    let (inp, first) = operand(input)?;
+    let (inp, rest) = many0(pair(operator, operand)).parse(inp)?;
+    Ok((
+        inp,
+        rest.into_iter().fold(first, |lhs, (op, rhs)| {
+            Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+        }),
+    ))
+}
+
+fn term_op(input: &str) -> IResult<&str, BinaryOp> {
+    alt((
+        value(BinaryOp::Mul, op("*", "*=")),
+        value(BinaryOp::Div, op("/", "=")),
+        value(BinaryOp::Cross, op("^", "=")),
+    ))
+    .parse(input)
+}
+fn term(input: &str) -> IResult<&str, Expr> {
+    binary_chain(input, unary, term_op)
+}
+fn arith_op(input: &str) -> IResult<&str, BinaryOp> {
+    alt((
+        value(BinaryOp::Add, op("+", "+=")),
+        value(BinaryOp::Sub, op("-", "-=")),
+    ))
+    .parse(input)
+}
+fn arith(input: &str) -> IResult<&str, Expr> {
+    binary_chain(input, term, arith_op)
+}
+fn compare_op(input: &str) -> IResult<&str, BinaryOp> {
+    alt((
+        value(BinaryOp::Eq, sym("==")),
+        value(BinaryOp::Ne, sym("!=")),
+        value(BinaryOp::Le, sym("<=")),
+        value(BinaryOp::Ge, sym(">=")),
+        value(BinaryOp::Lt, sym("<")),
+        value(BinaryOp::Gt, sym(">")),
+        value(BinaryOp::In, kw("in")),
+        value(BinaryOp::NotIn, pair(kw("not"), kw("in"))),
+    ))
+    .parse(input)
+}
+fn comparison(input: &str) -> IResult<&str, Expr> {
+    binary_chain(input, arith, compare_op)
+}
+fn not_expr(input: &str) -> IResult<&str, Expr> {
+    alt((
+        map(preceded(alt((kw("not"), op("!", "="))), not_expr), |e| {
+            Expr::Unary(UnaryOp::Not, Box::new(e))
+        }),
+        comparison,
+    ))
+    .parse(input)
+}
+fn and_op(input: &str) -> IResult<&str, BinaryOp> {
+    value(BinaryOp::And, alt((kw("and"), sym("&&")))).parse(input)
+}
+fn and_expr(input: &str) -> IResult<&str, Expr> {
+    binary_chain(input, not_expr, and_op)
+}
+fn or_op(input: &str) -> IResult<&str, BinaryOp> {
+    value(BinaryOp::Or, alt((kw("or"), sym("||")))).parse(input)
+}
+pub fn expr(input: &str) -> IResult<&str, Expr> {
+    binary_chain(input, and_expr, or_op)
+}
+
+fn block(input: &str) -> IResult<&str, Vec<Stmt>> {
+    alt((
+        map(delimited(sym("{"), many0(statement), sym("}")), |s| {
+            s.into_iter().flatten().collect()
+        }),
+        statement,
+    ))
+    .parse(input)
+}
+
+fn if_stmt(input: &str) -> IResult<&str, Vec<Stmt>> {
+    let condition = || delimited(sym("("), expr, sym(")"));
+    let (inp, first) = preceded(kw("if"), pair(condition(), block)).parse(input)?;
+    let (inp, mut branches) = many0(preceded(
+        alt((kw("elseif"), recognize(pair(kw("else"), kw("if"))))),
+        pair(condition(), block),
+    ))
+    .parse(inp)?;
+    let (inp, otherwise) = opt(preceded(kw("else"), block)).parse(inp)?;
+    branches.insert(0, first);
+    Ok((
+        inp,
+        vec![Stmt::If {
+            branches,
+            otherwise: otherwise.unwrap_or_default(),
+        }],
+    ))
+}
+fn for_stmt(input: &str) -> IResult<&str, Vec<Stmt>> {
+    let (inp, (var, iter, body)) =
+        (preceded(kw("for"), ident), preceded(kw("in"), expr), block).parse(input)?;
+    Ok((inp, vec![Stmt::For { var, iter, body }]))
+}
+fn loop_stmt(input: &str) -> IResult<&str, Vec<Stmt>> {
+    let (inp, (alias, category, index, body)) = (
+        preceded(kw("loop"), ident),
+        preceded(kw("as"), ident),
+        opt(preceded(sym(":"), ident)),
+        block,
+    )
+        .parse(input)?;
+    Ok((
+        inp,
+        vec![Stmt::Loop {
+            alias,
+            category,
+            index,
+            body,
+        }],
+    ))
+}
+fn do_stmt(input: &str) -> IResult<&str, Vec<Stmt>> {
+    let (inp, (var, start, end, step, body)) = (
+        preceded(kw("do"), ident),
+        preceded(op("=", "="), expr),
+        preceded(sym(","), expr),
+        opt(preceded(sym(","), expr)),
+        block,
+    )
+        .parse(input)?;
+    Ok((
+        inp,
+        vec![Stmt::Do {
+            var,
+            start,
+            end,
+            step,
+            body,
+        }],
+    ))
+}
+fn with_stmt(input: &str) -> IResult<&str, Vec<Stmt>> {
+    let (inp, (alias, category)) =
+        (preceded(kw("with"), ident), preceded(kw("as"), ident)).parse(input)?;
+    let (inp, body) = opt(delimited(sym("{"), many0(statement), sym("}"))).parse(inp)?;
+    let mut stmts = vec![Stmt::With { alias, category }];
+    stmts.extend(body.into_iter().flatten().flatten());
+    Ok((inp, stmts))
+}
+fn function_stmt(input: &str) -> IResult<&str, Vec<Stmt>> {
+    // Parameter type annotations such as `a :[Array, Real]` are not used
+    let param = terminated(ident, opt(preceded(sym(":"), expr)));
+    let (inp, (name, params, body)) = (
+        preceded(kw("function"), ident),
+        delimited(sym("("), separated_list0(sym(","), param), sym(")")),
+        block,
+    )
+        .parse(input)?;
+    Ok((inp, vec![Stmt::Function { name, params, body }]))
+}
+fn assign_op(input: &str) -> IResult<&str, AssignOp> {
+    alt((
+        value(AssignOp::Append, sym("++=")),
+        value(AssignOp::AddAssign, sym("+=")),
+        value(AssignOp::SubAssign, sym("-=")),
+        value(AssignOp::MulAssign, sym("*=")),
+        value(AssignOp::Assign, op("=", "=")),
+    ))
+    .parse(input)
+}
+fn simple_stmt(input: &str) -> IResult<&str, Vec<Stmt>> {
+    let (inp, target) = expr(input)?;
+    let (inp, assignment) = opt(pair(assign_op, cut(expr))).parse(inp)?;
+    Ok((
+        inp,
+        vec![match assignment {
+            Some((op, value)) => Stmt::Assign { target, op, value },
+            None => Stmt::Expr(target),
+        }],
+    ))
+}
+
+fn statement(input: &str) -> IResult<&str, Vec<Stmt>> {
+    terminated(
+        alt((
+            if_stmt,
+            for_stmt,
+            loop_stmt,
+            do_stmt,
+            with_stmt,
+            function_stmt,
+            map(preceded(kw("repeat"), block), |body| {
+                vec![Stmt::Repeat(body)]
+            }),
+            value(vec![Stmt::Break], kw("break")),
+            value(vec![Stmt::Next], kw("next")),
+            simple_stmt,
+        )),
+        opt(sym(";")),
+    )
+    .parse(input)
+}
+
+/// Parse the text of a `_method.expression`
+pub fn parse_method(input: &str) -> Result<Vec<Stmt>, DrelError> {
+    let (rest, stmts) = many0(statement).parse(input).map_err(|e| match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => parse_error(input, e.input),
+        nom::Err::Incomplete(_) => parse_error(input, ""),
+    })?;
+    let (rest, _) = sp(rest).map_err(|_| parse_error(input, rest))?;
+    if !rest.is_empty() {
+        return Err(parse_error(input, rest));
+    }
+    Ok(stmts.into_iter().flatten().collect())
+}
+
+fn parse_error(input: &str, rest: &str) -> DrelError {
+    let rest = rest.trim_start();
+    let consumed = &input[..input.len() - rest.len()];
+    let line = consumed.matches('\n').count() + 1;
+    let line_start = consumed.rfind('\n').map_or(0, |i| i + 1);
+    let column = consumed[line_start..].chars().count() + 1;
+    DrelError::Parse {
+        line,
+        column,
+        near: rest.lines().next().unwrap_or_default().trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+    fn ident(name: &str) -> Expr {
+        Expr::Ident(name.to_string())
+    }
+
+    #[rstest]
+    #[case(
+        "1 + 2 * 3",
+        binary(
+            BinaryOp::Add,
+            Expr::Integer(1),
+            binary(BinaryOp::Mul, Expr::Integer(2), Expr::Integer(3))
+        )
+    )]
+    #[case(
+        "-x**2",
+        Expr::Unary(
+            UnaryOp::Neg,
+            Box::new(binary(BinaryOp::Pow, ident("x"), Expr::Integer(2)))
+        )
+    )]
+    #[case("2.*ca", binary(BinaryOp::Mul, Expr::Real(2.0), ident("ca")))]
+    #[case("C.Length_A", Expr::Attribute(Box::new(ident("c")), "length_a".to_string()))]
+    #[case("_Cell.Volume", Expr::DataName("_cell.volume".to_string()))]
+    #[case("Cosd(90)", Expr::Call("cosd".to_string(), vec![Expr::Integer(90)]))]
+    #[case("m[0,1]", Expr::Subscript(Box::new(ident("m")), vec![Expr::Integer(0), Expr::Integer(1)]))]
+    #[case("a not in [1]", binary(BinaryOp::NotIn, ident("a"), Expr::List(vec![Expr::Integer(1)])))]
+    #[case(
+        "a < b and not c",
+        binary(
+            BinaryOp::And,
+            binary(BinaryOp::Lt, ident("a"), ident("b")),
+            Expr::Unary(UnaryOp::Not, Box::new(ident("c")))
+        )
+    )]
+    #[case("'abc'", Expr::Str("abc".to_string()))]
+    #[case("1.5e-3", Expr::Real(1.5e-3))]
+    fn test_expr(#[case] input: &str, #[case] expected: Expr) {
+        let (rest, parsed) = expr(input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn test_statements_across_lines() {
+        let input = "
+    With c as cell
+
+    ca = Cosd(c.angle_alpha)   # comment
+    _cell.volume = c.length_a *
+                   Sqrt(1. - ca**2)
+";
+        let stmts = parse_method(input).unwrap();
+        assert_eq!(stmts.len(), 3);
+        assert_eq!(
+            stmts[0],
+            Stmt::With {
+                alias: "c".to_string(),
+                category: "cell".to_string()
+            }
+        );
+        let Stmt::Assign { target, op, .. } = &stmts[2] else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(target, &Expr::DataName("_cell.volume".to_string()));
+        assert_eq!(op, &AssignOp::Assign);
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let input = "
+    Function Double(x :[Single, Real]) {
+        Double = 2 * x
+    }
+    total = 0
+    Loop t as atom_type : i {
+        If (t.symbol == 'C') { total += 1 }
+        Else If (t.symbol == 'H') total += 2
+        Else { Next }
+    }
+    Do j = 1, 10, 2 { total ++= [j] }
+    Repeat { Break }
+    For x in [1, 2] { y = x }
+";
+        let stmts = parse_method(input).unwrap();
+        assert_eq!(stmts.len(), 6);
+        assert!(matches!(&stmts[0], Stmt::Function { name, params, .. }
+            if name == "double" && params == &["x"]));
+        let Stmt::Loop { body, index, .. } = &stmts[2] else {
+            panic!("expected a loop");
+        };
+        assert_eq!(index.as_deref(), Some("i"));
+        assert!(matches!(&body[0], Stmt::If { branches, otherwise }
+            if branches.len() == 2 && otherwise == &[Stmt::Next]));
+        assert!(matches!(&stmts[3], Stmt::Do { step: Some(_), .. }));
+    }
+
+    #[rstest]
+    #[case("x = (1 + ", 1, 5)]
+    #[case("x = 1\ny = }", 2, 5)]
+    #[case("x = 1\n  }", 2, 3)]
+    #[case("x = 'é'\ny = 'é' }", 2, 9)]
+    fn test_parse_error(#[case] input: &str, #[case] line: usize, #[case] column: usize) {
+        let Err(DrelError::Parse {
+            line: l, column: c, ..
+        }) = parse_method(input)
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((l, c), (line, column));
+    }
+}
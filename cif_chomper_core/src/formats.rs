@@ -0,0 +1,1269 @@
+/// Structures written to and read from the file formats of other programs:
+/// XYZ, extended XYZ, PDB, VASP POSCAR and SHELX
+use std::fmt;
+use std::fmt::Write;
+
+use crate::cell::{Matrix, UnitCell, invert, multiply};
+use crate::formula::{Element, Formula, FormulaError};
+use crate::model::{push_item, push_loop};
+use crate::numeric::Measurement;
+use crate::raw_model::{DataBlock, DataValue, RawDataBlock};
+use crate::space_group::{block_operators, centring};
+use crate::structure::{DEFAULT_TOLERANCE, Site, Structure, StructureError};
+use crate::symmetry::{Fraction, SymOp, SymmetryError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatError {
+    /// A line of a file being read, from 1, that cannot be understood
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// A site whose element cannot be found
+    Formula(FormulaError),
+    Structure(StructureError),
+    Symmetry(SymmetryError),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            FormatError::Formula(e) => write!(f, "{e}"),
+            FormatError::Structure(e) => write!(f, "{e}"),
+            FormatError::Symmetry(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<FormulaError> for FormatError {
+    fn from(e: FormulaError) -> Self {
+        FormatError::Formula(e)
+    }
+}
+
+impl From<StructureError> for FormatError {
+    fn from(e: StructureError) -> Self {
+        FormatError::Structure(e)
+    }
+}
+
+impl From<SymmetryError> for FormatError {
+    fn from(e: SymmetryError) -> Self {
+        FormatError::Symmetry(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Coordinates {
+    #[default]
+    Cartesian,
+    Fractional,
+}
+
+/// The order in which elements, and the sites of each, are written
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ElementOrder {
+    /// In order of the first site of each element
+    #[default]
+    AsListed,
+    Alphabetical,
+    AtomicNumber,
+    /// The given symbols first, in order, then any others as listed
+    Given(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExportOptions {
+    /// Used by POSCAR only; the other formats are always Cartesian
+    pub coordinates: Coordinates,
+    pub order: ElementOrder,
+    /// The comment or title line, by default the formula of the cell
+    /// contents
+    pub title: Option<String>,
+}
+
+/// Every site of the cell of a block, from its atom sites and symmetry
+/// operators
+pub fn cell_contents(block: &RawDataBlock) -> Result<Structure, FormatError> {
+    let structure = Structure::from_block(block)?;
+    Ok(structure.expand(&block_operators(block)?, DEFAULT_TOLERANCE))
+}
+
+/// The sites of a structure with their elements, grouped by element in the
+/// requested order
+fn ordered_sites<'a>(
+    structure: &'a Structure,
+    order: &ElementOrder,
+) -> Result<Vec<(Element, &'a Site)>, FormatError> {
+    let sites = structure
+        .sites
+        .iter()
+        .map(|site| Ok((Element::of_site(site)?, site)))
+        .collect::<Result<Vec<_>, FormulaError>>()?;
+    let mut elements: Vec<Element> = Vec::new();
+    for (element, _) in &sites {
+        if !elements.contains(element) {
+            elements.push(*element);
+        }
+    }
+    match order {
+        ElementOrder::AsListed => {}
+        ElementOrder::Alphabetical => elements.sort_by_key(|e| e.symbol),
+        ElementOrder::AtomicNumber => elements.sort_by_key(|e| e.number),
+        ElementOrder::Given(symbols) => elements.sort_by_key(|e| {
+            symbols
+                .iter()
+                .position(|s| s == e.symbol)
+                .unwrap_or(symbols.len())
+        }),
+    }
+    Ok(elements
+        .iter()
+        .flat_map(|element| sites.iter().filter(move |(e, _)| e == element).copied())
+        .collect())
+}
+
+fn title(structure: &Structure, options: &ExportOptions) -> Result<String, FormatError> {
+    if let Some(title) = &options.title {
+        return Ok(title.clone());
+    }
+    let mut formula = Formula::default();
+    for site in &structure.sites {
+        formula.add(Element::of_site(site)?, site.occupancy.unwrap_or(1.0));
+    }
+    Ok(formula.to_string())
+}
+
+/// The cell vectors a, b and c in Cartesian coordinates
+fn lattice_vectors(structure: &Structure) -> [[f64; 3]; 3] {
+    [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+        .map(|axis| structure.cell.to_cartesian(axis))
+}
+
+/// XYZ: the number of atoms, a title line, then the element and Cartesian
+/// coordinates of each
+pub fn to_xyz(structure: &Structure, options: &ExportOptions) -> Result<String, FormatError> {
+    let sites = ordered_sites(structure, &options.order)?;
+    let mut out = format!("{}\n{}\n", sites.len(), title(structure, options)?);
+    for (element, site) in sites {
+        let [x, y, z] = structure.cell.to_cartesian(site.fract);
+        writeln!(out, "{:<2} {x:14.8} {y:14.8} {z:14.8}", element.symbol).unwrap();
+    }
+    Ok(out)
+}
+
+/// Extended XYZ, with the cell vectors, periodic boundaries and the
+/// columns in the comment line. The title is kept as `comment`.
+pub fn to_extended_xyz(
+    structure: &Structure,
+    options: &ExportOptions,
+) -> Result<String, FormatError> {
+    let sites = ordered_sites(structure, &options.order)?;
+    let lattice: Vec<_> = lattice_vectors(structure)
+        .iter()
+        .flatten()
+        .map(|x| format!("{x:.8}"))
+        .collect();
+    let comment = title(structure, options)?.replace('"', "'");
+    let mut out = format!(
+        "{}\nLattice=\"{}\" Properties=species:S:1:pos:R:3:occupancy:R:1 pbc=\"T T T\" comment=\"{comment}\"\n",
+        sites.len(),
+        lattice.join(" "),
+    );
+    for (element, site) in sites {
+        let [x, y, z] = structure.cell.to_cartesian(site.fract);
+        let occupancy = site.occupancy.unwrap_or(1.0);
+        writeln!(
+            out,
+            "{:<2} {x:14.8} {y:14.8} {z:14.8} {occupancy:8.4}",
+            element.symbol
+        )
+        .unwrap();
+    }
+    Ok(out)
+}
+
+/// PDB, with the cell as `CRYST1` in P 1 and each site as a `HETATM`
+/// record named after the first four characters of its label. The
+/// Cartesian axes follow the PDB convention of a along x and b in the xy
+/// plane.
+pub fn to_pdb(structure: &Structure, options: &ExportOptions) -> Result<String, FormatError> {
+    let sites = ordered_sites(structure, &options.order)?;
+    let title: String = title(structure, options)?.chars().take(70).collect();
+    let [a, b, c] = structure.cell.lengths.map(|l| l.value);
+    let [alpha, beta, gamma] = structure.cell.angles.map(|a| a.value);
+    let mut out = format!("TITLE     {title}\n");
+    writeln!(
+        out,
+        "CRYST1{a:9.3}{b:9.3}{c:9.3}{alpha:7.2}{beta:7.2}{gamma:7.2} P 1           1"
+    )
+    .unwrap();
+    for (serial, (element, site)) in sites.iter().enumerate() {
+        let name: String = site.label.chars().take(4).collect();
+        let [x, y, z] = structure.cell.to_cartesian(site.fract);
+        let occupancy = site.occupancy.unwrap_or(1.0);
+        // Serial numbers wrap at the width of their column
+        let serial = (serial + 1) % 100_000;
+        writeln!(
+            out,
+            "HETATM{serial:5} {name:<4} UNL A   1    {x:8.3}{y:8.3}{z:8.3}{occupancy:6.2}{:6.2}          {:>2}",
+            0.0,
+            element.symbol.to_ascii_uppercase(),
+        )
+        .unwrap();
+    }
+    out.push_str("END\n");
+    Ok(out)
+}
+
+/// VASP POSCAR, with the elements in the order given and the sites of
+/// each together. POSCAR has no occupancies, so every site is written.
+pub fn to_poscar(structure: &Structure, options: &ExportOptions) -> Result<String, FormatError> {
+    let sites = ordered_sites(structure, &options.order)?;
+    let mut elements: Vec<_> = sites.iter().map(|(e, _)| *e).collect();
+    elements.dedup();
+    let mut out = format!("{}\n1.0\n", title(structure, options)?);
+    for [x, y, z] in lattice_vectors(structure) {
+        writeln!(out, "  {x:14.8} {y:14.8} {z:14.8}").unwrap();
+    }
+    let symbols: Vec<_> = elements
+        .iter()
+        .map(|e| format!("{:>4}", e.symbol))
+        .collect();
+    let counts: Vec<_> = elements
+        .iter()
+        .map(|element| {
+            let count = sites.iter().filter(|(e, _)| e == element).count();
+            format!("{count:>4}")
+        })
+        .collect();
+    writeln!(out, "{}\n{}", symbols.join(" "), counts.join(" ")).unwrap();
+    out.push_str(match options.coordinates {
+        Coordinates::Cartesian => "Cartesian\n",
+        Coordinates::Fractional => "Direct\n",
+    });
+    for (_, site) in sites {
+        let [x, y, z] = match options.coordinates {
+            Coordinates::Cartesian => structure.cell.to_cartesian(site.fract),
+            Coordinates::Fractional => site.fract,
+        };
+        writeln!(out, "  {x:14.8} {y:14.8} {z:14.8}").unwrap();
+    }
+    Ok(out)
+}
+
+/// Reads the lines of a file in turn, counting them for errors
+struct Lines<'a> {
+    lines: std::str::Lines<'a>,
+    number: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn new(text: &'a str) -> Self {
+        Lines {
+            lines: text.lines(),
+            number: 0,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> FormatError {
+        FormatError::Syntax {
+            line: self.number,
+            message: message.into(),
+        }
+    }
+
+    fn advance(&mut self) -> Option<&'a str> {
+        let line = self.lines.next()?;
+        self.number += 1;
+        Some(line)
+    }
+
+    /// The next line, or an error on the line after the last
+    fn next(&mut self, expected: &str) -> Result<&'a str, FormatError> {
+        self.advance().ok_or_else(|| FormatError::Syntax {
+            line: self.number + 1,
+            message: format!("expected {expected}"),
+        })
+    }
+
+    /// The first `count` fields of a line as numbers
+    fn numbers(&self, line: &str, count: usize, expected: &str) -> Result<Vec<f64>, FormatError> {
+        let numbers: Vec<f64> = line
+            .split_whitespace()
+            .take(count)
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| self.error(format!("expected {expected}")))?;
+        match numbers.len() == count {
+            true => Ok(numbers),
+            false => Err(self.error(format!("expected {expected}"))),
+        }
+    }
+}
+
+/// Fractional coordinates of a Cartesian point, for cell vectors in any
+/// orientation
+fn fractional(vectors: &[[f64; 3]; 3], cartesian: [f64; 3]) -> [f64; 3] {
+    let columns: Matrix = std::array::from_fn(|i| std::array::from_fn(|j| vectors[j][i]));
+    multiply(&invert(&columns), cartesian)
+}
+
+/// `a · (b × c)`, the volume of the cell of the vectors, negative for a
+/// left-handed set
+fn triple_product([a, b, c]: &[[f64; 3]; 3]) -> f64 {
+    a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+        + a[2] * (b[0] * c[1] - b[1] * c[0])
+}
+
+/// Sites labelled by element and a count within each, as `Zn1`, `N1`, `N2`
+fn numbered_sites(atoms: Vec<(Element, [f64; 3], Option<f64>)>) -> Vec<Site> {
+    let mut counts: Vec<(Element, usize)> = Vec::new();
+    atoms
+        .into_iter()
+        .map(|(element, fract, occupancy)| {
+            let n = match counts.iter_mut().find(|(e, _)| *e == element) {
+                Some((_, n)) => {
+                    *n += 1;
+                    *n
+                }
+                None => {
+                    counts.push((element, 1));
+                    1
+                }
+            };
+            Site {
+                label: format!("{}{n}", element.symbol),
+                type_symbol: Some(element.symbol.to_string()),
+                fract,
+                fract_su: [0.0; 3],
+                occupancy,
+                u_iso_or_equiv: None,
+            }
+        })
+        .collect()
+}
+
+fn element(symbol: &str) -> Result<Element, FormatError> {
+    Element::from_symbol(symbol)
+        .ok_or_else(|| FormulaError::UnknownElement(symbol.to_string()).into())
+}
+
+/// Reads a VASP POSCAR or CONTCAR into a block in P 1. The element symbols
+/// come from the line before the counts, or failing that from the title as
+/// older files have them.
+pub fn from_poscar(text: &str, heading: &str) -> Result<DataBlock, FormatError> {
+    let mut lines = Lines::new(text);
+    let title = lines.next("a title")?;
+    let line = lines.next("a scale factor")?;
+    let scale = match line.split_whitespace().count() {
+        3 => lines.numbers(line, 3, "a scale factor")?,
+        _ => lines.numbers(line, 1, "a scale factor")?.repeat(3),
+    };
+    let mut vectors = [[0.0; 3]; 3];
+    for vector in &mut vectors {
+        let line = lines.next("a cell vector")?;
+        let numbers = lines.numbers(line, 3, "a cell vector")?;
+        *vector = std::array::from_fn(|i| numbers[i]);
+    }
+    // A negative scale is the volume of the cell
+    let scale: [f64; 3] = match scale[..] {
+        [volume, ..] if volume < 0.0 => [(-volume / triple_product(&vectors).abs()).cbrt(); 3],
+        _ => std::array::from_fn(|i| scale[i]),
+    };
+    let vectors = vectors.map(|v| std::array::from_fn(|i| v[i] * scale[i]));
+    let mut line = lines.next("the element symbols or counts")?;
+    let symbols: Vec<&str> = match line.split_whitespace().next() {
+        Some(first) if first.parse::<usize>().is_err() => {
+            let symbols = line.split_whitespace().collect();
+            line = lines.next("the counts of each element")?;
+            symbols
+        }
+        _ => title.split_whitespace().collect(),
+    };
+    let counts: Vec<usize> = line
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|_| lines.error("expected the counts of each element"))?;
+    if symbols.len() < counts.len() {
+        return Err(lines.error("no element symbols for the counts"));
+    }
+    // Symbols may carry the name of the potential, as `Zn_pv` or `Zn/1234`
+    let elements = symbols[..counts.len()]
+        .iter()
+        .map(|s| element(s.split(['_', '/']).next().unwrap_or(s)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut line = lines.next("the coordinate mode")?;
+    if line.trim_start().starts_with(['s', 'S']) {
+        line = lines.next("the coordinate mode")?;
+    }
+    let cartesian = line.trim_start().starts_with(['c', 'C', 'k', 'K']);
+    let mut atoms = Vec::new();
+    for (element, count) in elements.iter().zip(&counts) {
+        for _ in 0..*count {
+            let line = lines.next("atom coordinates")?;
+            let numbers = lines.numbers(line, 3, "atom coordinates")?;
+            let point: [f64; 3] = std::array::from_fn(|i| numbers[i]);
+            let fract = match cartesian {
+                true => fractional(&vectors, std::array::from_fn(|i| point[i] * scale[i])),
+                false => point,
+            };
+            atoms.push((*element, fract, None));
+        }
+    }
+    let cell = UnitCell::from_vectors(vectors).map_err(StructureError::from)?;
+    let structure = Structure {
+        cell,
+        sites: numbered_sites(atoms),
+    };
+    Ok(structure.to_block(heading))
+}
+
+/// The `key=value` pairs of an extended XYZ comment line, where values may
+/// be in double quotes
+fn comment_fields(line: &str) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let end = rest.find([' ', '\t', '=']).unwrap_or(rest.len());
+        let key = rest[..end].to_string();
+        rest = &rest[end..];
+        let value = match rest.strip_prefix('=') {
+            Some(after) => match after.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted.find('"').unwrap_or(quoted.len());
+                    rest = quoted.get(end + 1..).unwrap_or("");
+                    quoted[..end].to_string()
+                }
+                None => {
+                    let end = after.find([' ', '\t']).unwrap_or(after.len());
+                    rest = &after[end..];
+                    after[..end].to_string()
+                }
+            },
+            None => String::new(),
+        };
+        fields.push((key, value));
+        rest = rest.trim_start();
+    }
+    fields
+}
+
+/// Reads extended XYZ into a block in P 1. The comment line needs a
+/// `Lattice`; `Properties` gives the columns, by default
+/// `species:S:1:pos:R:3`, and an `occupancy` column is kept.
+pub fn from_extended_xyz(text: &str, heading: &str) -> Result<DataBlock, FormatError> {
+    let mut lines = Lines::new(text);
+    let count: usize = lines
+        .next("the number of atoms")?
+        .trim()
+        .parse()
+        .map_err(|_| lines.error("expected the number of atoms"))?;
+    let fields = comment_fields(lines.next("a comment line")?);
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    };
+    let lattice = field("Lattice").ok_or_else(|| lines.error("no Lattice in the comment line"))?;
+    let lattice = lines.numbers(lattice, 9, "nine numbers in the Lattice")?;
+    let vectors: [[f64; 3]; 3] =
+        std::array::from_fn(|i| std::array::from_fn(|j| lattice[3 * i + j]));
+    // The first column of each property
+    let properties: Vec<_> = field("Properties")
+        .unwrap_or("species:S:1:pos:R:3")
+        .split(':')
+        .collect();
+    let mut columns = Vec::new();
+    let mut width = 0;
+    for property in properties.chunks(3) {
+        let [name, _, n] = property else {
+            return Err(lines.error("expected name:type:count triples in the Properties"));
+        };
+        let n: usize = n
+            .parse()
+            .map_err(|_| lines.error(format!("'{n}' is not a column count")))?;
+        columns.push((name.to_ascii_lowercase(), width));
+        width += n;
+    }
+    let column = |name: &str| columns.iter().find(|(n, _)| n == name).map(|(_, c)| *c);
+    let species = column("species").ok_or_else(|| lines.error("no species in the Properties"))?;
+    let pos = column("pos").ok_or_else(|| lines.error("no pos in the Properties"))?;
+    let occupancy = column("occupancy");
+    let mut atoms = Vec::new();
+    for _ in 0..count {
+        let line = lines.next("an atom")?;
+        let tokens: Vec<_> = line.split_whitespace().collect();
+        if tokens.len() < width {
+            return Err(lines.error(format!("expected {width} columns")));
+        }
+        let number = |i: usize| {
+            tokens[i]
+                .parse::<f64>()
+                .map_err(|_| lines.error(format!("'{}' is not a number", tokens[i])))
+        };
+        let point = [number(pos)?, number(pos + 1)?, number(pos + 2)?];
+        let occupancy = occupancy.map(number).transpose()?;
+        atoms.push((
+            element(tokens[species])?,
+            fractional(&vectors, point),
+            occupancy,
+        ));
+    }
+    let cell = UnitCell::from_vectors(vectors).map_err(StructureError::from)?;
+    let structure = Structure {
+        cell,
+        sites: numbered_sites(atoms),
+    };
+    Ok(structure.to_block(heading))
+}
+
+/// SHELXL instructions; other lines are atoms if shaped like one, see
+/// `atom_values`
+const SHELX_INSTRUCTIONS: [&str; 79] = [
+    "ABIN", "ACTA", "AFIX", "ANIS", "ANSC", "ANSR", "BASF", "BIND", "BLOC", "BOND", "BUMP", "CELL",
+    "CGLS", "CHIV", "CONF", "CONN", "DAMP", "DANG", "DEFS", "DELU", "DFIX", "DISP", "EADP", "END",
+    "EQIV", "EXTI", "EXYZ", "FEND", "FLAT", "FMAP", "FRAG", "FREE", "FVAR", "GRID", "HFIX", "HKLF",
+    "HTAB", "ISOR", "LATT", "LAUE", "LIST", "L.S.", "MERG", "MORE", "MOVE", "MPLA", "NCSY", "NEUT",
+    "OMIT", "PART", "PLAN", "PRIG", "REM", "RESI", "RIGU", "RTAB", "SADI", "SAME", "SFAC", "SHEL",
+    "SIMU", "SIZE", "SPEC", "STIR", "SUMP", "SWAT", "SYMM", "TEMP", "TITL", "TWIN", "TWST", "UNIT",
+    "WGHT", "WIGL", "WPDB", "XNPD", "ZERR", "BEDE", "LONE",
+];
+
+/// The value of a parameter written as `10m + p`: `p` fixed for `m` of ±1,
+/// `p` times free variable `m` for `m > 1` and `p` times one less than free
+/// variable `-m` for `m < -1`
+fn free_variable(value: f64, fvar: &[f64]) -> Option<f64> {
+    let m = (value / 10.0).round();
+    let p = value - 10.0 * m;
+    let m = m as i64;
+    let variable = || {
+        let index = usize::try_from(m.unsigned_abs()).ok()?.checked_sub(1)?;
+        fvar.get(index)
+    };
+    match m {
+        0 => Some(value),
+        1 | -1 => Some(p),
+        m if m > 1 => variable().map(|v| p * v),
+        _ => variable().map(|v| p * (v - 1.0)),
+    }
+}
+
+/// The numbers of a line shaped like an atom: an SFAC number, x, y and z,
+/// then optionally the occupancy and one or six U values, or the occupancy,
+/// U and peak height of a Q peak
+fn atom_values(tokens: &[&str]) -> Option<Vec<f64>> {
+    if !matches!(tokens.len(), 4..=7 | 11) {
+        return None;
+    }
+    let values: Vec<f64> = tokens
+        .iter()
+        .map(|t| t.parse().ok())
+        .collect::<Option<_>>()?;
+    (values[0] >= 0.0 && values[0].fract() == 0.0).then_some(values)
+}
+
+/// An atom line of a SHELX file
+struct ShelxAtom {
+    site: Site,
+    /// U11, U22, U33, U23, U13 and U12
+    aniso: Option<[f64; 6]>,
+}
+
+/// `Ueq`, a third of the trace of the orthogonalised U tensor
+fn u_equivalent(cell: &UnitCell, u: &[f64; 6]) -> f64 {
+    let [u11, u22, u33, u23, u13, u12] = *u;
+    let u = [[u11, u12, u13], [u12, u22, u23], [u13, u23, u33]];
+    let g = cell.metric_tensor();
+    let r = cell.reciprocal_metric_tensor();
+    let star: [f64; 3] = std::array::from_fn(|i| r[i][i].sqrt());
+    (0..3)
+        .flat_map(|i| (0..3).map(move |j| (i, j)))
+        .map(|(i, j)| u[i][j] * star[i] * star[j] * g[i][j])
+        .sum::<f64>()
+        / 3.0
+}
+
+/// The operators of the `SYMM` lines with the identity, inversion for a
+/// positive `LATT` and the centring translations of `|LATT|`
+fn shelx_operators(symm: &[SymOp], latt: i64) -> Option<Vec<SymOp>> {
+    let lattice = "PIRFABC".chars().nth(latt.unsigned_abs() as usize - 1)?;
+    let mut ops = vec![SymOp::identity()];
+    ops.extend_from_slice(symm);
+    if latt > 0 {
+        let inverted: Vec<_> = ops
+            .iter()
+            .map(|op| SymOp {
+                rotation: op.rotation.map(|row| row.map(|r| -r)),
+                translation: op.translation.map(|t| -t),
+            })
+            .collect();
+        ops.extend(inverted);
+    }
+    let mut all: Vec<SymOp> = Vec::new();
+    for translation in std::iter::once([Fraction::ZERO; 3]).chain(centring(lattice)?) {
+        for op in &ops {
+            let op = SymOp {
+                rotation: op.rotation,
+                translation: std::array::from_fn(|i| op.translation[i] + translation[i]),
+            }
+            .normalized();
+            if !all.contains(&op) {
+                all.push(op);
+            }
+        }
+    }
+    Some(all)
+}
+
+/// Reads a SHELX `.ins` or `.res` file into a block with the cell and its
+/// su, the symmetry operators, the atoms with their occupancies and
+/// displacement parameters decoded from free variables, and the formula
+/// from `SFAC` and `UNIT`. Reading stops at `HKLF` or `END`. Occupancies
+/// are those of the atoms rather than the SHELX ones, which are reduced on
+/// special positions.
+pub fn from_shelx(text: &str, heading: &str) -> Result<DataBlock, FormatError> {
+    let mut lines = Lines::new(text);
+    let mut parameters: Option<Vec<f64>> = None;
+    let mut su: Option<Vec<f64>> = None;
+    let mut z: Option<f64> = None;
+    let mut latt = 1;
+    let mut symm = Vec::new();
+    let mut sfac: Vec<Element> = Vec::new();
+    let mut unit: Vec<f64> = Vec::new();
+    let mut fvar: Vec<f64> = Vec::new();
+    let mut atoms: Vec<ShelxAtom> = Vec::new();
+    // Ueq of the last atom without a riding U, for those with one
+    let mut parent_u = None;
+    while let Some(mut line) = lines.advance().map(str::to_string) {
+        // `=` at the end of a line continues it on the next
+        while line.trim_end().ends_with('=') {
+            let trimmed = line.trim_end().trim_end_matches('=').to_string();
+            let next = lines.next("a continuation line")?;
+            line = format!("{trimmed} {next}");
+        }
+        let line = line.split('!').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let Some(first) = tokens.next() else {
+            continue;
+        };
+        let rest: Vec<_> = tokens.collect();
+        let keyword = first.to_ascii_uppercase();
+        let numbers =
+            |count: usize, expected: &str| lines.numbers(&rest.join(" "), count, expected);
+        match keyword.as_str() {
+            "HKLF" | "END" => break,
+            "CELL" => parameters = Some(numbers(7, "a wavelength and six cell parameters")?),
+            "ZERR" => {
+                let values = numbers(7, "Z and six su")?;
+                z = Some(values[0]);
+                su = Some(values[1..].to_vec());
+            }
+            "LATT" => {
+                latt = rest
+                    .first()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|n: &i64| (1..=7).contains(&n.abs()))
+                    .ok_or_else(|| lines.error("expected a lattice type from -7 to 7"))?;
+            }
+            "SYMM" => symm.push(rest.join("").parse::<SymOp>()?),
+            "SFAC" => {
+                // Either symbols, or one symbol followed by scattering factors
+                let symbols = match rest.get(1).map(|t| t.parse::<f64>()) {
+                    Some(Ok(_)) => &rest[..1],
+                    _ => &rest[..],
+                };
+                for symbol in symbols {
+                    let mut chars = symbol.chars();
+                    let symbol: String = chars
+                        .next()
+                        .map(|c| c.to_ascii_uppercase())
+                        .into_iter()
+                        .chain(chars.map(|c| c.to_ascii_lowercase()))
+                        .collect();
+                    sfac.push(element(&symbol)?);
+                }
+            }
+            "UNIT" => unit = numbers(rest.len(), "element counts")?,
+            "FVAR" => fvar.extend(numbers(rest.len(), "free variables")?),
+            keyword
+                // SHELX reads only the first four characters
+                if SHELX_INSTRUCTIONS.contains(&keyword.get(..4).unwrap_or(keyword)) => {}
+            _ => {
+                // Instructions SHELX added since, or this reader does not know
+                let Some(values) = atom_values(&rest) else {
+                    continue;
+                };
+                let Some(cell) = &parameters else {
+                    return Err(lines.error("an atom before CELL"));
+                };
+                let cell =
+                    UnitCell::from_parameters(cell[1], cell[2], cell[3], cell[4], cell[5], cell[6])
+                        .map_err(StructureError::from)?;
+                let decode = |value: f64| {
+                    free_variable(value, &fvar).ok_or_else(|| {
+                        lines.error(format!("{value} refers to a missing free variable"))
+                    })
+                };
+                let index = values[0] as usize;
+                let element = index
+                    .checked_sub(1)
+                    .and_then(|i| sfac.get(i))
+                    .ok_or_else(|| lines.error(format!("no SFAC element {index}")))?;
+                let fract = [decode(values[1])?, decode(values[2])?, decode(values[3])?];
+                let occupancy = decode(values.get(4).copied().unwrap_or(11.0))?;
+                let (u_iso, aniso) = match *values.get(5..).unwrap_or_default() {
+                    [u11, u22, u33, u23, u13, u12] => {
+                        let mut u = [u11, u22, u33, u23, u13, u12];
+                        for value in &mut u {
+                            *value = decode(*value)?;
+                        }
+                        let ueq = u_equivalent(&cell, &u);
+                        parent_u = Some(ueq);
+                        (Some(ueq), Some(u))
+                    }
+                    // A negative U rides on the atom before
+                    [u] if u < -0.5 => {
+                        let parent = parent_u
+                            .ok_or_else(|| lines.error("a riding U with no atom before"))?;
+                        (Some(-u * parent), None)
+                    }
+                    [u] => {
+                        let u = decode(u)?;
+                        parent_u = Some(u);
+                        (Some(u), None)
+                    }
+                    _ => (None, None),
+                };
+                atoms.push(ShelxAtom {
+                    site: Site {
+                        label: first.to_string(),
+                        type_symbol: Some(element.symbol.to_string()),
+                        fract,
+                        fract_su: [0.0; 3],
+                        occupancy: Some(occupancy),
+                        u_iso_or_equiv: u_iso,
+                    },
+                    aniso,
+                });
+            }
+        }
+    }
+    let parameters = parameters.ok_or_else(|| lines.error("no CELL instruction"))?;
+    let measurement = |i: usize| {
+        let su = su.as_ref().map(|su| su[i - 1]).filter(|su| *su > 0.0);
+        Measurement::new(parameters[i], su)
+    };
+    let cell = UnitCell::new(
+        [measurement(1), measurement(2), measurement(3)],
+        [measurement(4), measurement(5), measurement(6)],
+    )
+    .map_err(StructureError::from)?;
+    let ops = shelx_operators(&symm, latt).ok_or_else(|| lines.error("invalid LATT"))?;
+    let (sites, aniso): (Vec<_>, Vec<_>) = atoms.into_iter().map(|a| (a.site, a.aniso)).unzip();
+    let mut structure = Structure { cell, sites };
+    // SHELX occupancies include the fraction of the operators that move a
+    // site on a special position
+    for i in 0..structure.sites.len() {
+        let site = Structure {
+            cell,
+            sites: vec![structure.sites[i].clone()],
+        };
+        let images = site.expand(&ops, DEFAULT_TOLERANCE).sites.len();
+        if let Some(occupancy) = &mut structure.sites[i].occupancy {
+            *occupancy *= ops.len() as f64 / images as f64;
+        }
+    }
+    let mut block = structure.to_block_with_operators(heading, &ops);
+    let items = &mut block.content;
+    let text = |s: String| Some(DataValue::Str(s));
+    push_item(
+        items,
+        "_diffrn_radiation_wavelength.value",
+        text(parameters[0].to_string()),
+    );
+    if let Some(z) = z {
+        push_item(items, "_cell.formula_units_Z", text(z.to_string()));
+        if !unit.is_empty() {
+            let mut formula = Formula::default();
+            for (element, count) in sfac.iter().zip(&unit) {
+                formula.add(*element, count / z);
+            }
+            push_item(items, "_chemical_formula.sum", text(formula.to_string()));
+        }
+    }
+    let anisotropic: Vec<_> = structure
+        .sites
+        .iter()
+        .zip(&aniso)
+        .filter_map(|(site, u)| u.map(|u| (site, u)))
+        .collect();
+    let names = ["11", "22", "33", "23", "13", "12"].map(|ij| format!("_atom_site_aniso.U_{ij}"));
+    let mut columns = vec![(
+        "_atom_site_aniso.label",
+        anisotropic
+            .iter()
+            .map(|(site, _)| text(site.label.clone()))
+            .collect(),
+    )];
+    for (i, name) in names.iter().enumerate() {
+        columns.push((
+            name.as_str(),
+            anisotropic
+                .iter()
+                .map(|(_, u)| text(u[i].to_string()))
+                .collect(),
+        ));
+    }
+    push_loop(items, columns);
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use rstest::rstest;
+
+    fn zif_8() -> Structure {
+        let text = include_str!("../../cif_chomper/example_data/zif-8.cif");
+        let model = cif2_file(text).unwrap();
+        cell_contents(&model.content[0]).unwrap()
+    }
+
+    fn site(label: &str, fract: [f64; 3]) -> Site {
+        Site {
+            label: label.to_string(),
+            type_symbol: None,
+            fract,
+            fract_su: [0.0; 3],
+            occupancy: None,
+            u_iso_or_equiv: None,
+        }
+    }
+
+    /// Sodium chloride in P 1 with one of each atom
+    fn small() -> Structure {
+        let length = Measurement::exact(5.64);
+        Structure {
+            cell: UnitCell::new([length; 3], [Measurement::exact(90.0); 3]).unwrap(),
+            sites: vec![site("Na1", [0.0; 3]), site("Cl1", [0.5, 0.5, 0.5])],
+        }
+    }
+
+    #[test]
+    fn test_xyz() {
+        let xyz = to_xyz(&small(), &ExportOptions::default()).unwrap();
+        assert_eq!(
+            xyz,
+            "2\nCl Na\n\
+             Na     0.00000000     0.00000000     0.00000000\n\
+             Cl     2.82000000     2.82000000     2.82000000\n"
+        );
+    }
+
+    #[test]
+    fn test_extended_xyz() {
+        let options = ExportOptions {
+            title: Some("rock \"salt\"".to_string()),
+            ..Default::default()
+        };
+        let xyz = to_extended_xyz(&small(), &options).unwrap();
+        let lines: Vec<_> = xyz.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[1],
+            "Lattice=\"5.64000000 0.00000000 0.00000000 0.00000000 5.64000000 0.00000000 \
+             0.00000000 0.00000000 5.64000000\" Properties=species:S:1:pos:R:3:occupancy:R:1 \
+             pbc=\"T T T\" comment=\"rock 'salt'\""
+        );
+        assert_eq!(
+            lines[3],
+            "Cl     2.82000000     2.82000000     2.82000000   1.0000"
+        );
+    }
+
+    #[test]
+    fn test_pdb() {
+        let pdb = to_pdb(&small(), &ExportOptions::default()).unwrap();
+        let lines: Vec<_> = pdb.lines().collect();
+        assert_eq!(
+            lines[1],
+            "CRYST1    5.640    5.640    5.640  90.00  90.00  90.00 P 1           1"
+        );
+        assert_eq!(
+            lines[3],
+            "HETATM    2 Cl1  UNL A   1       2.820   2.820   2.820  1.00  0.00          CL"
+        );
+        assert_eq!(lines[3].len(), 78);
+        assert_eq!(lines.last(), Some(&"END"));
+    }
+
+    #[rstest]
+    #[case(
+        Coordinates::Fractional,
+        "Direct",
+        "      0.50000000     0.50000000     0.50000000"
+    )]
+    #[case(
+        Coordinates::Cartesian,
+        "Cartesian",
+        "      2.82000000     2.82000000     2.82000000"
+    )]
+    fn test_poscar(#[case] coordinates: Coordinates, #[case] mode: &str, #[case] chlorine: &str) {
+        let options = ExportOptions {
+            coordinates,
+            order: ElementOrder::Alphabetical,
+            title: Some("NaCl".to_string()),
+        };
+        let poscar = to_poscar(&small(), &options).unwrap();
+        let lines: Vec<_> = poscar.lines().collect();
+        assert_eq!(lines[..2], ["NaCl", "1.0"]);
+        assert_eq!(lines[2], "      5.64000000     0.00000000     0.00000000");
+        assert_eq!(lines[5..8], ["  Cl   Na", "   1    1", mode]);
+        assert_eq!(lines[8], chlorine);
+    }
+
+    #[rstest]
+    #[case(ElementOrder::AsListed, ["Zn", "N", "C", "H"])]
+    #[case(ElementOrder::Alphabetical, ["C", "H", "N", "Zn"])]
+    #[case(ElementOrder::AtomicNumber, ["H", "C", "N", "Zn"])]
+    #[case(ElementOrder::Given(vec!["H".to_string(), "Zn".to_string()]), ["H", "Zn", "N", "C"])]
+    fn test_element_order(#[case] order: ElementOrder, #[case] expected: [&str; 4]) {
+        let structure = zif_8();
+        let sites = ordered_sites(&structure, &order).unwrap();
+        assert_eq!(sites.len(), 300);
+        // The sites of each element are together
+        let mut symbols: Vec<_> = sites.iter().map(|(e, _)| e.symbol).collect();
+        symbols.dedup();
+        assert_eq!(symbols, expected);
+    }
+
+    #[test]
+    fn test_zif_8() {
+        let structure = zif_8();
+        let options = ExportOptions {
+            coordinates: Coordinates::Fractional,
+            ..Default::default()
+        };
+        let poscar = to_poscar(&structure, &options).unwrap();
+        let lines: Vec<_> = poscar.lines().collect();
+        assert_eq!(lines[0], "C72 H72 N48 Zn12");
+        assert_eq!(lines[5..7], ["  Zn    N    C    H", "  12   48  120  120"]);
+        assert_eq!(lines.len(), 8 + 300);
+        let xyz = to_xyz(&structure, &options).unwrap();
+        assert!(xyz.starts_with("300\nC72 H72 N48 Zn12\nZn"));
+    }
+
+    #[test]
+    fn test_unknown_element() {
+        let mut structure = small();
+        structure.sites[0].label = "Q1".to_string();
+        assert_eq!(
+            to_xyz(&structure, &ExportOptions::default()),
+            Err(FormatError::Formula(FormulaError::UnknownElement(
+                "Q1".to_string()
+            )))
+        );
+    }
+
+    /// The structure of a block written by a reader, checked to be CIF
+    fn read_back(block: &DataBlock) -> (Structure, Vec<SymOp>) {
+        let text = format!("#\\#CIF_2.0\n{block}");
+        let model = cif2_file(&text).unwrap();
+        let block = &model.content[0];
+        (
+            Structure::from_block(block).unwrap(),
+            block_operators(block).unwrap(),
+        )
+    }
+
+    /// The sites of a structure in the order they are exported
+    fn exported(structure: &Structure, order: &ElementOrder) -> Structure {
+        let sites = ordered_sites(structure, order).unwrap();
+        Structure {
+            cell: structure.cell,
+            sites: sites.into_iter().map(|(_, site)| site.clone()).collect(),
+        }
+    }
+
+    fn assert_same_sites(read: &Structure, written: &Structure) {
+        for i in 0..3 {
+            assert!((read.cell.lengths[i].value - written.cell.lengths[i].value).abs() < 1e-6);
+            assert!((read.cell.angles[i].value - written.cell.angles[i].value).abs() < 1e-6);
+        }
+        assert_eq!(read.sites.len(), written.sites.len());
+        for (read_site, written_site) in read.sites.iter().zip(&written.sites) {
+            assert_eq!(read_site.type_symbol, written_site.type_symbol);
+            assert!(written.periodic_distance(read_site.fract, written_site.fract) < 1e-4);
+        }
+    }
+
+    #[rstest]
+    #[case(Coordinates::Fractional)]
+    #[case(Coordinates::Cartesian)]
+    fn test_poscar_round_trip(#[case] coordinates: Coordinates) {
+        let structure = zif_8();
+        let options = ExportOptions {
+            coordinates,
+            order: ElementOrder::AtomicNumber,
+            title: None,
+        };
+        let block = from_poscar(&to_poscar(&structure, &options).unwrap(), "zif_8").unwrap();
+        let (read, ops) = read_back(&block);
+        assert_eq!(ops, [SymOp::identity()]);
+        assert_eq!(read.sites[0].label, "H1");
+        assert_eq!(read.sites[299].label, "Zn12");
+        assert_same_sites(&read, &exported(&structure, &options.order));
+    }
+
+    #[test]
+    fn test_poscar_variants() {
+        // Symbols in the title, a volume for the scale, selective dynamics
+        // and a cell not along x
+        let poscar = "Na Cl\n-44.851536\n0 0.5 0.5\n0.5 0 0.5\n0.5 0.5 0\n1 1\n\
+                      Selective dynamics\nCartesian\n0 0 0 T T T\n0.5 0.5 0.5 F F F\n";
+        let (read, _) = read_back(&from_poscar(poscar, "nacl").unwrap());
+        assert!((read.cell.lengths[0].value - 3.988).abs() < 1e-3);
+        assert!((read.cell.angles[0].value - 60.0).abs() < 1e-9);
+        let labels: Vec<_> = read.sites.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["Na1", "Cl1"]);
+        assert!(read.sites[1].fract.iter().all(|x| (x - 0.5).abs() < 1e-9));
+        // Symbols with potentials, three scale factors
+        let poscar = "NaCl\n1 1 2\n5.64 0 0\n0 5.64 0\n0 0 2.82\nNa_pv Cl\n1 1\nDirect\n\
+                      0 0 0\n0.5 0.5 0.5\n";
+        let (read, _) = read_back(&from_poscar(poscar, "nacl").unwrap());
+        assert_eq!(read.cell.lengths.map(|l| l.value), [5.64, 5.64, 5.64]);
+    }
+
+    #[rstest]
+    #[case("NaCl\n1.0\n5.64 0 0\n0 5.64 0\n", "line 5: expected a cell vector")]
+    #[case(
+        "NaCl\n1.0\n5.64 0 0\n0 5.64 0\n0 0 x\n",
+        "line 5: expected a cell vector"
+    )]
+    #[case(
+        "x\n1.0\n1 0 0\n0 1 0\n0 0 1\n1 1\nDirect\n0 0 0\n",
+        "line 6: no element symbols for the counts"
+    )]
+    #[case(
+        "Q\n1.0\n1 0 0\n0 1 0\n0 0 1\n1\nDirect\n0 0 0\n",
+        "'Q' is not an element"
+    )]
+    #[case(
+        "Na\n1.0\n1 0 0\n0 1 0\n0 0 1\n2\nDirect\n0 0 0\n",
+        "line 9: expected atom coordinates"
+    )]
+    fn test_poscar_errors(#[case] text: &str, #[case] message: &str) {
+        assert_eq!(from_poscar(text, "x").unwrap_err().to_string(), message);
+    }
+
+    #[test]
+    fn test_comment_fields() {
+        let fields = comment_fields(r#"Lattice="1 0 0 0 1 0 0 0 1" pbc="T T T" energy=-1.5 flag"#);
+        let expected = [
+            ("Lattice", "1 0 0 0 1 0 0 0 1"),
+            ("pbc", "T T T"),
+            ("energy", "-1.5"),
+            ("flag", ""),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        assert_eq!(fields, expected);
+    }
+
+    #[test]
+    fn test_extended_xyz_round_trip() {
+        let structure = exported(&zif_8(), &ElementOrder::AsListed);
+        let xyz = to_extended_xyz(&structure, &ExportOptions::default()).unwrap();
+        let (read, _) = read_back(&from_extended_xyz(&xyz, "zif_8").unwrap());
+        assert_same_sites(&read, &structure);
+        let occupancies: Vec<_> = read.sites.iter().map(|s| s.occupancy.unwrap()).collect();
+        let expected: Vec<_> = structure
+            .sites
+            .iter()
+            .map(|s| s.occupancy.unwrap())
+            .collect();
+        assert_eq!(occupancies, expected);
+    }
+
+    #[test]
+    fn test_extended_xyz_columns() {
+        let xyz = "2\nProperties=id:I:1:species:S:1:force:R:3:pos:R:3 Lattice=\"4 0 0 0 4 0 0 0 4\"\n\
+                   1 Na 0 0 0 0 0 0\n2 Cl 0 0 0 1 2 3\n";
+        let (read, _) = read_back(&from_extended_xyz(xyz, "x").unwrap());
+        assert_eq!(read.sites[1].fract, [0.25, 0.5, 0.75]);
+        assert_eq!(read.sites[1].occupancy, None);
+    }
+
+    #[rstest]
+    #[case("2\nplain xyz\n", "line 2: no Lattice in the comment line")]
+    #[case("x\n", "line 1: expected the number of atoms")]
+    #[case(
+        "1\nLattice=\"4 0 0 0 4 0 0 0 4\" Properties=species:S\n",
+        "line 2: expected name:type:count triples in the Properties"
+    )]
+    #[case(
+        "1\nLattice=\"4 0 0 0 4 0 0 0 4\"\nNa 0 0\n",
+        "line 3: expected 4 columns"
+    )]
+    fn test_extended_xyz_errors(#[case] text: &str, #[case] message: &str) {
+        assert_eq!(
+            from_extended_xyz(text, "x").unwrap_err().to_string(),
+            message
+        );
+    }
+
+    const ZIF_8_RES: &str = "TITL zif-8 in I-43m
+CELL 0.71073 16.8303 16.8303 16.8303 90 90 90
+ZERR 12 0.0002 0.0002 0.0002 0 0 0
+LATT -2
+SYMM -x,-y,z
+SYMM -x,y,-z
+SYMM x,-y,-z
+SYMM z,x,y
+SYMM z,-x,-y
+SYMM -z,-x,y
+SYMM -z,x,-y
+SYMM y,z,x
+SYMM -y,z,-x
+SYMM y,-z,-x
+SYMM -y,-z,x
+SYMM y,x,z
+SYMM -y,-x,z
+SYMM y,-x,-z
+SYMM -y,x,-z
+SYMM x,z,y
+SYMM -x,z,-y
+SYMM -x,-z,y
+SYMM x,-z,-y
+SYMM z,y,x
+SYMM z,-y,-x
+SYMM -z,y,-x
+SYMM -z,-y,x
+SFAC C H N ZN
+UNIT 72 72 48 12
+L.S. 10
+REM the methyl group is disordered
+FVAR 1.2345 0.63
+Zn1 4 10.5 10.25 10.0 10.25 0.05526 0.0446 0.05526 0 0 0
+N1 3 0.53668 0.18295 0.08924 11.0 0.0591 0.0588 0.0712 0.0165 -0.0037 =
+ -0.0051
+C1 1 0.5039 0.12063 0.12063 10.5 0.0583
+H1 2 0.4569 0.0985 0.0985 10.5 -1.2
+PART 1
+C2 1 0.6083 0.1825 0.1263 21.0 0.098 ! the major part
+PART 2
+C2A 1 0.5763 0.2129 0.1536 -21.0 0.126
+PART 0
+HKLF 4
+END
+Q1 1 0.5 0.5 0.5 11.0 0.05 0.5
+";
+
+    #[test]
+    fn test_shelx() {
+        let block = from_shelx(ZIF_8_RES, "zif_8").unwrap();
+        let (read, ops) = read_back(&block);
+        assert_eq!(ops.len(), 48);
+        let raw = block.as_raw();
+        let value = |name: &str| {
+            crate::model::column(&raw, &[name]).map(|c| c[0].as_str().unwrap().to_string())
+        };
+        assert_eq!(value("_space_group.name_H-M_alt").unwrap(), "I -4 3 m");
+        assert_eq!(value("_chemical_formula.sum").unwrap(), "C6 H6 N4 Zn");
+        assert_eq!(value("_cell.formula_units_Z").unwrap(), "12");
+        assert_eq!(
+            value("_diffrn_radiation_wavelength.value").unwrap(),
+            "0.71073"
+        );
+        assert_eq!(value("_atom_site_aniso.label").unwrap(), "Zn1");
+        assert_eq!(read.cell.lengths[0].to_string(), "16.8303(2)");
+        let labels: Vec<_> = read.sites.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["Zn1", "N1", "C1", "H1", "C2", "C2A"]);
+        let occupancies: Vec<_> = read.sites.iter().map(|s| s.occupancy.unwrap()).collect();
+        assert_eq!(occupancies, [1.0, 1.0, 1.0, 1.0, 0.63, 0.37]);
+        assert_eq!(read.sites[0].fract, [0.5, 0.25, 0.0]);
+        assert_eq!(read.sites[3].type_symbol.as_deref(), Some("H"));
+        let u: Vec<_> = read
+            .sites
+            .iter()
+            .map(|s| s.u_iso_or_equiv.unwrap())
+            .collect();
+        assert!((u[0] - 0.05172).abs() < 1e-4);
+        assert!((u[1] - 0.0630).abs() < 1e-4);
+        assert!((u[3] - 1.2 * 0.0583).abs() < 1e-12);
+        // The file is consistent with the formula it gives
+        let text = format!("#\\#CIF_2.0\n{block}");
+        let model = cif2_file(&text).unwrap();
+        let mismatches = crate::formula::check_block(&model.content[0]).unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert!(matches!(
+            &mismatches[0],
+            crate::formula::CompositionMismatch::Sites { computed, .. } if computed.count("H") == 2.0
+        ));
+    }
+
+    #[rstest]
+    #[case("C1 1 0 0 0\n", "line 1: an atom before CELL")]
+    #[case(
+        "CELL 1 5 5 5 90 90\n",
+        "line 1: expected a wavelength and six cell parameters"
+    )]
+    #[case(
+        "CELL 1 5 5 5 90 90 90\nSFAC C\nC1 2 0 0 0\n",
+        "line 3: no SFAC element 2"
+    )]
+    #[case(
+        "CELL 1 5 5 5 90 90 90\nSFAC C\nC1 1 0 0 0 31.0\n",
+        "line 3: 31 refers to a missing free variable"
+    )]
+    #[case(
+        "CELL 1 5 5 5 90 90 90\nLATT 8\n",
+        "line 2: expected a lattice type from -7 to 7"
+    )]
+    #[case(
+        "CELL 1 5 5 5 90 90 90\nSYMM x,y\n",
+        "'x,y' is not a symmetry operator"
+    )]
+    #[case("CELL 1 5 5 5 90 90 90\nSFAC Xx\n", "'Xx' is not an element")]
+    #[case("SFAC C\n", "line 1: no CELL instruction")]
+    fn test_shelx_errors(#[case] text: &str, #[case] message: &str) {
+        assert_eq!(from_shelx(text, "x").unwrap_err().to_string(), message);
+    }
+
+    #[rstest]
+    #[case(0.25, 0.25)]
+    #[case(10.5, 0.5)]
+    #[case(-10.25, -0.25)]
+    #[case(21.0, 0.63)]
+    #[case(-21.0, 1.0 - 0.63)]
+    #[case(30.5, 0.5 * 0.2)]
+    fn test_free_variable(#[case] value: f64, #[case] expected: f64) {
+        let decoded = free_variable(value, &[1.5, 0.63, 0.2]).unwrap();
+        assert!((decoded - expected).abs() < 1e-12);
+    }
+
+    #[rstest]
+    #[case(41.0)]
+    #[case(-41.0)]
+    #[case(-1e300)]
+    #[case(1e300)]
+    fn test_missing_free_variable(#[case] value: f64) {
+        assert_eq!(free_variable(value, &[1.5, 0.63, 0.2]), None);
+    }
+
+    #[test]
+    fn test_shelx_unknown_instructions() {
+        let text = "CELL 1 5 5 5 90 90 90\nSFAC C\nMOLE 1\nCONN 3 C1\nBIND 1 2\n\
+                    EQIV $1 -x, y, z\nNEWI 1 2 3\nC1 1 0.1 0.2 0.3 11.0 0.05\n";
+        let block = from_shelx(text, "x").unwrap();
+        let (read, _) = read_back(&block);
+        let labels: Vec<_> = read.sites.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["C1"]);
+    }
+}
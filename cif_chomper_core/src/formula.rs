@@ -0,0 +1,574 @@
+/// Chemical formulas from `_chemical_formula_sum` and
+/// `_chemical_formula_moiety`, their weights, and checks of them against the
+/// atom sites, `_cell.formula_units_Z` and the stated density
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cell::UnitCell;
+use crate::model::{ModelError, single};
+use crate::numeric::Measurement;
+use crate::raw_model::RawDataBlock;
+use crate::space_group::block_operators;
+use crate::structure::{DEFAULT_TOLERANCE, Site, Structure, StructureError};
+use crate::symmetry::{SymOp, SymmetryError};
+
+const NAMES: &str = include_str!("data/element_names.txt");
+const WEIGHTS: &str = include_str!("data/atomic_weights.txt");
+
+const SUM: [&str; 2] = ["_chemical_formula.sum", "_chemical_formula_sum"];
+const MOIETY: [&str; 2] = ["_chemical_formula.moiety", "_chemical_formula_moiety"];
+const WEIGHT: [&str; 2] = ["_chemical_formula.weight", "_chemical_formula_weight"];
+const Z: [&str; 2] = ["_cell.formula_units_Z", "_cell_formula_units_Z"];
+const DENSITY: [&str; 2] = [
+    "_exptl_crystal.density_diffrn",
+    "_exptl_crystal_density_diffrn",
+];
+
+/// Avogadro's number, per mole
+const AVOGADRO: f64 = 6.022_140_76e23;
+
+/// Stated and computed values may differ by this fraction, beyond three
+/// combined su, as tables of atomic weights differ and values are rounded
+pub const RELATIVE_TOLERANCE: f64 = 2e-3;
+/// Element counts per formula unit may differ by this much as well, for
+/// occupancies given to two places
+const COUNT_TOLERANCE: f64 = 0.01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormulaError {
+    UnknownElement(String),
+    Invalid(String),
+    Model(ModelError),
+    Structure(StructureError),
+    Symmetry(SymmetryError),
+}
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormulaError::UnknownElement(symbol) => write!(f, "'{symbol}' is not an element"),
+            FormulaError::Invalid(text) => write!(f, "'{text}' is not a chemical formula"),
+            FormulaError::Model(e) => write!(f, "{e}"),
+            FormulaError::Structure(e) => write!(f, "{e}"),
+            FormulaError::Symmetry(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for FormulaError {}
+
+impl From<ModelError> for FormulaError {
+    fn from(e: ModelError) -> Self {
+        FormulaError::Model(e)
+    }
+}
+
+impl From<StructureError> for FormulaError {
+    fn from(e: StructureError) -> Self {
+        FormulaError::Structure(e)
+    }
+}
+
+impl From<SymmetryError> for FormulaError {
+    fn from(e: SymmetryError) -> Self {
+        FormulaError::Symmetry(e)
+    }
+}
+
+/// An element from the tables of `data/element_names.txt` and
+/// `data/atomic_weights.txt`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Element {
+    pub number: u8,
+    pub symbol: &'static str,
+    pub name: &'static str,
+    /// Standard atomic weight, or the mass number of the longest lived
+    /// isotope where there is none
+    pub weight: f64,
+}
+
+/// The number, symbol and last field of a line such as `( 1  "H",  1.008)`
+fn table_fields(line: &'static str) -> (u8, &'static str, &'static str) {
+    let line = line.trim().trim_start_matches('(').trim_end_matches(')');
+    let (head, last) = line.split_once(',').expect("two fields per element");
+    let (number, symbol) = head.trim().split_once(' ').expect("a number and a symbol");
+    (
+        number.parse().expect("an atomic number"),
+        symbol.trim().trim_matches('"'),
+        last.trim().trim_matches('"'),
+    )
+}
+
+impl Element {
+    /// All elements by atomic number
+    pub fn all() -> impl Iterator<Item = Element> {
+        NAMES.lines().zip(WEIGHTS.lines()).map(|(names, weights)| {
+            let (number, symbol, name) = table_fields(names);
+            let (_, _, weight) = table_fields(weights);
+            Element {
+                number,
+                symbol,
+                name,
+                weight: weight.parse().expect("an atomic weight"),
+            }
+        })
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Element> {
+        Element::all().find(|e| e.symbol == symbol)
+    }
+
+    /// The element of an atom type or site label such as `Zn2+`, `O1` or
+    /// `C2A`: a capital letter and any lower case letters following it
+    pub fn from_type_symbol(type_symbol: &str) -> Option<Element> {
+        let mut chars = type_symbol.char_indices();
+        let (_, first) = chars.next()?;
+        if !first.is_ascii_uppercase() {
+            return None;
+        }
+        let end = chars
+            .find(|(_, c)| !c.is_ascii_lowercase())
+            .map_or(type_symbol.len(), |(i, _)| i);
+        Element::from_symbol(&type_symbol[..end])
+    }
+
+    /// The element of a site from its type symbol, or failing that its label
+    pub fn of_site(site: &Site) -> Result<Element, FormulaError> {
+        let symbol = site.type_symbol.as_ref().unwrap_or(&site.label);
+        Element::from_type_symbol(symbol)
+            .ok_or_else(|| FormulaError::UnknownElement(symbol.clone()))
+    }
+}
+
+/// Counts of each element, in the order they were added
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Formula {
+    pub counts: Vec<(Element, f64)>,
+}
+
+impl Formula {
+    pub fn add(&mut self, element: Element, count: f64) {
+        match self
+            .counts
+            .iter_mut()
+            .find(|(e, _)| e.number == element.number)
+        {
+            Some((_, total)) => *total += count,
+            None => self.counts.push((element, count)),
+        }
+    }
+
+    /// The count of an element, zero if it is absent
+    pub fn count(&self, symbol: &str) -> f64 {
+        self.counts
+            .iter()
+            .find(|(e, _)| e.symbol == symbol)
+            .map_or(0.0, |(_, count)| *count)
+    }
+
+    /// The formula weight in daltons
+    pub fn weight(&self) -> f64 {
+        self.counts.iter().map(|(e, count)| e.weight * count).sum()
+    }
+
+    pub fn scaled(&self, factor: f64) -> Formula {
+        Formula {
+            counts: self.counts.iter().map(|(e, n)| (*e, n * factor)).collect(),
+        }
+    }
+
+    /// Whether each element has the same count in both, within rounding
+    pub fn agrees_with(&self, other: &Formula) -> bool {
+        self.counts.iter().chain(&other.counts).all(|(element, _)| {
+            let (a, b) = (self.count(element.symbol), other.count(element.symbol));
+            (a - b).abs() <= COUNT_TOLERANCE + RELATIVE_TOLERANCE * a.max(b)
+        })
+    }
+
+    /// Reads a moiety formula: formulas separated by commas, each with an
+    /// optional multiplier as in `2(H2 O)` and an optional charge as in
+    /// `C6 H5 O7 3-`. The result is the sum of the moieties.
+    pub fn from_moiety(text: &str) -> Result<Formula, FormulaError> {
+        let invalid = || FormulaError::Invalid(text.to_string());
+        let mut total = Formula::default();
+        for moiety in text.split(',') {
+            let moiety = moiety.trim();
+            let (multiplier, formula) = match moiety.split_once('(') {
+                Some((multiplier, rest)) => (
+                    multiplier.trim().parse().map_err(|_| invalid())?,
+                    rest.strip_suffix(')').ok_or_else(invalid)?,
+                ),
+                None => (1.0, moiety),
+            };
+            let is_charge = |token: &str| {
+                token
+                    .strip_suffix(['+', '-'])
+                    .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+            };
+            let formula: Vec<_> = formula.split_whitespace().collect();
+            let formula = match formula.split_last() {
+                Some((last, rest)) if is_charge(last) => rest,
+                _ => &formula[..],
+            };
+            for (element, count) in parse_tokens(formula)? {
+                total.add(element, count * multiplier);
+            }
+        }
+        Ok(total)
+    }
+
+    /// The contents of the unit cell of a structure, from its sites
+    /// expanded by the operators and weighted by occupancy
+    pub fn from_structure(structure: &Structure, ops: &[SymOp]) -> Result<Formula, FormulaError> {
+        let mut formula = Formula::default();
+        for site in structure.expand(ops, DEFAULT_TOLERANCE).sites {
+            formula.add(Element::of_site(&site)?, site.occupancy.unwrap_or(1.0));
+        }
+        Ok(formula)
+    }
+}
+
+/// The elements and counts of whitespace separated tokens such as `C6`,
+/// `H` or `O0.5`
+fn parse_tokens(tokens: &[&str]) -> Result<Vec<(Element, f64)>, FormulaError> {
+    tokens
+        .iter()
+        .map(|token| {
+            let split = token
+                .find(|c: char| c.is_ascii_digit() || c == '.')
+                .unwrap_or(token.len());
+            let (symbol, count) = token.split_at(split);
+            let element = Element::from_symbol(symbol)
+                .ok_or_else(|| FormulaError::UnknownElement(symbol.to_string()))?;
+            let count = match count {
+                "" => 1.0,
+                count => count
+                    .parse()
+                    .map_err(|_| FormulaError::Invalid(token.to_string()))?,
+            };
+            Ok((element, count))
+        })
+        .collect()
+}
+
+impl FromStr for Formula {
+    type Err = FormulaError;
+
+    /// Reads a sum formula such as `C6 H6 N4 Zn`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<_> = s.split_whitespace().collect();
+        let mut formula = Formula::default();
+        for (element, count) in parse_tokens(&tokens)? {
+            formula.add(element, count);
+        }
+        Ok(formula)
+    }
+}
+
+impl fmt::Display for Formula {
+    /// Writes the formula in Hill order: carbon, hydrogen, then the other
+    /// elements alphabetically, or all alphabetically without carbon
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let carbon = self.counts.iter().any(|(e, _)| e.symbol == "C");
+        let mut counts = self.counts.clone();
+        counts.sort_by_key(|(e, _)| match e.symbol {
+            "C" if carbon => (0, e.symbol),
+            "H" if carbon => (1, e.symbol),
+            symbol => (2, symbol),
+        });
+        let tokens: Vec<_> = counts
+            .iter()
+            .map(|(e, count)| {
+                if (count - 1.0).abs() < 5e-3 {
+                    return e.symbol.to_string();
+                }
+                let count = format!("{count:.2}");
+                let count = count.trim_end_matches('0').trim_end_matches('.');
+                format!("{}{count}", e.symbol)
+            })
+            .collect();
+        f.write_str(&tokens.join(" "))
+    }
+}
+
+/// The density in Mg m⁻³ of a cell holding `z` formula units of the given
+/// weight, with su from the cell volume
+pub fn density(weight: f64, z: f64, cell: &UnitCell) -> Measurement {
+    let volume = cell.volume();
+    // Daltons per cubic ångström to grams per cubic centimetre
+    let scale = z * weight * 1e24 / AVOGADRO;
+    let value = scale / volume.value;
+    Measurement::new(
+        value,
+        volume
+            .su
+            .map(|su| scale * su / (volume.value * volume.value)),
+    )
+}
+
+fn agrees(stated: Measurement, computed: Measurement) -> bool {
+    let su = (stated.su_or_zero().powi(2) + computed.su_or_zero().powi(2)).sqrt();
+    (stated.value - computed.value).abs() <= 3.0 * su + RELATIVE_TOLERANCE * stated.value.abs()
+}
+
+/// A disagreement between the formula of a block and its other items
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompositionMismatch {
+    Moiety {
+        sum: Formula,
+        moiety: Formula,
+    },
+    Weight {
+        stated: Measurement,
+        computed: f64,
+    },
+    /// The atom sites over `_cell.formula_units_Z`
+    Sites {
+        sum: Formula,
+        computed: Formula,
+    },
+    Density {
+        stated: Measurement,
+        computed: Measurement,
+    },
+    /// The structure or its site elements could not be read, so the checks
+    /// against them were skipped
+    Unchecked(FormulaError),
+}
+
+impl fmt::Display for CompositionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompositionMismatch::Moiety { sum, moiety } => write!(
+                f,
+                "moiety formula adds up to {moiety} but the sum formula is {sum}"
+            ),
+            CompositionMismatch::Weight { stated, computed } => write!(
+                f,
+                "formula weight is {computed:.2} but is given as {stated}"
+            ),
+            CompositionMismatch::Sites { sum, computed } => write!(
+                f,
+                "atom sites give {computed} per formula unit but the sum formula is {sum}"
+            ),
+            CompositionMismatch::Density { stated, computed } => {
+                write!(f, "density is {computed} Mg m⁻³ but is given as {stated}")
+            }
+            CompositionMismatch::Unchecked(e) => write!(f, "cannot check the structure: {e}"),
+        }
+    }
+}
+
+fn formula(block: &RawDataBlock, names: &[&str; 2]) -> Result<Option<String>, FormulaError> {
+    Ok(single::<String>(block, names[0], names)?.filter(|text| !text.trim().is_empty()))
+}
+
+/// Compares the chemical formula of a block with its moiety formula, formula
+/// weight, atom sites and density, where each is given
+pub fn check_block(block: &RawDataBlock) -> Result<Vec<CompositionMismatch>, FormulaError> {
+    let mut mismatches = Vec::new();
+    let sum: Option<Formula> = formula(block, &SUM)?.map(|text| text.parse()).transpose()?;
+    let moiety = formula(block, &MOIETY)?
+        .map(|text| Formula::from_moiety(&text))
+        .transpose()?;
+    if let (Some(sum), Some(moiety)) = (&sum, moiety)
+        && !sum.agrees_with(&moiety)
+    {
+        mismatches.push(CompositionMismatch::Moiety {
+            sum: sum.clone(),
+            moiety,
+        });
+    }
+    let stated_weight: Option<Measurement> = single(block, WEIGHT[0], &WEIGHT)?;
+    if let (Some(sum), Some(stated)) = (&sum, stated_weight)
+        && !agrees(stated, Measurement::exact(sum.weight()))
+    {
+        mismatches.push(CompositionMismatch::Weight {
+            stated,
+            computed: sum.weight(),
+        });
+    }
+    let Some(z) = single::<f64>(block, Z[0], &Z)?.filter(|z| *z > 0.0) else {
+        return Ok(mismatches);
+    };
+    let structure = match Structure::from_block(block) {
+        Ok(structure) => structure,
+        Err(e) => {
+            mismatches.push(CompositionMismatch::Unchecked(e.into()));
+            return Ok(mismatches);
+        }
+    };
+    if let Some(sum) = &sum
+        && !structure.sites.is_empty()
+    {
+        match block_operators(block)
+            .map_err(FormulaError::from)
+            .and_then(|ops| Formula::from_structure(&structure, &ops))
+        {
+            Ok(computed) => {
+                let computed = computed.scaled(1.0 / z);
+                if !sum.agrees_with(&computed) {
+                    mismatches.push(CompositionMismatch::Sites {
+                        sum: sum.clone(),
+                        computed,
+                    });
+                }
+            }
+            Err(e) => mismatches.push(CompositionMismatch::Unchecked(e)),
+        }
+    }
+    let weight = sum
+        .as_ref()
+        .map(Formula::weight)
+        .or(stated_weight.map(|w| w.value));
+    let stated: Option<Measurement> = single(block, DENSITY[0], &DENSITY)?;
+    if let (Some(weight), Some(stated)) = (weight, stated) {
+        let computed = density(weight, z, &structure.cell);
+        if !agrees(stated, computed) {
+            mismatches.push(CompositionMismatch::Density { stated, computed });
+        }
+    }
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use rstest::rstest;
+
+    #[test]
+    fn test_elements() {
+        let elements: Vec<_> = Element::all().collect();
+        assert_eq!(elements.len(), 118);
+        assert!(
+            elements
+                .iter()
+                .enumerate()
+                .all(|(i, e)| e.number as usize == i + 1)
+        );
+        assert!(elements.windows(2).all(|w| w[0].weight < w[1].weight + 5.0));
+        let zinc = Element::from_symbol("Zn").unwrap();
+        assert_eq!((zinc.number, zinc.name, zinc.weight), (30, "Zinc", 65.38));
+        assert_eq!(Element::all().last().unwrap().symbol, "Og");
+    }
+
+    #[rstest]
+    #[case("Zn2+", Some("Zn"))]
+    #[case("O1", Some("O"))]
+    #[case("C2A", Some("C"))]
+    #[case("Cr", Some("Cr"))]
+    #[case("cr1", None)]
+    #[case("Xx", None)]
+    fn test_from_type_symbol(#[case] text: &str, #[case] expected: Option<&str>) {
+        assert_eq!(Element::from_type_symbol(text).map(|e| e.symbol), expected);
+    }
+
+    #[rstest]
+    #[case("C6 H6 N4 Zn", "C6 H6 N4 Zn", 199.52)]
+    #[case("C H Cr O", "C H Cr O", 81.014)]
+    #[case("Zn N4 H6 C6", "C6 H6 N4 Zn", 199.52)]
+    #[case("O4 Si2 O", "O5 Si2", 136.165)]
+    #[case("Cl2 Na0.5", "Cl2 Na0.5", 82.395)]
+    fn test_sum_formula(#[case] text: &str, #[case] hill: &str, #[case] weight: f64) {
+        let formula: Formula = text.parse().unwrap();
+        assert_eq!(formula.to_string(), hill);
+        assert!((formula.weight() - weight).abs() < 0.005);
+    }
+
+    #[rstest]
+    #[case("C6 H6 N4 Zn", "C6 H6 N4 Zn")]
+    #[case("C6 H5 O7 3-, 3(Na 1+), 2(H2 O)", "C6 H9 Na3 O9")]
+    #[case("C10 H8 N2, 0.5(C2 H6 O)", "C11 H11 N2 O0.5")]
+    fn test_moiety_formula(#[case] text: &str, #[case] sum: &str) {
+        assert_eq!(Formula::from_moiety(text).unwrap().to_string(), sum);
+    }
+
+    #[rstest]
+    #[case("C6 Q2", FormulaError::UnknownElement("Q".to_string()))]
+    #[case("C6 H1.2.3", FormulaError::Invalid("H1.2.3".to_string()))]
+    fn test_invalid_formula(#[case] text: &str, #[case] expected: FormulaError) {
+        assert_eq!(text.parse::<Formula>(), Err(expected));
+    }
+
+    #[rstest]
+    #[case("x(H2 O)")]
+    #[case("2(H2 O")]
+    fn test_invalid_moiety(#[case] text: &str) {
+        assert_eq!(
+            Formula::from_moiety(text),
+            Err(FormulaError::Invalid(text.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_zif_8() {
+        let text = include_str!("../../cif_chomper/example_data/zif-8.cif");
+        let model = cif2_file(text).unwrap();
+        let block = &model.content[0];
+        let structure = Structure::from_block(block).unwrap();
+        let cell = Formula::from_structure(&structure, &block_operators(block).unwrap()).unwrap();
+        assert_eq!(cell.to_string(), "C72 H72 N48 Zn12");
+        let density = density(199.52, 12.0, &structure.cell);
+        assert_eq!(density.to_string(), "0.833952(17)");
+        assert_eq!(check_block(block), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_mil_101() {
+        let text = include_str!("../../cif_chomper/example_data/mil-101.cif");
+        let model = cif2_file(text).unwrap();
+        assert_eq!(check_block(&model.content[0]), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_check_block() {
+        let text = "#\\#CIF_2.0\ndata_x\n\
+                    _chemical_formula_sum 'C2 H6 O'\n_chemical_formula_moiety 'C2 H6 O, H2 O'\n\
+                    _chemical_formula_weight 50.0\n_cell_formula_units_Z 2\n\
+                    _exptl_crystal_density_diffrn 0.100\n\
+                    _cell.length_a 10.000(1)\n_cell.length_b 10.000(1)\n_cell.length_c 10.000(1)\n\
+                    loop_\n_symmetry_equiv_pos_as_xyz\nx,y,z\n-x,-y,-z\n\
+                    loop_\n_atom_site.label\n_atom_site.fract_x\n_atom_site.fract_y\n\
+                    _atom_site.fract_z\n_atom_site.occupancy\n\
+                    C1 0.1 0.1 0.1 1\nC2 0.2 0.1 0.1 1\nO1 0.3 0.1 0.1 0.5\n";
+        let model = cif2_file(text).unwrap();
+        let mismatches: Vec<_> = check_block(&model.content[0])
+            .unwrap()
+            .iter()
+            .map(CompositionMismatch::to_string)
+            .collect();
+        assert_eq!(
+            mismatches,
+            [
+                "moiety formula adds up to C2 H8 O2 but the sum formula is C2 H6 O",
+                "formula weight is 46.07 but is given as 50",
+                "atom sites give C2 O0.5 per formula unit but the sum formula is C2 H6 O",
+                "density is 0.15300(3) Mg m⁻³ but is given as 0.1",
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "_cell.length_a 10\n_cell.length_b 10\n",
+        "cannot check the structure: "
+    )]
+    #[case(
+        "_cell.length_a 10\n_cell.length_b 10\n_cell.length_c 10\n\
+         loop_\n_atom_site.label\n_atom_site.fract_x\n_atom_site.fract_y\n\
+         _atom_site.fract_z\nD1 0.1 0.1 0.1\n",
+        "cannot check the structure: 'D1' is not an element"
+    )]
+    fn test_check_block_unchecked(#[case] items: &str, #[case] expected: &str) {
+        let text = format!(
+            "#\\#CIF_2.0\ndata_x\n_chemical_formula_sum 'C2 H6 O'\n\
+             _chemical_formula_moiety 'C2 H6 O, H2 O'\n_cell_formula_units_Z 2\n{items}"
+        );
+        let model = cif2_file(&text).unwrap();
+        let mismatches = check_block(&model.content[0]).unwrap();
+        assert_eq!(mismatches.len(), 2);
+        assert!(matches!(mismatches[0], CompositionMismatch::Moiety { .. }));
+        assert!(mismatches[1].to_string().starts_with(expected));
+    }
+}
@@ -0,0 +1,646 @@
+/// Bond lengths and angles between atom sites and their symmetry images,
+/// with su propagated from the cell and the coordinates, and checks of the
+/// `_geom_bond` and `_geom_angle` loops against them
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cell::UnitCell;
+use crate::model::{column, optional_value};
+use crate::numeric::Measurement;
+use crate::raw_model::RawDataBlock;
+use crate::structure::Structure;
+use crate::symmetry::SymOp;
+
+/// Distances given in a file may differ from those computed by this much,
+/// in ångströms, beyond three combined su, as coordinates are rounded
+pub const DISTANCE_TOLERANCE: f64 = 0.005;
+/// Angles given in a file may differ from those computed by this much, in
+/// degrees, beyond three combined su
+pub const ANGLE_TOLERANCE: f64 = 0.5;
+
+/// Neighbours closer than this, in ångströms, are the site itself
+const SAME_POSITION: f64 = 1e-3;
+
+/// A symmetry operator and lattice translation as in `_geom_bond.site_symmetry_2`:
+/// `3_655` for the third operator followed by a translation of one along a,
+/// `.` for the identity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SymmetryCode {
+    Identity,
+    Image {
+        /// Index of the operator, from 0 for the first
+        op: usize,
+        translation: [i64; 3],
+    },
+}
+
+impl FromStr for SymmetryCode {
+    type Err = ();
+
+    /// Reads `.`, `3` or `3_655`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "." {
+            return Ok(SymmetryCode::Identity);
+        }
+        let (op, translation) = s.split_once('_').unwrap_or((s, "555"));
+        let op: usize = op.parse().map_err(|_| ())?;
+        let digits: Vec<_> = translation.chars().map(|c| c.to_digit(10)).collect();
+        match digits[..] {
+            [Some(a), Some(b), Some(c)] if op > 0 => Ok(SymmetryCode::Image {
+                op: op - 1,
+                translation: [a, b, c].map(|d| d as i64 - 5),
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for SymmetryCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymmetryCode::Identity => f.write_str("."),
+            SymmetryCode::Image { op, translation } => {
+                let [a, b, c] = translation.map(|t| t + 5);
+                write!(f, "{}_{a}{b}{c}", op + 1)
+            }
+        }
+    }
+}
+
+/// A site of the structure, by index, or one of its symmetry images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AtomRef {
+    pub site: usize,
+    pub symmetry: SymmetryCode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bond {
+    /// The first atom is a site of the structure itself
+    pub atoms: [AtomRef; 2],
+    /// In ångströms
+    pub distance: Measurement,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Angle {
+    /// The vertex is the second atom, and a site of the structure itself
+    pub atoms: [AtomRef; 3],
+    /// In degrees
+    pub angle: Measurement,
+}
+
+/// A row of `_geom_bond` or `_geom_angle` that does not agree with the
+/// structure
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeometryMismatch {
+    /// A row naming a site or operator that is not in the structure
+    Unresolved { category: String, atoms: String },
+    Distance {
+        atoms: String,
+        stated: Measurement,
+        computed: Measurement,
+    },
+    Angle {
+        atoms: String,
+        stated: Measurement,
+        computed: Measurement,
+    },
+}
+
+impl fmt::Display for GeometryMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryMismatch::Unresolved { category, atoms } => {
+                write!(
+                    f,
+                    "{category} {atoms} names a site or operator that is not in the structure"
+                )
+            }
+            GeometryMismatch::Distance {
+                atoms,
+                stated,
+                computed,
+            } => write!(f, "bond {atoms} is {computed} Å but is given as {stated}"),
+            GeometryMismatch::Angle {
+                atoms,
+                stated,
+                computed,
+            } => write!(f, "angle {atoms} is {computed}° but is given as {stated}"),
+        }
+    }
+}
+
+fn difference(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (0..3).map(|i| a[i] * b[i]).sum()
+}
+
+fn length(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn angle_between(points: &[[f64; 3]]) -> f64 {
+    let u = difference(points[0], points[1]);
+    let v = difference(points[2], points[1]);
+    (dot(u, v) / (length(u) * length(v)))
+        .clamp(-1.0, 1.0)
+        .acos()
+        .to_degrees()
+}
+
+/// Geometry of a structure, whose symmetry codes other than `.` refer to
+/// `ops` in order
+pub struct Geometry<'a> {
+    structure: &'a Structure,
+    ops: &'a [SymOp],
+}
+
+impl<'a> Geometry<'a> {
+    pub fn new(structure: &'a Structure, ops: &'a [SymOp]) -> Self {
+        Geometry { structure, ops }
+    }
+
+    /// The site label followed by any symmetry code, as `N1` or `N1(9_655)`
+    pub fn describe(&self, atom: &AtomRef) -> String {
+        let label = &self.structure.sites[atom.site].label;
+        match atom.symmetry {
+            SymmetryCode::Identity => label.clone(),
+            code => format!("{label}({code})"),
+        }
+    }
+
+    fn resolve(&self, label: &str, code: &str) -> Option<AtomRef> {
+        let site = self.structure.sites.iter().position(|s| s.label == label)?;
+        let symmetry: SymmetryCode = code.parse().ok()?;
+        let known = match symmetry {
+            SymmetryCode::Identity => true,
+            SymmetryCode::Image { op, .. } => op < self.ops.len(),
+        };
+        known.then_some(AtomRef { site, symmetry })
+    }
+
+    /// Fractional coordinates of an atom, from the given coordinates of the
+    /// sites
+    fn position(&self, atom: &AtomRef, sites: &[[f64; 3]]) -> [f64; 3] {
+        match atom.symmetry {
+            SymmetryCode::Identity => sites[atom.site],
+            SymmetryCode::Image { op, translation } => {
+                let fract = match self.ops.get(op) {
+                    Some(op) => op.apply(sites[atom.site]),
+                    None => sites[atom.site],
+                };
+                std::array::from_fn(|i| fract[i] + translation[i] as f64)
+            }
+        }
+    }
+
+    /// `f` of the Cartesian positions of the atoms, with an su from its
+    /// numerical derivatives by the cell parameters and by the coordinates
+    /// of the sites involved, taking their su as uncorrelated. Images of
+    /// one site move together.
+    fn measure(&self, atoms: &[AtomRef], f: fn(&[[f64; 3]]) -> f64) -> Measurement {
+        let evaluate = |cell: &UnitCell, sites: &[[f64; 3]]| {
+            let points: Vec<_> = atoms
+                .iter()
+                .map(|atom| cell.to_cartesian(self.position(atom, sites)))
+                .collect();
+            f(&points)
+        };
+        let cell = self.structure.cell;
+        let sites: Vec<_> = self.structure.sites.iter().map(|s| s.fract).collect();
+        let value = evaluate(&cell, &sites);
+        let mut variance = 0.0;
+        let mut any_su = false;
+        for i in 0..6 {
+            let parameter = match i {
+                0..3 => cell.lengths[i],
+                _ => cell.angles[i - 3],
+            };
+            let Some(su) = parameter.su.filter(|su| *su > 0.0) else {
+                continue;
+            };
+            any_su = true;
+            let step = 1e-6 * parameter.value.abs().max(1.0);
+            let shifted = |delta: f64| {
+                let mut cell = cell;
+                match i {
+                    0..3 => cell.lengths[i].value += delta,
+                    _ => cell.angles[i - 3].value += delta,
+                }
+                evaluate(&cell, &sites)
+            };
+            let derivative = (shifted(step) - shifted(-step)) / (2.0 * step);
+            variance += (derivative * su).powi(2);
+        }
+        let involved: BTreeSet<_> = atoms.iter().map(|a| a.site).collect();
+        for site in involved {
+            for axis in 0..3 {
+                let su = self.structure.sites[site].fract_su[axis];
+                if su <= 0.0 {
+                    continue;
+                }
+                any_su = true;
+                let step = 1e-7;
+                let shifted = |delta: f64| {
+                    let mut sites = sites.clone();
+                    sites[site][axis] += delta;
+                    evaluate(&cell, &sites)
+                };
+                let derivative = (shifted(step) - shifted(-step)) / (2.0 * step);
+                variance += (derivative * su).powi(2);
+            }
+        }
+        Measurement::new(value, any_su.then(|| variance.sqrt()))
+    }
+
+    /// The distance between two atoms in ångströms
+    pub fn distance(&self, a: &AtomRef, b: &AtomRef) -> Measurement {
+        self.measure(&[*a, *b], |p| length(difference(p[0], p[1])))
+    }
+
+    /// The angle at `vertex` in degrees
+    pub fn angle(&self, a: &AtomRef, vertex: &AtomRef, b: &AtomRef) -> Measurement {
+        self.measure(&[*a, *vertex, *b], angle_between)
+    }
+
+    /// The atoms within `cutoff` ångströms of a site, nearest first. Where
+    /// several operators give the same position, as on special positions,
+    /// the first is taken.
+    pub fn neighbours(&self, site: usize, cutoff: f64) -> Vec<(AtomRef, f64)> {
+        let cell = &self.structure.cell;
+        let origin = self.structure.sites[site].fract;
+        let centre = cell.to_cartesian(origin);
+        // Lattice translations far enough to reach the cutoff
+        let reciprocal = cell.reciprocal_metric_tensor();
+        let reach: [i64; 3] =
+            std::array::from_fn(|i| (cutoff * reciprocal[i][i].sqrt()).ceil() as i64 + 1);
+        let mut found: Vec<(AtomRef, [f64; 3], f64)> = Vec::new();
+        for (other, other_site) in self.structure.sites.iter().enumerate() {
+            for (op_index, op) in self.ops.iter().enumerate() {
+                let image = op.apply(other_site.fract);
+                let nearest: [i64; 3] =
+                    std::array::from_fn(|i| (origin[i] - image[i]).round() as i64);
+                for a in -reach[0]..=reach[0] {
+                    for b in -reach[1]..=reach[1] {
+                        for c in -reach[2]..=reach[2] {
+                            let translation = [nearest[0] + a, nearest[1] + b, nearest[2] + c];
+                            let point = cell.to_cartesian(std::array::from_fn(|i| {
+                                image[i] + translation[i] as f64
+                            }));
+                            let distance = length(difference(point, centre));
+                            if distance < SAME_POSITION
+                                || distance > cutoff
+                                || found
+                                    .iter()
+                                    .any(|(_, p, _)| length(difference(*p, point)) < SAME_POSITION)
+                            {
+                                continue;
+                            }
+                            let symmetry = match *op == SymOp::identity() && translation == [0; 3] {
+                                true => SymmetryCode::Identity,
+                                false => SymmetryCode::Image {
+                                    op: op_index,
+                                    translation,
+                                },
+                            };
+                            let atom = AtomRef {
+                                site: other,
+                                symmetry,
+                            };
+                            found.push((atom, point, distance));
+                        }
+                    }
+                }
+            }
+        }
+        found.sort_by(|a, b| a.2.total_cmp(&b.2));
+        found.into_iter().map(|(atom, _, d)| (atom, d)).collect()
+    }
+
+    /// The bonds from every site to its neighbours within `cutoff` ångströms
+    pub fn bonds(&self, cutoff: f64) -> Vec<Bond> {
+        (0..self.structure.sites.len())
+            .flat_map(|site| {
+                let from = AtomRef {
+                    site,
+                    symmetry: SymmetryCode::Identity,
+                };
+                self.neighbours(site, cutoff)
+                    .into_iter()
+                    .map(move |(to, _)| (from, to))
+            })
+            .map(|(from, to)| Bond {
+                atoms: [from, to],
+                distance: self.distance(&from, &to),
+            })
+            .collect()
+    }
+
+    /// The angles at every site between pairs of its neighbours within
+    /// `cutoff` ångströms
+    pub fn angles(&self, cutoff: f64) -> Vec<Angle> {
+        let mut angles = Vec::new();
+        for site in 0..self.structure.sites.len() {
+            let vertex = AtomRef {
+                site,
+                symmetry: SymmetryCode::Identity,
+            };
+            let neighbours = self.neighbours(site, cutoff);
+            for (i, (a, _)) in neighbours.iter().enumerate() {
+                for (b, _) in &neighbours[i + 1..] {
+                    angles.push(Angle {
+                        atoms: [*a, vertex, *b],
+                        angle: self.angle(a, &vertex, b),
+                    });
+                }
+            }
+        }
+        angles
+    }
+
+    /// Compares the `_geom_bond` and `_geom_angle` loops of a block with the
+    /// structure. Values differing by more than three combined su and the
+    /// tolerances for rounding are reported, as are rows that cannot be
+    /// resolved.
+    pub fn check_block(&self, block: &RawDataBlock) -> Vec<GeometryMismatch> {
+        let mut mismatches = Vec::new();
+        let bonds = GeometryLoop::read(block, "_geom_bond", 2, &["distance"]);
+        let angles = GeometryLoop::read(block, "_geom_angle", 3, &["value", ""]);
+        for (rows, category, tolerance, angle) in [
+            (bonds, "_geom_bond", DISTANCE_TOLERANCE, false),
+            (angles, "_geom_angle", ANGLE_TOLERANCE, true),
+        ] {
+            for (atoms, stated) in rows {
+                let text = atoms
+                    .iter()
+                    .map(|(label, code)| match code.as_str() {
+                        "." => label.clone(),
+                        code => format!("{label}({code})"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let resolved: Option<Vec<_>> = atoms
+                    .iter()
+                    .map(|(label, code)| self.resolve(label, code))
+                    .collect();
+                let Some(resolved) = resolved else {
+                    mismatches.push(GeometryMismatch::Unresolved {
+                        category: category.to_string(),
+                        atoms: text,
+                    });
+                    continue;
+                };
+                let computed = match angle {
+                    false => self.distance(&resolved[0], &resolved[1]),
+                    true => self.angle(&resolved[0], &resolved[1], &resolved[2]),
+                };
+                let su = (stated.su_or_zero().powi(2) + computed.su_or_zero().powi(2)).sqrt();
+                if (computed.value - stated.value).abs() <= 3.0 * su + tolerance {
+                    continue;
+                }
+                mismatches.push(match angle {
+                    false => GeometryMismatch::Distance {
+                        atoms: text,
+                        stated,
+                        computed,
+                    },
+                    true => GeometryMismatch::Angle {
+                        atoms: text,
+                        stated,
+                        computed,
+                    },
+                });
+            }
+        }
+        mismatches
+    }
+}
+
+/// Rows of `_geom_bond` or `_geom_angle`: the labels and symmetry codes of
+/// the atoms, and the value
+struct GeometryLoop;
+
+impl GeometryLoop {
+    /// Rows with a value; `objects` are the object ids the value may have,
+    /// where `""` is the DDL1 name of the category itself
+    fn read(
+        block: &RawDataBlock,
+        category: &str,
+        atoms: usize,
+        objects: &[&str],
+    ) -> Vec<(Vec<(String, String)>, Measurement)> {
+        let names = |object: &str| {
+            let ddl1 = format!("{category}_{object}");
+            [format!("{category}.{object}"), ddl1]
+        };
+        let find = |object: &str| {
+            let names = names(object);
+            column(block, &[names[0].as_str(), names[1].as_str()])
+        };
+        let values = objects.iter().find_map(|object| match *object {
+            "" => column(block, &[category]),
+            object => find(object),
+        });
+        let Some(values) = values else {
+            return Vec::new();
+        };
+        let labels: Vec<_> = (1..=atoms)
+            .map(|n| find(&format!("atom_site_label_{n}")))
+            .collect();
+        let codes: Vec<_> = (1..=atoms)
+            .map(|n| find(&format!("site_symmetry_{n}")))
+            .collect();
+        (0..values.len())
+            .filter_map(|row| {
+                let value = values[row].as_str()?.parse::<Measurement>().ok()?;
+                let atoms = labels
+                    .iter()
+                    .zip(&codes)
+                    .map(|(labels, codes)| {
+                        let label: String = optional_value(category, labels, row).ok()??;
+                        let code: Option<String> = optional_value(category, codes, row).ok()?;
+                        Some((label, code.unwrap_or_else(|| ".".to_string())))
+                    })
+                    .collect::<Option<_>>()?;
+                Some((atoms, value))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use crate::space_group::block_operators;
+    use rstest::rstest;
+
+    const ZIF_8: &str = include_str!("../../cif_chomper/example_data/zif-8.cif");
+
+    fn zif_8() -> (Structure, Vec<SymOp>) {
+        let model = cif2_file(ZIF_8).unwrap();
+        let block = &model.content[0];
+        (
+            Structure::from_block(block).unwrap(),
+            block_operators(block).unwrap(),
+        )
+    }
+
+    fn atom(site: usize, code: &str) -> AtomRef {
+        AtomRef {
+            site,
+            symmetry: code.parse().unwrap(),
+        }
+    }
+
+    #[rstest]
+    #[case(".", SymmetryCode::Identity)]
+    #[case("34", SymmetryCode::Image { op: 33, translation: [0, 0, 0] })]
+    #[case("9_655", SymmetryCode::Image { op: 8, translation: [1, 0, 0] })]
+    #[case("32_554", SymmetryCode::Image { op: 31, translation: [0, 0, -1] })]
+    fn test_symmetry_code(#[case] text: &str, #[case] expected: SymmetryCode) {
+        assert_eq!(text.parse(), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("0")]
+    #[case("1_55")]
+    #[case("a_555")]
+    #[case("2_5x5")]
+    fn test_invalid_symmetry_code(#[case] text: &str) {
+        assert_eq!(text.parse::<SymmetryCode>(), Err(()));
+    }
+
+    #[test]
+    fn test_symmetry_code_display() {
+        assert_eq!(SymmetryCode::Identity.to_string(), ".");
+        assert_eq!("34".parse::<SymmetryCode>().unwrap().to_string(), "34_555");
+        assert_eq!(
+            "9_655".parse::<SymmetryCode>().unwrap().to_string(),
+            "9_655"
+        );
+    }
+
+    #[test]
+    fn test_distance() {
+        let (structure, ops) = zif_8();
+        let geometry = Geometry::new(&structure, &ops);
+        // Zn1 N1 1.9775(16) 9_655
+        let distance = geometry.distance(&atom(0, "."), &atom(1, "9_655"));
+        assert!((distance.value - 1.9775).abs() < 5e-4);
+        assert!((distance.su.unwrap() - 0.0016).abs() < 3e-4);
+        // C1 H1 0.9500, from coordinates without su
+        let riding = geometry.distance(&atom(2, "."), &atom(3, "."));
+        assert!((riding.value - 0.95).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_angle() {
+        let (structure, ops) = zif_8();
+        let geometry = Geometry::new(&structure, &ops);
+        // N1 Zn1 N1 110.41(11) 9_655 .
+        let angle = geometry.angle(&atom(1, "9_655"), &atom(0, "."), &atom(1, "."));
+        assert!((angle.value - 110.41).abs() < 0.01);
+        // The refinement used the full covariance of the parameters
+        assert!((angle.su.unwrap() - 0.11).abs() < 0.02);
+        assert_eq!(geometry.describe(&atom(1, "9_655")), "N1(9_655)");
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let (structure, ops) = zif_8();
+        let geometry = Geometry::new(&structure, &ops);
+        // Zn1 is tetrahedrally bonded to four N1
+        let neighbours = geometry.neighbours(0, 2.2);
+        assert_eq!(neighbours.len(), 4);
+        assert!(
+            neighbours
+                .iter()
+                .all(|(a, d)| a.site == 1 && (d - 1.9775).abs() < 5e-4)
+        );
+        let bonds = geometry.bonds(2.2);
+        assert!(
+            bonds
+                .iter()
+                .all(|b| b.atoms[0].symmetry == SymmetryCode::Identity)
+        );
+        let angles: Vec<_> = geometry
+            .angles(2.2)
+            .into_iter()
+            .filter(|a| a.atoms[1].site == 0)
+            .collect();
+        assert_eq!(angles.len(), 6);
+        assert!(
+            angles.iter().all(
+                |a| (a.angle.value - 109.0).abs() < 0.1 || (a.angle.value - 110.41).abs() < 0.1
+            )
+        );
+    }
+
+    #[test]
+    fn test_check_example() {
+        let model = cif2_file(ZIF_8).unwrap();
+        let block = &model.content[0];
+        let (structure, ops) = zif_8();
+        assert_eq!(Geometry::new(&structure, &ops).check_block(block), vec![]);
+    }
+
+    #[test]
+    fn test_identity_not_first() {
+        let text = "#\\#CIF_2.0\ndata_x\n\
+                    _cell.length_a 10\n_cell.length_b 10\n_cell.length_c 10\n\
+                    loop_\n_space_group_symop.operation_xyz\n-x,-y,-z\nx,y,z\n\
+                    loop_\n_atom_site.label\n_atom_site.fract_x\n_atom_site.fract_y\n_atom_site.fract_z\n\
+                    A 0.1 0 0\nB 0.25 0 0\n\
+                    loop_\n_geom_bond.atom_site_label_1\n_geom_bond.atom_site_label_2\n\
+                    _geom_bond.distance\n_geom_bond.site_symmetry_2\n\
+                    A B 1.500 .\nA B 3.500 1_555\nA B 1.500 2_555\n";
+        let model = cif2_file(text).unwrap();
+        let block = &model.content[0];
+        let structure = Structure::from_block(block).unwrap();
+        let ops = block_operators(block).unwrap();
+        assert_ne!(ops[0], SymOp::identity());
+        let geometry = Geometry::new(&structure, &ops);
+        assert_eq!(geometry.check_block(block), vec![]);
+        let neighbours = geometry.neighbours(0, 1.8);
+        assert_eq!(neighbours.len(), 1);
+        assert_eq!(neighbours[0].0, atom(1, "."));
+        assert_eq!(geometry.describe(&neighbours[0].0), "B");
+    }
+
+    #[test]
+    fn test_check_block() {
+        let text = "#\\#CIF_2.0\ndata_x\n\
+                    _cell.length_a 10\n_cell.length_b 10\n_cell.length_c 10\n\
+                    loop_\n_atom_site.label\n_atom_site.fract_x\n_atom_site.fract_y\n_atom_site.fract_z\n\
+                    A 0 0 0\nB 0.15 0 0\nC 0 0.2 0\n\
+                    loop_\n_geom_bond.atom_site_label_1\n_geom_bond.atom_site_label_2\n\
+                    _geom_bond.distance\n_geom_bond.site_symmetry_2\n\
+                    A B 1.500 .\nA C 2.10(2) .\nA D 1 .\nA B 8.5 1_455\n\
+                    loop_\n_geom_angle.atom_site_label_1\n_geom_angle.atom_site_label_2\n\
+                    _geom_angle.atom_site_label_3\n_geom_angle.value\n\
+                    B A C 90\nB A C 95\n";
+        let model = cif2_file(text).unwrap();
+        let block = &model.content[0];
+        let structure = Structure::from_block(block).unwrap();
+        let ops = [SymOp::identity()];
+        let mismatches: Vec<_> = Geometry::new(&structure, &ops)
+            .check_block(block)
+            .iter()
+            .map(GeometryMismatch::to_string)
+            .collect();
+        assert_eq!(
+            mismatches,
+            [
+                "bond A C is 2 Å but is given as 2.10(2)",
+                "_geom_bond A D names a site or operator that is not in the structure",
+                "angle B A C is 90° but is given as 95",
+            ]
+        );
+    }
+}
@@ -0,0 +1,593 @@
+/// Resolution of `_import.get` between dictionary files
+/// Details on the import mechanism:
+/// https://www.iucr.org/resources/cif/ddl/ddlm/docs/import
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::dictionary::{AttributeValue, CategoryClass, Frame, frames_from_block};
+use crate::parser::{ParseError, cif2_file};
+
+/// Attributes that identify the importing definition, so are never taken
+/// from the imported frame in `Contents` mode
+const IDENTITY_ATTRIBUTES: [&str; 4] = [
+    "_definition.id",
+    "_definition.update",
+    "_name.category_id",
+    "_name.object_id",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum ImportError {
+    Io { file: String, reason: String },
+    Parse { file: String, error: ParseError },
+    MissingFile(String),
+    MissingFrame { file: String, save: String },
+    Duplicate { frame: String, name: String },
+    Cycle(String),
+    InvalidSpec { frame: String, reason: String },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io { file, reason } => write!(f, "failed to read {file}: {reason}"),
+            ImportError::Parse { file, error } => write!(f, "failed to parse {file}: {error}"),
+            ImportError::MissingFile(file) => write!(f, "imported file {file} not found"),
+            ImportError::MissingFrame { file, save } => {
+                write!(f, "save_{save} not found in imported file {file}")
+            }
+            ImportError::Duplicate { frame, name } => {
+                write!(f, "import into save_{frame} duplicates {name}")
+            }
+            ImportError::Cycle(key) => write!(f, "import of {key} is circular"),
+            ImportError::InvalidSpec { frame, reason } => {
+                write!(f, "invalid _import.get in save_{frame}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Supplies the text of files named by `_import.get`. A missing file should
+/// be reported as [`io::ErrorKind::NotFound`] so that `if_miss` can apply.
+pub trait ImportResolver {
+    fn resolve(&self, file: &str) -> io::Result<String>;
+}
+
+/// Final path component of a file name or URI
+fn file_name(file: &str) -> &str {
+    file.rsplit(['/', '\\']).next().unwrap_or(file)
+}
+
+/// Looks for imported files in a list of directories, in order
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryResolver {
+    pub search_path: Vec<PathBuf>,
+}
+
+impl DirectoryResolver {
+    pub fn new(search_path: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        DirectoryResolver {
+            search_path: search_path.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Read `file` from the first directory holding it, with the path read
+    pub fn read(&self, file: &str) -> io::Result<(PathBuf, String)> {
+        for dir in &self.search_path {
+            for candidate in [dir.join(file), dir.join(file_name(file))] {
+                match std::fs::read_to_string(&candidate) {
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                    result => return result.map(|text| (candidate, text)),
+                }
+            }
+        }
+        Err(io::ErrorKind::NotFound.into())
+    }
+}
+
+impl ImportResolver for DirectoryResolver {
+    fn resolve(&self, file: &str) -> io::Result<String> {
+        self.read(file).map(|(_, text)| text)
+    }
+}
+
+/// Serves imported files from memory, keyed by file name
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+    pub files: HashMap<String, String>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        MemoryResolver::default()
+    }
+
+    pub fn insert(&mut self, file: impl Into<String>, contents: impl Into<String>) {
+        self.files.insert(file.into(), contents.into());
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for MemoryResolver {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        MemoryResolver {
+            files: iter
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        }
+    }
+}
+
+impl ImportResolver for MemoryResolver {
+    fn resolve(&self, file: &str) -> io::Result<String> {
+        self.files
+            .get(file)
+            .or_else(|| self.files.get(file_name(file)))
+            .cloned()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Merge the attributes of the imported frame into the importing one
+    Contents,
+    /// Add every definition of the imported dictionary below the importing
+    /// category
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IfDuplicate {
+    Exit,
+    Ignore,
+    Replace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IfMissing {
+    Exit,
+    Ignore,
+}
+
+/// One entry of an `_import.get` list
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportSpec {
+    pub file: String,
+    pub save: String,
+    pub mode: ImportMode,
+    pub if_dupl: IfDuplicate,
+    pub if_miss: IfMissing,
+}
+
+impl ImportSpec {
+    fn from_value(frame: &str, value: &AttributeValue) -> Result<Self, ImportError> {
+        let invalid = |reason: String| ImportError::InvalidSpec {
+            frame: frame.to_string(),
+            reason,
+        };
+        let AttributeValue::Table(entries) = value else {
+            return Err(invalid("expected a table".to_string()));
+        };
+        let get = |key: &str| {
+            entries
+                .iter()
+                .find(|(k, _)| k.as_str().is_some_and(|k| k.eq_ignore_ascii_case(key)))
+                .and_then(|(_, v)| v.as_str())
+        };
+        let require = |key: &str| get(key).ok_or_else(|| invalid(format!("missing '{key}'")));
+        let choice = |key: &str, value: Option<&str>| {
+            invalid(format!("unknown {key} '{}'", value.unwrap_or_default()))
+        };
+        Ok(ImportSpec {
+            file: require("file")?.to_string(),
+            save: require("save")?.to_string(),
+            mode: match get("mode").map(str::to_lowercase).as_deref() {
+                None | Some("contents") => ImportMode::Contents,
+                Some("full") => ImportMode::Full,
+                other => return Err(choice("mode", other)),
+            },
+            if_dupl: match get("if_dupl").map(str::to_lowercase).as_deref() {
+                None | Some("exit") => IfDuplicate::Exit,
+                Some("ignore") => IfDuplicate::Ignore,
+                Some("replace") => IfDuplicate::Replace,
+                other => return Err(choice("if_dupl", other)),
+            },
+            if_miss: match get("if_miss").map(str::to_lowercase).as_deref() {
+                None | Some("exit") => IfMissing::Exit,
+                Some("ignore") => IfMissing::Ignore,
+                other => return Err(choice("if_miss", other)),
+            },
+        })
+    }
+}
+
+/// The `_import.get` entries of a frame
+pub fn import_specs(frame: &Frame) -> Result<Vec<ImportSpec>, ImportError> {
+    frame
+        .values("_import.get")
+        .iter()
+        .flat_map(|value| match value {
+            AttributeValue::List(specs) => specs.iter().collect(),
+            other => vec![other],
+        })
+        .map(|value| ImportSpec::from_value(&frame.name, value))
+        .collect()
+}
+
+type ParsedFile = Rc<Vec<Frame>>;
+
+/// A frame with its `Contents` imports merged in, plus the definitions
+/// brought in by `Full` imports
+struct Expanded {
+    frame: Frame,
+    imported: Vec<(Frame, IfDuplicate)>,
+}
+
+struct Importer<'r, R: ImportResolver + ?Sized> {
+    resolver: &'r R,
+    files: HashMap<String, ParsedFile>,
+    stack: Vec<String>,
+}
+
+impl<R: ImportResolver + ?Sized> Importer<'_, R> {
+    fn load(&mut self, file: &str) -> Result<Option<ParsedFile>, ImportError> {
+        if let Some(parsed) = self.files.get(file) {
+            return Ok(Some(parsed.clone()));
+        }
+        let text = match self.resolver.resolve(file) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(ImportError::Io {
+                    file: file.to_string(),
+                    reason: e.to_string(),
+                });
+            }
+        };
+        let model = cif2_file(&text).map_err(|error| ImportError::Parse {
+            file: file.to_string(),
+            error,
+        })?;
+        let frames: Vec<Frame> = model
+            .content
+            .iter()
+            .flat_map(|block| frames_from_block(block).1)
+            .collect();
+        let parsed = Rc::new(frames);
+        self.files.insert(file.to_string(), parsed.clone());
+        Ok(Some(parsed))
+    }
+
+    fn enter(&mut self, key: String) -> Result<(), ImportError> {
+        if self.stack.contains(&key) {
+            return Err(ImportError::Cycle(key));
+        }
+        self.stack.push(key);
+        Ok(())
+    }
+
+    fn expand(&mut self, mut frame: Frame) -> Result<Expanded, ImportError> {
+        let specs = import_specs(&frame)?;
+        frame.attributes.remove("_import.get");
+        let mut imported = Vec::new();
+        for spec in specs {
+            let missing = |error| match spec.if_miss {
+                IfMissing::Exit => Err(error),
+                IfMissing::Ignore => Ok(()),
+            };
+            let Some(frames) = self.load(&spec.file)? else {
+                missing(ImportError::MissingFile(spec.file.clone()))?;
+                continue;
+            };
+            let Some(target) = frames
+                .iter()
+                .find(|f| f.name.eq_ignore_ascii_case(&spec.save))
+            else {
+                missing(ImportError::MissingFrame {
+                    file: spec.file.clone(),
+                    save: spec.save.clone(),
+                })?;
+                continue;
+            };
+            match spec.mode {
+                ImportMode::Contents => {
+                    self.enter(format!("{}#{}", spec.file, spec.save))?;
+                    let source = self.expand(target.clone())?;
+                    self.stack.pop();
+                    merge_contents(&mut frame, source.frame, spec.if_dupl)?;
+                    imported.extend(source.imported);
+                }
+                ImportMode::Full => {
+                    self.enter(spec.file.clone())?;
+                    let head = target.get("_definition.id").unwrap_or(&target.name);
+                    let parent = frame.get("_definition.id").unwrap_or(&frame.name);
+                    let (head, parent) = (head.to_string(), parent.to_string());
+                    for source in frames.iter() {
+                        if source.name.eq_ignore_ascii_case(&target.name)
+                            || source.get("_definition.id").is_none()
+                        {
+                            continue;
+                        }
+                        let mut source = self.expand(source.clone())?;
+                        reparent(&mut source.frame, &head, &parent);
+                        imported.push((source.frame, spec.if_dupl));
+                        imported.extend(source.imported);
+                    }
+                    self.stack.pop();
+                }
+            }
+        }
+        Ok(Expanded { frame, imported })
+    }
+}
+
+fn merge_contents(
+    frame: &mut Frame,
+    source: Frame,
+    if_dupl: IfDuplicate,
+) -> Result<(), ImportError> {
+    for (name, values) in source.attributes {
+        if IDENTITY_ATTRIBUTES.contains(&name.as_str()) {
+            continue;
+        }
+        match (frame.attributes.contains_key(&name), if_dupl) {
+            (false, _) | (true, IfDuplicate::Replace) => {
+                frame.attributes.insert(name, values);
+            }
+            (true, IfDuplicate::Ignore) => (),
+            (true, IfDuplicate::Exit) => {
+                return Err(ImportError::Duplicate {
+                    frame: frame.name.clone(),
+                    name,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Attach the children of an imported head category to the importing category
+fn reparent(frame: &mut Frame, head: &str, parent: &str) {
+    let is_child = frame
+        .get("_name.category_id")
+        .is_some_and(|c| c.eq_ignore_ascii_case(head));
+    if is_child {
+        frame.attributes.insert(
+            "_name.category_id".to_string(),
+            vec![AttributeValue::Str(parent.to_string())],
+        );
+    }
+}
+
+fn definition_id(frame: &Frame) -> Option<String> {
+    frame.get("_definition.id").map(str::to_lowercase)
+}
+
+fn is_head(frame: &Frame) -> bool {
+    frame
+        .get("_definition.class")
+        .is_some_and(|c| c.eq_ignore_ascii_case(CategoryClass::Head.as_str()))
+}
+
+/// Expand every `_import.get` in a dictionary's frames. Definitions brought
+/// in by `Full` imports are appended, subject to their `if_dupl` setting.
+pub fn resolve_imports<R: ImportResolver + ?Sized>(
+    frames: Vec<Frame>,
+    resolver: &R,
+) -> Result<Vec<Frame>, ImportError> {
+    let mut importer = Importer {
+        resolver,
+        files: HashMap::new(),
+        stack: Vec::new(),
+    };
+    let mut resolved: Vec<Frame> = Vec::with_capacity(frames.len());
+    let mut imported = Vec::new();
+    for frame in frames {
+        let expanded = importer.expand(frame)?;
+        resolved.push(expanded.frame);
+        imported.extend(expanded.imported);
+    }
+    for (frame, if_dupl) in imported {
+        // An imported dictionary's own head category never replaces ours
+        if is_head(&frame) {
+            continue;
+        }
+        let id = definition_id(&frame);
+        let existing = resolved
+            .iter()
+            .position(|f| id.is_some() && definition_id(f) == id);
+        match (existing, if_dupl) {
+            (None, _) => resolved.push(frame),
+            (Some(i), IfDuplicate::Replace) => resolved[i] = frame,
+            (Some(_), IfDuplicate::Ignore) => (),
+            (Some(i), IfDuplicate::Exit) => {
+                return Err(ImportError::Duplicate {
+                    frame: resolved[i].name.clone(),
+                    name: frame.name,
+                });
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::{ContentType, Dictionary, DictionaryError, TypeContents};
+
+    const TEMPLATES: &str = r#"#\#CIF_2.0
+data_TEMPL_ATTR
+    _dictionary.title             TEMPL_ATTR
+
+save_cell_length
+    _definition.update            2012-11-22
+    _type.purpose                 Measurand
+    _type.contents                Real
+    _enumeration.range            1.:
+    _units.code                   angstroms
+save_
+
+save_units_nested
+    _import.get                   [{'file':templ_attr.cif 'save':cell_length}]
+save_
+"#;
+
+    const EXTENSION: &str = r#"#\#CIF_2.0
+data_EXT_DIC
+    _dictionary.title             EXT_DIC
+
+save_EXT_HEAD
+    _definition.id                EXT_HEAD
+    _definition.scope             Category
+    _definition.class             Head
+    _name.category_id             EXT_DIC
+    _name.object_id               EXT_HEAD
+save_
+
+save_EXTRA
+    _definition.id                EXTRA
+    _definition.scope             Category
+    _definition.class             Set
+    _name.category_id             EXT_HEAD
+    _name.object_id               EXTRA
+save_
+
+save_extra.value
+    _definition.id                '_extra.value'
+    _name.category_id             extra
+    _name.object_id               value
+    _type.contents                Integer
+save_
+"#;
+
+    fn dictionary(body: &str) -> String {
+        r#"#\#CIF_2.0
+data_MAIN_DIC
+    _dictionary.title             MAIN_DIC
+
+save_MAIN_HEAD
+    _definition.id                MAIN_HEAD
+    _definition.scope             Category
+    _definition.class             Head
+    _name.category_id             MAIN_DIC
+    _name.object_id               MAIN_HEAD
+{body}
+save_
+
+save_CELL
+    _definition.id                CELL
+    _definition.scope             Category
+    _definition.class             Set
+    _name.category_id             MAIN_HEAD
+    _name.object_id               CELL
+save_
+
+save_cell.length_a
+    _definition.id                '_cell.length_a'
+    _name.category_id             cell
+    _name.object_id               length_a
+    _import.get                   [{'file':templ_attr.cif 'save':units_nested}]
+save_
+"#
+        .replace("{body}", body)
+    }
+
+    fn resolver() -> MemoryResolver {
+        MemoryResolver::from_iter([("templ_attr.cif", TEMPLATES), ("ext.dic", EXTENSION)])
+    }
+
+    #[test]
+    fn test_contents_import() {
+        let dict = Dictionary::parse_with_imports(&dictionary(""), &resolver()).unwrap();
+        let length_a = dict.item("_cell.length_a").unwrap();
+        assert_eq!(length_a.contents, TypeContents::Simple(ContentType::Real));
+        assert_eq!(length_a.units.as_deref(), Some("angstroms"));
+        assert_eq!(length_a.category_id, "cell");
+    }
+
+    #[test]
+    fn test_full_import() {
+        let body =
+            "    _import.get                   [{'file':'ext.dic' 'save':EXT_HEAD 'mode':Full}]";
+        let dict = Dictionary::parse_with_imports(&dictionary(body), &resolver()).unwrap();
+        assert_eq!(dict.head, "MAIN_HEAD");
+        assert!(dict.category("ext_head").is_none());
+        assert_eq!(
+            dict.category("extra").unwrap().parent.as_deref(),
+            Some("MAIN_HEAD")
+        );
+        assert!(dict.item("_extra.value").is_some());
+    }
+
+    #[test]
+    fn test_missing_import() {
+        let body = "    _import.get                   [{'file':nowhere.cif 'save':x}]";
+        assert_eq!(
+            Dictionary::parse_with_imports(&dictionary(body), &resolver()),
+            Err(DictionaryError::Import(ImportError::MissingFile(
+                "nowhere.cif".to_string()
+            )))
+        );
+        let body =
+            "    _import.get                   [{'file':nowhere.cif 'save':x 'if_miss':Ignore}]";
+        assert!(Dictionary::parse_with_imports(&dictionary(body), &resolver()).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_import() {
+        let extended = dictionary("").replace(
+            "    _import.get                   [{'file':templ_attr.cif 'save':units_nested}]",
+            "    _units.code                   metres\n    _import.get                   [{'file':templ_attr.cif 'save':units_nested 'if_dupl':DUPL}]",
+        );
+        let load = |dupl| {
+            Dictionary::parse_with_imports(&extended.replace("DUPL", dupl), &resolver())
+                .map(|d| d.item("_cell.length_a").unwrap().units.clone())
+        };
+        assert_eq!(load("Ignore"), Ok(Some("metres".to_string())));
+        assert_eq!(load("Replace"), Ok(Some("angstroms".to_string())));
+        assert_eq!(
+            load("Exit"),
+            Err(DictionaryError::Import(ImportError::Duplicate {
+                frame: "cell.length_a".to_string(),
+                name: "_units.code".to_string()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_import_cycle() {
+        let looped = TEMPLATES.replace("'save':cell_length", "'save':units_nested");
+        let resolver = MemoryResolver::from_iter([("templ_attr.cif", looped)]);
+        assert_eq!(
+            Dictionary::parse_with_imports(&dictionary(""), &resolver),
+            Err(DictionaryError::Import(ImportError::Cycle(
+                "templ_attr.cif#units_nested".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_directory_resolver() {
+        let dir = std::env::temp_dir().join(format!("cif_chomper_import_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("templ_attr.cif"), TEMPLATES).unwrap();
+        let resolver = DirectoryResolver::new([&dir]);
+        assert!(
+            resolver
+                .resolve("https://example.org/dictionaries/templ_attr.cif")
+                .is_ok()
+        );
+        assert_eq!(
+            resolver.resolve("missing.cif").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
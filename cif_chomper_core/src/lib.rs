@@ -1,3 +1,20 @@
+pub mod binary_cif;
+pub mod cell;
+pub mod container;
+pub mod dictionary;
+pub mod drel;
+pub mod formats;
+pub mod formula;
+pub mod geometry;
+pub mod import;
 pub mod logging;
+pub mod model;
+pub mod numeric;
 pub mod parser;
 pub mod raw_model;
+pub mod space_group;
+pub mod structure;
+pub mod symmetry;
+#[cfg(test)]
+mod test_fixtures;
+pub mod validation;
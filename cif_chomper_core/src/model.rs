@@ -0,0 +1,505 @@
+/// Support for the models generated by `make_model!`: reading typed values
+/// from data blocks and writing them back
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::container::shape;
+use crate::numeric::Measurement;
+use crate::raw_model::{DataItem, DataValue, RawDataBlock, RawDataItem, RawDataItemContent};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelError {
+    /// A value that cannot be read as the item's type
+    InvalidValue {
+        name: String,
+        value: String,
+        expected: String,
+    },
+    /// Several values for an item of a Set category
+    MultipleValues { name: String },
+    /// Columns of a Loop category with different numbers of rows
+    RowCount { category: String },
+    /// No value for an item that every packet must have
+    Missing { name: String },
+    /// A list value whose shape is not that of the item's `_type.dimension`;
+    /// `found` is `None` for lists that are not rectangular
+    Dimension {
+        name: String,
+        expected: Vec<usize>,
+        found: Option<Vec<usize>>,
+    },
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::InvalidValue {
+                name,
+                value,
+                expected,
+            } => write!(f, "{name}: {value} is not {expected}"),
+            ModelError::MultipleValues { name } => {
+                write!(f, "{name} has several values but belongs to a Set category")
+            }
+            ModelError::RowCount { category } => {
+                write!(f, "the items of {category} have different numbers of rows")
+            }
+            ModelError::Missing { name } => write!(f, "{name} has no value"),
+            ModelError::Dimension {
+                name,
+                expected,
+                found: Some(found),
+            } => write!(
+                f,
+                "{name} has shape {found:?} but is declared as {expected:?}"
+            ),
+            ModelError::Dimension {
+                name, found: None, ..
+            } => write!(f, "{name} is not a rectangular array"),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+/// A struct holding the packets of a category, one field per item
+pub trait CifCategory: Sized {
+    /// `_definition.id` of the category
+    const CATEGORY: &'static str;
+    /// Data names of the fields
+    const NAMES: &'static [&'static str];
+
+    /// The packets of the category in the block, from loops or single items
+    fn rows_from_block(block: &RawDataBlock) -> Result<Vec<Self>, ModelError>;
+
+    /// Adds a loop of the packets, unless none has a value
+    fn write_rows(rows: &[Self], items: &mut Vec<DataItem>);
+
+    /// The packet of a Set category; `None` if none of its items are present
+    fn from_block(block: &RawDataBlock) -> Result<Option<Self>, ModelError> {
+        let mut rows = Self::rows_from_block(block)?;
+        match rows.len() {
+            0 | 1 => Ok(rows.pop()),
+            _ => Err(ModelError::MultipleValues {
+                name: Self::NAMES.first().unwrap_or(&Self::CATEGORY).to_string(),
+            }),
+        }
+    }
+}
+
+/// A type of item value in a generated model
+pub trait CifValue: Sized {
+    /// `None` if the content is not a value of this type
+    fn from_content(content: &RawDataItemContent) -> Option<Self>;
+    fn to_content(&self) -> DataValue;
+    /// Description of the values, for errors
+    fn expected() -> String;
+    /// Sizes of the nested lists of a fixed-size array type, outermost first
+    fn shape() -> Option<Vec<usize>> {
+        None
+    }
+}
+
+impl CifValue for f64 {
+    /// Reads the value of a measurement; the su is not kept
+    fn from_content(content: &RawDataItemContent) -> Option<Self> {
+        content
+            .as_str()?
+            .parse::<Measurement>()
+            .ok()
+            .map(|m| m.value)
+    }
+
+    fn to_content(&self) -> DataValue {
+        DataValue::Str(self.to_string())
+    }
+
+    fn expected() -> String {
+        "a real number".to_string()
+    }
+}
+
+impl CifValue for Measurement {
+    fn from_content(content: &RawDataItemContent) -> Option<Self> {
+        content.as_str()?.parse().ok()
+    }
+
+    fn to_content(&self) -> DataValue {
+        DataValue::Str(self.to_string())
+    }
+
+    fn expected() -> String {
+        "a real number".to_string()
+    }
+}
+
+impl CifValue for i64 {
+    fn from_content(content: &RawDataItemContent) -> Option<Self> {
+        content.as_str()?.parse().ok()
+    }
+
+    fn to_content(&self) -> DataValue {
+        DataValue::Str(self.to_string())
+    }
+
+    fn expected() -> String {
+        "an integer".to_string()
+    }
+}
+
+impl CifValue for String {
+    fn from_content(content: &RawDataItemContent) -> Option<Self> {
+        content.as_str().map(str::to_string)
+    }
+
+    fn to_content(&self) -> DataValue {
+        DataValue::Str(self.clone())
+    }
+
+    fn expected() -> String {
+        "a string".to_string()
+    }
+}
+
+impl<T: CifValue> CifValue for Vec<T> {
+    fn from_content(content: &RawDataItemContent) -> Option<Self> {
+        match content {
+            RawDataItemContent::List(values) => values.iter().map(T::from_content).collect(),
+            _ => None,
+        }
+    }
+
+    fn to_content(&self) -> DataValue {
+        DataValue::List(self.iter().map(T::to_content).collect())
+    }
+
+    fn expected() -> String {
+        format!("a list of {}", T::expected())
+    }
+}
+
+impl<T: CifValue, const N: usize> CifValue for [T; N] {
+    fn from_content(content: &RawDataItemContent) -> Option<Self> {
+        Vec::<T>::from_content(content)?.try_into().ok()
+    }
+
+    fn to_content(&self) -> DataValue {
+        DataValue::List(self.iter().map(T::to_content).collect())
+    }
+
+    fn expected() -> String {
+        format!("a list of {N} of {}", T::expected())
+    }
+
+    fn shape() -> Option<Vec<usize>> {
+        Some(
+            std::iter::once(N)
+                .chain(T::shape().unwrap_or_default())
+                .collect(),
+        )
+    }
+}
+
+impl<T: CifValue> CifValue for BTreeMap<String, T> {
+    fn from_content(content: &RawDataItemContent) -> Option<Self> {
+        match content {
+            RawDataItemContent::Table(entries) => entries
+                .iter()
+                .map(|(k, v)| Some((k.as_str()?.to_string(), T::from_content(v)?)))
+                .collect(),
+            _ => None,
+        }
+    }
+
+    fn to_content(&self) -> DataValue {
+        DataValue::Table(
+            self.iter()
+                .map(|(k, v)| (DataValue::Str(k.clone()), v.to_content()))
+                .collect(),
+        )
+    }
+
+    fn expected() -> String {
+        format!("a table of {}", T::expected())
+    }
+}
+
+fn is_null(content: &RawDataItemContent) -> bool {
+    matches!(
+        content,
+        RawDataItemContent::Empty | RawDataItemContent::Str("?" | ".")
+    )
+}
+
+/// Values of the first of `names` in the block, from a single item or a
+/// loop column. Names are matched case-insensitively.
+pub fn column<'b, 'a>(
+    block: &'b RawDataBlock<'a>,
+    names: &[&str],
+) -> Option<Vec<&'b RawDataItemContent<'a>>> {
+    block.content.iter().find_map(|item| match item {
+        RawDataItem::Data { name, value } => names
+            .iter()
+            .any(|n| n.eq_ignore_ascii_case(name))
+            .then(|| vec![value]),
+        RawDataItem::Loop {
+            names: loop_names,
+            values,
+        } => {
+            let i = loop_names
+                .iter()
+                .position(|l| names.iter().any(|n| n.eq_ignore_ascii_case(l)))?;
+            Some(values.iter().skip(i).step_by(loop_names.len()).collect())
+        }
+        RawDataItem::SaveFrame { .. } => None,
+    })
+}
+
+/// A value of `name`; `None` for the nulls `?` and `.`
+pub fn parse_value<T: CifValue>(
+    name: &str,
+    content: &RawDataItemContent,
+) -> Result<Option<T>, ModelError> {
+    if is_null(content) {
+        return Ok(None);
+    }
+    if let (Some(expected), RawDataItemContent::List(_)) = (T::shape(), content) {
+        let found = shape(content);
+        if found.as_ref() != Some(&expected) {
+            return Err(ModelError::Dimension {
+                name: name.to_string(),
+                expected,
+                found,
+            });
+        }
+    }
+    T::from_content(content)
+        .map(Some)
+        .ok_or_else(|| ModelError::InvalidValue {
+            name: name.to_string(),
+            value: DataValue::from(content).to_string(),
+            expected: T::expected(),
+        })
+}
+
+/// The value of an item of a Set category, under its name or an alias
+pub fn single<T: CifValue>(
+    block: &RawDataBlock,
+    name: &str,
+    names: &[&str],
+) -> Result<Option<T>, ModelError> {
+    match column(block, names).as_deref() {
+        None | Some([]) => Ok(None),
+        Some([value]) => parse_value(name, value),
+        Some(_) => Err(ModelError::MultipleValues {
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// The value in a row of a column, if any
+pub fn optional_value<T: CifValue>(
+    name: &str,
+    column: &Option<Vec<&RawDataItemContent>>,
+    row: usize,
+) -> Result<Option<T>, ModelError> {
+    match column.as_ref().and_then(|c| c.get(row)) {
+        Some(content) => parse_value(name, content),
+        None => Ok(None),
+    }
+}
+
+/// The value in a row of a column, which must not be absent or null
+pub fn required_value<T: CifValue>(
+    name: &str,
+    column: &Option<Vec<&RawDataItemContent>>,
+    row: usize,
+) -> Result<T, ModelError> {
+    optional_value(name, column, row)?.ok_or_else(|| ModelError::Missing {
+        name: name.to_string(),
+    })
+}
+
+/// Number of rows of a Loop category from the columns present
+pub fn row_count(
+    category: &str,
+    columns: &[Option<Vec<&RawDataItemContent>>],
+) -> Result<usize, ModelError> {
+    let mut counts = columns.iter().flatten().map(Vec::len);
+    let count = counts.next().unwrap_or(0);
+    if counts.all(|n| n == count) {
+        Ok(count)
+    } else {
+        Err(ModelError::RowCount {
+            category: category.to_string(),
+        })
+    }
+}
+
+/// Adds an item of a Set category, unless it has no value
+pub fn push_item(items: &mut Vec<DataItem>, name: &str, value: Option<DataValue>) {
+    if let Some(value) = value {
+        items.push(DataItem::Data {
+            name: name.to_string(),
+            value,
+        });
+    }
+}
+
+/// Adds a loop of the columns with any values, writing `?` for the others
+pub fn push_loop(items: &mut Vec<DataItem>, columns: Vec<(&str, Vec<Option<DataValue>>)>) {
+    let columns: Vec<_> = columns
+        .into_iter()
+        .filter(|(_, values)| values.iter().any(Option::is_some))
+        .collect();
+    let Some(rows) = columns.first().map(|(_, values)| values.len()) else {
+        return;
+    };
+    let names = columns.iter().map(|(name, _)| name.to_string()).collect();
+    let values = (0..rows)
+        .flat_map(|row| {
+            columns.iter().map(move |(_, values)| {
+                values[row]
+                    .clone()
+                    .unwrap_or_else(|| DataValue::Str("?".to_string()))
+            })
+        })
+        .collect();
+    items.push(DataItem::Loop { names, values });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("5.4321(3)", Some(5.4321))]
+    #[case("?", None)]
+    #[case(".", None)]
+    fn test_parse_real(#[case] value: &str, #[case] expected: Option<f64>) {
+        let content = RawDataItemContent::Str(value);
+        assert_eq!(parse_value("_cell.length_a", &content), Ok(expected));
+    }
+
+    #[test]
+    fn test_parse_measurement() {
+        let content = RawDataItemContent::Str("16.8303(2)");
+        let length: Measurement = parse_value("_cell.length_a", &content).unwrap().unwrap();
+        assert_eq!(length.value, 16.8303);
+        assert!((length.su_or_zero() - 0.0002).abs() < 1e-12);
+        assert_eq!(
+            length.to_content(),
+            DataValue::Str("16.8303(2)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let content = RawDataItemContent::List(vec![
+            RawDataItemContent::Str("1"),
+            RawDataItemContent::Str("x"),
+            RawDataItemContent::Str("3"),
+        ]);
+        assert_eq!(
+            parse_value::<[f64; 3]>("_atom_site.fract_xyz", &content),
+            Err(ModelError::InvalidValue {
+                name: "_atom_site.fract_xyz".to_string(),
+                value: "[1 x 3]".to_string(),
+                expected: "a list of 3 of a real number".to_string(),
+            })
+        );
+    }
+
+    #[rstest]
+    #[case(vec![RawDataItemContent::Str("1")], Some(vec![1]))]
+    #[case(
+        vec![
+            RawDataItemContent::List(vec![]),
+            RawDataItemContent::List(vec![]),
+            RawDataItemContent::List(vec![]),
+        ],
+        Some(vec![3, 0])
+    )]
+    #[case(
+        vec![
+            RawDataItemContent::List(vec![RawDataItemContent::Str("1")]),
+            RawDataItemContent::Str("2"),
+        ],
+        None
+    )]
+    fn test_parse_wrong_dimension(
+        #[case] values: Vec<RawDataItemContent>,
+        #[case] found: Option<Vec<usize>>,
+    ) {
+        let content = RawDataItemContent::List(values);
+        assert_eq!(
+            parse_value::<[[f64; 2]; 3]>("_cell.convert_uij_to_betaij", &content),
+            Err(ModelError::Dimension {
+                name: "_cell.convert_uij_to_betaij".to_string(),
+                expected: vec![3, 2],
+                found,
+            })
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let input = "#\\#CIF_2.0\ndata_test\n_cell_length_a 5.0\n\
+            loop_\n_atom_site.label\n_atom_site.fract_x\nO1 0.1\nO2 0.2\n";
+        let model = cif2_file(input).unwrap();
+        let block = &model.content[0];
+        let length_a: Option<f64> = single(
+            block,
+            "_cell.length_a",
+            &["_cell.length_a", "_cell_length_a"],
+        )
+        .unwrap();
+        assert_eq!(length_a, Some(5.0));
+        assert_eq!(
+            single::<String>(block, "_atom_site.label", &["_atom_site.label"]),
+            Err(ModelError::MultipleValues {
+                name: "_atom_site.label".to_string()
+            })
+        );
+        let columns = [
+            column(block, &["_atom_site.label"]),
+            column(block, &["_atom_site.fract_x"]),
+            column(block, &["_atom_site.fract_y"]),
+        ];
+        assert_eq!(row_count("ATOM_SITE", &columns), Ok(2));
+        assert_eq!(columns[1].as_ref().unwrap()[1].as_str(), Some("0.2"));
+    }
+
+    #[test]
+    fn test_push_loop() {
+        let mut items = Vec::new();
+        push_loop(
+            &mut items,
+            vec![
+                (
+                    "_atom_site.label",
+                    vec![Some("O1".to_string().to_content()), None],
+                ),
+                ("_atom_site.fract_x", vec![None, Some(0.5.to_content())]),
+                ("_atom_site.fract_y", vec![None, None]),
+            ],
+        );
+        assert_eq!(
+            items,
+            vec![DataItem::Loop {
+                names: vec![
+                    "_atom_site.label".to_string(),
+                    "_atom_site.fract_x".to_string()
+                ],
+                values: vec![
+                    DataValue::Str("O1".to_string()),
+                    DataValue::Str("?".to_string()),
+                    DataValue::Str("?".to_string()),
+                    DataValue::Str("0.5".to_string()),
+                ],
+            }]
+        );
+    }
+}
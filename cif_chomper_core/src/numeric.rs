@@ -0,0 +1,141 @@
+/// CIF numeric values, optionally followed by a standard uncertainty in
+/// parentheses in units of the last digit, e.g. `1.234(5)` for 1.234 ± 0.005
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub value: f64,
+    /// Standard uncertainty, in the same units as the value
+    pub su: Option<f64>,
+}
+
+impl Measurement {
+    pub fn new(value: f64, su: Option<f64>) -> Self {
+        Measurement { value, su }
+    }
+
+    pub fn exact(value: f64) -> Self {
+        Measurement { value, su: None }
+    }
+
+    /// Standard uncertainty, taking exact values as zero
+    pub fn su_or_zero(&self) -> f64 {
+        self.su.unwrap_or(0.0)
+    }
+}
+
+fn is_number(s: &str) -> bool {
+    let mantissa = s.split(['e', 'E']).next().unwrap_or(s);
+    let mantissa = mantissa.strip_prefix(['+', '-']).unwrap_or(mantissa);
+    mantissa.chars().any(|c| c.is_ascii_digit())
+        && mantissa.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && s.chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+}
+
+impl FromStr for Measurement {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, su) = match s.strip_suffix(')').and_then(|s| s.split_once('(')) {
+            Some((number, su)) => (number, Some(su)),
+            None => (s, None),
+        };
+        if !is_number(number) {
+            return Err(());
+        }
+        let value: f64 = number.parse().map_err(|_| ())?;
+        let su = match su {
+            None => None,
+            Some(su) if !su.is_empty() && su.chars().all(|c| c.is_ascii_digit()) => {
+                let (mantissa, exponent) = match number.split_once(['e', 'E']) {
+                    Some((m, e)) => (m, e.parse::<i32>().map_err(|_| ())?),
+                    None => (number, 0),
+                };
+                let decimals = mantissa.split_once('.').map_or(0, |(_, d)| d.len() as i32);
+                let su: f64 = su.parse().map_err(|_| ())?;
+                Some(su * 10f64.powi(exponent - decimals))
+            }
+            Some(_) => return Err(()),
+        };
+        Ok(Measurement { value, su })
+    }
+}
+
+impl fmt::Display for Measurement {
+    /// Writes the value with the su in parentheses, rounded to the precision
+    /// of the su using the usual crystallographic "rule of 19"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.su {
+            Some(su) if su > 0.0 => {
+                // Scale the su to two significant digits, keeping both only
+                // when they are 19 or less
+                let mut decimals = 1 - su.log10().floor() as i32;
+                if (su * 10f64.powi(decimals)).round() >= 100.0 {
+                    decimals -= 1;
+                }
+                if (su * 10f64.powi(decimals)).round() > 19.0 {
+                    decimals -= 1;
+                }
+                let scaled = (su * 10f64.powi(decimals)).round();
+                if decimals > 0 {
+                    write!(f, "{:.*}({})", decimals as usize, self.value, scaled)
+                } else {
+                    let unit = 10f64.powi(-decimals);
+                    let value = (self.value / unit).round() * unit;
+                    write!(f, "{:.0}({:.0})", value, scaled * unit)
+                }
+            }
+            _ => write!(f, "{}", self.value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("1.234(5)", 1.234, Some(0.005))]
+    #[case("88.86899", 88.86899, None)]
+    #[case("-5", -5.0, None)]
+    #[case("123(14)", 123.0, Some(14.0))]
+    #[case("1.2e3(1)", 1200.0, Some(100.0))]
+    #[case(".5", 0.5, None)]
+    fn test_parse(#[case] input: &str, #[case] value: f64, #[case] su: Option<f64>) {
+        let m: Measurement = input.parse().unwrap();
+        assert!((m.value - value).abs() < 1e-12);
+        match (m.su, su) {
+            (Some(a), Some(b)) => assert!((a - b).abs() < 1e-12),
+            (a, b) => assert_eq!(a, b),
+        }
+    }
+
+    #[rstest]
+    #[case("abc")]
+    #[case("1.2(a)")]
+    #[case("inf")]
+    #[case("?")]
+    #[case("1,2")]
+    fn test_parse_invalid(#[case] input: &str) {
+        assert!(input.parse::<Measurement>().is_err());
+    }
+
+    #[rstest]
+    #[case(Measurement::new(1.23456, Some(0.0012)), "1.2346(12)")]
+    #[case(Measurement::new(1.23456, Some(0.0015)), "1.2346(15)")]
+    #[case(Measurement::new(1.23456, Some(0.0016)), "1.2346(16)")]
+    #[case(Measurement::new(1.23456, Some(0.0017)), "1.2346(17)")]
+    #[case(Measurement::new(1.23456, Some(0.0018)), "1.2346(18)")]
+    #[case(Measurement::new(1.23456, Some(0.0019)), "1.2346(19)")]
+    #[case(Measurement::new(1.23456, Some(0.0021)), "1.235(2)")]
+    #[case(Measurement::new(1.23456, Some(0.00999)), "1.235(10)")]
+    #[case(Measurement::new(1.23456, Some(0.003)), "1.235(3)")]
+    #[case(Measurement::new(701867.3, Some(23.0)), "701870(20)")]
+    #[case(Measurement::exact(2.5), "2.5")]
+    fn test_display(#[case] m: Measurement, #[case] expected: &str) {
+        assert_eq!(m.to_string(), expected);
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::raw_model::{RawDataBlock, RawDataItem, RawDataItemContent, RawModel};
 use const_str::to_char_array;
 use nom::{
@@ -74,11 +76,11 @@ fn non_blank_chars(input: &str) -> IResult<&str, &str> {
 fn text_content(input: &str) -> IResult<&str, &str> {
     alt((take_until("\n;"), take_until("\r\n;"), take_until("\r;"))).parse(input)
 }
-fn text_field(input: &str) -> IResult<&str, RawDataItemContent> {
+fn text_field(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let (inp, _) = text_delim(input)?;
     let (inp, value) = text_content(inp)?;
     let (inp, _) = text_delim(inp)?;
-    Ok((inp, RawDataItemContent::Str(value)))
+    Ok((inp, RawDataItemContent::Text(value)))
 }
 
 res_word!(magic_code, r"#\#CIF_2.0");
@@ -91,34 +93,34 @@ res_word_nocase!(stop_token, "stop_");
 res_word!(quote_3_delim, "\"\"\"");
 res_word!(apostrophe_3_delim, "'''");
 
-fn triple_dquote_string(input: &str) -> IResult<&str, RawDataItemContent> {
+fn triple_dquote_string(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let (inp, _) = quote_3_delim(input)?;
     let (inp, value) = take_until("\"\"\"").parse(inp)?;
     let (inp, _) = quote_3_delim(inp)?;
     Ok((inp, RawDataItemContent::Str(value)))
 }
-fn triple_apo_string(input: &str) -> IResult<&str, RawDataItemContent> {
+fn triple_apo_string(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let (inp, _) = apostrophe_3_delim(input)?;
     let (inp, value) = take_until("'''").parse(inp)?;
     let (inp, _) = apostrophe_3_delim(inp)?;
     Ok((inp, RawDataItemContent::Str(value)))
 }
-fn triple_quoted_string(input: &str) -> IResult<&str, RawDataItemContent> {
+fn triple_quoted_string(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     alt((triple_dquote_string, triple_apo_string)).parse(input)
 }
-fn single_squote_string(input: &str) -> IResult<&str, RawDataItemContent> {
+fn single_squote_string(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let (inp, _) = char('\'')(input)?;
     let (inp, value) = take_while1(|c| c != '\'').parse(inp)?;
     let (inp, _) = char('\'')(inp)?;
     Ok((inp, RawDataItemContent::Str(value)))
 }
-fn single_dquote_string(input: &str) -> IResult<&str, RawDataItemContent> {
+fn single_dquote_string(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let (inp, _) = char('"')(input)?;
     let (inp, value) = take_while1(|c| c != '"').parse(inp)?;
     let (inp, _) = char('"')(inp)?;
     Ok((inp, RawDataItemContent::Str(value)))
 }
-fn single_quoted_string(input: &str) -> IResult<&str, RawDataItemContent> {
+fn single_quoted_string(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     alt((single_dquote_string, single_squote_string)).parse(input)
 }
 fn not_token(input: &str) -> IResult<&str, ()> {
@@ -128,13 +130,13 @@ fn not_token(input: &str) -> IResult<&str, ()> {
     not(global_token).parse(input)?;
     not(stop_token).parse(input)
 }
-fn wsdelim_string(input: &str) -> IResult<&str, RawDataItemContent> {
+fn wsdelim_string(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     not_token(input)?;
     peek(is_not(LEAD)).parse(input)?;
     let (inp, value) = take_while1(restrict_char).parse(input)?;
     Ok((inp, RawDataItemContent::Str(value)))
 }
-fn wsdelim_string_sol(input: &str) -> IResult<&str, RawDataItemContent> {
+fn wsdelim_string_sol(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     not_token(input)?;
     peek(is_not(LEAD)).parse(input)?;
     if peek(char::<&str, Error<&str>>(';')).parse(input).is_ok() {
@@ -151,7 +153,7 @@ fn data_name(input: &str) -> IResult<&str, &str> {
     let (inp, name) = non_blank_chars(input)?;
     Ok((inp, name))
 }
-fn list_values_start(input: &str) -> IResult<&str, RawDataItemContent> {
+fn list_values_start(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let p1 = |inp| nospace_value(wspace_any(inp)?.0);
     let p2 = |inp| {
         let (inp_, _) = wspace_any(inp)?;
@@ -168,59 +170,61 @@ fn list_values_start(input: &str) -> IResult<&str, RawDataItemContent> {
     };
     alt((p1, p2, p3, p4)).parse(input)
 }
-fn list(input: &str) -> IResult<&str, RawDataItemContent> {
+fn list(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let (inp, _) = char('[')(input)?;
-    let (inp, _) = opt(list_values_start).parse(inp)?;
-    let (inp, values) = many0(wspace_data_value).parse(inp)?;
+    let (inp, first) = opt(list_values_start).parse(inp)?;
+    let (inp, rest) = many0(wspace_data_value).parse(inp)?;
     let (inp, _) = wspace_any(space0(inp)?.0)?;
     let (inp, _) = char(']')(inp)?;
+    let values = first.into_iter().chain(rest).collect();
     Ok((inp, RawDataItemContent::List(values)))
 }
-fn table(input: &str) -> IResult<&str, RawDataItemContent> {
-    let wspace_tentry = |inp| table_entry(space0(inp)?.0);
+fn table(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
+    let wspace_tentry = |inp| table_entry(wspace_any(inp)?.0);
     // TODO: replace with separated_list (0)
     let (inp, _) = char('{')(input)?;
     let (inp, _) = opt(wspace_any).parse(inp)?;
     let (inp, entry_1) = opt(table_entry).parse(inp)?;
-    if entry_1.is_none() {
+    let Some(entry_1) = entry_1 else {
         let (inp, _) = char('}')(inp)?;
         return Ok((inp, RawDataItemContent::Table(Vec::new())));
-    }
-    let (inp, entries) = many0(wspace_tentry).parse(inp)?;
-    let (inp, _) = space0(inp)?;
+    };
+    let (inp, rest) = many0(wspace_tentry).parse(inp)?;
+    let (inp, _) = wspace_any(inp)?;
     let (inp, _) = char('}')(inp)?;
+    let entries = std::iter::once(entry_1).chain(rest).collect();
     Ok((inp, RawDataItemContent::Table(entries)))
 }
-fn nospace_value(input: &str) -> IResult<&str, RawDataItemContent> {
+fn nospace_value(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     alt((single_quoted_string, triple_quoted_string, list, table)).parse(input)
 }
-fn wspace_dv_1(input: &str) -> IResult<&str, RawDataItemContent> {
+fn wspace_dv_1(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     nospace_value(wspace(input)?.0)
 }
-fn wspace_dv_2(input: &str) -> IResult<&str, RawDataItemContent> {
+fn wspace_dv_2(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let (inp, _) = opt(wspace_lines).parse(input)?;
     let (inp, _) = space1(inp)?;
     let (inp, value) = wsdelim_string(inp)?;
     Ok((inp, value))
 }
-fn wspace_dv_3(input: &str) -> IResult<&str, RawDataItemContent> {
+fn wspace_dv_3(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let (inp, value) = wsdelim_string_sol(wspace_lines(input)?.0)?;
     Ok((inp, value))
 }
-fn wspace_dv_4(input: &str) -> IResult<&str, RawDataItemContent> {
+fn wspace_dv_4(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     let (inp, value) = text_field(opt(comment).parse(opt(space0).parse(input)?.0)?.0)?;
     Ok((inp, value))
 }
-fn wspace_data_value(input: &str) -> IResult<&str, RawDataItemContent> {
+fn wspace_data_value(input: &str) -> IResult<&str, RawDataItemContent<'_>> {
     alt((wspace_dv_1, wspace_dv_2, wspace_dv_3, wspace_dv_4)).parse(input)
 }
-fn table_entry(input: &str) -> IResult<&str, (RawDataItemContent, RawDataItemContent)> {
+fn table_entry(input: &str) -> IResult<&str, (RawDataItemContent<'_>, RawDataItemContent<'_>)> {
     let (inp, key) = alt((single_quoted_string, triple_quoted_string)).parse(input)?;
     let (inp, _) = char(':')(inp)?;
     let (inp, value) = alt((nospace_value, wsdelim_string, wspace_data_value)).parse(inp)?;
     Ok((inp, (key, value)))
 }
-fn data_loop(input: &str) -> IResult<&str, RawDataItem> {
+fn data_loop(input: &str) -> IResult<&str, RawDataItem<'_>> {
     let (inp, _) = loop_token(input)?;
     let (inp, _) = wspace(inp)?;
     let (inp, names) = separated_list1(wspace, data_name).parse(inp)?;
@@ -231,7 +235,7 @@ fn data_loop(input: &str) -> IResult<&str, RawDataItem> {
     // }
     Ok((inp, RawDataItem::Loop { names, values }))
 }
-fn data(input: &str) -> IResult<&str, RawDataItem> {
+fn data(input: &str) -> IResult<&str, RawDataItem<'_>> {
     let data_item = |inp| {
         let (inp_, name) = data_name(inp)?;
         let (inp_, value) = wspace_data_value(inp_)?;
@@ -242,7 +246,7 @@ fn data(input: &str) -> IResult<&str, RawDataItem> {
 fn container_code(input: &str) -> IResult<&str, &str> {
     take_while1(non_blank).parse(input)
 }
-fn frame_content(input: &str) -> IResult<&str, RawDataItem> {
+fn frame_content(input: &str) -> IResult<&str, RawDataItem<'_>> {
     let (inp, _) = wspace(input)?;
     data(inp)
 }
@@ -250,14 +254,14 @@ fn save_heading(input: &str) -> IResult<&str, &str> {
     let (inp, _) = save_token(input)?;
     container_code(inp)
 }
-fn save_frame(input: &str) -> IResult<&str, RawDataItem> {
+fn save_frame(input: &str) -> IResult<&str, RawDataItem<'_>> {
     let (inp, name) = save_heading(input)?;
     let (inp, content) = many0(frame_content).parse(inp)?;
     let (inp, _) = wspace(inp)?;
     let (inp, _) = save_token(inp)?;
     Ok((inp, RawDataItem::SaveFrame { name, content }))
 }
-fn block_content(input: &str) -> IResult<&str, RawDataItem> {
+fn block_content(input: &str) -> IResult<&str, RawDataItem<'_>> {
     let (inp, _) = wspace(input)?;
     let (inp, cont) = alt((data, save_frame)).parse(inp)?;
     Ok((inp, cont))
@@ -266,7 +270,7 @@ fn data_heading(input: &str) -> IResult<&str, &str> {
     let (inp, _) = data_token(input)?;
     container_code(inp)
 }
-fn data_block(input: &str) -> IResult<&str, RawDataBlock> {
+fn data_block(input: &str) -> IResult<&str, RawDataBlock<'_>> {
     let (inp, heading) = data_heading(input)?;
     let (inp, content) = many0(block_content).parse(inp)?;
     Ok((inp, RawDataBlock { heading, content }))
@@ -277,20 +281,64 @@ fn file_heading(input: &str) -> IResult<&str, &str> {
     let (inp, _) = space0(inp)?;
     Ok((inp, code))
 }
-fn file_content(input: &str) -> IResult<&str, Vec<RawDataBlock>> {
+fn file_content(input: &str) -> IResult<&str, Vec<RawDataBlock<'_>>> {
     let (inp, _) = line_ending(input)?;
     let (inp, _) = wspace_any(inp)?;
     let (inp, blocks) = separated_list1(wspace, data_block).parse(inp)?;
     Ok((inp, blocks))
 }
-pub fn cif2_file(input: &str) -> Result<RawModel, &str> {
-    let (inp, heading) = file_heading(input).map_err(|_| "heading")?;
-    let (inp, content) = file_content(inp).map_err(|_| "content")?;
-    let (inp, _) = wspace_any(inp).map_err(|_| "trailing wspace")?;
+/// Where `cif2_file` stopped: the rule that failed and the 1-based line and
+/// column of the text it could not read
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub rule: &'static str,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ParseError {
+    /// Error for `rule`, failing on `rest`, the unread end of `input`
+    fn new(rule: &'static str, input: &str, rest: &str) -> Self {
+        let read = &input[..input.len() - rest.len()];
+        let line_start = read.rfind('\n').map_or(0, |i| i + 1);
+        ParseError {
+            rule,
+            line: read.matches('\n').count() + 1,
+            column: read[line_start..].chars().count() + 1,
+        }
+    }
+
+    fn from_nom(rule: &'static str, input: &str, e: nom::Err<Error<&str>>) -> Self {
+        match e {
+            nom::Err::Error(e) | nom::Err::Failure(e) => ParseError::new(rule, input, e.input),
+            nom::Err::Incomplete(_) => ParseError::new(rule, input, ""),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.rule, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn cif2_file(input: &str) -> Result<RawModel<'_>, ParseError> {
+    let (inp, heading) =
+        file_heading(input).map_err(|e| ParseError::from_nom("heading", input, e))?;
+    let (inp, content) =
+        file_content(inp).map_err(|e| ParseError::from_nom("content", input, e))?;
+    let (inp, _) =
+        wspace_any(inp).map_err(|e| ParseError::from_nom("trailing wspace", input, e))?;
     let (inp, _) = opt(comment)
         .parse(inp)
-        .map_err(|_| "trailing opt comment")?;
-    eof::<&str, ()>(inp).map_err(|_| "expected eof")?;
+        .map_err(|e| ParseError::from_nom("trailing opt comment", input, e))?;
+    eof::<&str, ()>(inp).map_err(|_| ParseError::new("expected eof", input, inp))?;
     Ok(RawModel { heading, content })
 }
 
@@ -352,7 +400,7 @@ mod tests {
     #[case(triple_quoted_string, "\"\"\"asdf  7' \n\t \"\"a\"abc", "", false)]
     #[case(triple_quoted_string, "'''asdf  7' \n\t '''abc", "abc", true)]
     fn test_parser_data_components(
-        #[case] func: fn(&str) -> IResult<&str, RawDataItemContent>,
+        #[case] func: fn(&str) -> IResult<&str, RawDataItemContent<'_>>,
         #[case] input: &str,
         #[case] expected: &str,
         #[case] good: bool,
@@ -384,7 +432,7 @@ loop_
 _atom_site_label,",
         RawDataItem::Loop{
             names: vec!["_symmetry_equiv_pos_as_xyz"], 
-            values: vec!["x,y,z","x,-y+1/4,-z+1/4","-x+1/4,y,-z+1/4","-x,-z+1/2,-y+1/2","-x,z+1/4,y+1/4","x+3/4,z+1/4,-y+1/2","x+3/4,-z+1/2,y+1/4",].iter().map(|s| RawDataItemContent::Str(s)).collect()
+            values: ["x,y,z","x,-y+1/4,-z+1/4","-x+1/4,y,-z+1/4","-x,-z+1/2,-y+1/2","-x,z+1/4,y+1/4","x+3/4,z+1/4,-y+1/2","x+3/4,-z+1/2,y+1/4",].iter().map(|s| RawDataItemContent::Str(s)).collect()
         },
         true
     )]
@@ -433,6 +481,27 @@ x,-y+1/4,-z+1/4
         }
     }
 
+    #[rstest]
+    #[case("[a b c]", vec!["a", "b", "c"])]
+    #[case("['a']", vec!["a"])]
+    #[case("[]", vec![])]
+    fn test_list(#[case] input: &str, #[case] expected: Vec<&str>) {
+        let expected = expected.into_iter().map(RawDataItemContent::Str).collect();
+        assert_eq!(list(input), Ok(("", RawDataItemContent::List(expected))));
+    }
+
+    #[rstest]
+    #[case("{'file':templ_attr.cif 'save':cell_length}", vec![("file", "templ_attr.cif"), ("save", "cell_length")])]
+    #[case("{'a':'x'\n   'b':y}", vec![("a", "x"), ("b", "y")])]
+    #[case("{}", vec![])]
+    fn test_table(#[case] input: &str, #[case] expected: Vec<(&str, &str)>) {
+        let expected = expected
+            .into_iter()
+            .map(|(k, v)| (RawDataItemContent::Str(k), RawDataItemContent::Str(v)))
+            .collect();
+        assert_eq!(table(input), Ok(("", RawDataItemContent::Table(expected))));
+    }
+
     #[rstest]
     #[case("\n#'F\\'erey, G.'\n_publ_section_title abcde", "", false)]
     #[case("\nx,y,z\nx,-y+1/4,-z+1/4", "\nx,-y+1/4,-z+1/4", true)]
@@ -440,10 +509,24 @@ x,-y+1/4,-z+1/4
         if good {
             let (inp, _) = wspace_lines(input).unwrap();
             let (inp, val) = wsdelim_string_sol(inp).unwrap();
+            assert_eq!((inp, val), (expected, RawDataItemContent::Str("x,y,z")));
         } else {
             let (inp, _) = wspace_lines(input).unwrap();
-            let t = peek(is_not::<&str, &str, Error<&str>>(LEAD)).parse(inp);
             assert!(wsdelim_string_sol(inp).is_err());
         }
     }
+
+    #[rstest]
+    #[case("data_x\n", "heading", 1, 1)]
+    #[case("#\\#CIF_2.0\n", "content", 2, 1)]
+    #[case("#\\#CIF_2.0\ndata_x\n_a 1\n  _b [1\n", "expected eof", 4, 3)]
+    fn test_parse_error(
+        #[case] input: &str,
+        #[case] rule: &str,
+        #[case] line: usize,
+        #[case] column: usize,
+    ) {
+        let error = cif2_file(input).unwrap_err();
+        assert_eq!((error.rule, error.line, error.column), (rule, line, column));
+    }
 }
@@ -1,3 +1,5 @@
+use std::fmt;
+
 /// Corresponds to the hierarchy expressed by the CIF 2.0 / DDLm syntax, without
 /// any parsing or interpretation of data
 #[derive(Debug, PartialEq)]
@@ -17,6 +19,9 @@ pub struct RawDataBlock<'a> {
 pub enum RawDataItemContent<'a> {
     Empty,
     Str(&'a str),
+    /// A semicolon delimited text field, which may use the text prefix
+    /// protocol
+    Text(&'a str),
     List(Vec<RawDataItemContent<'a>>),
     Table(Vec<(RawDataItemContent<'a>, RawDataItemContent<'a>)>),
 }
@@ -36,3 +41,330 @@ pub enum RawDataItem<'a> {
         values: Vec<RawDataItemContent<'a>>,
     },
 }
+
+impl<'a> RawDataItemContent<'a> {
+    /// Content for a string, as a text field when [`DataValue`] would write it
+    /// as one without the text prefix protocol
+    pub fn from_text(s: &'a str) -> Self {
+        match is_text_field(s) && !needs_text_prefix(s) {
+            true => RawDataItemContent::Text(s),
+            false => RawDataItemContent::Str(s),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            RawDataItemContent::Str(s) | RawDataItemContent::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// An owned data block, for CIF content built in memory rather than parsed
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DataBlock {
+    pub heading: String,
+    pub content: Vec<DataItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataValue {
+    Empty,
+    Str(String),
+    List(Vec<DataValue>),
+    Table(Vec<(DataValue, DataValue)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataItem {
+    Data {
+        name: String,
+        value: DataValue,
+    },
+    Loop {
+        names: Vec<String>,
+        values: Vec<DataValue>,
+    },
+}
+
+impl DataValue {
+    pub fn as_raw(&self) -> RawDataItemContent<'_> {
+        match self {
+            DataValue::Empty => RawDataItemContent::Empty,
+            DataValue::Str(s) => RawDataItemContent::from_text(s),
+            DataValue::List(values) => {
+                RawDataItemContent::List(values.iter().map(DataValue::as_raw).collect())
+            }
+            DataValue::Table(entries) => RawDataItemContent::Table(
+                entries
+                    .iter()
+                    .map(|(k, v)| (k.as_raw(), v.as_raw()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<&RawDataItemContent<'_>> for DataValue {
+    fn from(content: &RawDataItemContent<'_>) -> Self {
+        match content {
+            RawDataItemContent::Empty => DataValue::Empty,
+            RawDataItemContent::Str(s) => DataValue::Str(s.to_string()),
+            RawDataItemContent::Text(s) => {
+                DataValue::Str(remove_text_prefix(s).unwrap_or_else(|| s.to_string()))
+            }
+            RawDataItemContent::List(values) => {
+                DataValue::List(values.iter().map(DataValue::from).collect())
+            }
+            RawDataItemContent::Table(entries) => DataValue::Table(
+                entries
+                    .iter()
+                    .map(|(k, v)| (DataValue::from(k), DataValue::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl DataBlock {
+    /// View the block as the same model the text parser produces
+    pub fn as_raw(&self) -> RawDataBlock<'_> {
+        RawDataBlock {
+            heading: &self.heading,
+            content: self
+                .content
+                .iter()
+                .map(|item| match item {
+                    DataItem::Data { name, value } => RawDataItem::Data {
+                        name,
+                        value: value.as_raw(),
+                    },
+                    DataItem::Loop { names, values } => RawDataItem::Loop {
+                        names: names.iter().map(String::as_str).collect(),
+                        values: values.iter().map(DataValue::as_raw).collect(),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Whether a string can be written without delimiters
+fn is_bare(s: &str) -> bool {
+    const RESERVED: [&str; 5] = ["data_", "save_", "loop_", "global_", "stop_"];
+    !s.is_empty()
+        && !s.starts_with(['_', '#', '$', '\'', '"', ';'])
+        && !s.contains(|c: char| c.is_whitespace() || "[]{}".contains(c))
+        && !RESERVED.iter().any(|r| {
+            s.get(..r.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(r))
+        })
+}
+
+/// Whether a string that is not bare has to be written as a text field: it
+/// spans lines, or no quote delimiter can enclose it. A string ending with a
+/// quote would run into the closing `'''`.
+fn is_text_field(s: &str) -> bool {
+    !is_bare(s)
+        && (s.contains('\n')
+            || (s.contains('\'')
+                && s.contains('"')
+                && (s.contains("'''") || s.ends_with(['\'', '"']))))
+}
+
+impl fmt::Display for DataValue {
+    /// CIF 2.0 text of the value, delimited as needed
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataValue::Empty => f.write_str("''"),
+            DataValue::Str(s) if is_bare(s) => f.write_str(s),
+            DataValue::Str(s) if needs_text_prefix(s) => {
+                write!(f, "\n;{TEXT_PREFIX}\\")?;
+                for line in s.split('\n') {
+                    write!(f, "\n{TEXT_PREFIX}{line}")?;
+                }
+                f.write_str("\n;\n")
+            }
+            DataValue::Str(s) if is_text_field(s) => write!(f, "\n;{s}\n;\n"),
+            DataValue::Str(s) if !s.contains('\'') => write!(f, "'{s}'"),
+            DataValue::Str(s) if !s.contains('"') => write!(f, "\"{s}\""),
+            DataValue::Str(s) => write!(f, "'''{s}'''"),
+            DataValue::List(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str("]")
+            }
+            DataValue::Table(entries) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    let key = match key {
+                        DataValue::Str(s) if !s.contains('\'') => format!("'{s}'"),
+                        key => key.to_string(),
+                    };
+                    write!(f, "{key}:{value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for DataBlock {
+    /// CIF 2.0 text of the block, without the magic code
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "data_{}", self.heading)?;
+        for item in &self.content {
+            match item {
+                DataItem::Data { name, value } => writeln!(f, "{name} {value}")?,
+                DataItem::Loop { names, values } => {
+                    writeln!(f, "loop_")?;
+                    for name in names {
+                        writeln!(f, "  {name}")?;
+                    }
+                    for row in values.chunks(names.len().max(1)) {
+                        let row: Vec<String> = row.iter().map(ToString::to_string).collect();
+                        writeln!(f, "  {}", row.join(" "))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prefix written before every line of a text field by the CIF 2.0 text
+/// prefix protocol
+const TEXT_PREFIX: &str = ">";
+
+/// Whether text needs the text prefix protocol to be written as a text
+/// field: a line starting with `;` would end the field, and a first line
+/// ending with `\` would be read as a prefix
+fn needs_text_prefix(s: &str) -> bool {
+    match s.split_once('\n') {
+        Some((first, rest)) => {
+            first.ends_with('\\') || rest.split('\n').any(|line| line.starts_with(';'))
+        }
+        None => false,
+    }
+}
+
+/// Undo the text prefix protocol: a first line of a prefix and `\`, with
+/// every following line starting with the prefix
+fn remove_text_prefix(s: &str) -> Option<String> {
+    let (first, rest) = s.split_once('\n')?;
+    let prefix = first.strip_suffix('\\')?;
+    if prefix.is_empty() || prefix.contains('\\') {
+        return None;
+    }
+    let lines: Option<Vec<_>> = rest
+        .split('\n')
+        .map(|line| line.strip_prefix(prefix))
+        .collect();
+    Some(lines?.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+
+    fn text(s: &str) -> DataValue {
+        DataValue::Str(s.to_string())
+    }
+
+    #[test]
+    fn test_data_block_round_trip() {
+        let block = DataBlock {
+            heading: "test".to_string(),
+            content: vec![
+                DataItem::Data {
+                    name: "_cell.length_a".to_string(),
+                    value: text("5.4321"),
+                },
+                DataItem::Data {
+                    name: "_space_group.name_h-m_alt".to_string(),
+                    value: text("P 21/c"),
+                },
+                DataItem::Data {
+                    name: "_publ.section_title".to_string(),
+                    value: text("It's\na title"),
+                },
+                DataItem::Data {
+                    name: "_test.table".to_string(),
+                    value: DataValue::Table(vec![(
+                        text("a"),
+                        DataValue::List(vec![text("1"), text("data_x")]),
+                    )]),
+                },
+                DataItem::Loop {
+                    names: vec![
+                        "_atom_site.label".to_string(),
+                        "_atom_site.note".to_string(),
+                    ],
+                    values: vec![text("O1"), text("say \"hi\""), text("C1"), text("_x")],
+                },
+            ],
+        };
+        let input = format!("#\\#CIF_2.0\n{block}");
+        let model = cif2_file(&input).unwrap();
+        assert_eq!(model.content[0], block.as_raw());
+    }
+
+    #[rstest::rstest]
+    #[case("a\n;b\nc", "\n;>\\\n>a\n>;b\n>c\n;\n")]
+    #[case("C:\\\nC:\\x", "\n;>\\\n>C:\\\n>C:\\x\n;\n")]
+    #[case("a\nb", "\n;a\nb\n;\n")]
+    #[case("a 'b\"c'", "\n;a 'b\"c'\n;\n")]
+    #[case("a 'b\"c\"", "\n;a 'b\"c\"\n;\n")]
+    #[case("a 'b\"'''c", "\n;a 'b\"'''c\n;\n")]
+    #[case("a 'b\"c", "'''a 'b\"c'''")]
+    fn test_text_prefix_round_trip(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(text(value).to_string(), expected);
+        let block = DataBlock {
+            heading: "test".to_string(),
+            content: vec![DataItem::Data {
+                name: "_publ.section_title".to_string(),
+                value: text(value),
+            }],
+        };
+        let input = format!("#\\#CIF_2.0\n{block}");
+        let model = cif2_file(&input).unwrap();
+        let RawDataItem::Data { value: parsed, .. } = &model.content[0].content[0] else {
+            panic!("expected a data item");
+        };
+        assert_eq!(DataValue::from(parsed), text(value));
+    }
+
+    #[rstest::rstest]
+    #[case("'''>\\\n>a'''", ">\\\n>a")]
+    #[case("\"\"\">\\\n>a\"\"\"", ">\\\n>a")]
+    #[case("\n;>\\\n>a\n;", "a")]
+    fn test_text_prefix_only_in_text_fields(#[case] value: &str, #[case] expected: &str) {
+        let input = format!("#\\#CIF_2.0\ndata_test\n_publ.section_title {value}\n");
+        let model = cif2_file(&input).unwrap();
+        let RawDataItem::Data { value: parsed, .. } = &model.content[0].content[0] else {
+            panic!("expected a data item");
+        };
+        assert_eq!(DataValue::from(parsed), text(expected));
+    }
+
+    #[rstest::rstest]
+    #[case("O1", "O1")]
+    #[case("", "''")]
+    #[case("a b", "'a b'")]
+    #[case("it's", "it's")]
+    #[case("'s x", "\"'s x\"")]
+    #[case("loop_x", "'loop_x'")]
+    #[case("[1]", "'[1]'")]
+    fn test_value_text(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(text(value).to_string(), expected);
+    }
+}
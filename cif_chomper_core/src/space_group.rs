@@ -0,0 +1,799 @@
+/// Space groups looked up by number, Hermann-Mauguin or Hall symbol in the
+/// tables of `data/space_groups.txt`, with their operators generated from
+/// the Hall symbol
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::model::column;
+use crate::raw_model::RawDataBlock;
+use crate::symmetry::{Fraction, SymOp, SymmetryError, operators_from_block};
+
+const TABLE: &str = include_str!("data/space_groups.txt");
+
+/// Data names a Hall symbol is declared under, current first
+pub const HALL_NAMES: [&str; 3] = [
+    "_space_group.name_Hall",
+    "_space_group_name_Hall",
+    "_symmetry_space_group_name_Hall",
+];
+
+/// Data names a Hermann-Mauguin symbol is declared under, current first
+pub const HERMANN_MAUGUIN_NAMES: [&str; 3] = [
+    "_space_group.name_H-M_alt",
+    "_space_group_name_H-M_alt",
+    "_symmetry_space_group_name_H-M",
+];
+
+/// Data names an International Tables number is declared under, current first
+pub const NUMBER_NAMES: [&str; 3] = [
+    "_space_group.IT_number",
+    "_space_group_IT_number",
+    "_symmetry_Int_Tables_number",
+];
+
+/// Generated groups larger than this come from symbols that are not space groups
+const MAX_ORDER: usize = 192;
+
+/// A setting of a space group from the tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpaceGroup {
+    /// International Tables number, 1 to 230
+    pub number: u16,
+    /// Full symbol with any origin choice or axes, e.g. `F d -3 m :2`
+    pub hermann_mauguin: &'static str,
+    pub hall: &'static str,
+}
+
+impl SpaceGroup {
+    /// All settings in the tables, ordered by number with the default
+    /// setting of each number first
+    pub fn all() -> impl Iterator<Item = SpaceGroup> {
+        TABLE
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+            .map(|line| {
+                let mut fields = line.split('\t');
+                let mut field = || fields.next().expect("three fields per setting");
+                SpaceGroup {
+                    number: field().parse().expect("a space group number"),
+                    hermann_mauguin: field(),
+                    hall: field(),
+                }
+            })
+    }
+
+    /// The default setting: unique axis b, origin choice 1, hexagonal axes
+    pub fn from_number(number: u16) -> Result<Self, SymmetryError> {
+        SpaceGroup::all()
+            .find(|g| g.number == number)
+            .ok_or_else(|| SymmetryError::UnknownSpaceGroup(number.to_string()))
+    }
+
+    /// Reads full or short symbols, with or without spaces, e.g. `P 1 21/c 1`,
+    /// `P21/c` or `R -3 :R`. Without a setting the first listed is taken.
+    pub fn from_hermann_mauguin(symbol: &str) -> Result<Self, SymmetryError> {
+        hermann_mauguin_settings(symbol)
+            .next()
+            .ok_or_else(|| SymmetryError::UnknownSpaceGroup(symbol.to_string()))
+    }
+
+    /// The setting with the Hall symbol, or failing that with the same
+    /// operators, so that e.g. `P 2yb -1` finds `-P 2yb`
+    pub fn from_hall(symbol: &str) -> Result<Self, SymmetryError> {
+        let key = hall_key(symbol);
+        if let Some(group) = SpaceGroup::all().find(|g| hall_key(g.hall) == key) {
+            return Ok(group);
+        }
+        SpaceGroup::from_operators(&hall_operators(symbol)?)
+            .ok_or_else(|| SymmetryError::UnknownSpaceGroup(symbol.to_string()))
+    }
+
+    /// The setting with the same operators, up to lattice translations
+    pub fn from_operators(ops: &[SymOp]) -> Option<Self> {
+        let ops = operator_set(ops);
+        SpaceGroup::all().find(|g| operator_set(&g.operators()) == ops)
+    }
+
+    /// All operators of the setting, including lattice centring, with
+    /// translations in `[0, 1)` and the identity first
+    pub fn operators(&self) -> Vec<SymOp> {
+        hall_operators(self.hall).expect("the tables hold valid Hall symbols")
+    }
+}
+
+impl fmt::Display for SpaceGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.hermann_mauguin, self.number)
+    }
+}
+
+fn hall_key(symbol: &str) -> String {
+    symbol
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_ascii_lowercase()
+}
+
+/// A symbol without spaces or underscores, and its setting after `:`
+fn hermann_mauguin_key(symbol: &str) -> (String, Option<String>) {
+    let squash = |s: &str| {
+        s.chars()
+            .filter(|c| !c.is_whitespace() && *c != '_')
+            .collect::<String>()
+            .to_ascii_lowercase()
+    };
+    match symbol.split_once(':') {
+        Some((base, setting)) => (squash(base), Some(squash(setting))),
+        None => (squash(symbol), None),
+    }
+}
+
+/// The settings a symbol may name, first listed first
+fn hermann_mauguin_settings(symbol: &str) -> impl Iterator<Item = SpaceGroup> {
+    let (base, setting) = hermann_mauguin_key(symbol);
+    SpaceGroup::all().filter(move |group| {
+        let (full, group_setting) = hermann_mauguin_key(group.hermann_mauguin);
+        // Monoclinic groups are also written without the 1s, as `P 21/c`
+        let short = match group.hermann_mauguin.split_whitespace().collect::<Vec<_>>()[..] {
+            [lattice, "1", axis, "1"] => Some(hermann_mauguin_key(&format!("{lattice}{axis}")).0),
+            _ => None,
+        };
+        (full == base || short.as_ref() == Some(&base))
+            && (setting.is_none() || setting == group_setting)
+    })
+}
+
+fn operator_set(ops: &[SymOp]) -> HashSet<SymOp> {
+    ops.iter().map(SymOp::normalized).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    X,
+    Y,
+    Z,
+    /// Along `a - b` for a preceding z axis, `b - c` for x and `c - a` for y
+    Prime(usize),
+    /// Along `a + b` for a preceding z axis, `b + c` for x and `c + a` for y
+    DoublePrime(usize),
+    /// Along `a + b + c`
+    Diagonal,
+}
+
+fn rotation(order: u32, axis: Axis) -> Option<[[i64; 3]; 3]> {
+    Some(match (order, axis) {
+        (1, _) => [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+        (2, Axis::X) => [[1, 0, 0], [0, -1, 0], [0, 0, -1]],
+        (3, Axis::X) => [[1, 0, 0], [0, 0, -1], [0, 1, -1]],
+        (4, Axis::X) => [[1, 0, 0], [0, 0, -1], [0, 1, 0]],
+        (6, Axis::X) => [[1, 0, 0], [0, 1, -1], [0, 1, 0]],
+        (2, Axis::Y) => [[-1, 0, 0], [0, 1, 0], [0, 0, -1]],
+        (3, Axis::Y) => [[-1, 0, 1], [0, 1, 0], [-1, 0, 0]],
+        (4, Axis::Y) => [[0, 0, 1], [0, 1, 0], [-1, 0, 0]],
+        (6, Axis::Y) => [[0, 0, 1], [0, 1, 0], [-1, 0, 1]],
+        (2, Axis::Z) => [[-1, 0, 0], [0, -1, 0], [0, 0, 1]],
+        (3, Axis::Z) => [[0, -1, 0], [1, -1, 0], [0, 0, 1]],
+        (4, Axis::Z) => [[0, -1, 0], [1, 0, 0], [0, 0, 1]],
+        (6, Axis::Z) => [[1, -1, 0], [1, 0, 0], [0, 0, 1]],
+        (2, Axis::Prime(0)) => [[-1, 0, 0], [0, 0, -1], [0, -1, 0]],
+        (2, Axis::Prime(1)) => [[0, 0, -1], [0, -1, 0], [-1, 0, 0]],
+        (2, Axis::Prime(2)) => [[0, -1, 0], [-1, 0, 0], [0, 0, -1]],
+        (2, Axis::DoublePrime(0)) => [[-1, 0, 0], [0, 0, 1], [0, 1, 0]],
+        (2, Axis::DoublePrime(1)) => [[0, 0, 1], [0, -1, 0], [1, 0, 0]],
+        (2, Axis::DoublePrime(2)) => [[0, 1, 0], [1, 0, 0], [0, 0, -1]],
+        (3, Axis::Diagonal) => [[0, 0, 1], [1, 0, 0], [0, 1, 0]],
+        _ => return None,
+    })
+}
+
+fn half(axes: [i64; 3]) -> [Fraction; 3] {
+    axes.map(|n| Fraction::new(n, 2))
+}
+
+/// Centring translations of a lattice symbol, besides the origin
+pub fn centring(lattice: char) -> Option<Vec<[Fraction; 3]>> {
+    Some(match lattice {
+        'P' => vec![],
+        'A' => vec![half([0, 1, 1])],
+        'B' => vec![half([1, 0, 1])],
+        'C' => vec![half([1, 1, 0])],
+        'I' => vec![half([1, 1, 1])],
+        'R' => vec![
+            [2, 1, 1].map(|n| Fraction::new(n, 3)),
+            [1, 2, 2].map(|n| Fraction::new(n, 3)),
+        ],
+        'F' => vec![half([0, 1, 1]), half([1, 0, 1]), half([1, 1, 0])],
+        _ => return None,
+    })
+}
+
+fn translation_symbol(symbol: char) -> Option<[Fraction; 3]> {
+    let quarter = |axes: [i64; 3]| axes.map(|n| Fraction::new(n, 4));
+    Some(match symbol {
+        'a' => half([1, 0, 0]),
+        'b' => half([0, 1, 0]),
+        'c' => half([0, 0, 1]),
+        'n' => half([1, 1, 1]),
+        'u' => quarter([1, 0, 0]),
+        'v' => quarter([0, 1, 0]),
+        'w' => quarter([0, 0, 1]),
+        'd' => quarter([1, 1, 1]),
+        _ => return None,
+    })
+}
+
+/// A matrix symbol such as `-4bd`, `31` or `2"c`; `previous` is the order
+/// and axis of the matrix before it
+fn matrix_symbol(
+    symbol: &str,
+    index: usize,
+    previous: Option<(u32, Axis)>,
+) -> Option<(u32, Axis, SymOp)> {
+    let (improper, symbol) = match symbol.strip_prefix('-') {
+        Some(symbol) => (true, symbol),
+        None => (false, symbol),
+    };
+    let mut chars = symbol.chars().peekable();
+    let order = chars.next()?.to_digit(10)?;
+    let screw = chars
+        .next_if(char::is_ascii_digit)
+        .map(|c| c as i64 - '0' as i64);
+    let reference = match previous {
+        Some((_, Axis::X)) => 0,
+        Some((_, Axis::Y)) => 1,
+        _ => 2,
+    };
+    let axis = match chars.next_if(|c| "xyz'\"*".contains(*c)) {
+        Some('x') => Axis::X,
+        Some('y') => Axis::Y,
+        Some('z') => Axis::Z,
+        Some('\'') => Axis::Prime(reference),
+        Some('"') => Axis::DoublePrime(reference),
+        Some(_) => Axis::Diagonal,
+        None => match (index, order, previous) {
+            (0, _, _) | (_, 1, _) => Axis::Z,
+            (1, 2, Some((2 | 4, _))) => Axis::X,
+            (1, 2, Some((3 | 6, _))) => Axis::Prime(reference),
+            (2, 3, _) => Axis::Diagonal,
+            _ => return None,
+        },
+    };
+    let mut rotation = rotation(order, axis)?;
+    if improper {
+        rotation = rotation.map(|row| row.map(|r| -r));
+    }
+    let mut translation = [Fraction::ZERO; 3];
+    if let Some(screw) = screw {
+        let direction = match axis {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+            _ => return None,
+        };
+        if screw >= order as i64 {
+            return None;
+        }
+        translation[direction] = Fraction::new(screw, order as i64);
+    }
+    for c in chars {
+        let shift = translation_symbol(c)?;
+        translation = std::array::from_fn(|i| translation[i] + shift[i]);
+    }
+    Some((
+        order,
+        axis,
+        SymOp {
+            rotation,
+            translation,
+        },
+    ))
+}
+
+/// The operators generated by the Hall symbol of a space group, e.g.
+/// `-P 2ybc` or `P 31 2c (0 0 1)`, with translations in `[0, 1)` and the
+/// identity first
+pub fn hall_operators(symbol: &str) -> Result<Vec<SymOp>, SymmetryError> {
+    let invalid = || SymmetryError::InvalidHall(symbol.to_string());
+    let (matrices, shift) = match symbol.split_once('(') {
+        Some((matrices, shift)) => (matrices, Some(shift)),
+        None => (symbol, None),
+    };
+    let mut tokens = matrices.split_whitespace();
+    let lattice = tokens.next().ok_or_else(invalid)?;
+    let (centrosymmetric, lattice) = match lattice.strip_prefix('-') {
+        Some(lattice) => (true, lattice),
+        None => (false, lattice),
+    };
+    let mut lattice = lattice.chars();
+    let centring = match (lattice.next(), lattice.next()) {
+        (Some(c), None) => centring(c.to_ascii_uppercase()).ok_or_else(invalid)?,
+        _ => return Err(invalid()),
+    };
+    let mut generators: Vec<_> = centring
+        .into_iter()
+        .map(|translation| SymOp {
+            translation,
+            ..SymOp::identity()
+        })
+        .collect();
+    if centrosymmetric {
+        generators.push(SymOp {
+            rotation: [[-1, 0, 0], [0, -1, 0], [0, 0, -1]],
+            ..SymOp::identity()
+        });
+    }
+    let mut previous = None;
+    for (index, token) in tokens.enumerate() {
+        let (order, axis, op) = matrix_symbol(token, index, previous).ok_or_else(invalid)?;
+        generators.push(op);
+        previous = Some((order, axis));
+    }
+    let shift = match shift {
+        Some(shift) => {
+            let shift: Vec<i64> = shift
+                .strip_suffix(')')
+                .ok_or_else(invalid)?
+                .split_whitespace()
+                .map(|n| n.parse().map_err(|_| invalid()))
+                .collect::<Result<_, _>>()?;
+            let shift: [i64; 3] = shift.try_into().map_err(|_| invalid())?;
+            shift.map(|n| Fraction::new(n, 12))
+        }
+        None => [Fraction::ZERO; 3],
+    };
+    let mut ops = vec![SymOp::identity()];
+    let mut seen: HashSet<_> = ops.iter().copied().collect();
+    let mut next = 0;
+    while next < ops.len() {
+        let op = ops[next];
+        for generator in &generators {
+            let product = op.then(generator).normalized();
+            if seen.insert(product) {
+                if ops.len() == MAX_ORDER {
+                    return Err(invalid());
+                }
+                ops.push(product);
+            }
+        }
+        next += 1;
+    }
+    // Moving the origin by v changes the translations to t + v - R v
+    Ok(ops
+        .into_iter()
+        .map(|op| {
+            let rotated = op.rotation.map(|row| {
+                row.iter()
+                    .zip(&shift)
+                    .fold(Fraction::ZERO, |sum, (r, v)| sum + Fraction::from(*r) * *v)
+            });
+            SymOp {
+                rotation: op.rotation,
+                translation: std::array::from_fn(|i| op.translation[i] + shift[i] - rotated[i]),
+            }
+            .normalized()
+        })
+        .collect())
+}
+
+/// A disagreement between the space group a data block declares and the
+/// operators it lists
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymmetryMismatch {
+    /// A symbol or number that is not in the tables
+    Unknown { name: String, value: String },
+    /// A symbol or number naming a different group from an earlier declaration
+    Conflict { name: String, value: String },
+    /// An operator of the declared group that is not listed
+    Missing(SymOp),
+    /// A listed operator that is not in the declared group
+    Unexpected(SymOp),
+}
+
+impl fmt::Display for SymmetryMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymmetryMismatch::Unknown { name, value } => {
+                write!(f, "{name} value '{value}' is not a known space group")
+            }
+            SymmetryMismatch::Conflict { name, value } => {
+                write!(f, "{name} value '{value}' names a different space group")
+            }
+            SymmetryMismatch::Missing(op) => {
+                write!(f, "operator {op} of the declared space group is not listed")
+            }
+            SymmetryMismatch::Unexpected(op) => {
+                write!(f, "listed operator {op} is not in the declared space group")
+            }
+        }
+    }
+}
+
+/// The first of `names` in the block with a known value, and that value
+fn declaration<'b>(block: &'b RawDataBlock, names: &[&str]) -> Option<(String, &'b str)> {
+    names.iter().find_map(|name| {
+        let value = column(block, &[name])?.first()?.as_str()?.trim();
+        (!matches!(value, "" | "?" | ".")).then(|| (name.to_string(), value))
+    })
+}
+
+/// A group a declaration may name: its number, if in the tables, and its
+/// operators
+type Candidate = (Option<u16>, HashSet<SymOp>);
+
+/// The operators of a data block: those listed, or failing that those of
+/// the space group declared by Hall symbol, Hermann-Mauguin symbol or
+/// number. Blocks with neither have only the identity.
+pub fn block_operators(block: &RawDataBlock) -> Result<Vec<SymOp>, SymmetryError> {
+    let listed = operators_from_block(block)?;
+    if !listed.is_empty() {
+        return Ok(listed);
+    }
+    if let Some((_, hall)) = declaration(block, &HALL_NAMES) {
+        return hall_operators(hall);
+    }
+    if let Some((_, symbol)) = declaration(block, &HERMANN_MAUGUIN_NAMES) {
+        return Ok(SpaceGroup::from_hermann_mauguin(symbol)?.operators());
+    }
+    if let Some((_, number)) = declaration(block, &NUMBER_NAMES) {
+        let number = number
+            .parse()
+            .map_err(|_| SymmetryError::UnknownSpaceGroup(number.to_string()))?;
+        return Ok(SpaceGroup::from_number(number)?.operators());
+    }
+    Ok(vec![SymOp::identity()])
+}
+
+/// Compares the space group declared by Hall symbol, Hermann-Mauguin symbol
+/// or number, in that order of preference, with the operators listed in the
+/// block. Where a declaration leaves the setting open, the setting matching
+/// the listed operators is taken.
+pub fn check_block(block: &RawDataBlock) -> Result<Vec<SymmetryMismatch>, SymmetryError> {
+    let listed = operators_from_block(block)?;
+    let listed_set = operator_set(&listed);
+    let table = |groups: &mut dyn Iterator<Item = SpaceGroup>| -> Vec<Candidate> {
+        groups
+            .map(|g| (Some(g.number), operator_set(&g.operators())))
+            .collect()
+    };
+    let mut mismatches = Vec::new();
+    let mut declared: Option<Candidate> = None;
+    for names in [&HALL_NAMES, &HERMANN_MAUGUIN_NAMES, &NUMBER_NAMES] {
+        let Some((name, value)) = declaration(block, names) else {
+            continue;
+        };
+        let by_number = names == &NUMBER_NAMES;
+        let candidates = if by_number {
+            match value.parse::<u16>() {
+                Ok(number) => table(&mut SpaceGroup::all().filter(|g| g.number == number)),
+                Err(_) => Vec::new(),
+            }
+        } else if names == &HALL_NAMES {
+            match hall_operators(value) {
+                Ok(ops) => vec![(
+                    SpaceGroup::from_hall(value).ok().map(|g| g.number),
+                    operator_set(&ops),
+                )],
+                Err(_) => Vec::new(),
+            }
+        } else {
+            table(&mut hermann_mauguin_settings(value))
+        };
+        let unknown = SymmetryMismatch::Unknown {
+            name: name.clone(),
+            value: value.to_string(),
+        };
+        match &declared {
+            _ if candidates.is_empty() => mismatches.push(unknown),
+            None => {
+                let index = candidates
+                    .iter()
+                    .position(|(_, ops)| *ops == listed_set)
+                    .unwrap_or(0);
+                declared = candidates.into_iter().nth(index);
+            }
+            Some((number, ops)) => {
+                // A number only fixes the group, a symbol also the setting
+                let agrees = candidates.iter().any(|(n, o)| {
+                    if by_number {
+                        number.is_none() || n == number
+                    } else {
+                        o == ops
+                    }
+                });
+                if !agrees {
+                    mismatches.push(SymmetryMismatch::Conflict {
+                        name,
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    if let Some((_, ops)) = declared
+        && !listed.is_empty()
+    {
+        let mut missing: Vec<_> = ops.difference(&listed_set).copied().collect();
+        missing.sort_by_key(SymOp::to_string);
+        mismatches.extend(missing.into_iter().map(SymmetryMismatch::Missing));
+        let mut unexpected = HashSet::new();
+        for op in &listed {
+            let normalized = op.normalized();
+            if !ops.contains(&normalized) && unexpected.insert(normalized) {
+                mismatches.push(SymmetryMismatch::Unexpected(*op));
+            }
+        }
+    }
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use crate::symmetry::check_closure;
+    use rstest::rstest;
+
+    const MIL_101: &str = include_str!("../../cif_chomper/example_data/mil-101.cif");
+    const ZIF_8: &str = include_str!("../../cif_chomper/example_data/zif-8.cif");
+
+    /// Order of the point group of a space group number
+    fn point_group_order(number: u16) -> usize {
+        match number {
+            1 => 1,
+            2..=9 => 2,
+            10..=46 | 75..=82 => 4,
+            47..=74 | 83..=122 => 8,
+            123..=142 => 16,
+            143..=146 => 3,
+            147..=161 | 168..=174 => 6,
+            162..=167 | 175..=190 | 195..=199 => 12,
+            191..=194 | 200..=220 => 24,
+            _ => 48,
+        }
+    }
+
+    fn strings(ops: &[SymOp]) -> Vec<String> {
+        ops.iter().map(SymOp::to_string).collect()
+    }
+
+    #[test]
+    fn test_table() {
+        let groups: Vec<_> = SpaceGroup::all().collect();
+        let numbers: HashSet<_> = groups.iter().map(|g| g.number).collect();
+        assert_eq!(numbers, (1..=230).collect());
+        for group in groups {
+            let ops = hall_operators(group.hall).unwrap();
+            let centring = match group.hall.trim_start_matches('-').chars().next() {
+                Some('P') => 1,
+                Some('A' | 'B' | 'C' | 'I') => 2,
+                Some('R') => 3,
+                _ => 4,
+            };
+            assert_eq!(
+                ops.len(),
+                point_group_order(group.number) * centring,
+                "{group}"
+            );
+            assert_eq!(check_closure(&ops), Ok(()), "{group}");
+            assert_eq!(ops[0], SymOp::identity());
+        }
+    }
+
+    /// The order of an operator's rotation, and its screw or glide
+    /// component: the translation of its power of that order, divided by it
+    fn intrinsic(op: &SymOp) -> (usize, [Fraction; 3]) {
+        let mut power = *op;
+        let mut translation = op.translation;
+        let mut order = 1;
+        while power.rotation != SymOp::identity().rotation {
+            power = power.compose(op);
+            translation = power.translation;
+            order += 1;
+        }
+        (
+            order,
+            translation.map(|t| Fraction::new(t.numerator(), t.denominator() * order as i64)),
+        )
+    }
+
+    /// The operators with the origin moved by `shift`
+    fn shifted(ops: &[SymOp], shift: [Fraction; 3]) -> HashSet<SymOp> {
+        let to = SymOp {
+            translation: shift,
+            ..SymOp::identity()
+        };
+        let back = to.inverse().unwrap();
+        ops.iter()
+            .map(|op| to.compose(op).compose(&back).normalized())
+            .collect()
+    }
+
+    #[test]
+    fn test_origin_choices() {
+        // The two origin choices of a group are the same operators about
+        // points an eighth of a cell or more apart
+        let eighths: Vec<_> = (0..8).map(|n| Fraction::new(n, 8)).collect();
+        let numbers: HashSet<_> = SpaceGroup::all()
+            .filter(|g| g.hermann_mauguin.ends_with(":2"))
+            .map(|g| g.number)
+            .collect();
+        assert_eq!(numbers.len(), 24);
+        for number in numbers {
+            let settings: Vec<_> = SpaceGroup::all().filter(|g| g.number == number).collect();
+            let first = settings[0].operators();
+            let second = operator_set(&settings[1].operators());
+            let found = eighths.iter().any(|x| {
+                eighths.iter().any(|y| {
+                    eighths
+                        .iter()
+                        .any(|z| shifted(&first, [*x, *y, *z]) == second)
+                })
+            });
+            assert!(found, "{number}");
+        }
+    }
+
+    #[rstest]
+    #[case("P 41", 4, "0,0,1/4")]
+    #[case("P 43", 4, "0,0,3/4")]
+    #[case("P 31", 3, "0,0,1/3")]
+    #[case("P 65", 6, "0,0,5/6")]
+    #[case("P 1 21/c 1", 2, "0,1/2,0")]
+    #[case("P 21 3", 2, "1/2,0,0")]
+    fn test_screw_axes(#[case] symbol: &str, #[case] order: usize, #[case] screw: &str) {
+        let group = SpaceGroup::from_hermann_mauguin(symbol).unwrap();
+        let screw: Vec<Fraction> = screw.split(',').map(|t| t.parse().unwrap()).collect();
+        assert!(
+            group.operators().iter().any(|op| op.determinant() == 1
+                && intrinsic(op) == (order, screw[..].try_into().unwrap()))
+        );
+    }
+
+    #[rstest]
+    #[case("-P 2ybc", &["x,y,z", "-x,y+1/2,-z+1/2", "-x,-y,-z", "x,-y+1/2,z+1/2"])]
+    #[case("P 31 2c (0 0 1)", &["x,y,z", "-y,x-y,z+1/3", "-x+y,-x,z+2/3", "-y,-x,-z+2/3", "-x+y,y,-z+1/3", "x,x-y,-z"])]
+    #[case("P 3*", &["x,y,z", "z,x,y", "y,z,x"])]
+    #[case("R 3", &["x,y,z", "x+2/3,y+1/3,z+1/3", "x+1/3,y+2/3,z+2/3", "-y,x-y,z", "-y+2/3,x-y+1/3,z+1/3", "-y+1/3,x-y+2/3,z+2/3", "-x+y,-x,z", "-x+y+2/3,-x+1/3,z+1/3", "-x+y+1/3,-x+2/3,z+2/3"])]
+    fn test_hall_operators(#[case] symbol: &str, #[case] expected: &[&str]) {
+        let ops = strings(&hall_operators(symbol).unwrap());
+        let mut sorted = ops.clone();
+        sorted.sort();
+        let mut expected: Vec<_> = expected.iter().map(|s| s.to_string()).collect();
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("Q 2")]
+    #[case("P 5")]
+    #[case("P 2 3")]
+    #[case("P 2q")]
+    #[case("P 4 2 (0 0)")]
+    #[case("P 3 4x")]
+    fn test_invalid_hall(#[case] symbol: &str) {
+        assert_eq!(
+            hall_operators(symbol),
+            Err(SymmetryError::InvalidHall(symbol.to_string()))
+        );
+    }
+
+    #[rstest]
+    #[case("P 1 21/c 1", 14, "P 1 21/c 1")]
+    #[case("P21/c", 14, "P 1 21/c 1")]
+    #[case("p 21/n", 14, "P 1 21/n 1")]
+    #[case("F d -3 m", 227, "F d -3 m :1")]
+    #[case("F d -3 m :2", 227, "F d -3 m :2")]
+    #[case("Fd-3m:2", 227, "F d -3 m :2")]
+    #[case("R -3", 148, "R -3 :H")]
+    #[case("R -3 :R", 148, "R -3 :R")]
+    #[case("C m c e", 64, "C m c e")]
+    #[case("I -4 3 m", 217, "I -4 3 m")]
+    fn test_from_hermann_mauguin(#[case] symbol: &str, #[case] number: u16, #[case] setting: &str) {
+        let group = SpaceGroup::from_hermann_mauguin(symbol).unwrap();
+        assert_eq!((group.number, group.hermann_mauguin), (number, setting));
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(SpaceGroup::from_number(14).unwrap().hall, "-P 2ybc");
+        assert_eq!(
+            SpaceGroup::from_number(231),
+            Err(SymmetryError::UnknownSpaceGroup("231".to_string()))
+        );
+        assert_eq!(
+            SpaceGroup::from_hall("-f 4vw  2vw 3")
+                .unwrap()
+                .hermann_mauguin,
+            "F d -3 m :2"
+        );
+        // The same operators written differently
+        assert_eq!(SpaceGroup::from_hall("P 2yb -1").unwrap().number, 11);
+        let ops: Vec<SymOp> = ["x,y,z", "-x,-y,-z"].map(|op| op.parse().unwrap()).to_vec();
+        assert_eq!(SpaceGroup::from_operators(&ops).unwrap().number, 2);
+        assert_eq!(SpaceGroup::from_operators(&ops[1..]), None);
+        assert_eq!(
+            SpaceGroup::from_hermann_mauguin("P 6/q")
+                .unwrap_err()
+                .to_string(),
+            "no space group 'P 6/q' in the tables"
+        );
+        assert_eq!(
+            SpaceGroup::from_number(227).unwrap().to_string(),
+            "F d -3 m :1 (227)"
+        );
+    }
+
+    #[test]
+    fn test_example_operators() {
+        for (text, count) in [(MIL_101, 192), (ZIF_8, 48)] {
+            let model = cif2_file(text).unwrap();
+            let block = &model.content[0];
+            assert_eq!(operators_from_block(block).unwrap().len(), count);
+            assert_eq!(check_block(block), Ok(vec![]));
+        }
+    }
+
+    #[rstest]
+    #[case("_space_group.name_Hall '-P 2ybc'", 4)]
+    #[case("_symmetry_space_group_name_H-M 'C 2/c'", 8)]
+    #[case("_space_group.IT_number 227", 192)]
+    #[case("_cell.length_a 5", 1)]
+    fn test_block_operators(#[case] declarations: &str, #[case] count: usize) {
+        let text = block_text(declarations, &[]);
+        let model = cif2_file(&text).unwrap();
+        assert_eq!(block_operators(&model.content[0]).unwrap().len(), count);
+    }
+
+    fn block_text(declarations: &str, operators: &[&str]) -> String {
+        let mut text = format!("#\\#CIF_2.0\ndata_x\n{declarations}\n");
+        if !operators.is_empty() {
+            text.push_str("loop_\n_space_group_symop.operation_xyz\n");
+            for op in operators {
+                text.push_str(&format!("'{op}'\n"));
+            }
+        }
+        text
+    }
+
+    #[rstest]
+    #[case("_space_group.IT_number 14", &["x,y,z", "-x,y+1/2,-z+1/2", "-x,-y,-z", "x,-y+1/2,z+1/2"], &[])]
+    #[case("_space_group.IT_number 14", &["x,y,z", "-x+1/2,y+1/2,-z+1/2", "-x,-y,-z", "x+1/2,-y+1/2,z+1/2"], &[])]
+    #[case("_space_group.name_H-M_alt 'P 21/n'", &[], &[])]
+    #[case(
+        "_space_group.name_H-M_alt 'P 21/c'",
+        &["x,y,z", "-x,y+1/2,-z+1/2", "-x,-y,-z", "x,-y,z+1/2"],
+        &["operator x,-y+1/2,z+1/2 of the declared space group is not listed", "listed operator x,-y,z+1/2 is not in the declared space group"]
+    )]
+    #[case(
+        "_space_group.name_H-M_alt 'P -1'",
+        &["x,y,z", "-x,-y,-z", "-x,y,-z"],
+        &["listed operator -x,y,-z is not in the declared space group"]
+    )]
+    #[case(
+        "_space_group.name_Hall '-P 2ybc'\n_space_group.name_H-M_alt 'P 1 21/n 1'\n_space_group.IT_number 13",
+        &[],
+        &["_space_group.name_H-M_alt value 'P 1 21/n 1' names a different space group", "_space_group.IT_number value '13' names a different space group"]
+    )]
+    #[case(
+        "_space_group.name_Hall 'P 7'\n_space_group.IT_number 2",
+        &["x,y,z", "-x,-y,-z"],
+        &["_space_group.name_Hall value 'P 7' is not a known space group"]
+    )]
+    #[case("_space_group.IT_number ?", &["x,y,z", "-x,-y,-z"], &[])]
+    fn test_check_block(
+        #[case] declarations: &str,
+        #[case] operators: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        let text = block_text(declarations, operators);
+        let model = cif2_file(&text).unwrap();
+        let mismatches: Vec<_> = check_block(&model.content[0])
+            .unwrap()
+            .iter()
+            .map(SymmetryMismatch::to_string)
+            .collect();
+        assert_eq!(mismatches, expected);
+    }
+}
@@ -0,0 +1,416 @@
+/// Crystal structures as a unit cell and atom sites, expanded from the
+/// asymmetric unit to the full cell or to supercells and written back as P1
+use std::fmt;
+
+use crate::cell::{CellError, UnitCell};
+use crate::model::{
+    CifValue, ModelError, column, optional_value, push_item, push_loop, required_value, row_count,
+};
+use crate::numeric::Measurement;
+use crate::raw_model::{DataBlock, DataValue, RawDataBlock};
+use crate::space_group::SpaceGroup;
+use crate::symmetry::SymOp;
+
+/// Symmetry images of a site closer than this, in ångströms, are one site
+pub const DEFAULT_TOLERANCE: f64 = 0.01;
+
+const LABEL: [&str; 2] = ["_atom_site.label", "_atom_site_label"];
+const TYPE_SYMBOL: [&str; 2] = ["_atom_site.type_symbol", "_atom_site_type_symbol"];
+const FRACT: [[&str; 2]; 3] = [
+    ["_atom_site.fract_x", "_atom_site_fract_x"],
+    ["_atom_site.fract_y", "_atom_site_fract_y"],
+    ["_atom_site.fract_z", "_atom_site_fract_z"],
+];
+const OCCUPANCY: [&str; 2] = ["_atom_site.occupancy", "_atom_site_occupancy"];
+const U_ISO: [&str; 2] = ["_atom_site.U_iso_or_equiv", "_atom_site_U_iso_or_equiv"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StructureError {
+    Cell(CellError),
+    /// An `_atom_site` item that is missing or cannot be read
+    Model(ModelError),
+}
+
+impl fmt::Display for StructureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructureError::Cell(e) => write!(f, "{e}"),
+            StructureError::Model(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for StructureError {}
+
+impl From<CellError> for StructureError {
+    fn from(e: CellError) -> Self {
+        StructureError::Cell(e)
+    }
+}
+
+impl From<ModelError> for StructureError {
+    fn from(e: ModelError) -> Self {
+        StructureError::Model(e)
+    }
+}
+
+/// A packet of `_atom_site`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Site {
+    pub label: String,
+    pub type_symbol: Option<String>,
+    /// Fractional coordinates
+    pub fract: [f64; 3],
+    /// Standard uncertainties of the coordinates, zero where none is given
+    pub fract_su: [f64; 3],
+    pub occupancy: Option<f64>,
+    /// In square ångströms
+    pub u_iso_or_equiv: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Structure {
+    pub cell: UnitCell,
+    pub sites: Vec<Site>,
+}
+
+/// Coordinates moved into `[0, 1)`
+fn wrap(fract: [f64; 3]) -> [f64; 3] {
+    fract.map(|x| {
+        let x = x.rem_euclid(1.0);
+        if x > 1.0 - 1e-9 { 0.0 } else { x }
+    })
+}
+
+impl Structure {
+    /// Reads the cell and the `_atom_site` loop. Sites need a label and
+    /// fractional coordinates.
+    pub fn from_block(block: &RawDataBlock) -> Result<Self, StructureError> {
+        let cell = UnitCell::from_block(block)?;
+        let labels = column(block, &LABEL);
+        let type_symbols = column(block, &TYPE_SYMBOL);
+        let fract = FRACT.map(|names| column(block, &names));
+        let occupancies = column(block, &OCCUPANCY);
+        let u_isos = column(block, &U_ISO);
+        let mut columns = vec![
+            labels.clone(),
+            type_symbols.clone(),
+            occupancies.clone(),
+            u_isos.clone(),
+        ];
+        columns.extend(fract.iter().cloned());
+        let count = row_count("ATOM_SITE", &columns)?;
+        let sites = (0..count)
+            .map(|row| {
+                let mut coordinates = [Measurement::exact(0.0); 3];
+                for (i, names) in FRACT.iter().enumerate() {
+                    coordinates[i] = required_value(names[0], &fract[i], row)?;
+                }
+                Ok(Site {
+                    label: required_value(LABEL[0], &labels, row)?,
+                    type_symbol: optional_value(TYPE_SYMBOL[0], &type_symbols, row)?,
+                    fract: coordinates.map(|c| c.value),
+                    fract_su: coordinates.map(|c| c.su_or_zero()),
+                    occupancy: optional_value(OCCUPANCY[0], &occupancies, row)?,
+                    u_iso_or_equiv: optional_value(U_ISO[0], &u_isos, row)?,
+                })
+            })
+            .collect::<Result<_, ModelError>>()?;
+        Ok(Structure { cell, sites })
+    }
+
+    /// The distance in ångströms between two points, or their nearest
+    /// lattice translations
+    pub fn periodic_distance(&self, a: [f64; 3], b: [f64; 3]) -> f64 {
+        let d = std::array::from_fn(|i| {
+            let d = a[i] - b[i];
+            d - d.round()
+        });
+        let [x, y, z] = self.cell.to_cartesian(d);
+        (x * x + y * y + z * z).sqrt()
+    }
+
+    /// Every site of the cell, from the images of each site under the
+    /// operators, moved into the cell. Images within `tolerance` ångströms
+    /// of an earlier one, as on special positions, are merged. The first
+    /// image of a site keeps its label and the others are numbered, as
+    /// `C1_2`, `C1_3`.
+    pub fn expand(&self, ops: &[SymOp], tolerance: f64) -> Structure {
+        let identity = SymOp::identity();
+        let mut sites = Vec::new();
+        for site in &self.sites {
+            let mut images: Vec<([f64; 3], [f64; 3])> = Vec::new();
+            for op in std::iter::once(&identity).chain(ops) {
+                let image = wrap(op.apply(site.fract));
+                if images
+                    .iter()
+                    .all(|(other, _)| self.periodic_distance(image, *other) >= tolerance)
+                {
+                    // Each coordinate is a sum of the original ones, whose
+                    // su are taken as uncorrelated
+                    let su = op.rotation.map(|row| {
+                        row.iter()
+                            .zip(site.fract_su)
+                            .map(|(r, su)| (*r as f64 * su).powi(2))
+                            .sum::<f64>()
+                            .sqrt()
+                    });
+                    images.push((image, su));
+                }
+            }
+            sites.extend(
+                images
+                    .into_iter()
+                    .enumerate()
+                    .map(|(n, (fract, fract_su))| Site {
+                        label: match n {
+                            0 => site.label.clone(),
+                            n => format!("{}_{}", site.label, n + 1),
+                        },
+                        fract,
+                        fract_su,
+                        ..site.clone()
+                    }),
+            );
+        }
+        Structure {
+            cell: self.cell,
+            sites,
+        }
+    }
+
+    /// The cell repeated `size` times along each axis, with the sites of
+    /// every copy. Copies other than the first have labels ending in their
+    /// position, as `C1_1_0_0`.
+    ///
+    /// Panics if a size is zero
+    pub fn supercell(&self, size: [usize; 3]) -> Structure {
+        assert!(size.iter().all(|n| *n > 0), "supercell with a zero size");
+        let mut cell = self.cell;
+        for (length, n) in cell.lengths.iter_mut().zip(size) {
+            *length = Measurement::new(length.value * n as f64, length.su.map(|su| su * n as f64));
+        }
+        let mut sites = Vec::new();
+        for i in 0..size[0] {
+            for j in 0..size[1] {
+                for k in 0..size[2] {
+                    let offset = [i, j, k];
+                    sites.extend(self.sites.iter().map(|site| Site {
+                        label: match offset {
+                            [0, 0, 0] => site.label.clone(),
+                            _ => format!("{}_{i}_{j}_{k}", site.label),
+                        },
+                        fract: std::array::from_fn(|axis| {
+                            (site.fract[axis] + offset[axis] as f64) / size[axis] as f64
+                        }),
+                        fract_su: std::array::from_fn(|axis| {
+                            site.fract_su[axis] / size[axis] as f64
+                        }),
+                        ..site.clone()
+                    }));
+                }
+            }
+        }
+        Structure { cell, sites }
+    }
+
+    /// A block describing the structure in P 1, with the cell, the identity
+    /// operator and every site
+    pub fn to_block(&self, heading: &str) -> DataBlock {
+        self.to_block_with_operators(heading, &[SymOp::identity()])
+    }
+
+    /// A block describing the sites as an asymmetric unit of the operators,
+    /// with the space group named where the tables have it
+    pub fn to_block_with_operators(&self, heading: &str, ops: &[SymOp]) -> DataBlock {
+        let mut items = Vec::new();
+        let names = ["a", "b", "c"].map(|axis| format!("_cell.length_{axis}"));
+        for (name, length) in names.iter().zip(&self.cell.lengths) {
+            push_item(&mut items, name, Some(length.to_content()));
+        }
+        let names = ["alpha", "beta", "gamma"].map(|angle| format!("_cell.angle_{angle}"));
+        for (name, angle) in names.iter().zip(&self.cell.angles) {
+            push_item(&mut items, name, Some(angle.to_content()));
+        }
+        let text = |s: &str| Some(DataValue::Str(s.to_string()));
+        if let Some(group) = SpaceGroup::from_operators(ops) {
+            push_item(
+                &mut items,
+                "_space_group.name_H-M_alt",
+                text(group.hermann_mauguin),
+            );
+            push_item(&mut items, "_space_group.name_Hall", text(group.hall));
+            push_item(
+                &mut items,
+                "_space_group.IT_number",
+                text(&group.number.to_string()),
+            );
+        }
+        push_loop(
+            &mut items,
+            vec![(
+                "_space_group_symop.operation_xyz",
+                ops.iter().map(|op| text(&op.to_string())).collect(),
+            )],
+        );
+        let fract = |axis: usize| -> Vec<_> {
+            self.sites
+                .iter()
+                .map(|site| {
+                    Some(match site.fract_su[axis] {
+                        su if su > 0.0 => Measurement::new(site.fract[axis], Some(su)).to_content(),
+                        _ => DataValue::Str(format!("{:.6}", site.fract[axis])),
+                    })
+                })
+                .collect()
+        };
+        push_loop(
+            &mut items,
+            vec![
+                (
+                    LABEL[0],
+                    self.sites.iter().map(|s| text(&s.label)).collect(),
+                ),
+                (
+                    TYPE_SYMBOL[0],
+                    self.sites
+                        .iter()
+                        .map(|s| s.type_symbol.as_ref().map(CifValue::to_content))
+                        .collect(),
+                ),
+                (FRACT[0][0], fract(0)),
+                (FRACT[1][0], fract(1)),
+                (FRACT[2][0], fract(2)),
+                (
+                    OCCUPANCY[0],
+                    self.sites
+                        .iter()
+                        .map(|s| s.occupancy.as_ref().map(CifValue::to_content))
+                        .collect(),
+                ),
+                (
+                    U_ISO[0],
+                    self.sites
+                        .iter()
+                        .map(|s| s.u_iso_or_equiv.as_ref().map(CifValue::to_content))
+                        .collect(),
+                ),
+            ],
+        );
+        DataBlock {
+            heading: heading.to_string(),
+            content: items,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use crate::space_group::block_operators;
+    use std::collections::BTreeMap;
+
+    const ZIF_8: &str = include_str!("../../cif_chomper/example_data/zif-8.cif");
+
+    fn zif_8() -> (Structure, Vec<SymOp>) {
+        let model = cif2_file(ZIF_8).unwrap();
+        let block = &model.content[0];
+        (
+            Structure::from_block(block).unwrap(),
+            block_operators(block).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_from_block() {
+        let (structure, _) = zif_8();
+        assert_eq!(structure.sites.len(), 8);
+        let c2 = &structure.sites[4];
+        assert_eq!(c2.label, "C2");
+        assert_eq!(c2.type_symbol.as_deref(), Some("C"));
+        assert_eq!(c2.fract, [0.6083, 0.1825, 0.1263]);
+        assert!((c2.fract_su[1] - 0.0008).abs() < 1e-12);
+        assert_eq!(c2.occupancy, Some(0.63));
+    }
+
+    #[test]
+    fn test_expand() {
+        let (structure, ops) = zif_8();
+        let expanded = structure.expand(&ops, DEFAULT_TOLERANCE);
+        let mut counts = BTreeMap::new();
+        for site in &expanded.sites {
+            let base = site.label.split('_').next().unwrap();
+            *counts.entry(base).or_insert(0) += 1;
+        }
+        // 48 operators over the site symmetry orders in
+        // `_atom_site_symmetry_multiplicity`: 4 for Zn1, 2 for C1 and H1
+        let expected = [
+            ("C1", 24),
+            ("C2", 48),
+            ("C2A", 48),
+            ("H1", 24),
+            ("H2", 48),
+            ("H2A", 48),
+            ("N1", 48),
+            ("Zn1", 12),
+        ];
+        assert_eq!(counts, BTreeMap::from(expected));
+        assert_eq!(expanded.sites[0].label, "Zn1");
+        assert_eq!(expanded.sites[1].label, "Zn1_2");
+        assert!(
+            expanded
+                .sites
+                .iter()
+                .all(|s| s.fract.iter().all(|x| (0.0..1.0).contains(x)))
+        );
+    }
+
+    #[test]
+    fn test_supercell() {
+        let (structure, _) = zif_8();
+        let supercell = structure.supercell([2, 1, 3]);
+        assert_eq!(supercell.sites.len(), 8 * 6);
+        assert_eq!(supercell.cell.lengths[2].to_string(), "50.4909(6)");
+        let last = supercell.sites.last().unwrap();
+        assert_eq!(last.label, "H2A_1_0_2");
+        let h2a = structure.sites.last().unwrap().fract;
+        let expected = [(h2a[0] + 1.0) / 2.0, h2a[1], (h2a[2] + 2.0) / 3.0];
+        assert!((0..3).all(|i| (last.fract[i] - expected[i]).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_to_block_round_trip() {
+        let (structure, ops) = zif_8();
+        let expanded = structure.expand(&ops, DEFAULT_TOLERANCE);
+        let text = format!("#\\#CIF_2.0\n{}", expanded.to_block("zif_8_P1"));
+        let model = cif2_file(&text).unwrap();
+        let block = &model.content[0];
+        assert_eq!(block.heading, "zif_8_P1");
+        assert_eq!(block_operators(block).unwrap(), vec![SymOp::identity()]);
+        let read = Structure::from_block(block).unwrap();
+        assert_eq!(read.cell, expanded.cell);
+        assert_eq!(read.sites.len(), 300);
+        for (read, written) in read.sites.iter().zip(&expanded.sites) {
+            assert_eq!(read.label, written.label);
+            assert_eq!(read.occupancy, written.occupancy);
+            assert_eq!(read.u_iso_or_equiv, written.u_iso_or_equiv);
+            // Written to the precision of the su, or to six decimals
+            for i in 0..3 {
+                let difference = (read.fract[i] - written.fract[i]).abs();
+                assert!(difference <= written.fract_su[i].max(1e-6));
+            }
+        }
+        // Expanding again finds nothing new
+        assert_eq!(read.expand(&[], DEFAULT_TOLERANCE).sites.len(), 300);
+    }
+
+    #[test]
+    fn test_missing_coordinates() {
+        let text = "#\\#CIF_2.0\ndata_x\n_cell.length_a 5\n_cell.length_b 5\n_cell.length_c 5\n\
+                    loop_\n_atom_site.label\n_atom_site.fract_x\n_atom_site.fract_y\nC1 0 0\n";
+        let model = cif2_file(text).unwrap();
+        let error = Structure::from_block(&model.content[0]).unwrap_err();
+        assert_eq!(error.to_string(), "_atom_site.fract_z has no value");
+    }
+}
@@ -0,0 +1,625 @@
+/// Symmetry operators written as in `_space_group_symop.operation_xyz`, e.g.
+/// `-x+1/4,y,-z+1/4`, as integer rotation matrices and rational translations
+/// acting on fractional coordinates
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use crate::model::column;
+use crate::raw_model::RawDataBlock;
+
+/// Data names the operators of a data block are read from, current first
+pub const OPERATOR_NAMES: [&str; 4] = [
+    "_space_group_symop.operation_xyz",
+    "_space_group_symop_operation_xyz",
+    "_symmetry_equiv.pos_as_xyz",
+    "_symmetry_equiv_pos_as_xyz",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymmetryError {
+    /// Text that is not an operator
+    InvalidOperator(String),
+    /// Two operators whose product is not among the operators, even after
+    /// a lattice translation
+    NotClosed {
+        first: String,
+        second: String,
+        product: String,
+    },
+    /// Text that is not a Hall symbol
+    InvalidHall(String),
+    /// A space group symbol or number not in the tables
+    UnknownSpaceGroup(String),
+}
+
+impl fmt::Display for SymmetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymmetryError::InvalidOperator(text) => {
+                write!(f, "'{text}' is not a symmetry operator")
+            }
+            SymmetryError::NotClosed {
+                first,
+                second,
+                product,
+            } => write!(
+                f,
+                "the operators are not a group: {first} followed by {second} gives {product}"
+            ),
+            SymmetryError::InvalidHall(text) => write!(f, "'{text}' is not a Hall symbol"),
+            SymmetryError::UnknownSpaceGroup(text) => {
+                write!(f, "no space group '{text}' in the tables")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SymmetryError {}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// An exact rational number, for translations such as `1/4`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fraction {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Fraction {
+    pub const ZERO: Fraction = Fraction {
+        numerator: 0,
+        denominator: 1,
+    };
+
+    /// Panics if `denominator` is zero or the reduced fraction does not fit,
+    /// see [`Fraction::checked_new`]
+    pub fn new(numerator: i64, denominator: i64) -> Self {
+        assert!(denominator != 0, "fraction with a zero denominator");
+        Fraction::checked_new(numerator, denominator).expect("fraction out of range")
+    }
+
+    /// `None` if `denominator` is zero or the reduced fraction does not fit,
+    /// as for `i64::MIN / -1`
+    pub fn checked_new(numerator: i64, denominator: i64) -> Option<Self> {
+        Fraction::reduce(numerator.into(), denominator.into())
+    }
+
+    /// Reduce the result of arithmetic done in `i128`, where products of
+    /// `i64` cannot overflow
+    fn reduce(numerator: i128, denominator: i128) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator.unsigned_abs(), denominator.unsigned_abs()) as i128
+            * denominator.signum();
+        Some(Fraction {
+            numerator: (numerator / divisor).try_into().ok()?,
+            denominator: (denominator / divisor).try_into().ok()?,
+        })
+    }
+
+    pub fn checked_add(self, other: Fraction) -> Option<Fraction> {
+        let (a, b) = (self.numerator as i128, self.denominator as i128);
+        let (c, d) = (other.numerator as i128, other.denominator as i128);
+        Fraction::reduce(a * d + c * b, b * d)
+    }
+
+    pub fn checked_mul(self, other: Fraction) -> Option<Fraction> {
+        Fraction::reduce(
+            self.numerator as i128 * other.numerator as i128,
+            self.denominator as i128 * other.denominator as i128,
+        )
+    }
+
+    pub fn checked_neg(self) -> Option<Fraction> {
+        Fraction::reduce(-(self.numerator as i128), self.denominator.into())
+    }
+
+    pub fn numerator(&self) -> i64 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> i64 {
+        self.denominator
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denominator == 1
+    }
+
+    /// The value modulo 1, in `[0, 1)`
+    pub fn fract(&self) -> Self {
+        Fraction::new(
+            self.numerator.rem_euclid(self.denominator),
+            self.denominator,
+        )
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl Ord for Fraction {
+    /// Compares by value, the denominators being positive
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.numerator as i128 * other.denominator as i128)
+            .cmp(&(other.numerator as i128 * self.denominator as i128))
+    }
+}
+
+impl PartialOrd for Fraction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<i64> for Fraction {
+    fn from(n: i64) -> Self {
+        Fraction::new(n, 1)
+    }
+}
+
+impl Add for Fraction {
+    type Output = Fraction;
+
+    /// Panics if the sum does not fit, see [`Fraction::checked_add`]
+    fn add(self, other: Fraction) -> Fraction {
+        self.checked_add(other).expect("fraction out of range")
+    }
+}
+
+impl Sub for Fraction {
+    type Output = Fraction;
+
+    fn sub(self, other: Fraction) -> Fraction {
+        self + -other
+    }
+}
+
+impl Neg for Fraction {
+    type Output = Fraction;
+
+    fn neg(self) -> Fraction {
+        self.checked_neg().expect("fraction out of range")
+    }
+}
+
+impl Mul for Fraction {
+    type Output = Fraction;
+
+    /// Panics if the product does not fit, see [`Fraction::checked_mul`]
+    fn mul(self, other: Fraction) -> Fraction {
+        self.checked_mul(other).expect("fraction out of range")
+    }
+}
+
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.denominator {
+            1 => write!(f, "{}", self.numerator),
+            d => write!(f, "{}/{d}", self.numerator),
+        }
+    }
+}
+
+impl FromStr for Fraction {
+    type Err = ();
+
+    /// Reads `1/4`, `-3` or a decimal such as `0.25`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((numerator, denominator)) = s.split_once('/') {
+            let denominator: i64 = denominator.trim().parse().map_err(|_| ())?;
+            let numerator: i64 = numerator.trim().parse().map_err(|_| ())?;
+            return Fraction::checked_new(numerator, denominator).ok_or(());
+        }
+        let (sign, digits) = match s.strip_prefix('-') {
+            Some(digits) => (-1, digits),
+            None => (1, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (whole, decimals) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.is_empty() && decimals.is_empty()
+            || !whole
+                .chars()
+                .chain(decimals.chars())
+                .all(|c| c.is_ascii_digit())
+            || decimals.len() > 9
+        {
+            return Err(());
+        }
+        let scale = 10i64.pow(decimals.len() as u32);
+        let whole: i64 = match whole {
+            "" => 0,
+            whole => whole.parse().map_err(|_| ())?,
+        };
+        let decimals: i64 = match decimals {
+            "" => 0,
+            decimals => decimals.parse().map_err(|_| ())?,
+        };
+        let numerator = whole
+            .checked_mul(scale)
+            .and_then(|n| n.checked_add(decimals))
+            .ok_or(())?;
+        Fraction::checked_new(sign * numerator, scale).ok_or(())
+    }
+}
+
+/// `rotation` applied to a translation
+fn rotate(rotation: &[[i64; 3]; 3], translation: &[Fraction; 3]) -> [Fraction; 3] {
+    rotation.map(|row| {
+        row.iter()
+            .zip(translation)
+            .fold(Fraction::ZERO, |sum, (r, t)| sum + Fraction::from(*r) * *t)
+    })
+}
+
+/// A symmetry operator `x' = R x + t` on fractional coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymOp {
+    /// Rows give the new coordinates, e.g. `[0, -1, 0]` for `-y`
+    pub rotation: [[i64; 3]; 3],
+    pub translation: [Fraction; 3],
+}
+
+impl SymOp {
+    pub fn identity() -> Self {
+        SymOp {
+            rotation: [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+            translation: [Fraction::ZERO; 3],
+        }
+    }
+
+    pub fn determinant(&self) -> i64 {
+        let r = &self.rotation;
+        r[0][0] * (r[1][1] * r[2][2] - r[1][2] * r[2][1])
+            - r[0][1] * (r[1][0] * r[2][2] - r[1][2] * r[2][0])
+            + r[0][2] * (r[1][0] * r[2][1] - r[1][1] * r[2][0])
+    }
+
+    /// The operator applying `self`, then `other`
+    pub fn then(&self, other: &SymOp) -> SymOp {
+        other.compose(self)
+    }
+
+    /// The operator applying `other`, then `self`: `R1 R2 x + R1 t2 + t1`
+    pub fn compose(&self, other: &SymOp) -> SymOp {
+        let rotated = rotate(&self.rotation, &other.translation);
+        SymOp {
+            rotation: std::array::from_fn(|i| {
+                std::array::from_fn(|j| {
+                    (0..3)
+                        .map(|k| self.rotation[i][k] * other.rotation[k][j])
+                        .sum()
+                })
+            }),
+            translation: std::array::from_fn(|i| rotated[i] + self.translation[i]),
+        }
+    }
+
+    /// The inverse operator; `None` unless the rotation has determinant ±1
+    pub fn inverse(&self) -> Option<SymOp> {
+        let det = self.determinant();
+        if det.abs() != 1 {
+            return None;
+        }
+        let r = &self.rotation;
+        // The adjugate, divided by the determinant
+        let rotation = std::array::from_fn(|i| {
+            std::array::from_fn(|j| {
+                let (a, b) = ((j + 1) % 3, (j + 2) % 3);
+                let (c, d) = ((i + 1) % 3, (i + 2) % 3);
+                (r[a][c] * r[b][d] - r[a][d] * r[b][c]) * det
+            })
+        });
+        Some(SymOp {
+            rotation,
+            translation: rotate(&rotation, &self.translation).map(|t| -t),
+        })
+    }
+
+    /// The same operator with its translation in `[0, 1)`
+    pub fn normalized(&self) -> SymOp {
+        SymOp {
+            rotation: self.rotation,
+            translation: self.translation.map(|t| t.fract()),
+        }
+    }
+
+    /// Whether the operators differ only by a lattice translation
+    pub fn equivalent(&self, other: &SymOp) -> bool {
+        self.normalized() == other.normalized()
+    }
+
+    /// The image of a point in fractional coordinates
+    pub fn apply(&self, point: [f64; 3]) -> [f64; 3] {
+        std::array::from_fn(|i| {
+            (0..3)
+                .map(|j| self.rotation[i][j] as f64 * point[j])
+                .sum::<f64>()
+                + self.translation[i].to_f64()
+        })
+    }
+}
+
+/// Adds a term of a component, e.g. `-y` or `+1/2`, to a row
+fn read_term(term: &str, row: &mut [i64; 3], translation: &mut Fraction) -> Option<()> {
+    let (sign, term) = match term.as_bytes().first()? {
+        b'-' => (-1, &term[1..]),
+        b'+' => (1, &term[1..]),
+        _ => (1, term),
+    };
+    let term = term.trim();
+    let axis = term
+        .chars()
+        .last()
+        .and_then(|c| "xyz".find(c.to_ascii_lowercase()));
+    match axis {
+        Some(axis) => {
+            let coefficient = term[..term.len() - 1].trim().trim_end_matches('*');
+            let coefficient = match coefficient.trim() {
+                "" => Fraction::from(1),
+                c => c.parse().ok()?,
+            };
+            if !coefficient.is_integer() {
+                return None;
+            }
+            let coefficient = coefficient.numerator().checked_mul(sign)?;
+            row[axis] = row[axis].checked_add(coefficient)?;
+        }
+        None => {
+            let term: Fraction = term.parse().ok()?;
+            let term = if sign < 0 { term.checked_neg()? } else { term };
+            *translation = translation.checked_add(term)?;
+        }
+    }
+    Some(())
+}
+
+fn read_component(component: &str) -> Option<([i64; 3], Fraction)> {
+    let mut row = [0; 3];
+    let mut translation = Fraction::ZERO;
+    let mut start = 0;
+    let component = component.trim();
+    if component.is_empty() {
+        return None;
+    }
+    for (i, c) in component.char_indices().skip(1) {
+        // A sign after `*` or `/` belongs to the number that follows, as in `1/-2`
+        if matches!(c, '+' | '-') && !component[..i].trim_end().ends_with(['*', '/']) {
+            read_term(&component[start..i], &mut row, &mut translation)?;
+            start = i;
+        }
+    }
+    read_term(&component[start..], &mut row, &mut translation)?;
+    Some((row, translation))
+}
+
+impl FromStr for SymOp {
+    type Err = SymmetryError;
+
+    /// Reads operators such as `-x+1/4,y,-z+1/4`, `1/2+X, Y-X, z` or
+    /// `x,y,z+0.5`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SymmetryError::InvalidOperator(s.to_string());
+        let components: Vec<_> = s.split(',').collect();
+        if components.len() != 3 {
+            return Err(invalid());
+        }
+        let mut op = SymOp {
+            rotation: [[0; 3]; 3],
+            translation: [Fraction::ZERO; 3],
+        };
+        for (i, component) in components.iter().enumerate() {
+            let (row, translation) = read_component(component).ok_or_else(invalid)?;
+            op.rotation[i] = row;
+            op.translation[i] = translation;
+        }
+        Ok(op)
+    }
+}
+
+impl fmt::Display for SymOp {
+    /// Canonical form: the axes in order, then the translation, e.g.
+    /// `-x+1/4,x-y,z`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (row, translation)) in self.rotation.iter().zip(&self.translation).enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            let mut empty = true;
+            for (coefficient, axis) in row.iter().zip(["x", "y", "z"]) {
+                match coefficient {
+                    0 => continue,
+                    1 if empty => write!(f, "{axis}")?,
+                    1 => write!(f, "+{axis}")?,
+                    -1 => write!(f, "-{axis}")?,
+                    c if *c > 0 && !empty => write!(f, "+{c}{axis}")?,
+                    c => write!(f, "{c}{axis}")?,
+                }
+                empty = false;
+            }
+            if *translation != Fraction::ZERO || empty {
+                if translation.numerator() > 0 && !empty {
+                    f.write_str("+")?;
+                }
+                write!(f, "{translation}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that every product of two operators is among them, up to a
+/// lattice translation
+pub fn check_closure(ops: &[SymOp]) -> Result<(), SymmetryError> {
+    let normalized: std::collections::HashSet<_> = ops.iter().map(SymOp::normalized).collect();
+    for first in ops {
+        for second in ops {
+            let product = second.compose(first);
+            if !normalized.contains(&product.normalized()) {
+                return Err(SymmetryError::NotClosed {
+                    first: first.to_string(),
+                    second: second.to_string(),
+                    product: product.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The symmetry operators of a data block, from the first of
+/// `OPERATOR_NAMES` present; empty if there are none
+pub fn operators_from_block(block: &RawDataBlock) -> Result<Vec<SymOp>, SymmetryError> {
+    let Some(values) = column(block, &OPERATOR_NAMES) else {
+        return Ok(Vec::new());
+    };
+    values
+        .iter()
+        .map(|value| {
+            value
+                .as_str()
+                .ok_or_else(|| SymmetryError::InvalidOperator(format!("{value:?}")))?
+                .parse()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use rstest::rstest;
+
+    const MIL_101: &str = include_str!("../../cif_chomper/example_data/mil-101.cif");
+
+    fn op(s: &str) -> SymOp {
+        s.parse().unwrap()
+    }
+
+    #[rstest]
+    #[case("1/4", Fraction::new(1, 4))]
+    #[case("-2/4", Fraction::new(-1, 2))]
+    #[case("0.25", Fraction::new(1, 4))]
+    #[case(".5", Fraction::new(1, 2))]
+    #[case("3", Fraction::from(3))]
+    #[case("2/-6", Fraction::new(-1, 3))]
+    fn test_parse_fraction(#[case] text: &str, #[case] expected: Fraction) {
+        assert_eq!(text.parse(), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("1/0")]
+    #[case("1/-9223372036854775808")]
+    #[case("-9223372036854775808/-1")]
+    #[case("9223372036854775807.5")]
+    #[case("x")]
+    #[case(".")]
+    #[case("")]
+    fn test_parse_fraction_error(#[case] text: &str) {
+        assert_eq!(text.parse::<Fraction>(), Err(()));
+    }
+
+    #[test]
+    fn test_fraction_arithmetic() {
+        let quarter = Fraction::new(1, 4);
+        assert_eq!(quarter + Fraction::new(1, 3), Fraction::new(7, 12));
+        assert_eq!(quarter - Fraction::from(1), Fraction::new(-3, 4));
+        assert_eq!((quarter - Fraction::from(1)).fract(), Fraction::new(1, 4));
+        assert_eq!(Fraction::new(7, 4).fract(), Fraction::new(3, 4));
+        assert_eq!(Fraction::new(-3, 4).to_string(), "-3/4");
+    }
+
+    #[rstest]
+    #[case(Fraction::new(1, 3), Fraction::new(1, 2))]
+    #[case(Fraction::new(-1, 2), Fraction::new(-1, 3))]
+    #[case(Fraction::new(2, 3), Fraction::new(3, 4))]
+    #[case(Fraction::new(5, -4), Fraction::ZERO)]
+    #[case(Fraction::from(1), Fraction::new(5, 4))]
+    fn test_fraction_order(#[case] smaller: Fraction, #[case] larger: Fraction) {
+        assert!(smaller < larger);
+        assert_eq!(larger.cmp(&smaller), Ordering::Greater);
+        assert_eq!(smaller.max(larger), larger);
+    }
+
+    #[rstest]
+    #[case("-x+1/4,y,-z+1/4", "-x+1/4,y,-z+1/4")]
+    #[case("1/2+X, Y-X, z", "x+1/2,-x+y,z")]
+    #[case("x,y,z+0.5", "x,y,z+1/2")]
+    #[case("-y , x-y , 2/3+z", "-y,x-y,z+2/3")]
+    #[case("x-1/2,2*y,-z", "x-1/2,2y,-z")]
+    #[case("1/2,y,z", "1/2,y,z")]
+    #[case("+x,-y-1/4,+z", "x,-y-1/4,z")]
+    #[case("x+1/-2,y,z", "x-1/2,y,z")]
+    fn test_parse_operator(#[case] text: &str, #[case] canonical: &str) {
+        let parsed = op(text);
+        assert_eq!(parsed.to_string(), canonical);
+        assert_eq!(op(canonical), parsed);
+    }
+
+    #[rstest]
+    #[case("x,y")]
+    #[case("x,y,z,x")]
+    #[case("x,y,")]
+    #[case("x,y,w")]
+    #[case("1/2x,y,z")]
+    #[case("x+,y,z")]
+    #[case("x,y,z+1/-9223372036854775808")]
+    #[case("x,y,z+9223372036854775807-1/2")]
+    #[case("9223372036854775807x+x,y,z")]
+    fn test_parse_operator_error(#[case] text: &str) {
+        assert_eq!(
+            text.parse::<SymOp>(),
+            Err(SymmetryError::InvalidOperator(text.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_compose_and_inverse() {
+        let a = op("-y,x-y,z+1/3");
+        let b = op("-x+1/2,y,-z");
+        let point = [0.1, 0.2, 0.3];
+        let composed = a.compose(&b).apply(point);
+        let in_turn = a.apply(b.apply(point));
+        assert!((0..3).all(|i| (composed[i] - in_turn[i]).abs() < 1e-12));
+        assert_eq!(a.then(&b), b.compose(&a));
+        let inverse = a.inverse().unwrap();
+        assert_eq!(a.compose(&inverse), SymOp::identity());
+        assert_eq!(inverse.compose(&a), SymOp::identity());
+        assert_eq!(inverse.to_string(), "-x+y,-x,z-1/3");
+        assert_eq!(op("2x,y,z").inverse(), None);
+    }
+
+    #[test]
+    fn test_equivalent() {
+        assert!(op("x+1,-y,z-1/2").equivalent(&op("x,-y,z+1/2")));
+        assert!(!op("x,-y,z").equivalent(&op("x,y,z")));
+        assert_eq!(
+            op("-x-1/4,y+5/4,z").normalized().to_string(),
+            "-x+3/4,y+1/4,z"
+        );
+    }
+
+    #[test]
+    fn test_operators_from_block() {
+        let model = cif2_file(MIL_101).unwrap();
+        let ops = operators_from_block(&model.content[0]).unwrap();
+        assert_eq!(ops.len(), 192);
+        assert_eq!(ops[1].to_string(), "x,-y+1/4,-z+1/4");
+        assert_eq!(check_closure(&ops), Ok(()));
+    }
+
+    #[test]
+    fn test_not_closed() {
+        let ops = [op("x,y,z"), op("-y,x,z")];
+        assert_eq!(
+            check_closure(&ops).unwrap_err().to_string(),
+            "the operators are not a group: -y,x,z followed by -y,x,z gives -x,-y,z"
+        );
+    }
+}
@@ -0,0 +1,12 @@
+/// Fixtures shared by the tests of several modules
+use rstest::fixture;
+
+use crate::dictionary::Dictionary;
+
+/// A small DDLm dictionary with cell and atom site items
+pub const TEST_DIC: &str = include_str!("data/test_dict.dic");
+
+#[fixture]
+pub fn dict() -> Dictionary {
+    Dictionary::parse(TEST_DIC).unwrap()
+}
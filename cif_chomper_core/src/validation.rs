@@ -0,0 +1,476 @@
+/// Checks the data names and values of a data block against the definitions
+/// of a DDLm dictionary
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::container::shape;
+use crate::dictionary::{
+    CategoryClass, ContentType, Dictionary, ItemDefinition, Range, TypeContainer, TypeContents,
+    TypePurpose,
+};
+use crate::numeric::Measurement;
+use crate::raw_model::{RawDataBlock, RawDataItem, RawDataItemContent};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    UnknownName {
+        name: String,
+    },
+    WrongType {
+        name: String,
+        value: String,
+        expected: String,
+    },
+    OutOfRange {
+        name: String,
+        value: String,
+        range: Range,
+    },
+    NotEnumerated {
+        name: String,
+        value: String,
+    },
+    UnexpectedSu {
+        name: String,
+        value: String,
+    },
+    LoopedSetCategory {
+        category: String,
+    },
+    MissingKey {
+        category: String,
+        key: String,
+    },
+    WrongDimension {
+        name: String,
+        value: String,
+        dimension: String,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::UnknownName { name } => write!(f, "{name} is not defined"),
+            Violation::WrongType {
+                name,
+                value,
+                expected,
+            } => write!(f, "{name} value '{value}' is not of type {expected}"),
+            Violation::OutOfRange { name, value, range } => {
+                write!(f, "{name} value '{value}' is outside the range {range}")
+            }
+            Violation::NotEnumerated { name, value } => {
+                write!(f, "{name} value '{value}' is not a permitted state")
+            }
+            Violation::UnexpectedSu { name, value } => {
+                write!(f, "{name} value '{value}' has an su but is not a Measurand")
+            }
+            Violation::LoopedSetCategory { category } => {
+                write!(f, "Set category {category} is looped")
+            }
+            Violation::MissingKey { category, key } => {
+                write!(f, "{category} is missing its key {key}")
+            }
+            Violation::WrongDimension {
+                name,
+                value,
+                dimension,
+            } => write!(
+                f,
+                "{name} value '{value}' does not have the dimension {dimension}"
+            ),
+        }
+    }
+}
+
+/// Validate every data item of a block, returning the violations in the order
+/// they appear. Aliases are accepted as their canonical items, null values `?`
+/// and `.` are always accepted, and save frames are not checked.
+pub fn validate_block(dict: &Dictionary, block: &RawDataBlock) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    // Lower-cased category name to the lower-cased names present in the block
+    let mut present: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for item in &block.content {
+        match item {
+            RawDataItem::Data { name, value } => {
+                if let Some(definition) = check_name(dict, name, &mut violations) {
+                    record(&mut present, definition);
+                    check_value(definition, value, &mut violations);
+                }
+            }
+            RawDataItem::Loop { names, values } => {
+                let definitions: Vec<_> = names
+                    .iter()
+                    .map(|name| check_name(dict, name, &mut violations))
+                    .collect();
+                for (i, value) in values.iter().enumerate() {
+                    if let Some(definition) = definitions[i % names.len()] {
+                        check_value(definition, value, &mut violations);
+                    }
+                }
+                let mut looped_sets = BTreeSet::new();
+                for definition in definitions.iter().flatten() {
+                    record(&mut present, definition);
+                    let is_set = dict
+                        .item_category(definition)
+                        .is_some_and(|c| c.class == CategoryClass::Set);
+                    if is_set && values.len() > names.len() {
+                        looped_sets.insert(definition.category_id.to_lowercase());
+                    }
+                }
+                violations.extend(looped_sets.into_iter().map(|category| {
+                    Violation::LoopedSetCategory {
+                        category: dict
+                            .category(&category)
+                            .map_or(category.clone(), |c| c.name.clone()),
+                    }
+                }));
+            }
+            RawDataItem::SaveFrame { .. } => (),
+        }
+    }
+    for (category, names) in &present {
+        let Some(category) = dict.category(category) else {
+            continue;
+        };
+        if category.class != CategoryClass::Loop {
+            continue;
+        }
+        for key in &category.keys {
+            if !names.contains(&key.to_lowercase()) {
+                violations.push(Violation::MissingKey {
+                    category: category.name.clone(),
+                    key: key.clone(),
+                });
+            }
+        }
+    }
+    violations
+}
+
+fn record(present: &mut BTreeMap<String, BTreeSet<String>>, definition: &ItemDefinition) {
+    present
+        .entry(definition.category_id.to_lowercase())
+        .or_default()
+        .insert(definition.name.to_lowercase());
+}
+
+fn check_name<'d>(
+    dict: &'d Dictionary,
+    name: &str,
+    violations: &mut Vec<Violation>,
+) -> Option<&'d ItemDefinition> {
+    let definition = dict.item(name);
+    if definition.is_none() {
+        violations.push(Violation::UnknownName {
+            name: name.to_string(),
+        });
+    }
+    definition
+}
+
+fn is_null(value: &RawDataItemContent) -> bool {
+    matches!(
+        value,
+        RawDataItemContent::Empty | RawDataItemContent::Str("?" | ".")
+    )
+}
+
+fn render(value: &RawDataItemContent) -> String {
+    match value {
+        RawDataItemContent::Empty => "?".to_string(),
+        RawDataItemContent::Str(s) | RawDataItemContent::Text(s) => s.to_string(),
+        RawDataItemContent::List(items) => {
+            let items: Vec<_> = items.iter().map(render).collect();
+            format!("[{}]", items.join(" "))
+        }
+        RawDataItemContent::Table(entries) => {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|(k, v)| format!("{}:{}", render(k), render(v)))
+                .collect();
+            format!("{{{}}}", entries.join(" "))
+        }
+    }
+}
+
+fn check_value(
+    definition: &ItemDefinition,
+    value: &RawDataItemContent,
+    violations: &mut Vec<Violation>,
+) {
+    if is_null(value) {
+        return;
+    }
+    let wrong_type = |expected: String| Violation::WrongType {
+        name: definition.name.clone(),
+        value: render(value),
+        expected,
+    };
+    match (definition.container, value) {
+        (
+            TypeContainer::List | TypeContainer::Array | TypeContainer::Matrix,
+            RawDataItemContent::List(elements),
+        ) => {
+            if let Some(dimensions) = definition.dimensions()
+                && shape(value) != Some(dimensions)
+            {
+                violations.push(Violation::WrongDimension {
+                    name: definition.name.clone(),
+                    value: render(value),
+                    dimension: definition.dimension.clone().unwrap_or_default(),
+                });
+            }
+            for element in elements {
+                check_element(definition, element, violations);
+            }
+        }
+        (TypeContainer::List | TypeContainer::Array | TypeContainer::Matrix, _) => {
+            violations.push(wrong_type(format!("{}", definition.container)))
+        }
+        (TypeContainer::Table, RawDataItemContent::Table(entries)) => {
+            for (_, value) in entries {
+                check_element(definition, value, violations);
+            }
+        }
+        (TypeContainer::Table, _) => violations.push(wrong_type("Table".to_string())),
+        (_, RawDataItemContent::Str(s) | RawDataItemContent::Text(s)) => {
+            check_scalar(definition, s, violations)
+        }
+        // Multiple and Implied containers may hold either form
+        (TypeContainer::Multiple | TypeContainer::Implied, _) => (),
+        (_, _) => violations.push(wrong_type(definition.contents.to_string())),
+    }
+}
+
+/// Check an element of a container value, descending into nested lists
+fn check_element(
+    definition: &ItemDefinition,
+    element: &RawDataItemContent,
+    violations: &mut Vec<Violation>,
+) {
+    match element {
+        RawDataItemContent::List(nested) => {
+            for element in nested {
+                check_element(definition, element, violations);
+            }
+        }
+        RawDataItemContent::Str(s) | RawDataItemContent::Text(s) if !is_null(element) => {
+            check_scalar(definition, s, violations)
+        }
+        _ => (),
+    }
+}
+
+fn check_scalar(definition: &ItemDefinition, value: &str, violations: &mut Vec<Violation>) {
+    let name = || definition.name.clone();
+    let wrong_type = || Violation::WrongType {
+        name: name(),
+        value: value.to_string(),
+        expected: definition.contents.to_string(),
+    };
+    let content_type = match &definition.contents {
+        TypeContents::Simple(content_type) => *content_type,
+        TypeContents::Other(_) => return,
+    };
+    let number = match content_type {
+        ContentType::Real => match value.parse::<Measurement>() {
+            Ok(m) => Some(m),
+            Err(()) => return violations.push(wrong_type()),
+        },
+        ContentType::Integer | ContentType::Count | ContentType::Index => {
+            let (digits, m) = match value.split_once('(') {
+                Some((digits, _)) => (digits, value.parse::<Measurement>()),
+                None => (value, value.parse::<Measurement>()),
+            };
+            let minimum = match content_type {
+                ContentType::Count => 0,
+                ContentType::Index => 1,
+                _ => i64::MIN,
+            };
+            match (digits.parse::<i64>(), m) {
+                (Ok(n), Ok(m)) if n >= minimum => Some(m),
+                _ => return violations.push(wrong_type()),
+            }
+        }
+        ContentType::Code | ContentType::Name | ContentType::Word => {
+            if value.chars().any(char::is_whitespace) {
+                return violations.push(wrong_type());
+            }
+            None
+        }
+        ContentType::Tag => {
+            if !value.starts_with('_') || value.chars().any(char::is_whitespace) {
+                return violations.push(wrong_type());
+            }
+            None
+        }
+        ContentType::Date => {
+            if !is_date(value) {
+                return violations.push(wrong_type());
+            }
+            None
+        }
+        _ => None,
+    };
+    if let Some(m) = number {
+        if m.su.is_some() && definition.purpose != TypePurpose::Measurand {
+            violations.push(Violation::UnexpectedSu {
+                name: name(),
+                value: value.to_string(),
+            });
+        }
+        if let Some(range) = definition.enumeration.range
+            && !range.contains(m.value)
+        {
+            violations.push(Violation::OutOfRange {
+                name: name(),
+                value: value.to_string(),
+                range,
+            });
+        }
+    }
+    let states = &definition.enumeration.states;
+    if !states.is_empty() && !definition.enumeration.open {
+        let case_insensitive = content_type == ContentType::Code;
+        let permitted = states.iter().any(|s| {
+            if case_insensitive {
+                s.state.eq_ignore_ascii_case(value)
+            } else {
+                s.state == value
+            }
+        });
+        if !permitted {
+            violations.push(Violation::NotEnumerated {
+                name: name(),
+                value: value.to_string(),
+            });
+        }
+    }
+}
+
+/// `YYYY-MM-DD`
+fn is_date(value: &str) -> bool {
+    let parts: Vec<_> = value.split('-').collect();
+    matches!(parts.as_slice(), [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2)
+        && parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::cif2_file;
+    use crate::test_fixtures::{TEST_DIC, dict};
+    use rstest::rstest;
+
+    fn validate(input: &str) -> Vec<Violation> {
+        let dict = dict();
+        let model = cif2_file(input).unwrap();
+        validate_block(&dict, &model.content[0])
+    }
+
+    #[test]
+    fn test_valid_block() {
+        let input = "#\\#CIF_2.0\ndata_test\n_cell.length_a 5.431(2)\n_cell.formula_units_z 8\n\
+            loop_\n_atom_site.label\n_atom_site.adp_type\n_atom_site.fract_xyz\n\
+            Si1 uiso [0.0 0.0 0.0]\nSi2 Uani [0.25 0.25 ?]\n";
+        assert_eq!(validate(input), vec![]);
+    }
+
+    #[rstest]
+    #[case("_cell.nonesuch 160.2", Violation::UnknownName { name: "_cell.nonesuch".to_string() })]
+    #[case("_cell.length_a abc", Violation::WrongType {
+        name: "_cell.length_a".to_string(),
+        value: "abc".to_string(),
+        expected: "Real".to_string(),
+    })]
+    #[case("_cell.formula_units_z 2.5", Violation::WrongType {
+        name: "_cell.formula_units_z".to_string(),
+        value: "2.5".to_string(),
+        expected: "Count".to_string(),
+    })]
+    #[case("_cell.length_a 0.5", Violation::OutOfRange {
+        name: "_cell.length_a".to_string(),
+        value: "0.5".to_string(),
+        range: Range { min: Some(1.0), max: None },
+    })]
+    #[case("_cell.formula_units_z 4(1)", Violation::UnexpectedSu {
+        name: "_cell.formula_units_z".to_string(),
+        value: "4(1)".to_string(),
+    })]
+    #[case("_atom_site.label A\n_atom_site.adp_type Uovl", Violation::NotEnumerated {
+        name: "_atom_site.adp_type".to_string(),
+        value: "Uovl".to_string(),
+    })]
+    #[case("_atom_site.adp_type Uiso", Violation::MissingKey {
+        category: "ATOM_SITE".to_string(),
+        key: "_atom_site.label".to_string(),
+    })]
+    #[case("loop_ _cell.length_a 4.0 5.0", Violation::LoopedSetCategory {
+        category: "CELL".to_string(),
+    })]
+    #[case("_atom_site.label A\n_atom_site.fract_xyz 0.5", Violation::WrongType {
+        name: "_atom_site.fract_xyz".to_string(),
+        value: "0.5".to_string(),
+        expected: "Matrix".to_string(),
+    })]
+    #[case("_atom_site.label A\n_atom_site.fract_xyz [0.5 0.5]", Violation::WrongDimension {
+        name: "_atom_site.fract_xyz".to_string(),
+        value: "[0.5 0.5]".to_string(),
+        dimension: "[3]".to_string(),
+    })]
+    fn test_violation(#[case] items: &str, #[case] expected: Violation) {
+        let input = format!("#\\#CIF_2.0\ndata_test\n{items}\n");
+        assert_eq!(validate(&input), vec![expected]);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let input = "#\\#CIF_2.0\ndata_test\n_cell_length_a 0.5\n\
+            loop_\n_atom_site.label\n_atom_site_adp_type\nC1 Uiso\n";
+        assert_eq!(
+            validate(input),
+            vec![Violation::OutOfRange {
+                name: "_cell.length_a".to_string(),
+                value: "0.5".to_string(),
+                range: Range {
+                    min: Some(1.0),
+                    max: None
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_table_values() {
+        let dic = format!(
+            "{TEST_DIC}\nsave_cell.orientation\n_definition.id '_cell.orientation'\n\
+            _name.category_id cell\n_name.object_id orientation\n\
+            _type.container Table\n_type.contents Real\nsave_\n"
+        );
+        let dict = Dictionary::parse(&dic).unwrap();
+        let input = "#\\#CIF_2.0\ndata_test\n_cell.orientation {'a':1.5 'b':[2.0 x] 'c':?}\n";
+        let model = cif2_file(input).unwrap();
+        assert_eq!(
+            validate_block(&dict, &model.content[0]),
+            vec![Violation::WrongType {
+                name: "_cell.orientation".to_string(),
+                value: "x".to_string(),
+                expected: "Real".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_open_enumeration() {
+        let input = "#\\#CIF_2.0\ndata_test\n_atom_site.label A\n_atom_site.calc_flag dum\n";
+        assert_eq!(validate(input), vec![]);
+    }
+
+    #[test]
+    fn test_null_values() {
+        let input = "#\\#CIF_2.0\ndata_test\n_cell.length_a ?\n_cell.formula_units_z .\n";
+        assert_eq!(validate(input), vec![]);
+    }
+}
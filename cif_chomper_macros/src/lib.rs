@@ -1,9 +1,16 @@
+use cif_chomper_macros_core::derive::derive_cif_category;
 use cif_chomper_macros_core::{ModelMacroInput, make_model_core};
 use proc_macro::TokenStream;
-use syn::parse_macro_input;
+use syn::{DeriveInput, parse_macro_input};
 
 #[proc_macro]
 pub fn make_model(tokens: TokenStream) -> TokenStream {
     let input = parse_macro_input!(tokens as ModelMacroInput);
     make_model_core(input).into()
 }
+
+#[proc_macro_derive(CifCategory, attributes(cif))]
+pub fn cif_category(tokens: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(tokens as DeriveInput);
+    derive_cif_category(input).into()
+}
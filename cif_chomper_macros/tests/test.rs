@@ -1,8 +1,201 @@
 use cif_chomper_macros::make_model;
-const DICT: &str = include_str!("../../cif_core/cif_core.dic");
 
 #[test]
 fn it_works() {
     make_model!(StructName);
-    assert_eq!(StructName { x: 6 }.x, 6);
+    assert_eq!(StructName::NAMESPACE, "CifCore");
+
+    let cell = Cell {
+        length_a: Some(5.0),
+        length_b: Some(6.0),
+        length_c: Some(7.0),
+        angle_alpha: Some(90.0),
+        angle_beta: Some(100.0),
+        angle_gamma: Some(90.0),
+        ..Default::default()
+    };
+    let volume = 5.0 * 6.0 * 7.0 * 100f64.to_radians().sin();
+    assert!((cell.volume().unwrap() - volume).abs() < 1e-9);
+    assert_eq!(Cell::default().volume(), None);
+    let metric_tensor = cell.metric_tensor().unwrap();
+    assert_eq!(metric_tensor[0][0], 25.0);
+    assert!((metric_tensor[0][2] - 35.0 * 100f64.to_radians().cos()).abs() < 1e-9);
+
+    let site = AtomSite {
+        fract_x: Some(0.1),
+        fract_y: Some(0.2),
+        fract_z: Some(0.3),
+        ..Default::default()
+    };
+    assert_eq!(site.fract_xyz(), Some([0.1, 0.2, 0.3]));
+
+    let mut model = StructName {
+        cell,
+        ..Default::default()
+    };
+    model.atom.atom_site.push(site);
+    assert_eq!(model.cell.length_a, Some(5.0));
+    assert_eq!(model.atom.atom_site[0].fract_xyz(), Some([0.1, 0.2, 0.3]));
+}
+
+#[test]
+fn local_dictionary() {
+    make_model!(
+        TestModel,
+        dictionary = "../cif_chomper_core/src/data/test_dict.dic",
+        categories = [cell]
+    );
+    assert_eq!(TestModel::NAMESPACE, "TestDic");
+    let model = TestModel {
+        cell: Cell {
+            length_a: Some(2.0),
+            length_b: Some(2.0),
+            length_c: Some(2.0),
+            angle_alpha: Some(90.0),
+            angle_beta: Some(90.0),
+            angle_gamma: Some(90.0),
+            ..Default::default()
+        },
+    };
+    assert!((model.cell.volume().unwrap() - 8.0).abs() < 1e-9);
+}
+
+#[test]
+fn enumerations() {
+    make_model!(EnumModel, categories = [atom_site, space_group]);
+    let adp_type: AtomSiteAdpType = "UANI".parse().unwrap();
+    assert_eq!(adp_type, AtomSiteAdpType::Uani);
+    assert_eq!(adp_type.to_string(), "Uani");
+    assert_eq!("Uxyz".parse::<AtomSiteAdpType>(), Err(()));
+    assert_eq!(
+        SpaceGroupCrystalSystem::Cubic.as_str(),
+        SpaceGroupCrystalSystem::STATES[6]
+    );
+}
+
+#[test]
+fn read_and_write_blocks() {
+    use cif_chomper_core::model::ModelError;
+    use cif_chomper_core::parser::cif2_file;
+
+    make_model!(BlockModel, categories = [cell, atom_site, space_group]);
+    let input = "#\\#CIF_2.0\ndata_test\n_cell_length_a 5.4321(3)\n_cell.angle_beta 100\n\
+        _space_group.crystal_system Monoclinic\n\
+        loop_\n_atom_site.label\n_atom_site.fract_x\n_atom_site.adp_type\n\
+        O1 0.1 Uiso\nC1 0.25(2) ?\n";
+    let raw = cif2_file(input).unwrap();
+    let model = BlockModel::try_from(&raw.content[0]).unwrap();
+    assert_eq!(model.cell.length_a, Some(5.4321));
+    assert_eq!(model.cell.angle_beta, Some(100.0));
+    assert_eq!(
+        model.space_group.crystal_system,
+        Some(SpaceGroupCrystalSystem::Monoclinic)
+    );
+    let c1 = model.atom.find_atom_site("C1").unwrap();
+    assert_eq!(c1.fract_x, Some(0.25));
+    assert_eq!(c1.adp_type, None);
+    assert_eq!(
+        model.atom.find_atom_site("O1").unwrap().adp_type,
+        Some(AtomSiteAdpType::Uiso)
+    );
+
+    let block = model.to_block("test");
+    assert_eq!(BlockModel::try_from(&block.as_raw()), Ok(model.clone()));
+    let text = format!("#\\#CIF_2.0\n{block}");
+    let reparsed = cif2_file(&text).unwrap();
+    assert_eq!(BlockModel::try_from(&reparsed.content[0]), Ok(model));
+
+    let input = "#\\#CIF_2.0\ndata_test\n_cell.formula_units_z 2.5\n";
+    let raw = cif2_file(input).unwrap();
+    assert_eq!(
+        BlockModel::try_from(&raw.content[0]),
+        Err(ModelError::InvalidValue {
+            name: "_cell.formula_units_Z".to_string(),
+            value: "2.5".to_string(),
+            expected: "an integer".to_string(),
+        })
+    );
+
+    let input = "#\\#CIF_2.0\ndata_test\n_cell.metric_tensor [[1 0 0] [0 1 0]]\n";
+    let raw = cif2_file(input).unwrap();
+    assert_eq!(
+        BlockModel::try_from(&raw.content[0]),
+        Err(ModelError::Dimension {
+            name: "_cell.metric_tensor".to_string(),
+            expected: vec![3, 3],
+            found: Some(vec![2, 3]),
+        })
+    );
+}
+
+#[test]
+fn derive_category() {
+    use cif_chomper_core::model::{CifCategory, ModelError};
+    use cif_chomper_core::parser::cif2_file;
+    use cif_chomper_macros::CifCategory;
+
+    #[derive(Debug, PartialEq, CifCategory)]
+    #[cif(category = "atom_site", check)]
+    struct Site {
+        label: String,
+        #[cif(name = "fract_x")]
+        x: Option<f64>,
+        #[cif(name = "U_iso_or_equiv")]
+        u_iso: Option<f64>,
+        #[cif(skip)]
+        selected: bool,
+    }
+
+    #[derive(Debug, PartialEq, CifCategory)]
+    #[cif(category = "cell")]
+    struct CellLengths {
+        length_a: f64,
+        length_b: Option<f64>,
+    }
+
+    let input = "#\\#CIF_2.0\ndata_test\n_cell.length_a 5.0\n\
+        loop_\n_atom_site.label\n_atom_site.fract_x\nO1 0.1\nC1 ?\n";
+    let raw = cif2_file(input).unwrap();
+    let sites = Site::rows_from_block(&raw.content[0]).unwrap();
+    assert_eq!(
+        sites,
+        vec![
+            Site {
+                label: "O1".to_string(),
+                x: Some(0.1),
+                u_iso: None,
+                selected: false,
+            },
+            Site {
+                label: "C1".to_string(),
+                x: None,
+                u_iso: None,
+                selected: false,
+            },
+        ]
+    );
+    assert_eq!(
+        CellLengths::from_block(&raw.content[0]),
+        Ok(Some(CellLengths {
+            length_a: 5.0,
+            length_b: None,
+        }))
+    );
+
+    let mut items = Vec::new();
+    Site::write_rows(&sites, &mut items);
+    let block = cif_chomper_core::raw_model::DataBlock {
+        heading: "test".to_string(),
+        content: items,
+    };
+    assert_eq!(Site::rows_from_block(&block.as_raw()), Ok(sites));
+
+    let input = "#\\#CIF_2.0\ndata_test\n_cell.length_b 5.0\n";
+    let raw = cif2_file(input).unwrap();
+    assert_eq!(
+        CellLengths::from_block(&raw.content[0]),
+        Err(ModelError::Missing {
+            name: "_cell.length_a".to_string()
+        })
+    );
 }
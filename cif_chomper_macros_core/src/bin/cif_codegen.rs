@@ -0,0 +1,101 @@
+//! Generates the model `make_model!` would, as Rust source:
+//!
+//! `cif_codegen NAME [--dictionary FILE] [--imports DIR]... [--categories a,b] [--output FILE]`
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use cif_chomper_macros_core::codegen::ModelGenerator;
+
+const USAGE: &str = "usage: cif_codegen NAME [--dictionary FILE] [--imports DIR]... \
+    [--categories CATEGORY,...] [--output FILE]";
+
+fn parse_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(ModelGenerator, Option<PathBuf>), String> {
+    let mut args = args.into_iter();
+    let mut name = None;
+    let mut generator = ModelGenerator::new("");
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--dictionary" => generator.dictionary = Some(value()?.into()),
+            "--imports" => generator.imports.push(value()?.into()),
+            "--categories" => {
+                generator.categories = Some(value()?.split(',').map(str::to_string).collect())
+            }
+            "--output" => output = Some(value()?.into()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ if name.is_none() => name = Some(arg),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+    generator.name = name.ok_or("missing the model NAME")?;
+    Ok((generator, output))
+}
+
+fn main() -> ExitCode {
+    let (generator, output) = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = match output {
+        Some(path) => generator.write(path),
+        None => generator.generate().map(|source| print!("{source}")),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let (generator, output) = parse_args(args(
+            "PowModel --dictionary dicts/cif_pow.dic --imports dicts --categories cell,pd_calc --output src/pow.rs",
+        ))
+        .unwrap();
+        assert_eq!(generator.name, "PowModel");
+        assert_eq!(
+            generator.dictionary,
+            Some(PathBuf::from("dicts/cif_pow.dic"))
+        );
+        assert_eq!(generator.imports, vec![PathBuf::from("dicts")]);
+        assert_eq!(
+            generator.categories,
+            Some(vec!["cell".to_string(), "pd_calc".to_string()])
+        );
+        assert_eq!(output, Some(PathBuf::from("src/pow.rs")));
+    }
+
+    #[test]
+    fn test_parse_args_error() {
+        assert_eq!(parse_args(args("")).unwrap_err(), "missing the model NAME");
+        assert_eq!(
+            parse_args(args("A --output")).unwrap_err(),
+            "--output needs a value"
+        );
+        assert_eq!(
+            parse_args(args("A B")).unwrap_err(),
+            "unexpected argument B"
+        );
+        assert_eq!(
+            parse_args(args("A --dict x")).unwrap_err(),
+            "unknown option --dict"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+/// Reading generated structs from data blocks and writing them back, through
+/// `cif_chomper_core::model`
+use cif_chomper_core::dictionary::{CategoryClass, CategoryDefinition, Dictionary, ItemDefinition};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::docs::doc;
+use crate::naming::{field_ident, is_text, item_type, type_ident};
+
+/// The data names an item is read under: its own, then its aliases
+pub(crate) fn item_names(item: &ItemDefinition) -> Vec<&str> {
+    std::iter::once(item.name.as_str())
+        .chain(item.aliases.iter().map(String::as_str))
+        .collect()
+}
+
+/// `TryFrom<&RawDataBlock>` and `write_items` for a struct holding single
+/// items and the categories below it
+pub fn set_impls(
+    name: &Ident,
+    items: &[&ItemDefinition],
+    categories: &[&CategoryDefinition],
+) -> TokenStream {
+    let item_fields: Vec<_> = items.iter().map(|i| field_ident(&i.object_id)).collect();
+    let data_names: Vec<_> = items.iter().map(|i| &i.name).collect();
+    let read_items = items.iter().zip(&item_fields).map(|(item, field)| {
+        let names = item_names(item);
+        let data_name = &item.name;
+        quote!(#field: ::cif_chomper_core::model::single(__block, #data_name, &[#(#names),*])?)
+    });
+    let (read_categories, write_categories): (Vec<_>, Vec<_>) = categories
+        .iter()
+        .map(|category| {
+            let field = field_ident(&category.name);
+            let ty = type_ident(&category.name);
+            match category.class {
+                CategoryClass::Loop => (
+                    quote!(#field: <#ty as ::cif_chomper_core::model::CifCategory>::rows_from_block(__block)?),
+                    quote!(<#ty as ::cif_chomper_core::model::CifCategory>::write_rows(&self.#field, __items);),
+                ),
+                _ => (
+                    quote!(#field: <#ty as ::core::convert::TryFrom<_>>::try_from(__block)?),
+                    quote!(self.#field.write_items(__items);),
+                ),
+            }
+        })
+        .unzip();
+    quote! {
+        impl ::core::convert::TryFrom<&::cif_chomper_core::raw_model::RawDataBlock<'_>> for #name {
+            type Error = ::cif_chomper_core::model::ModelError;
+
+            fn try_from(
+                __block: &::cif_chomper_core::raw_model::RawDataBlock<'_>,
+            ) -> ::core::result::Result<Self, Self::Error> {
+                Ok(#name {
+                    #(#read_items,)*
+                    #(#read_categories,)*
+                })
+            }
+        }
+
+        impl #name {
+            /// Adds the items with values, then those of the categories below
+            pub fn write_items(
+                &self,
+                __items: &mut ::std::vec::Vec<::cif_chomper_core::raw_model::DataItem>,
+            ) {
+                #(
+                    ::cif_chomper_core::model::push_item(
+                        __items,
+                        #data_names,
+                        self.#item_fields
+                            .as_ref()
+                            .map(::cif_chomper_core::model::CifValue::to_content),
+                    );
+                )*
+                #(#write_categories)*
+            }
+        }
+    }
+}
+
+/// `CifCategory` for the packets of a Loop category
+pub fn loop_impls(
+    name: &Ident,
+    category: &CategoryDefinition,
+    items: &[&ItemDefinition],
+) -> TokenStream {
+    let fields: Vec<_> = items.iter().map(|i| field_ident(&i.object_id)).collect();
+    let data_names: Vec<_> = items.iter().map(|i| &i.name).collect();
+    let columns = items.iter().map(|item| {
+        let names = item_names(item);
+        quote!(::cif_chomper_core::model::column(__block, &[#(#names),*]))
+    });
+    let indices = 0..items.len();
+    let count = items.len();
+    let category_name = &category.name;
+    quote! {
+        impl ::cif_chomper_core::model::CifCategory for #name {
+            const CATEGORY: &'static str = #category_name;
+            const NAMES: &'static [&'static str] = &[#(#data_names),*];
+
+            fn rows_from_block(
+                __block: &::cif_chomper_core::raw_model::RawDataBlock,
+            ) -> ::core::result::Result<::std::vec::Vec<Self>, ::cif_chomper_core::model::ModelError> {
+                let __columns: [::core::option::Option<::std::vec::Vec<&::cif_chomper_core::raw_model::RawDataItemContent>>; #count] =
+                    [#(#columns),*];
+                let __count = ::cif_chomper_core::model::row_count(#category_name, &__columns)?;
+                #[allow(unused_mut)]
+                let mut __rows = ::std::vec![Self::default(); __count];
+                #(
+                    if let Some(__column) = &__columns[#indices] {
+                        for (__row, __value) in __rows.iter_mut().zip(__column) {
+                            __row.#fields = ::cif_chomper_core::model::parse_value(#data_names, __value)?;
+                        }
+                    }
+                )*
+                Ok(__rows)
+            }
+
+            fn write_rows(
+                __rows: &[Self],
+                __items: &mut ::std::vec::Vec<::cif_chomper_core::raw_model::DataItem>,
+            ) {
+                ::cif_chomper_core::model::push_loop(
+                    __items,
+                    ::std::vec![#(
+                        (
+                            #data_names,
+                            __rows
+                                .iter()
+                                .map(|__r| {
+                                    __r.#fields
+                                        .as_ref()
+                                        .map(::cif_chomper_core::model::CifValue::to_content)
+                                })
+                                .collect(),
+                        )
+                    ),*],
+                );
+            }
+        }
+    }
+}
+
+/// `find_<category>` methods looking up packets of the Loop categories held
+/// by a struct by their `_category_key.name` items
+pub fn key_lookups(dict: &Dictionary, categories: &[&CategoryDefinition]) -> TokenStream {
+    let lookups = categories.iter().filter_map(|category| {
+        if category.class != CategoryClass::Loop || category.keys.is_empty() {
+            return None;
+        }
+        let keys = category
+            .keys
+            .iter()
+            .map(|key| {
+                dict.item(key)
+                    .filter(|i| i.category_id.eq_ignore_ascii_case(&category.name))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let field = field_ident(&category.name);
+        let ty = type_ident(&category.name);
+        let method = format_ident!("find_{}", field.to_string().trim_start_matches("r#"));
+        let args: Vec<_> = keys.iter().map(|k| field_ident(&k.object_id)).collect();
+        let (params, tests): (Vec<_>, Vec<_>) = keys
+            .iter()
+            .zip(&args)
+            .map(|(key, arg)| {
+                if is_text(key) {
+                    (
+                        quote!(#arg: &str),
+                        quote!(__r.#arg.as_deref() == Some(#arg)),
+                    )
+                } else {
+                    let ty = item_type(key);
+                    (quote!(#arg: &#ty), quote!(__r.#arg.as_ref() == Some(#arg)))
+                }
+            })
+            .unzip();
+        let names: Vec<_> = keys.iter().map(|k| format!("`{}`", k.name)).collect();
+        let doc = doc(&format!(
+            "The packet of `{}` with the given {}",
+            category.name,
+            names.join(", ")
+        ));
+        Some(quote! {
+            #doc
+            pub fn #method(&self, #(#params),*) -> ::core::option::Option<&#ty> {
+                self.#field.iter().find(|__r| #(#tests)&&*)
+            }
+        })
+    });
+    quote!(#(#lookups)*)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::dict;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_item_names(dict: Dictionary) {
+        let adp_type = dict.item("_atom_site.adp_type").unwrap();
+        assert_eq!(
+            item_names(adp_type),
+            vec![
+                "_atom_site.adp_type",
+                "_atom_site_adp_type",
+                "_atom_site_thermal_displace_type"
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_key_lookups(dict: Dictionary) {
+        let atom_site = dict.category("atom_site").unwrap();
+        let tokens = key_lookups(&dict, &[atom_site]).to_string();
+        assert!(tokens.contains(
+            "pub fn find_atom_site (& self , label : & str) -> :: core :: option :: Option < & AtomSite >"
+        ));
+        let cell = dict.category("cell").unwrap();
+        assert!(key_lookups(&dict, &[cell]).is_empty());
+    }
+}
@@ -0,0 +1,225 @@
+/// Writes the model `make_model!` would generate as Rust source, so that it
+/// can be produced once from `build.rs` or checked in
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use cif_chomper_core::dictionary::{Dictionary, DictionaryError};
+use cif_chomper_core::import::DirectoryResolver;
+use quote::format_ident;
+
+use crate::{core_dictionary, model};
+
+#[derive(Debug)]
+pub enum CodegenError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Dictionary(DictionaryError),
+    UnknownCategory(String),
+    /// The model name is not a Rust identifier
+    InvalidName(String),
+    /// The generated tokens are not a valid file, e.g. from an item name
+    /// that cannot be made into an identifier
+    Syntax(String),
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            CodegenError::Dictionary(e) => write!(f, "cannot load the dictionary: {e}"),
+            CodegenError::UnknownCategory(c) => write!(f, "no category {c} in the dictionary"),
+            CodegenError::InvalidName(name) => write!(f, "{name} is not a Rust identifier"),
+            CodegenError::Syntax(e) => write!(f, "the generated model does not parse: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+impl From<DictionaryError> for CodegenError {
+    fn from(e: DictionaryError) -> Self {
+        CodegenError::Dictionary(e)
+    }
+}
+
+/// Canonical names of the given categories
+pub(crate) fn resolve_categories<'a>(
+    dict: &Dictionary,
+    categories: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<String>, String> {
+    categories
+        .into_iter()
+        .map(|c| {
+            dict.category(c)
+                .map(|definition| definition.name.clone())
+                .ok_or_else(|| c.to_string())
+        })
+        .collect()
+}
+
+/// Formatted source of the model of `dict`, limited to `categories` if given
+pub fn model_source(
+    dict: &Dictionary,
+    name: &str,
+    categories: Option<&[String]>,
+) -> Result<String, CodegenError> {
+    let ident = syn::parse_str::<syn::Ident>(name)
+        .map(|_| format_ident!("{}", name))
+        .map_err(|_| CodegenError::InvalidName(name.to_string()))?;
+    let categories = categories
+        .map(|c| resolve_categories(dict, c.iter().map(String::as_str)))
+        .transpose()
+        .map_err(CodegenError::UnknownCategory)?;
+    let tokens = model::generate_model(dict, &ident, categories.as_deref());
+    let file = syn::parse2::<syn::File>(tokens).map_err(|e| CodegenError::Syntax(e.to_string()))?;
+    Ok(prettyplease::unparse(&file))
+}
+
+/// A model to generate, with the same options as `make_model!` but with
+/// paths used as given
+#[derive(Debug, Clone)]
+pub struct ModelGenerator {
+    pub name: String,
+    /// The CIF core dictionary if absent
+    pub dictionary: Option<PathBuf>,
+    /// Directories searched for imported files before the dictionary's own
+    pub imports: Vec<PathBuf>,
+    pub categories: Option<Vec<String>>,
+}
+
+impl ModelGenerator {
+    pub fn new(name: impl Into<String>) -> Self {
+        ModelGenerator {
+            name: name.into(),
+            dictionary: None,
+            imports: Vec::new(),
+            categories: None,
+        }
+    }
+
+    fn load(&self) -> Result<Dictionary, CodegenError> {
+        let Some(path) = &self.dictionary else {
+            return Ok(core_dictionary()?);
+        };
+        let text = std::fs::read_to_string(path).map_err(|error| CodegenError::Io {
+            path: path.clone(),
+            error,
+        })?;
+        let search_path = self
+            .imports
+            .iter()
+            .cloned()
+            .chain(path.parent().map(Path::to_path_buf));
+        Ok(Dictionary::parse_with_imports(
+            &text,
+            &DirectoryResolver::new(search_path),
+        )?)
+    }
+
+    /// Formatted source of the model
+    pub fn generate(&self) -> Result<String, CodegenError> {
+        let dict = self.load()?;
+        let source = model_source(&dict, &self.name, self.categories.as_deref())?;
+        let from = match &self.dictionary {
+            Some(path) => path.display().to_string(),
+            None => "the CIF core dictionary".to_string(),
+        };
+        Ok(format!(
+            "// Generated by cif_chomper_macros_core from {from}; do not edit\n\n{source}"
+        ))
+    }
+
+    /// Writes the model to `out`, leaving the file untouched if it is
+    /// unchanged so that cargo does not rebuild needlessly
+    pub fn write(&self, out: impl AsRef<Path>) -> Result<(), CodegenError> {
+        let out = out.as_ref();
+        let source = self.generate()?;
+        if std::fs::read_to_string(out).is_ok_and(|existing| existing == source) {
+            return Ok(());
+        }
+        std::fs::write(out, source).map_err(|error| CodegenError::Io {
+            path: out.to_path_buf(),
+            error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{TEST_DIC, dict};
+    use crate::{ModelMacroInput, make_model_core};
+    use rstest::rstest;
+
+    #[test]
+    fn test_same_as_macro() {
+        let input: ModelMacroInput = syn::parse_str("CifCore, categories = [cell]").unwrap();
+        let from_macro = syn::parse2::<syn::File>(make_model_core(input)).unwrap();
+        let mut generator = ModelGenerator::new("CifCore");
+        generator.categories = Some(vec!["cell".to_string()]);
+        let source = generator.generate().unwrap();
+        assert!(source.starts_with("// Generated by cif_chomper_macros_core"));
+        assert_eq!(
+            source.split_once("\n\n").unwrap().1,
+            prettyplease::unparse(&from_macro)
+        );
+    }
+
+    #[rstest]
+    fn test_model_source(dict: Dictionary) {
+        let source = model_source(&dict, "TestModel", Some(&["CELL".to_string()])).unwrap();
+        assert!(source.contains(
+            "pub struct TestModel {\n    /// Items of the `CELL` category\n    pub cell: Cell,\n}"
+        ));
+        assert!(source.contains(
+            "    /// Length of the a axis.\n    ///\n    /// - Data name: `_cell.length_a`\n"
+        ));
+        assert!(source.contains("/// Derived with the dREL method of `_cell.volume`"));
+    }
+
+    #[rstest::rstest]
+    #[case("Test Model", None, "Test Model is not a Rust identifier")]
+    #[case(
+        "TestModel",
+        Some("nonesuch"),
+        "no category nonesuch in the dictionary"
+    )]
+    fn test_model_source_error(
+        dict: Dictionary,
+        #[case] name: &str,
+        #[case] category: Option<&str>,
+        #[case] message: &str,
+    ) {
+        let categories = category.map(|c| vec![c.to_string()]);
+        let error = model_source(&dict, name, categories.as_deref()).unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+
+    #[test]
+    fn test_write_unchanged() {
+        let dir = std::env::temp_dir().join(format!("cif_codegen_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dictionary = dir.join("test_dict.dic");
+        std::fs::write(&dictionary, TEST_DIC).unwrap();
+        let out = dir.join("model.rs");
+        let mut generator = ModelGenerator::new("TestModel");
+        generator.dictionary = Some(dictionary);
+        generator.categories = Some(vec!["cell".to_string()]);
+        generator.write(&out).unwrap();
+        let modified = std::fs::metadata(&out).unwrap().modified().unwrap();
+        generator.write(&out).unwrap();
+        assert_eq!(
+            std::fs::metadata(&out).unwrap().modified().unwrap(),
+            modified
+        );
+        assert!(
+            std::fs::read_to_string(&out)
+                .unwrap()
+                .contains("pub struct Cell")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,493 @@
+/// `#[derive(CifCategory)]`: a hand-written struct as the packets of a
+/// category, optionally checked against a dictionary
+use cif_chomper_core::dictionary::{ContentType, Dictionary, ItemDefinition, TypeContainer};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, GenericArgument, LitStr, PathArguments, Type};
+
+use crate::block::item_names;
+use crate::{load_dictionary, track_files};
+
+/// `#[cif(category = "...", check, dictionary = "...")]` on the struct
+struct CategoryAttr {
+    category: LitStr,
+    /// Check against a dictionary: `dictionary` if given, else the CIF core
+    check: bool,
+    dictionary: Option<LitStr>,
+}
+
+/// `#[cif(name = "...")]` or `#[cif(skip)]` on a field
+#[derive(Default)]
+struct FieldAttr {
+    name: Option<LitStr>,
+    skip: bool,
+}
+
+fn category_attr(input: &DeriveInput) -> Result<CategoryAttr, syn::Error> {
+    let mut category = None;
+    let mut check = false;
+    let mut dictionary = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("cif")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("category") {
+                category = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("check") {
+                check = true;
+            } else if meta.path.is_ident("dictionary") {
+                dictionary = Some(meta.value()?.parse()?);
+                check = true;
+            } else {
+                return Err(meta.error("expected `category`, `check` or `dictionary`"));
+            }
+            Ok(())
+        })?;
+    }
+    let category = category.ok_or_else(|| {
+        syn::Error::new(
+            input.ident.span(),
+            "missing #[cif(category = \"...\")] on the struct",
+        )
+    })?;
+    Ok(CategoryAttr {
+        category,
+        check,
+        dictionary,
+    })
+}
+
+fn field_attr(field: &syn::Field) -> Result<FieldAttr, syn::Error> {
+    let mut field_attr = FieldAttr::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("cif")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                field_attr.name = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("skip") {
+                field_attr.skip = true;
+            } else {
+                return Err(meta.error("expected `name` or `skip`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(field_attr)
+}
+
+/// `T` of `Option<T>`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Kinds of values a Rust type can hold, as far as can be told from its name
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Real,
+    Integer,
+    Text,
+    List,
+    Table,
+}
+
+/// The Rust type of `Vec<T>` or of the values of `BTreeMap<String, T>`
+fn element_type(segment: &syn::PathSegment) -> Option<&Type> {
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.last()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn rust_kind(ty: &Type) -> Option<Kind> {
+    match ty {
+        Type::Array(_) => Some(Kind::List),
+        Type::Path(path) => match path.path.segments.last()?.ident.to_string().as_str() {
+            "f64" | "Measurement" => Some(Kind::Real),
+            "i64" => Some(Kind::Integer),
+            "String" => Some(Kind::Text),
+            "Vec" => Some(Kind::List),
+            "BTreeMap" => Some(Kind::Table),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Why a type that is known not to implement `CifValue` cannot be used, so
+/// the user is not left with an error about a missing trait bound
+fn unsupported_type(ty: &Type) -> Option<String> {
+    let segment = match ty {
+        Type::Array(array) => return unsupported_type(&array.elem),
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    let ident = segment.ident.to_string();
+    let instead = match ident.as_str() {
+        "f32" => "f64",
+        "i32" | "i16" | "i8" | "u64" | "u32" | "u16" | "u8" | "isize" | "usize" => "i64",
+        "HashMap" => "BTreeMap<String, _>",
+        "Vec" | "BTreeMap" => return unsupported_type(element_type(segment)?),
+        _ => return None,
+    };
+    Some(format!("{ident} cannot hold CIF values, use {instead}"))
+}
+
+/// Why values of the item cannot be held by the Rust type, if they cannot
+fn type_mismatch(item: &ItemDefinition, ty: &Type) -> Option<String> {
+    let kind = rust_kind(ty)?;
+    let contents = item.contents.content_type();
+    let compatible = match item.container {
+        TypeContainer::List | TypeContainer::Array | TypeContainer::Matrix => kind == Kind::List,
+        TypeContainer::Table => kind == Kind::Table,
+        _ => match kind {
+            Kind::Real => matches!(
+                contents,
+                Some(
+                    ContentType::Real
+                        | ContentType::Integer
+                        | ContentType::Count
+                        | ContentType::Index
+                )
+            ),
+            Kind::Integer => matches!(
+                contents,
+                Some(ContentType::Integer | ContentType::Count | ContentType::Index)
+            ),
+            Kind::Text => true,
+            Kind::List | Kind::Table => false,
+        },
+    };
+    if compatible {
+        return None;
+    }
+    let contents = contents.map_or("Implied".to_string(), |c| c.to_string());
+    Some(format!(
+        "{} is a {} of {contents} in the dictionary",
+        item.name, item.container
+    ))
+}
+
+struct CifField<'f> {
+    ident: &'f syn::Ident,
+    ty: &'f Type,
+    /// Data name, absent for skipped fields
+    data_name: Option<String>,
+    /// Other names the value is read under, from the dictionary
+    aliases: Vec<String>,
+    optional: bool,
+    span: Span,
+}
+
+/// The type a field holds, without its `Option`
+fn value_type<'f>(field: &CifField<'f>) -> &'f Type {
+    match field.optional {
+        true => option_inner(field.ty).unwrap_or(field.ty),
+        false => field.ty,
+    }
+}
+
+fn combine(errors: Vec<syn::Error>) -> Result<(), syn::Error> {
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        None => Ok(()),
+        Some(mut first) => {
+            first.extend(errors);
+            Err(first)
+        }
+    }
+}
+
+/// Check the fields against the dictionary, and add the aliases of their items
+fn check_fields(
+    dict: &Dictionary,
+    category: &LitStr,
+    fields: &mut [CifField],
+) -> Result<(), syn::Error> {
+    if dict.category(&category.value()).is_none() {
+        return Err(syn::Error::new(
+            category.span(),
+            format!("no category {} in the dictionary", category.value()),
+        ));
+    }
+    let mut errors = Vec::new();
+    for field in fields {
+        let Some(data_name) = &field.data_name else {
+            continue;
+        };
+        let ty = value_type(field);
+        match dict.item(data_name) {
+            None => errors.push(syn::Error::new(
+                field.span,
+                format!("no item {data_name} in the dictionary"),
+            )),
+            Some(item) => {
+                if let Some(message) = type_mismatch(item, ty) {
+                    errors.push(syn::Error::new(ty.span(), message));
+                }
+                field.aliases = item_names(item)
+                    .into_iter()
+                    .filter(|name| !name.eq_ignore_ascii_case(data_name))
+                    .map(str::to_string)
+                    .collect();
+            }
+        }
+    }
+    combine(errors)
+}
+
+fn derive(input: &DeriveInput) -> Result<TokenStream, syn::Error> {
+    let attr = category_attr(input)?;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "CifCategory can only be derived for structs",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new(
+            data.fields.span(),
+            "CifCategory can only be derived for structs with named fields",
+        ));
+    };
+    let category = attr.category.value();
+    let mut fields = Vec::new();
+    for field in &named.named {
+        let field_attr = field_attr(field)?;
+        let ident = field.ident.as_ref().expect("named fields have idents");
+        let object = match &field_attr.name {
+            Some(name) => name.value(),
+            None => ident.to_string().trim_start_matches("r#").to_string(),
+        };
+        fields.push(CifField {
+            ident,
+            ty: &field.ty,
+            data_name: (!field_attr.skip).then(|| format!("_{category}.{object}")),
+            aliases: Vec::new(),
+            optional: option_inner(&field.ty).is_some(),
+            span: field_attr.name.as_ref().map_or(ident.span(), LitStr::span),
+        });
+    }
+    combine(
+        fields
+            .iter()
+            .filter(|f| f.data_name.is_some())
+            .filter_map(|f| {
+                let ty = value_type(f);
+                unsupported_type(ty).map(|message| syn::Error::new(ty.span(), message))
+            })
+            .collect(),
+    )?;
+    let mut tracked = TokenStream::new();
+    if attr.check {
+        let (dict, files) = load_dictionary(attr.dictionary.as_ref(), &[], attr.category.span())?;
+        check_fields(&dict, &attr.category, &mut fields)?;
+        tracked = track_files(&files);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let read: Vec<_> = fields.iter().filter(|f| f.data_name.is_some()).collect();
+    let data_names: Vec<_> = read.iter().map(|f| f.data_name.as_deref()).collect();
+    let column_names = read.iter().map(|f| {
+        let names = f.data_name.iter().chain(&f.aliases);
+        quote!(&[#(#names),*])
+    });
+    let count = read.len();
+    let indices = 0..count;
+    let values = read.iter().zip(indices).map(|(field, i)| {
+        let ident = field.ident;
+        let data_name = &field.data_name;
+        let getter = if field.optional {
+            quote!(optional_value)
+        } else {
+            quote!(required_value)
+        };
+        quote!(#ident: ::cif_chomper_core::model::#getter(#data_name, &__columns[#i], __row)?)
+    });
+    let skipped = fields.iter().filter(|f| f.data_name.is_none()).map(|f| {
+        let ident = f.ident;
+        quote!(#ident: ::core::default::Default::default())
+    });
+    let columns = read.iter().map(|field| {
+        let ident = field.ident;
+        let data_name = &field.data_name;
+        let value = if field.optional {
+            quote!(__r.#ident.as_ref().map(::cif_chomper_core::model::CifValue::to_content))
+        } else {
+            quote!(Some(::cif_chomper_core::model::CifValue::to_content(&__r.#ident)))
+        };
+        quote!((#data_name, __rows.iter().map(|__r| #value).collect()))
+    });
+    Ok(quote! {
+        impl #impl_generics ::cif_chomper_core::model::CifCategory for #name #ty_generics #where_clause {
+            const CATEGORY: &'static str = #category;
+            const NAMES: &'static [&'static str] = &[#(#data_names),*];
+
+            fn rows_from_block(
+                __block: &::cif_chomper_core::raw_model::RawDataBlock,
+            ) -> ::core::result::Result<::std::vec::Vec<Self>, ::cif_chomper_core::model::ModelError> {
+                let __columns: [::core::option::Option<::std::vec::Vec<&::cif_chomper_core::raw_model::RawDataItemContent>>; #count] =
+                    [#(::cif_chomper_core::model::column(__block, #column_names)),*];
+                let __count = ::cif_chomper_core::model::row_count(#category, &__columns)?;
+                (0..__count)
+                    .map(|__row| {
+                        Ok(#name {
+                            #(#values,)*
+                            #(#skipped,)*
+                        })
+                    })
+                    .collect()
+            }
+
+            fn write_rows(
+                __rows: &[Self],
+                __items: &mut ::std::vec::Vec<::cif_chomper_core::raw_model::DataItem>,
+            ) {
+                ::cif_chomper_core::model::push_loop(__items, ::std::vec![#(#columns),*]);
+            }
+        }
+
+        #tracked
+    })
+}
+
+pub fn derive_cif_category(input: DeriveInput) -> TokenStream {
+    derive(&input).unwrap_or_else(|e| e.to_compile_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::dict;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("_cell.length_a", "f64", None)]
+    #[case("_cell.length_a", "String", None)]
+    #[case("_cell.formula_units_z", "f64", None)]
+    #[case(
+        "_cell.length_a",
+        "i64",
+        Some("_cell.length_a is a Single of Real in the dictionary")
+    )]
+    #[case(
+        "_atom_site.fract_xyz",
+        "f64",
+        Some("_atom_site.fract_xyz is a Matrix of Real in the dictionary")
+    )]
+    #[case("_atom_site.fract_xyz", "[f64; 3]", None)]
+    #[case("_atom_site.label", "MyLabel", None)]
+    fn test_type_mismatch(
+        dict: Dictionary,
+        #[case] item: &str,
+        #[case] ty: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let ty: Type = syn::parse_str(ty).unwrap();
+        assert_eq!(
+            type_mismatch(dict.item(item).unwrap(), &ty).as_deref(),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("f64", None)]
+    #[case("Measurement", None)]
+    #[case("[Vec<i64>; 3]", None)]
+    #[case("MyLabel", None)]
+    #[case("[usize; 3]", Some("usize cannot hold CIF values, use i64"))]
+    #[case("BTreeMap<String, f32>", Some("f32 cannot hold CIF values, use f64"))]
+    fn test_unsupported_type(#[case] ty: &str, #[case] expected: Option<&str>) {
+        let ty: Type = syn::parse_str(ty).unwrap();
+        assert_eq!(unsupported_type(&ty).as_deref(), expected);
+    }
+
+    #[rstest]
+    fn test_check_fields_aliases(dict: Dictionary) {
+        let ident: syn::Ident = syn::parse_str("length_a").unwrap();
+        let ty: Type = syn::parse_str("f64").unwrap();
+        let mut fields = [CifField {
+            ident: &ident,
+            ty: &ty,
+            data_name: Some("_cell.length_a".to_string()),
+            aliases: Vec::new(),
+            optional: false,
+            span: Span::call_site(),
+        }];
+        let category = LitStr::new("cell", Span::call_site());
+        check_fields(&dict, &category, &mut fields).unwrap();
+        assert_eq!(fields[0].aliases, ["_cell_length_a"]);
+    }
+
+    #[test]
+    fn test_derive() {
+        let input: DeriveInput = syn::parse_str(
+            r#"#[cif(category = "atom_site")]
+            struct Site {
+                label: String,
+                #[cif(name = "fract_x")]
+                x: Option<f64>,
+                #[cif(skip)]
+                note: String,
+            }"#,
+        )
+        .unwrap();
+        let tokens = derive(&input).unwrap().to_string();
+        assert!(tokens.contains("\"_atom_site.label\" , \"_atom_site.fract_x\""));
+        assert!(tokens.contains("label : :: cif_chomper_core :: model :: required_value"));
+        assert!(tokens.contains("x : :: cif_chomper_core :: model :: optional_value"));
+        assert!(tokens.contains("note : :: core :: default :: Default :: default ()"));
+    }
+
+    #[rstest]
+    #[case(
+        "struct Site { label: String }",
+        "missing #[cif(category = \"...\")] on the struct"
+    )]
+    #[case(
+        "#[cif(category = \"atom_site\")] struct Site(String);",
+        "CifCategory can only be derived for structs with named fields"
+    )]
+    #[case(
+        "#[cif(category = \"atom_site\", check)] struct Site { #[cif(name = \"fract_q\")] q: f64 }",
+        "no item _atom_site.fract_q in the dictionary"
+    )]
+    #[case(
+        "#[cif(category = \"atom_site\", check)] struct Site { fract_x: i64 }",
+        "_atom_site.fract_x is a Single of Real in the dictionary"
+    )]
+    #[case(
+        "#[cif(categories = \"x\")] struct Site {}",
+        "expected `category`, `check` or `dictionary`"
+    )]
+    #[case(
+        "#[cif(category = \"atom_site\")] struct Site { fract_x: f32 }",
+        "f32 cannot hold CIF values, use f64"
+    )]
+    #[case(
+        "#[cif(category = \"atom_site\")] struct Site { ids: Option<Vec<u8>> }",
+        "u8 cannot hold CIF values, use i64"
+    )]
+    #[case(
+        "#[cif(category = \"atom_site\")] struct Site { t: HashMap<String, f64> }",
+        "HashMap cannot hold CIF values, use BTreeMap<String, _>"
+    )]
+    fn test_derive_error(#[case] input: &str, #[case] message: &str) {
+        let input: DeriveInput = syn::parse_str(input).unwrap();
+        assert_eq!(derive(&input).unwrap_err().to_string(), message);
+    }
+}
@@ -0,0 +1,187 @@
+/// Rustdoc for generated code, taken from the dictionary definitions so that
+/// `cargo doc` on a model reads as a reference to the dictionary
+use cif_chomper_core::dictionary::{CategoryClass, CategoryDefinition, Dictionary, ItemDefinition};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// `#[doc]` attributes for the text, one per line as `///` comments give
+pub fn doc(text: &str) -> TokenStream {
+    let lines = text.lines().map(|line| match line.trim_end() {
+        "" => String::new(),
+        line => format!(" {line}"),
+    });
+    quote!(#(#[doc = #lines])*)
+}
+
+/// Dictionary text as markdown: the indentation of the definition is removed,
+/// so that it is not read as code, and characters rustdoc would take for
+/// links or HTML are escaped
+pub fn markdown(text: &str) -> String {
+    let indent = text
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = match i {
+                0 => line.trim_start(),
+                _ => line.get(indent..).unwrap_or_else(|| line.trim_start()),
+            };
+            let mut escaped = String::with_capacity(line.len());
+            for c in line.chars() {
+                if "\\[]<>*`".contains(c) {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            escaped
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `description` of an item or category, for the first paragraph of its docs
+pub fn description(description: Option<&str>) -> Option<String> {
+    description
+        .map(markdown)
+        .filter(|text| !text.trim().is_empty())
+}
+
+/// Inline code span for a value, with a fence long enough for its backticks
+fn code(value: &str) -> String {
+    let mut fence = "`".to_string();
+    while value.contains(fence.as_str()) {
+        fence.push('`');
+    }
+    let pad = if value.starts_with('`') || value.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{fence}{pad}{value}{pad}{fence}")
+}
+
+fn join_code<'a>(values: impl IntoIterator<Item = &'a String>) -> String {
+    values
+        .into_iter()
+        .map(|v| code(v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The description of an item followed by its data name, units, range,
+/// default, aliases and examples
+pub fn item_doc(item: &ItemDefinition) -> String {
+    let mut lines = vec![format!("- Data name: {}", code(&item.name))];
+    if let Some(units) = &item.units {
+        lines.push(format!("- Units: {}", code(units)));
+    }
+    if let Some(range) = &item.enumeration.range {
+        lines.push(format!("- Range: {}", code(&range.to_string())));
+    }
+    if let Some(default) = &item.enumeration.default {
+        lines.push(format!("- Default: {}", code(default)));
+    }
+    if !item.aliases.is_empty() {
+        lines.push(format!("- Aliases: {}", join_code(&item.aliases)));
+    }
+    let mut text = description(item.description.as_deref())
+        .map(|d| format!("{d}\n\n"))
+        .unwrap_or_default();
+    text.push_str(&lines.join("\n"));
+    let (inline, blocks): (Vec<_>, Vec<_>) = item.examples.iter().partition(|e| !e.contains('\n'));
+    if !inline.is_empty() {
+        text.push_str(&format!("\n- Examples: {}", join_code(inline)));
+    }
+    for example in blocks {
+        text.push_str(&format!("\n\nExample:\n\n```text\n{example}\n```"));
+    }
+    text
+}
+
+/// The description of a category followed by its name, class and keys
+pub fn category_doc(category: &CategoryDefinition) -> String {
+    let mut lines = vec![format!(
+        "- Category: {} ({:?})",
+        code(&category.name),
+        category.class
+    )];
+    if category.class == CategoryClass::Loop && !category.keys.is_empty() {
+        lines.push(format!("- Keys: {}", join_code(&category.keys)));
+    }
+    let mut text = description(category.description.as_deref())
+        .map(|d| format!("{d}\n\n"))
+        .unwrap_or_default();
+    text.push_str(&lines.join("\n"));
+    text
+}
+
+/// The description of the dictionary followed by its title and version
+pub fn dictionary_doc(dict: &Dictionary) -> String {
+    let mut lines = vec![format!("- Dictionary: {}", code(&dict.title))];
+    if let Some(version) = &dict.version {
+        lines.push(format!("- Version: {}", code(version)));
+    }
+    let mut text = description(dict.description.as_deref())
+        .map(|d| format!("{d}\n\n"))
+        .unwrap_or_default();
+    text.push_str(&lines.join("\n"));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::dict;
+    use rstest::rstest;
+
+    #[test]
+    fn test_doc() {
+        assert_eq!(
+            doc("First line\n\nSecond").to_string(),
+            "# [doc = \" First line\"] # [doc = \"\"] # [doc = \" Second\"]"
+        );
+    }
+
+    #[rstest]
+    #[case("One line", "One line")]
+    #[case("First\n    second\n\n      indented", "First\nsecond\n\n  indented")]
+    #[case("See [1] and <b> *x*", "See \\[1\\] and \\<b\\> \\*x\\*")]
+    fn test_markdown(#[case] text: &str, #[case] expected: &str) {
+        assert_eq!(markdown(text), expected);
+    }
+
+    #[rstest]
+    #[case("P 1", "`P 1`")]
+    #[case("a`b", "``a`b``")]
+    #[case("`a", "`` `a ``")]
+    fn test_code(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(code(value), expected);
+    }
+
+    #[rstest]
+    fn test_item_doc(dict: Dictionary) {
+        let mut length_a = dict.item("_cell.length_a").unwrap().clone();
+        length_a.examples = vec!["5.4321".to_string(), "a\nb".to_string()];
+        assert_eq!(
+            item_doc(&length_a),
+            "Length of the a axis.\n\n\
+             - Data name: `_cell.length_a`\n\
+             - Units: `angstroms`\n\
+             - Range: `1:`\n\
+             - Aliases: `_cell_length_a`\n\
+             - Examples: `5.4321`\n\n\
+             Example:\n\n```text\na\nb\n```"
+        );
+    }
+
+    #[rstest]
+    fn test_category_doc(dict: Dictionary) {
+        let atom_site = category_doc(dict.category("atom_site").unwrap());
+        assert!(atom_site.ends_with("- Category: `ATOM_SITE` (Loop)\n- Keys: `_atom_site.label`"));
+    }
+}
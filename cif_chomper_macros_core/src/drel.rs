@@ -0,0 +1,779 @@
+/// Compiles dREL Evaluation methods into Rust methods on the generated
+/// category structs. Numbers are computed as `f64` and the methods return
+/// `None` when an item they need is absent and cannot itself be derived.
+use std::collections::HashMap;
+use std::fmt;
+
+use cif_chomper_core::dictionary::{
+    CategoryDefinition, ContentType, Dictionary, ItemDefinition, MethodPurpose, TypeContainer,
+};
+use cif_chomper_core::drel::ast::{AssignOp, BinaryOp, Expr, Stmt, UnaryOp};
+use cif_chomper_core::drel::parse_method;
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::docs::doc;
+use crate::enums::is_enumerated;
+use crate::naming::field_ident;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodError {
+    /// `_definition.id` of the item whose method failed
+    pub item: String,
+    pub reason: String,
+}
+
+impl fmt::Display for MethodError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cannot compile the dREL method of {}: {}",
+            self.item, self.reason
+        )
+    }
+}
+
+impl std::error::Error for MethodError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Ty {
+    Real,
+    Int,
+    Bool,
+    Str,
+    Array(Box<Ty>, usize),
+}
+
+impl Ty {
+    fn tokens(&self) -> TokenStream {
+        match self {
+            Ty::Real => quote!(f64),
+            Ty::Int => quote!(i64),
+            Ty::Bool => quote!(bool),
+            Ty::Str => quote!(::std::string::String),
+            Ty::Array(inner, n) => {
+                let inner = inner.tokens();
+                quote!([#inner; #n])
+            }
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Ty::Real | Ty::Int)
+    }
+}
+
+/// Type of an item value in compiled methods, `None` for values they cannot use
+fn value_type(item: &ItemDefinition) -> Option<Ty> {
+    let scalar = match item.contents.content_type()? {
+        ContentType::Real => Ty::Real,
+        ContentType::Integer | ContentType::Count | ContentType::Index => Ty::Int,
+        ContentType::Implied | ContentType::ByReference => return None,
+        _ => Ty::Str,
+    };
+    match item.container {
+        TypeContainer::Single => Some(scalar),
+        TypeContainer::List | TypeContainer::Array | TypeContainer::Matrix => Some(
+            item.dimensions()?
+                .into_iter()
+                .rev()
+                .fold(scalar, |inner, n| Ty::Array(Box::new(inner), n)),
+        ),
+        _ => None,
+    }
+}
+
+fn evaluation_method(item: &ItemDefinition) -> Option<&str> {
+    item.methods
+        .iter()
+        .find(|m| m.purpose == MethodPurpose::Evaluation)
+        .map(|m| m.expression.as_str())
+}
+
+type Typed = (TokenStream, Ty);
+
+struct Translator<'d> {
+    dict: &'d Dictionary,
+    category: &'d CategoryDefinition,
+    target: &'d ItemDefinition,
+    /// Names bound to the method's own category with `With`
+    aliases: Vec<String>,
+    /// Local variables in each enclosing block
+    scopes: Vec<HashMap<String, Ty>>,
+    /// Depth of enclosing Rust loops, for `Break` and `Next`
+    loops: usize,
+}
+
+fn unsupported<T>(what: impl Into<String>) -> Result<T, String> {
+    Err(what.into())
+}
+
+fn as_real((tokens, ty): Typed) -> Result<TokenStream, String> {
+    match ty {
+        Ty::Real => Ok(tokens),
+        Ty::Int => Ok(quote!((#tokens as f64))),
+        other => unsupported(format!("{other:?} used as a number")),
+    }
+}
+
+/// Convert a value to the type of a destination, e.g. integer literals in a
+/// list assigned to a Real matrix
+fn coerce((tokens, ty): Typed, to: &Ty) -> Result<TokenStream, String> {
+    if &ty == to {
+        return Ok(tokens);
+    }
+    match (&ty, to) {
+        (Ty::Int, Ty::Real) => Ok(quote!((#tokens as f64))),
+        (Ty::Real, Ty::Int) => Ok(quote!(((#tokens).round() as i64))),
+        (Ty::Array(from, n), Ty::Array(to_inner, m)) if n == m => {
+            let inner = coerce((quote!(__x), (**from).clone()), to_inner)?;
+            Ok(quote!((#tokens).map(|__x| #inner)))
+        }
+        _ => unsupported(format!("{ty:?} where {to:?} is expected")),
+    }
+}
+
+/// Common type of two operands, promoting integers to reals
+fn unify(a: &Ty, b: &Ty) -> Option<Ty> {
+    match (a, b) {
+        _ if a == b => Some(a.clone()),
+        (Ty::Int, Ty::Real) | (Ty::Real, Ty::Int) => Some(Ty::Real),
+        (Ty::Array(x, n), Ty::Array(y, m)) if n == m => Some(Ty::Array(Box::new(unify(x, y)?), *n)),
+        _ => None,
+    }
+}
+
+fn arithmetic(op: BinaryOp, lhs: Typed, rhs: Typed) -> Result<Typed, String> {
+    use BinaryOp::*;
+    match (&lhs.1, &rhs.1) {
+        (Ty::Int, Ty::Int) if matches!(op, Add | Sub | Mul) => {
+            let (l, r) = (lhs.0, rhs.0);
+            let tokens = match op {
+                Add => quote!((#l + #r)),
+                Sub => quote!((#l - #r)),
+                _ => quote!((#l * #r)),
+            };
+            Ok((tokens, Ty::Int))
+        }
+        (a, b) if a.is_number() && b.is_number() => {
+            let (l, r) = (as_real(lhs)?, as_real(rhs.clone())?);
+            let tokens = match op {
+                Add => quote!((#l + #r)),
+                Sub => quote!((#l - #r)),
+                Mul => quote!((#l * #r)),
+                Div => quote!((#l / #r)),
+                Pow if rhs.1 == Ty::Int => {
+                    let r = rhs.0;
+                    quote!((#l).powi(#r as i32))
+                }
+                Pow => quote!((#l).powf(#r)),
+                _ => return unsupported(format!("{op:?} of numbers")),
+            };
+            Ok((tokens, Ty::Real))
+        }
+        (Ty::Array(x, n), Ty::Array(y, m)) if matches!(op, Add | Sub) && n == m => {
+            let inner = arithmetic(
+                op,
+                (quote!(__l[__i]), (**x).clone()),
+                (quote!(__r[__i]), (**y).clone()),
+            )?;
+            Ok(elementwise(lhs.0, rhs.0, inner, *n))
+        }
+        (Ty::Array(x, n), Ty::Array(y, m)) if op == Mul => {
+            let (l, r) = (lhs.0, rhs.0);
+            match (x.as_ref(), y.as_ref()) {
+                (a, b) if a.is_number() && b.is_number() && n == m => {
+                    let products = arithmetic(
+                        Mul,
+                        (quote!(__l[__i]), a.clone()),
+                        (quote!(__r[__i]), b.clone()),
+                    )?;
+                    let sum = as_real(products)?;
+                    Ok((
+                        quote!({ let __l = #l; let __r = #r; (0..#n).map(|__i| #sum).sum::<f64>() }),
+                        Ty::Real,
+                    ))
+                }
+                (Ty::Array(row, k), b) if b.is_number() && k == m => {
+                    let dot = arithmetic(
+                        Mul,
+                        (quote!(__row), Ty::Array(row.clone(), *k)),
+                        (quote!(__r), rhs.1.clone()),
+                    )?;
+                    let dot = dot.0;
+                    Ok((
+                        quote!({ let __l = #l; let __r = #r; __l.map(|__row| #dot) }),
+                        Ty::Array(Box::new(Ty::Real), *n),
+                    ))
+                }
+                (Ty::Array(a, k), Ty::Array(b, p)) if k == m && a.is_number() && b.is_number() => {
+                    let tokens = quote!({
+                        let __l = #l;
+                        let __r = #r;
+                        ::core::array::from_fn::<_, #n, _>(|__i| {
+                            ::core::array::from_fn::<_, #p, _>(|__j| {
+                                (0..#k).map(|__k| __l[__i][__k] as f64 * __r[__k][__j] as f64).sum::<f64>()
+                            })
+                        })
+                    });
+                    Ok((
+                        tokens,
+                        Ty::Array(Box::new(Ty::Array(Box::new(Ty::Real), *p)), *n),
+                    ))
+                }
+                _ => unsupported("product of incompatible arrays"),
+            }
+        }
+        (Ty::Array(x, n), Ty::Array(y, 3))
+            if op == Cross && *n == 3 && x.is_number() && y.is_number() =>
+        {
+            let (l, r) = (lhs.0, rhs.0);
+            Ok((
+                quote!({
+                    let __l = (#l).map(|__x| __x as f64);
+                    let __r = (#r).map(|__x| __x as f64);
+                    [
+                        __l[1] * __r[2] - __l[2] * __r[1],
+                        __l[2] * __r[0] - __l[0] * __r[2],
+                        __l[0] * __r[1] - __l[1] * __r[0],
+                    ]
+                }),
+                Ty::Array(Box::new(Ty::Real), 3),
+            ))
+        }
+        (Ty::Array(x, n), b) if b.is_number() && matches!(op, Add | Sub | Mul | Div) => {
+            let inner = arithmetic(
+                op,
+                (quote!(__l[__i]), (**x).clone()),
+                (quote!(__r), b.clone()),
+            )?;
+            Ok(elementwise(lhs.0, rhs.0, inner, *n))
+        }
+        (a, Ty::Array(y, n)) if a.is_number() && matches!(op, Add | Mul) => {
+            let inner = arithmetic(
+                op,
+                (quote!(__l), a.clone()),
+                (quote!(__r[__i]), (**y).clone()),
+            )?;
+            Ok(elementwise(lhs.0, rhs.0, inner, *n))
+        }
+        (a, b) => unsupported(format!("{op:?} of {a:?} and {b:?}")),
+    }
+}
+
+/// An array built element by element from `__l`, `__r` and the index `__i`
+fn elementwise(l: TokenStream, r: TokenStream, (inner, ty): Typed, n: usize) -> Typed {
+    (
+        quote!({
+            let __l = #l;
+            let __r = #r;
+            ::core::array::from_fn::<_, #n, _>(|__i| #inner)
+        }),
+        Ty::Array(Box::new(ty), n),
+    )
+}
+
+fn comparison(op: BinaryOp, lhs: Typed, rhs: Typed) -> Result<Typed, String> {
+    let (l, r) = match (&lhs.1, &rhs.1) {
+        (Ty::Int, Ty::Int) => (lhs.0, rhs.0),
+        (a, b) if a.is_number() && b.is_number() => (as_real(lhs)?, as_real(rhs)?),
+        (Ty::Str, Ty::Str) if matches!(op, BinaryOp::Eq | BinaryOp::Ne) => (lhs.0, rhs.0),
+        (a, b) => return unsupported(format!("comparison of {a:?} and {b:?}")),
+    };
+    let tokens = match op {
+        BinaryOp::Eq => quote!((#l == #r)),
+        BinaryOp::Ne => quote!((#l != #r)),
+        BinaryOp::Lt => quote!((#l < #r)),
+        BinaryOp::Le => quote!((#l <= #r)),
+        BinaryOp::Gt => quote!((#l > #r)),
+        _ => quote!((#l >= #r)),
+    };
+    Ok((tokens, Ty::Bool))
+}
+
+fn call(name: &str, args: Vec<Typed>) -> Result<Typed, String> {
+    let real_fn = |body: fn(TokenStream) -> TokenStream| -> Result<Typed, String> {
+        match args.as_slice() {
+            [arg] => Ok((body(as_real(arg.clone())?), Ty::Real)),
+            _ => unsupported(format!("{name} with {} arguments", args.len())),
+        }
+    };
+    match name {
+        "sqrt" => real_fn(|x| quote!((#x).sqrt())),
+        "exp" => real_fn(|x| quote!((#x).exp())),
+        "log" => real_fn(|x| quote!((#x).ln())),
+        "log10" => real_fn(|x| quote!((#x).log10())),
+        "abs" => real_fn(|x| quote!((#x).abs())),
+        "sin" => real_fn(|x| quote!((#x).sin())),
+        "cos" => real_fn(|x| quote!((#x).cos())),
+        "tan" => real_fn(|x| quote!((#x).tan())),
+        "asin" => real_fn(|x| quote!((#x).asin())),
+        "acos" => real_fn(|x| quote!((#x).acos())),
+        "atan" => real_fn(|x| quote!((#x).atan())),
+        "sind" => real_fn(|x| quote!((#x).to_radians().sin())),
+        "cosd" => real_fn(|x| quote!((#x).to_radians().cos())),
+        "tand" => real_fn(|x| quote!((#x).to_radians().tan())),
+        "asind" => real_fn(|x| quote!((#x).asin().to_degrees())),
+        "acosd" => real_fn(|x| quote!((#x).acos().to_degrees())),
+        "atand" => real_fn(|x| quote!((#x).atan().to_degrees())),
+        "float" | "real" => real_fn(|x| x),
+        "int" => match args.as_slice() {
+            [arg] => {
+                let x = as_real(arg.clone())?;
+                Ok((quote!(((#x).trunc() as i64)), Ty::Int))
+            }
+            _ => unsupported("int with several arguments"),
+        },
+        "atan2" | "atan2d" | "mod" | "min" | "max" => match args.as_slice() {
+            [y, x] => {
+                let (y, x) = (as_real(y.clone())?, as_real(x.clone())?);
+                let tokens = match name {
+                    "atan2" => quote!((#y).atan2(#x)),
+                    "atan2d" => quote!((#y).atan2(#x).to_degrees()),
+                    "mod" => quote!((#y).rem_euclid(#x)),
+                    "min" => quote!((#y).min(#x)),
+                    _ => quote!((#y).max(#x)),
+                };
+                Ok((tokens, Ty::Real))
+            }
+            _ => unsupported(format!("{name} without two arguments")),
+        },
+        "len" => match args.as_slice() {
+            [(_, Ty::Array(_, n))] => {
+                let n = Literal::i64_suffixed(*n as i64);
+                Ok((quote!(#n), Ty::Int))
+            }
+            _ => unsupported("len of a value that is not an array"),
+        },
+        "norm" => match args.as_slice() {
+            [(v, Ty::Array(inner, _))] if inner.is_number() => Ok((
+                quote!((#v).iter().map(|&__x| (__x as f64).powi(2)).sum::<f64>().sqrt()),
+                Ty::Real,
+            )),
+            _ => unsupported("norm of a value that is not a vector"),
+        },
+        _ => unsupported(format!("function {name}")),
+    }
+}
+
+impl<'d> Translator<'d> {
+    fn variable(&self, name: &str) -> Option<&Ty> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// The item of this category named by an attribute or data name
+    fn own_item(&self, category: &str, object: &str) -> Option<&'d ItemDefinition> {
+        if !category.eq_ignore_ascii_case(&self.category.name) {
+            return None;
+        }
+        self.dict.item(&format!("_{category}.{object}"))
+    }
+
+    fn referenced_item(&self, expr: &Expr) -> Result<Option<&'d ItemDefinition>, String> {
+        match expr {
+            Expr::DataName(name) => {
+                let item = self
+                    .dict
+                    .item(name)
+                    .ok_or_else(|| format!("{name} is not defined"))?;
+                if !item.category_id.eq_ignore_ascii_case(&self.category.name) {
+                    return unsupported(format!("{name} is in another category"));
+                }
+                Ok(Some(item))
+            }
+            Expr::Attribute(base, attr) => match base.as_ref() {
+                Expr::Ident(alias)
+                    if self.aliases.contains(alias)
+                        || (self.variable(alias).is_none()
+                            && alias.eq_ignore_ascii_case(&self.category.name)) =>
+                {
+                    self.own_item(&self.category.name, attr)
+                        .map(Some)
+                        .ok_or_else(|| format!("{alias}.{attr} is not defined"))
+                }
+                Expr::Ident(alias) if self.dict.category(alias).is_some() => {
+                    unsupported(format!("{alias}.{attr} is in another category"))
+                }
+                _ => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn read_item(&self, item: &ItemDefinition) -> Result<Typed, String> {
+        let ty =
+            value_type(item).ok_or_else(|| format!("{} has an unsupported type", item.name))?;
+        let field = field_ident(&item.object_id);
+        let tokens = match (
+            &ty,
+            evaluation_method(item).is_some() && item.name != self.target.name,
+        ) {
+            (Ty::Str, _) if is_enumerated(item) => quote!(self.#field.as_ref()?.to_string()),
+            (Ty::Str, _) => quote!(self.#field.clone()?),
+            (_, true) => quote!(match self.#field { Some(__v) => __v, None => self.#field()? }),
+            (_, false) => quote!(self.#field?),
+        };
+        Ok((tokens, ty))
+    }
+
+    fn expr(&self, expr: &Expr) -> Result<Typed, String> {
+        if let Some(item) = self.referenced_item(expr)? {
+            return self.read_item(item);
+        }
+        match expr {
+            Expr::Integer(i) => {
+                let i = Literal::i64_suffixed(*i);
+                Ok((quote!(#i), Ty::Int))
+            }
+            Expr::Real(r) => {
+                let r = Literal::f64_suffixed(*r);
+                Ok((quote!(#r), Ty::Real))
+            }
+            Expr::Str(s) => Ok((quote!(::std::string::String::from(#s)), Ty::Str)),
+            Expr::Bool(b) => Ok((quote!(#b), Ty::Bool)),
+            Expr::Ident(name) => {
+                let ty = self
+                    .variable(name)
+                    .ok_or_else(|| format!("variable {name} is not assigned"))?
+                    .clone();
+                let var = field_ident(name);
+                let tokens = match ty {
+                    Ty::Str => quote!(#var.clone()),
+                    _ => quote!(#var),
+                };
+                Ok((tokens, ty))
+            }
+            Expr::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|i| self.expr(i))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let ty = items
+                    .iter()
+                    .try_fold(None, |acc: Option<Ty>, (_, ty)| match acc {
+                        None => Some(Some(ty.clone())),
+                        Some(acc) => unify(&acc, ty).map(Some),
+                    })
+                    .flatten()
+                    .ok_or("list with mixed or no elements")?;
+                let n = items.len();
+                let items = items
+                    .into_iter()
+                    .map(|item| coerce(item, &ty))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((quote!([#(#items),*]), Ty::Array(Box::new(ty), n)))
+            }
+            Expr::Unary(UnaryOp::Neg, operand) => {
+                arithmetic(BinaryOp::Mul, (quote!(-1i64), Ty::Int), self.expr(operand)?)
+            }
+            Expr::Unary(UnaryOp::Not, operand) => match self.expr(operand)? {
+                (tokens, Ty::Bool) => Ok((quote!((!#tokens)), Ty::Bool)),
+                (_, ty) => unsupported(format!("Not of {ty:?}")),
+            },
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), lhs, rhs) => {
+                match (self.expr(lhs)?, self.expr(rhs)?) {
+                    ((l, Ty::Bool), (r, Ty::Bool)) => Ok((
+                        if *op == BinaryOp::And {
+                            quote!((#l && #r))
+                        } else {
+                            quote!((#l || #r))
+                        },
+                        Ty::Bool,
+                    )),
+                    _ => unsupported("And or Or of values that are not conditions"),
+                }
+            }
+            Expr::Binary(
+                op @ (BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge),
+                lhs,
+                rhs,
+            ) => comparison(*op, self.expr(lhs)?, self.expr(rhs)?),
+            Expr::Binary(op, lhs, rhs) => arithmetic(*op, self.expr(lhs)?, self.expr(rhs)?),
+            Expr::Subscript(base, index) => {
+                let (mut tokens, mut ty) = self.expr(base)?;
+                for i in index {
+                    let Ty::Array(inner, _) = ty else {
+                        return unsupported("subscript of a value that is not an array");
+                    };
+                    let i = match self.expr(i)? {
+                        (i, Ty::Int) => i,
+                        _ => return unsupported("subscript that is not an integer"),
+                    };
+                    tokens = quote!(#tokens[(#i) as usize]);
+                    ty = *inner;
+                }
+                Ok((tokens, ty))
+            }
+            Expr::Call(name, args) => call(
+                name,
+                args.iter()
+                    .map(|a| self.expr(a))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expr::Table(_) => unsupported("tables"),
+            Expr::Attribute(_, attr) => unsupported(format!("attribute {attr}")),
+            Expr::DataName(name) => unsupported(format!("data name {name}")),
+        }
+    }
+
+    fn is_result(&self, target: &Expr) -> Result<bool, String> {
+        Ok(self
+            .referenced_item(target)?
+            .is_some_and(|item| item.name == self.target.name))
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Result<TokenStream, String> {
+        self.scopes.push(HashMap::new());
+        let stmts = stmts
+            .iter()
+            .map(|s| self.stmt(s))
+            .collect::<Result<Vec<_>, _>>();
+        self.scopes.pop();
+        let stmts = stmts?;
+        Ok(quote!(#(#stmts)*))
+    }
+
+    fn assign(&mut self, target: &Expr, op: AssignOp, value: &Expr) -> Result<TokenStream, String> {
+        let value = self.expr(value)?;
+        let compound = |current: Typed, value: Typed| match op {
+            AssignOp::Assign => Ok(value),
+            AssignOp::AddAssign => arithmetic(BinaryOp::Add, current, value),
+            AssignOp::SubAssign => arithmetic(BinaryOp::Sub, current, value),
+            AssignOp::MulAssign => arithmetic(BinaryOp::Mul, current, value),
+            AssignOp::Append => unsupported("appending to a list"),
+        };
+        if self.is_result(target)? {
+            let ty = value_type(self.target)
+                .ok_or_else(|| format!("{} has an unsupported type", self.target.name))?;
+            let value = coerce(compound((quote!(__result?), ty.clone()), value)?, &ty)?;
+            return Ok(quote!(__result = Some(#value);));
+        }
+        match target {
+            Expr::Ident(name) => {
+                let var = field_ident(name);
+                match self.variable(name).cloned() {
+                    Some(ty) => {
+                        let value = coerce(compound((quote!(#var), ty.clone()), value)?, &ty)?;
+                        Ok(quote!(#var = #value;))
+                    }
+                    None if op == AssignOp::Assign => {
+                        let ty = value.1.clone();
+                        let ty_tokens = ty.tokens();
+                        let value = value.0;
+                        if let Some(scope) = self.scopes.last_mut() {
+                            scope.insert(name.clone(), ty);
+                        }
+                        Ok(quote!(let mut #var: #ty_tokens = #value;))
+                    }
+                    None => unsupported(format!("variable {name} is not assigned")),
+                }
+            }
+            Expr::Subscript(..) => {
+                let (place, ty) = self.expr(target)?;
+                let value = coerce(compound((place.clone(), ty.clone()), value)?, &ty)?;
+                Ok(quote!(#place = #value;))
+            }
+            _ => unsupported("assignment to an item of another category"),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<TokenStream, String> {
+        match stmt {
+            Stmt::Assign { target, op, value } => self.assign(target, *op, value),
+            Stmt::Expr(expr) => {
+                let (tokens, _) = self.expr(expr)?;
+                Ok(quote!(let _ = #tokens;))
+            }
+            Stmt::With { alias, category } => {
+                if !category.eq_ignore_ascii_case(&self.category.name) {
+                    return unsupported(format!(
+                        "With {alias} as {category} from another category"
+                    ));
+                }
+                self.aliases.push(alias.clone());
+                Ok(TokenStream::new())
+            }
+            Stmt::If {
+                branches,
+                otherwise,
+            } => {
+                let mut branch_tokens = Vec::new();
+                for (condition, body) in branches {
+                    let condition = match self.expr(condition)? {
+                        (c, Ty::Bool) => c,
+                        _ => return unsupported("If condition that is not a comparison"),
+                    };
+                    let body = self.block(body)?;
+                    branch_tokens.push(quote!(if #condition { #body }));
+                }
+                let otherwise = self.block(otherwise)?;
+                Ok(quote!(#(#branch_tokens)else* else { #otherwise }))
+            }
+            Stmt::Do {
+                var,
+                start,
+                end,
+                step,
+                body,
+            } => {
+                let bound = |e: &Expr| match self.expr(e)? {
+                    (b, Ty::Int) => Ok(b),
+                    _ => unsupported("Do bound that is not an integer"),
+                };
+                let (start, end) = (bound(start)?, bound(end)?);
+                let step = match step {
+                    None => 1,
+                    Some(Expr::Integer(step)) if *step > 0 => *step as usize,
+                    Some(_) => {
+                        return unsupported("Do step that is not a positive integer literal");
+                    }
+                };
+                let var_ident = field_ident(var);
+                self.scopes.push(HashMap::from([(var.clone(), Ty::Int)]));
+                self.loops += 1;
+                let body = self.block(body);
+                self.loops -= 1;
+                self.scopes.pop();
+                let body = body?;
+                Ok(quote!(for #var_ident in (#start..=#end).step_by(#step) { #body }))
+            }
+            Stmt::For { var, iter, body } => {
+                let (iter, ty) = self.expr(iter)?;
+                let Ty::Array(inner, _) = ty else {
+                    return unsupported("For over a value that is not an array");
+                };
+                let var_ident = field_ident(var);
+                self.scopes.push(HashMap::from([(var.clone(), *inner)]));
+                self.loops += 1;
+                let body = self.block(body);
+                self.loops -= 1;
+                self.scopes.pop();
+                let body = body?;
+                Ok(quote!(for #var_ident in #iter { #body }))
+            }
+            Stmt::Break if self.loops > 0 => Ok(quote!(break;)),
+            Stmt::Next if self.loops > 0 => Ok(quote!(continue;)),
+            Stmt::Break | Stmt::Next => unsupported("Break or Next outside Do or For"),
+            Stmt::Loop { category, .. } => unsupported(format!("Loop over {category}")),
+            Stmt::Repeat(_) => unsupported("Repeat"),
+            Stmt::Function { name, .. } => unsupported(format!("Function {name}")),
+        }
+    }
+}
+
+/// Compile the Evaluation method of an item of the given category into a
+/// method named after the item's object id. `None` if it has no method.
+pub fn compile_method(
+    dict: &Dictionary,
+    category: &CategoryDefinition,
+    item: &ItemDefinition,
+) -> Result<Option<TokenStream>, MethodError> {
+    let Some(expression) = evaluation_method(item) else {
+        return Ok(None);
+    };
+    let error = |reason: String| MethodError {
+        item: item.name.clone(),
+        reason,
+    };
+    let stmts = parse_method(expression).map_err(|e| error(e.to_string()))?;
+    let ty =
+        value_type(item).ok_or_else(|| error("the item has an unsupported type".to_string()))?;
+    let mut translator = Translator {
+        dict,
+        category,
+        target: item,
+        aliases: Vec::new(),
+        scopes: Vec::new(),
+        loops: 0,
+    };
+    let body = translator.block(&stmts).map_err(error)?;
+    let name = field_ident(&item.object_id);
+    let ty = ty.tokens();
+    let doc = doc(&format!("Derived with the dREL method of `{}`", item.name));
+    let result = format_ident!("__result");
+    Ok(Some(quote! {
+        #doc
+        #[allow(unused_mut, unused_parens, unused_variables, clippy::all)]
+        pub fn #name(&self) -> ::core::option::Option<#ty> {
+            let mut #result: ::core::option::Option<#ty> = None;
+            #body
+            #result
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::dict;
+    use rstest::rstest;
+
+    fn compile(item: &str, method: Option<&str>) -> Result<Option<TokenStream>, MethodError> {
+        let mut dict = dict();
+        if let Some(method) = method {
+            dict.items.get_mut(item).unwrap().methods[0].expression = method.to_string();
+        }
+        let item = dict.item(item).unwrap();
+        let category = dict.item_category(item).unwrap();
+        compile_method(&dict, category, item)
+    }
+
+    #[test]
+    fn test_compile_volume() {
+        let tokens = compile("_cell.volume", None).unwrap().unwrap().to_string();
+        assert!(tokens.contains("pub fn volume (& self)"));
+        assert!(tokens.contains("self . length_a ?"));
+        assert!(tokens.contains("to_radians () . cos ()"));
+    }
+
+    #[test]
+    fn test_no_method() {
+        assert!(compile("_cell.length_a", None).unwrap().is_none());
+    }
+
+    #[rstest]
+    #[case(
+        "_atom_site.distance_origin",
+        None,
+        "With c as cell from another category"
+    )]
+    #[case(
+        "_cell.volume",
+        Some("_cell.volume = Hypot(1., 2., 3.)"),
+        "function hypot"
+    )]
+    #[case(
+        "_cell.volume",
+        Some("Loop a as atom_site { x = 1 }"),
+        "Loop over atom_site"
+    )]
+    #[case(
+        "_cell.volume",
+        Some("_cell.volume = _atom_site.fract_x"),
+        "_atom_site.fract_x is in another category"
+    )]
+    #[case(
+        "_cell.volume",
+        Some("_cell.volume = 'a'"),
+        "Str where Real is expected"
+    )]
+    #[case(
+        "_cell.volume",
+        Some("_cell.volume = (1"),
+        "dREL syntax error at 1:16 near '(1'"
+    )]
+    fn test_unsupported(#[case] item: &str, #[case] method: Option<&str>, #[case] reason: &str) {
+        assert_eq!(
+            compile(item, method).unwrap_err(),
+            MethodError {
+                item: item.to_string(),
+                reason: reason.to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,186 @@
+/// Rust enums for items with `_enumeration_set.state`
+use cif_chomper_core::dictionary::{ContentType, ItemDefinition, TypeContainer};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::docs::{description, doc, markdown};
+use crate::naming::type_ident;
+
+/// Whether an item's values are generated as an enum: single text values
+/// with listed states
+pub fn is_enumerated(item: &ItemDefinition) -> bool {
+    !item.enumeration.states.is_empty()
+        && item.container == TypeContainer::Single
+        && !matches!(
+            item.contents.content_type(),
+            Some(
+                ContentType::Real
+                    | ContentType::Integer
+                    | ContentType::Count
+                    | ContentType::Index
+                    | ContentType::Implied
+                    | ContentType::ByReference
+            )
+        )
+}
+
+/// `_atom_site.adp_type` to `AtomSiteAdpType`
+pub fn enum_ident(item: &ItemDefinition) -> Ident {
+    type_ident(&format!("{}_{}", item.category_id, item.object_id))
+}
+
+/// Variant names of the states, made unique by a numeric suffix, e.g.
+/// `1` and `-1` give `C1` and `C12`
+fn variant_idents(item: &ItemDefinition) -> Vec<Ident> {
+    let mut taken: Vec<String> = Vec::new();
+    if item.enumeration.open {
+        taken.push("Other".to_string());
+    }
+    item.enumeration
+        .states
+        .iter()
+        .map(|s| {
+            let base = type_ident(&s.state).to_string();
+            let mut name = base.clone();
+            let mut n = 2;
+            while taken.contains(&name) {
+                name = format!("{base}{n}");
+                n += 1;
+            }
+            taken.push(name.clone());
+            format_ident!("{}", name)
+        })
+        .collect()
+}
+
+/// The enum of an enumerated item. Codes are matched case-insensitively and
+/// other contents exactly; values are written as the dictionary states.
+/// Open enumerations keep unlisted values in `Other`.
+pub fn item_enum(item: &ItemDefinition) -> TokenStream {
+    let name = enum_ident(item);
+    let variants = variant_idents(item);
+    let states: Vec<&str> = item
+        .enumeration
+        .states
+        .iter()
+        .map(|s| s.state.as_str())
+        .collect();
+    let details = item.enumeration.states.iter().map(|s| match &s.detail {
+        Some(detail) => doc(&markdown(detail)),
+        None => quote!(),
+    });
+    let doc = doc(&match description(item.description.as_deref()) {
+        Some(description) => format!("Values of `{}`: {description}", item.name),
+        None => format!("Values of `{}`", item.name),
+    });
+    let matches = match item.contents.content_type() {
+        Some(ContentType::Code) => quote!(__state.eq_ignore_ascii_case(__s)),
+        _ => quote!(__state == __s),
+    };
+    let (other_variant, other_str, err, unmatched) = if item.enumeration.open {
+        (
+            quote!(Other(::std::string::String),),
+            quote!(#name::Other(__s) => __s,),
+            quote!(::core::convert::Infallible),
+            quote!(Ok(#name::Other(__s.to_string()))),
+        )
+    } else {
+        (quote!(), quote!(), quote!(()), quote!(Err(())))
+    };
+    quote! {
+        #doc
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum #name {
+            #(#details #variants,)*
+            #other_variant
+        }
+
+        impl #name {
+            pub const STATES: &'static [&'static str] = &[#(#states),*];
+
+            pub fn as_str(&self) -> &str {
+                match self {
+                    #(#name::#variants => #states,)*
+                    #other_str
+                }
+            }
+        }
+
+        impl ::core::str::FromStr for #name {
+            type Err = #err;
+
+            fn from_str(__s: &str) -> ::core::result::Result<Self, Self::Err> {
+                #(
+                    let __state = #states;
+                    if #matches {
+                        return Ok(#name::#variants);
+                    }
+                )*
+                #unmatched
+            }
+        }
+
+        impl ::core::fmt::Display for #name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl ::cif_chomper_core::model::CifValue for #name {
+            fn from_content(
+                __content: &::cif_chomper_core::raw_model::RawDataItemContent,
+            ) -> ::core::option::Option<Self> {
+                __content.as_str()?.parse().ok()
+            }
+
+            fn to_content(&self) -> ::cif_chomper_core::raw_model::DataValue {
+                ::cif_chomper_core::raw_model::DataValue::Str(self.as_str().to_string())
+            }
+
+            fn expected() -> ::std::string::String {
+                ::std::format!("one of {}", Self::STATES.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::dict;
+    use cif_chomper_core::dictionary::Dictionary;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_is_enumerated(dict: Dictionary) {
+        assert!(is_enumerated(dict.item("_atom_site.adp_type").unwrap()));
+        assert!(!is_enumerated(dict.item("_atom_site.label").unwrap()));
+    }
+
+    #[rstest]
+    fn test_variant_idents(mut dict: Dictionary) {
+        let item = dict.items.get_mut("_atom_site.calc_flag").unwrap();
+        item.enumeration.states[0].state = "other".to_string();
+        item.enumeration.states[1].state = "-1".to_string();
+        item.enumeration
+            .states
+            .push(item.enumeration.states[1].clone());
+        item.enumeration.states[2].state = "1".to_string();
+        let variants: Vec<_> = variant_idents(item).iter().map(|v| v.to_string()).collect();
+        assert_eq!(variants, vec!["Other2", "C1", "C12"]);
+    }
+
+    #[rstest]
+    fn test_item_enum(dict: Dictionary) {
+        let closed = item_enum(dict.item("_atom_site.adp_type").unwrap()).to_string();
+        assert!(
+            closed.contains("pub enum AtomSiteAdpType { # [doc = \" anisotropic Uij\"] Uani ,")
+        );
+        assert!(closed.contains("__state . eq_ignore_ascii_case (__s)"));
+        assert!(closed.contains("type Err = ()"));
+        let open = item_enum(dict.item("_atom_site.calc_flag").unwrap()).to_string();
+        assert!(open.contains("Other (:: std :: string :: String) ,"));
+        assert!(open.contains("__state == __s"));
+        assert!(open.contains("Values of `_atom_site.calc_flag`: How the site was located."));
+    }
+}
@@ -1,79 +1,255 @@
-use std::cell::LazyCell;
+pub mod block;
+pub mod codegen;
+pub mod derive;
+pub mod docs;
+pub mod drel;
+pub mod enums;
+pub mod model;
+pub mod naming;
+#[cfg(test)]
+mod test_fixtures;
 
-use cif_chomper_core::parser::cif2_file;
-use cif_chomper_core::raw_model::{RawDataBlock, RawDataItem, RawDataItemContent, RawModel};
-use quote::quote;
+use cif_chomper_core::dictionary::{Dictionary, DictionaryError};
+use cif_chomper_core::import::{DirectoryResolver, ImportResolver, MemoryResolver};
+use quote::{quote, quote_spanned};
+use std::cell::RefCell;
+use std::io;
+use std::path::{Path, PathBuf};
+use syn::Token;
 use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
 extern crate proc_macro;
 use proc_macro2::TokenStream;
 
 const DDL: &str = include_str!("../../cif_core/ddl.dic");
-const DDL_MODEL: LazyCell<RawModel> = LazyCell::new(|| cif2_file(DDL).unwrap());
 const DICT: &str = include_str!("../../cif_core/cif_core.dic");
-const DICT_MODEL: LazyCell<RawModel> = LazyCell::new(|| cif2_file(DICT).unwrap());
-
-// To extract info from the dictionary to construct the model
-// https://www.iucr.org/resources/cif/ddl/ddlm/docs/intro
-// per data block:
-// 1 - split into save frames & global attr
-// 2 - extract head save frame from list of save frames
-// 3 - construct a tree based on categories
-// 4 - construct structs from tree depth first
-// 5 - open outermost struct based on _dictionary.namespace (sanitise if necessary)
-// 6 - add constant valiues from global attrs
-
-// For each save frame:
-// sort into bucket of _name.category_id
-
-fn match_data_item(data_item: &RawDataItem) {
-    match &data_item {
-        RawDataItem::Data { name, value } => match &value {
-            RawDataItemContent::Str(v) => {
-                println!("content str {name}, {v}");
-            }
-            _ => (),
-        },
-        RawDataItem::SaveFrame { name, content } => {
-            println!("\n SAVE FRAME {name} \n");
-            content.iter().for_each(match_data_item);
-        }
-        _ => (),
-    }
+const TEMPL_ATTR: &str = include_str!("../../cif_core/templ_attr.cif");
+const TEMPL_ENUM: &str = include_str!("../../cif_core/templ_enum.cif");
+
+/// The DDLm reference dictionary, defining the attributes used by all others
+pub fn ddl_dictionary() -> Result<Dictionary, DictionaryError> {
+    Dictionary::parse(DDL)
 }
-fn iterate_data_block(data_block: &RawDataBlock) {
-    data_block.content.iter().for_each(match_data_item);
+
+/// The CIF core dictionary the model is generated from, with the templates
+/// it imports
+pub fn core_dictionary() -> Result<Dictionary, DictionaryError> {
+    let resolver = MemoryResolver::from_iter([
+        ("templ_attr.cif", TEMPL_ATTR),
+        ("templ_enum.cif", TEMPL_ENUM),
+    ]);
+    Dictionary::parse_with_imports(DICT, &resolver)
 }
 
+/// `make_model!(Name, dictionary = "...", imports = ["..."], categories = [...])`;
+/// paths are relative to the calling crate's `CARGO_MANIFEST_DIR`
 #[derive(Debug)]
 pub struct ModelMacroInput {
     name: syn::Ident,
+    dictionary: Option<syn::LitStr>,
+    imports: Vec<syn::LitStr>,
+    categories: Option<Vec<syn::LitStr>>,
+}
+
+/// A category given either as `atom_site` or `"pd_calc.overall"`
+fn parse_category(input: ParseStream) -> Result<syn::LitStr, syn::Error> {
+    if input.peek(syn::LitStr) {
+        input.parse()
+    } else {
+        let ident: syn::Ident = input.parse()?;
+        Ok(syn::LitStr::new(&ident.to_string(), ident.span()))
+    }
+}
+
+fn parse_list<T>(
+    input: ParseStream,
+    item: fn(ParseStream) -> Result<T, syn::Error>,
+) -> Result<Vec<T>, syn::Error> {
+    let content;
+    syn::bracketed!(content in input);
+    let items = Punctuated::<T, Token![,]>::parse_terminated_with(&content, item)?;
+    Ok(items.into_iter().collect())
 }
 
 impl Parse for ModelMacroInput {
     fn parse(input: ParseStream) -> Result<Self, syn::Error> {
         let lookahead = input.lookahead1();
-        if lookahead.peek(syn::Ident) {
-            Ok(ModelMacroInput {
-                name: input.parse()?,
-            })
-        } else {
-            Err(lookahead.error())
+        if !lookahead.peek(syn::Ident) {
+            return Err(lookahead.error());
         }
+        let mut model = ModelMacroInput {
+            name: input.parse()?,
+            dictionary: None,
+            imports: Vec::new(),
+            categories: None,
+        };
+        let mut seen = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: syn::Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let option = key.to_string();
+            if seen.contains(&option) {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("duplicate option `{option}`"),
+                ));
+            }
+            match option.as_str() {
+                "dictionary" => model.dictionary = Some(input.parse()?),
+                "imports" => model.imports = parse_list(input, |i| i.parse())?,
+                "categories" => model.categories = Some(parse_list(input, parse_category)?),
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!(
+                            "unknown option `{option}`, expected `dictionary`, `imports` or `categories`"
+                        ),
+                    ));
+                }
+            }
+            seen.push(option);
+        }
+        Ok(model)
     }
 }
 
-pub fn make_model_core(input: ModelMacroInput) -> TokenStream {
-    let name = input.name;
-    quote! {
-        struct #name {
-            x: usize
+/// Resolves imports like [`DirectoryResolver`], recording each file read
+struct TrackingResolver {
+    directories: DirectoryResolver,
+    read: RefCell<Vec<PathBuf>>,
+}
+
+impl ImportResolver for TrackingResolver {
+    fn resolve(&self, file: &str) -> io::Result<String> {
+        let (path, text) = self.directories.read(file)?;
+        self.read.borrow_mut().push(path);
+        Ok(text)
+    }
+}
+
+/// The dictionary named by the macro, or the CIF core dictionary, with every
+/// file read for it. Files imported by it are looked for in `imports`, then
+/// next to the dictionary.
+pub(crate) fn load_dictionary(
+    dictionary: Option<&syn::LitStr>,
+    imports: &[syn::LitStr],
+    span: proc_macro2::Span,
+) -> Result<(Dictionary, Vec<PathBuf>), syn::Error> {
+    let Some(file) = dictionary else {
+        if let Some(import) = imports.first() {
+            return Err(syn::Error::new(
+                import.span(),
+                "`imports` needs a `dictionary` to import into",
+            ));
+        }
+        return core_dictionary()
+            .map(|dict| (dict, Vec::new()))
+            .map_err(|e| syn::Error::new(span, format!("cannot load the dictionary: {e}")));
+    };
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").map_err(|_| {
+        syn::Error::new(
+            file.span(),
+            "CARGO_MANIFEST_DIR is not set, so the dictionary cannot be found",
+        )
+    })?;
+    let manifest_dir = PathBuf::from(manifest_dir);
+    let path = manifest_dir.join(file.value());
+    let text = std::fs::read_to_string(&path).map_err(|e| {
+        syn::Error::new(file.span(), format!("cannot read {}: {e}", path.display()))
+    })?;
+    let search_path = imports
+        .iter()
+        .map(|dir| manifest_dir.join(dir.value()))
+        .chain(path.parent().map(Path::to_path_buf));
+    let resolver = TrackingResolver {
+        directories: DirectoryResolver::new(search_path),
+        read: RefCell::new(vec![path.clone()]),
+    };
+    match Dictionary::parse_with_imports(&text, &resolver) {
+        Ok(dict) => Ok((dict, resolver.read.into_inner())),
+        Err(e) => {
+            let message = match e {
+                DictionaryError::Parse(e) => format!(
+                    "cannot parse the dictionary {}:{}:{} (rule: {})",
+                    path.display(),
+                    e.line,
+                    e.column,
+                    e.rule
+                ),
+                e => format!("cannot load the dictionary {}: {e}", path.display()),
+            };
+            Err(syn::Error::new(file.span(), message))
         }
     }
 }
 
+/// Makes the generated code depend on `files`, so it is rebuilt when one of
+/// them changes
+pub(crate) fn track_files(files: &[PathBuf]) -> TokenStream {
+    let paths = files.iter().map(|path| path.to_string_lossy());
+    quote!(#(const _: &str = include_str!(#paths);)*)
+}
+
+/// Compiler warnings for `messages`, spanned at `span`. There is no stable
+/// API for these, so each is the note of a deprecated constant that is used.
+fn warnings(messages: &[String], span: proc_macro2::Span) -> TokenStream {
+    let warnings = messages.iter().map(|message| {
+        quote_spanned! {span=>
+            const _: () = {
+                #[deprecated(note = #message)]
+                #[allow(non_upper_case_globals)]
+                const make_model_warning: () = ();
+                make_model_warning
+            };
+        }
+    });
+    quote!(#(#warnings)*)
+}
+
+pub fn make_model_core(input: ModelMacroInput) -> TokenStream {
+    let (dict, files) =
+        match load_dictionary(input.dictionary.as_ref(), &input.imports, input.name.span()) {
+            Ok(loaded) => loaded,
+            Err(e) => return e.to_compile_error(),
+        };
+    let categories = match &input.categories {
+        Some(categories) => {
+            let values: Vec<_> = categories.iter().map(syn::LitStr::value).collect();
+            match codegen::resolve_categories(&dict, values.iter().map(String::as_str)) {
+                Ok(names) => Some(names),
+                Err(unknown) => {
+                    let category =
+                        &categories[values.iter().position(|v| *v == unknown).unwrap_or(0)];
+                    let message = format!("no category {unknown} in the dictionary");
+                    return syn::Error::new(category.span(), message).to_compile_error();
+                }
+            }
+        }
+        None => None,
+    };
+    let mut tokens = model::generate_model(&dict, &input.name, categories.as_deref());
+    let span = input
+        .dictionary
+        .as_ref()
+        .map_or(input.name.span(), syn::LitStr::span);
+    tokens.extend(warnings(
+        &model::skipped_definitions(&dict, categories.as_deref()),
+        span,
+    ));
+    tokens.extend(track_files(&files));
+    tokens
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cif_chomper_core::dictionary::{CategoryClass, ContentType, TypeContents};
+    use cif_chomper_core::parser::cif2_file;
+    use rstest::rstest;
 
     #[test]
     fn test_load_ddl_str() {
@@ -82,27 +258,133 @@ mod tests {
 
     #[test]
     fn test_load_ddl_model() {
-        println!("{:?}", DDL_MODEL.content[0].heading);
-        println!("{:?}", DDL_MODEL.content[0].content.len());
-        let content = &DDL_MODEL.content;
+        let model = cif2_file(DDL).unwrap();
+        let content = &model.content;
         assert!(content[0].content.len() > 5);
     }
 
     #[test]
-    fn test_ddl_model_content() {
-        let content = &DDL_MODEL.content;
-        dbg!(&DDL_MODEL.heading);
-        for data_block in content.as_slice()[0..1].iter() {
-            iterate_data_block(data_block);
-        }
+    fn test_ddl_dictionary_content() {
+        let dict = ddl_dictionary().unwrap();
+        assert_eq!(dict.head_category().class, CategoryClass::Head);
+        assert!(dict.item("_definition.id").is_some());
     }
 
     #[test]
-    fn test_dict_model_content() {
-        let content = &DICT_MODEL.content;
-        dbg!(&DICT_MODEL.heading);
-        for data_block in content.as_slice()[0..1].iter() {
-            iterate_data_block(data_block);
-        }
+    fn test_dict_dictionary_content() {
+        let dict = core_dictionary().unwrap();
+        assert_eq!(dict.namespace.as_deref(), Some("CifCore"));
+        assert_eq!(dict.head_category().name, "CIF_CORE");
+        assert!(dict.category("cell").is_some());
+        let length_a = dict.item("_cell.length_a").unwrap();
+        assert_eq!(length_a.contents, TypeContents::Simple(ContentType::Real));
+        assert_eq!(length_a.units.as_deref(), Some("angstroms"));
+    }
+
+    #[test]
+    fn test_parse_macro_input() {
+        let input: ModelMacroInput = syn::parse_str(
+            r#"PowModel, dictionary = "dicts/cif_pow.dic", imports = ["dicts/"], categories = [cell, "pd_calc.overall"],"#,
+        )
+        .unwrap();
+        assert_eq!(input.name, "PowModel");
+        assert_eq!(input.dictionary.unwrap().value(), "dicts/cif_pow.dic");
+        assert_eq!(input.imports[0].value(), "dicts/");
+        let categories: Vec<_> = input
+            .categories
+            .unwrap()
+            .iter()
+            .map(|c| c.value())
+            .collect();
+        assert_eq!(categories, vec!["cell", "pd_calc.overall"]);
+    }
+
+    #[rstest]
+    #[case(
+        "Model, dictionary = \"a.dic\", dictionary = \"b.dic\"",
+        "duplicate option `dictionary`"
+    )]
+    #[case(
+        "Model, dictionaries = \"a.dic\"",
+        "unknown option `dictionaries`, expected `dictionary`, `imports` or `categories`"
+    )]
+    fn test_parse_macro_input_error(#[case] tokens: &str, #[case] message: &str) {
+        let error = syn::parse_str::<ModelMacroInput>(tokens).unwrap_err();
+        assert_eq!(error.to_string(), message);
+    }
+
+    #[test]
+    fn test_unknown_category() {
+        let input: ModelMacroInput = syn::parse_str("Model, categories = [nonesuch]").unwrap();
+        let tokens = make_model_core(input).to_string();
+        assert!(tokens.contains("no category nonesuch in the dictionary"));
+    }
+
+    #[test]
+    fn test_dictionary_parse_error() {
+        let path = std::env::temp_dir().join(format!("cif_broken_{}.dic", std::process::id()));
+        std::fs::write(&path, "#\\#CIF_2.0\ndata_x\n_a 1\n  _b [1\n").unwrap();
+        let file = syn::LitStr::new(&path.to_string_lossy(), proc_macro2::Span::call_site());
+        let error = load_dictionary(Some(&file), &[], file.span()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "cannot parse the dictionary {}:4:3 (rule: expected eof)",
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_imports_without_dictionary() {
+        let input: ModelMacroInput = syn::parse_str(r#"Model, imports = ["dicts/"]"#).unwrap();
+        let tokens = make_model_core(input).to_string();
+        assert!(tokens.contains("`imports` needs a `dictionary` to import into"));
+    }
+
+    #[test]
+    fn test_imported_files_tracked() {
+        let dir = std::env::temp_dir().join(format!("cif_imports_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.dic");
+        let extension = dir.join("ext.dic");
+        std::fs::write(
+            &main,
+            "#\\#CIF_2.0\ndata_MAIN\nsave_MAIN_HEAD\n_definition.id MAIN_HEAD\n\
+             _definition.scope Category\n_definition.class Head\n\
+             _import.get [{'file':ext.dic 'save':EXT_HEAD 'mode':Full}]\nsave_\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &extension,
+            "#\\#CIF_2.0\ndata_EXT\nsave_EXT_HEAD\n_definition.id EXT_HEAD\n\
+             _definition.scope Category\n_definition.class Head\nsave_\n",
+        )
+        .unwrap();
+        let file = syn::LitStr::new(&main.to_string_lossy(), proc_macro2::Span::call_site());
+        let result = load_dictionary(Some(&file), &[], file.span());
+        std::fs::remove_dir_all(&dir).unwrap();
+        let (_, files) = result.unwrap();
+        assert_eq!(files, vec![main.clone(), extension.clone()]);
+        let tokens = track_files(&files).to_string();
+        assert!(tokens.contains(&format!("include_str ! ({:?})", main.to_string_lossy())));
+        assert!(tokens.contains(&format!(
+            "include_str ! ({:?})",
+            extension.to_string_lossy()
+        )));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let tokens = warnings(
+            &["item `_a.b` is skipped".to_string()],
+            proc_macro2::Span::call_site(),
+        );
+        assert!(
+            tokens
+                .to_string()
+                .contains("# [deprecated (note = \"item `_a.b` is skipped\")]")
+        );
     }
 }
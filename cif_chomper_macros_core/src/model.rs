@@ -0,0 +1,358 @@
+/// Generates the model structs from a dictionary
+/// https://www.iucr.org/resources/cif/ddl/ddlm/docs/intro
+/// 1 - build a tree of categories from the head category via `_name.category_id`
+/// 2 - emit structs from the tree depth first: Set categories become a field
+///     of their parent, Loop categories a `Vec` of packets
+/// 3 - the outermost struct takes the macro's name, with the dictionary's
+///     global attributes as constants
+use cif_chomper_core::dictionary::{CategoryClass, CategoryDefinition, Dictionary};
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+
+use crate::block::{key_lookups, loop_impls, set_impls};
+use crate::docs::{category_doc, dictionary_doc, doc, item_doc};
+use crate::drel::compile_method;
+use crate::enums::{is_enumerated, item_enum};
+use crate::naming::{field_ident, item_type, type_ident};
+
+struct CategoryNode<'d> {
+    category: &'d CategoryDefinition,
+    /// Whether the category's items are generated, rather than just the
+    /// categories below it
+    selected: bool,
+    children: Vec<CategoryNode<'d>>,
+}
+
+impl<'d> CategoryNode<'d> {
+    /// The tree below `category`, keeping only the categories in `filter`
+    /// and those leading to them
+    fn build(
+        dict: &'d Dictionary,
+        category: &'d CategoryDefinition,
+        filter: Option<&[String]>,
+        path: &mut Vec<String>,
+    ) -> Self {
+        path.push(category.name.to_lowercase());
+        let mut children = Vec::new();
+        for child in dict.child_categories(&category.name) {
+            if child.class != CategoryClass::Functions && !path.contains(&child.name.to_lowercase())
+            {
+                let node = CategoryNode::build(dict, child, filter, path);
+                if node.selected || !node.children.is_empty() {
+                    children.push(node);
+                }
+            }
+        }
+        path.pop();
+        let selected = filter.is_none_or(|names| {
+            names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&category.name))
+        });
+        CategoryNode {
+            category,
+            selected,
+            children,
+        }
+    }
+
+    /// Categories held as fields: the children, plus those of Loop children,
+    /// since a packet cannot hold the whole of another category
+    fn fields(&self) -> Vec<&CategoryNode<'d>> {
+        self.children
+            .iter()
+            .flat_map(|child| {
+                let mut fields = vec![child];
+                if child.category.class == CategoryClass::Loop {
+                    fields.extend(child.fields());
+                }
+                fields
+            })
+            .collect()
+    }
+}
+
+fn category_field(node: &CategoryNode) -> TokenStream {
+    let field = field_ident(&node.category.name);
+    let ty = type_ident(&node.category.name);
+    let name = &node.category.name;
+    match node.category.class {
+        CategoryClass::Loop => {
+            let doc = doc(&format!("Packets of the `{name}` category"));
+            quote!(#doc pub #field: ::std::vec::Vec<#ty>)
+        }
+        _ => {
+            let doc = doc(&format!("Items of the `{name}` category"));
+            quote!(#doc pub #field: #ty)
+        }
+    }
+}
+
+/// The struct of a category, with an optional field per item and a method
+/// per item with a dREL Evaluation method, then the enums of its enumerated
+/// items, followed by those of its children.
+/// Methods that cannot be compiled become `compile_error!`s naming the item.
+fn category_structs(dict: &Dictionary, node: &CategoryNode, out: &mut Vec<TokenStream>) {
+    let category = node.category;
+    let name = type_ident(&category.name);
+    let items = if node.selected {
+        dict.category_items(&category.name)
+    } else {
+        Vec::new()
+    };
+    let item_fields = items.iter().map(|item| {
+        let field = field_ident(&item.object_id);
+        let ty = item_type(item);
+        let doc = doc(&item_doc(item));
+        quote!(#doc pub #field: ::core::option::Option<#ty>)
+    });
+    let held = match category.class {
+        CategoryClass::Loop => Vec::new(),
+        _ => node.fields(),
+    };
+    let category_fields = held.iter().map(|child| category_field(child));
+    let held: Vec<_> = held.iter().map(|child| child.category).collect();
+    let conversions = match category.class {
+        CategoryClass::Loop => loop_impls(&name, category, &items),
+        _ => set_impls(&name, &items, &held),
+    };
+    let lookups = key_lookups(dict, &held);
+    let methods = items
+        .iter()
+        .filter_map(|item| match compile_method(dict, category, item) {
+            Ok(method) => method,
+            Err(e) => {
+                let message = e.to_string();
+                Some(quote!(::core::compile_error!(#message);))
+            }
+        });
+    let doc = doc(&category_doc(category));
+    out.push(quote! {
+        #doc
+        #[derive(Debug, Clone, Default, PartialEq)]
+        pub struct #name {
+            #(#item_fields,)*
+            #(#category_fields,)*
+        }
+
+        impl #name {
+            #(#methods)*
+            #lookups
+        }
+
+        #conversions
+    });
+    out.extend(
+        items
+            .iter()
+            .filter(|item| is_enumerated(item))
+            .map(|item| item_enum(item)),
+    );
+    for child in &node.children {
+        category_structs(dict, child, out);
+    }
+}
+
+/// `categories` with the categories their keys and items link to, directly
+/// or through other links, e.g. `ATOM_TYPE` for `_atom_site.type_symbol`
+pub fn with_links(dict: &Dictionary, categories: &[String]) -> Vec<String> {
+    let mut names = categories.to_vec();
+    let mut next = 0;
+    while let Some(name) = names.get(next).cloned() {
+        let keys = dict.category(&name).into_iter().flat_map(|c| &c.keys);
+        let links = dict
+            .category_items(&name)
+            .into_iter()
+            .filter_map(|item| item.linked_item.as_ref());
+        for linked in keys.chain(links) {
+            let category = dict.item(linked).and_then(|item| dict.item_category(item));
+            if let Some(category) = category
+                && !names.iter().any(|n| n.eq_ignore_ascii_case(&category.name))
+            {
+                names.push(category.name.clone());
+            }
+        }
+        next += 1;
+    }
+    names
+}
+
+/// Definitions left out of the model because they are not below the head
+/// category, as messages for the user. With `categories`, only those asked
+/// for are reported.
+pub fn skipped_definitions(dict: &Dictionary, categories: Option<&[String]>) -> Vec<String> {
+    let categories = categories.map(|c| with_links(dict, c));
+    let categories = categories.as_deref();
+    let mut reachable = vec![dict.head.to_lowercase()];
+    let mut next = 0;
+    while let Some(name) = reachable.get(next).cloned() {
+        for child in dict.child_categories(&name) {
+            if !reachable.contains(&child.name.to_lowercase()) {
+                reachable.push(child.name.to_lowercase());
+            }
+        }
+        next += 1;
+    }
+    let wanted = |category: &str| {
+        categories.is_none_or(|names| names.iter().any(|n| n.eq_ignore_ascii_case(category)))
+    };
+    let skipped_categories = dict
+        .categories
+        .iter()
+        .filter(|(key, category)| !reachable.contains(key) && wanted(&category.name))
+        .map(|(_, category)| match &category.parent {
+            Some(parent) if dict.category(parent).is_none() => format!(
+                "category `{}` is skipped: its parent `{parent}` is not defined",
+                category.name
+            ),
+            _ => format!(
+                "category `{}` is skipped: it is not below the head category `{}`",
+                category.name, dict.head
+            ),
+        });
+    let skipped_items = dict
+        .items
+        .values()
+        .filter(|item| categories.is_none() && dict.item_category(item).is_none())
+        .map(|item| {
+            format!(
+                "item `{}` is skipped: its category `{}` is not defined",
+                item.name, item.category_id
+            )
+        });
+    skipped_categories.chain(skipped_items).collect()
+}
+
+/// The model of `dict`, limited to `categories` if given, together with
+/// those they link to and the categories above them
+pub fn generate_model(
+    dict: &Dictionary,
+    name: &Ident,
+    categories: Option<&[String]>,
+) -> TokenStream {
+    let categories = categories.map(|c| with_links(dict, c));
+    let categories = categories.as_deref();
+    let head = CategoryNode::build(dict, dict.head_category(), categories, &mut Vec::new());
+    let held = head.fields();
+    let fields = held.iter().map(|child| category_field(child));
+    let held: Vec<_> = held.iter().map(|child| child.category).collect();
+    let conversions = set_impls(name, &[], &held);
+    let lookups = key_lookups(dict, &held);
+    let mut structs = Vec::new();
+    for child in &head.children {
+        category_structs(dict, child, &mut structs);
+    }
+    let title = &dict.title;
+    let version = dict.version.as_deref().unwrap_or_default();
+    let namespace = dict.namespace.as_deref().unwrap_or_default();
+    let doc = doc(&dictionary_doc(dict));
+    quote! {
+        #doc
+        #[derive(Debug, Clone, Default, PartialEq)]
+        pub struct #name {
+            #(#fields,)*
+        }
+
+        impl #name {
+            /// `_dictionary.title` of the dictionary the model was generated from
+            pub const TITLE: &'static str = #title;
+            pub const VERSION: &'static str = #version;
+            pub const NAMESPACE: &'static str = #namespace;
+
+            #lookups
+
+            /// The model as a data block, in the same form as parsed CIF
+            pub fn to_block(&self, heading: &str) -> ::cif_chomper_core::raw_model::DataBlock {
+                let mut content = ::std::vec::Vec::new();
+                self.write_items(&mut content);
+                ::cif_chomper_core::raw_model::DataBlock {
+                    heading: heading.to_string(),
+                    content,
+                }
+            }
+        }
+
+        #conversions
+
+        #(#structs)*
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::dict;
+    use quote::format_ident;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_category_tree(dict: Dictionary) {
+        let head = CategoryNode::build(&dict, dict.head_category(), None, &mut Vec::new());
+        let children: Vec<_> = head
+            .children
+            .iter()
+            .map(|c| c.category.name.as_str())
+            .collect();
+        assert_eq!(children, vec!["ATOM_SITE", "CELL"]);
+    }
+
+    #[rstest]
+    fn test_generate_model(dict: Dictionary) {
+        let tokens = generate_model(&dict, &format_ident!("TestModel"), None).to_string();
+        assert!(tokens.contains("pub struct TestModel { # [doc = \" Packets of the `ATOM_SITE` category\"] pub atom_site : :: std :: vec :: Vec < AtomSite > , # [doc = \" Items of the `CELL` category\"] pub cell : Cell , }"));
+        assert!(tokens.contains("# [doc = \" - Aliases: `_cell_length_a`\"] pub length_a : :: core :: option :: Option < f64 >"));
+        assert!(tokens.contains("# [doc = \" - Category: `CELL` (Set)\"] # [derive"));
+        assert!(tokens.contains("pub fract_xyz : :: core :: option :: Option < [f64 ; 3usize] >"));
+        assert!(tokens.contains("pub const NAMESPACE : & 'static str = \"TestDic\""));
+        assert!(!tokens.contains("struct Function"));
+    }
+
+    #[rstest]
+    fn test_generate_model_subset(dict: Dictionary) {
+        let categories = ["cell".to_string()];
+        let tokens =
+            generate_model(&dict, &format_ident!("TestModel"), Some(&categories)).to_string();
+        assert!(tokens.contains(
+            "pub struct TestModel { # [doc = \" Items of the `CELL` category\"] pub cell : Cell , }"
+        ));
+        assert!(!tokens.contains("AtomSite"));
+    }
+
+    #[rstest]
+    fn test_skipped_definitions(mut dict: Dictionary) {
+        let mut orphan = dict.category("cell").unwrap().clone();
+        orphan.name = "ORPHAN".to_string();
+        orphan.parent = Some("NONESUCH".to_string());
+        dict.categories.insert("orphan".to_string(), orphan);
+        let mut item = dict.item("_cell.volume").unwrap().clone();
+        item.name = "_lost.volume".to_string();
+        item.category_id = "lost".to_string();
+        dict.items.insert("_lost.volume".to_string(), item);
+        assert_eq!(
+            skipped_definitions(&dict, None),
+            vec![
+                "category `ORPHAN` is skipped: its parent `NONESUCH` is not defined",
+                "item `_lost.volume` is skipped: its category `lost` is not defined",
+            ]
+        );
+        assert!(skipped_definitions(&dict, Some(&["cell".to_string()])).is_empty());
+        assert!(skipped_definitions(&crate::test_fixtures::dict(), None).is_empty());
+    }
+
+    #[test]
+    fn test_with_links() {
+        let dict = crate::core_dictionary().unwrap();
+        assert_eq!(
+            with_links(&dict, &["atom_site".to_string()]),
+            vec!["atom_site", "ATOM_TYPE"]
+        );
+        let tokens = generate_model(
+            &dict,
+            &format_ident!("Model"),
+            Some(&["atom_site".to_string()]),
+        )
+        .to_string();
+        assert!(tokens.contains("pub struct AtomType {"));
+        assert!(!tokens.contains("pub struct Cell {"));
+    }
+}
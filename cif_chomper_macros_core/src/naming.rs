@@ -0,0 +1,120 @@
+/// Rust names and types for dictionary categories and items
+use cif_chomper_core::dictionary::{ContentType, ItemDefinition, TypeContainer};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::enums::{enum_ident, is_enumerated};
+
+/// `ATOM_SITE` to `AtomSite`
+pub fn type_ident(category: &str) -> Ident {
+    let name: String = category
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap_or_default().to_ascii_uppercase();
+            std::iter::once(first)
+                .chain(chars.map(|c| c.to_ascii_lowercase()))
+                .collect::<String>()
+        })
+        .collect();
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => format_ident!("{}", name),
+        _ => format_ident!("C{}", name),
+    }
+}
+
+/// `name_H-M_alt` to `name_h_m_alt`, escaping Rust keywords
+pub fn field_ident(object: &str) -> Ident {
+    let mut name: String = object
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if syn::parse_str::<Ident>(&name).is_ok() {
+        Ident::new(&name, Span::call_site())
+    } else if matches!(name.as_str(), "self" | "super" | "crate" | "_") {
+        format_ident!("{}_", name)
+    } else {
+        Ident::new_raw(&name, Span::call_site())
+    }
+}
+
+/// Rust type of one value of a `_type.contents`
+pub fn scalar_type(contents: Option<ContentType>) -> TokenStream {
+    match contents {
+        Some(ContentType::Real) => quote!(f64),
+        Some(ContentType::Integer | ContentType::Count | ContentType::Index) => quote!(i64),
+        _ => quote!(::std::string::String),
+    }
+}
+
+/// Whether an item's values are held as `String`s
+pub fn is_text(item: &ItemDefinition) -> bool {
+    item.container == TypeContainer::Single
+        && !is_enumerated(item)
+        && !matches!(
+            item.contents.content_type(),
+            Some(
+                ContentType::Real | ContentType::Integer | ContentType::Count | ContentType::Index
+            )
+        )
+}
+
+/// Rust type of an item value: an enum for enumerated items, arrays for
+/// containers with a fixed `_type.dimension`, vectors otherwise
+pub fn item_type(item: &ItemDefinition) -> TokenStream {
+    if is_enumerated(item) {
+        let name = enum_ident(item);
+        return quote!(#name);
+    }
+    let scalar = scalar_type(item.contents.content_type());
+    match item.container {
+        TypeContainer::List | TypeContainer::Array | TypeContainer::Matrix => {
+            match item.dimensions() {
+                Some(dims) => dims
+                    .iter()
+                    .rev()
+                    .fold(scalar, |inner, n| quote!([#inner; #n])),
+                None => quote!(::std::vec::Vec<#scalar>),
+            }
+        }
+        TypeContainer::Table => {
+            quote!(::std::collections::BTreeMap<::std::string::String, #scalar>)
+        }
+        _ => scalar,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("ATOM_SITE", "AtomSite")]
+    #[case("cell", "Cell")]
+    #[case("PD_CALC.OVERALL", "PdCalcOverall")]
+    #[case("3D_THING", "C3dThing")]
+    fn test_type_ident(#[case] category: &str, #[case] expected: &str) {
+        assert_eq!(type_ident(category).to_string(), expected);
+    }
+
+    #[rstest]
+    #[case("length_a", "length_a")]
+    #[case("name_H-M_alt", "name_h_m_alt")]
+    #[case("type", "r#type")]
+    #[case("2theta", "_2theta")]
+    #[case("self", "self_")]
+    fn test_field_ident(#[case] object: &str, #[case] expected: &str) {
+        assert_eq!(field_ident(object).to_string(), expected);
+    }
+}
@@ -0,0 +1,11 @@
+/// Fixtures shared by the tests of several modules
+use cif_chomper_core::dictionary::Dictionary;
+use rstest::fixture;
+
+/// The dictionary the `cif_chomper_core` tests are written against
+pub const TEST_DIC: &str = include_str!("../../cif_chomper_core/src/data/test_dict.dic");
+
+#[fixture]
+pub fn dict() -> Dictionary {
+    Dictionary::parse(TEST_DIC).unwrap()
+}